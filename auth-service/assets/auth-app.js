@@ -6,6 +6,7 @@ document.addEventListener("DOMContentLoaded", () => {
     const signupSection = document.getElementById("signup-section");
     const passwordResetSection = document.getElementById("password-reset-section");
     const newPasswordSection = document.getElementById("new-password-section");
+    const linkActionSection = document.getElementById("link-action-section");
 
     const signupLink = document.getElementById("signup-link");
     const twoFALoginLink = document.getElementById("2fa-login-link");
@@ -18,6 +19,7 @@ document.addEventListener("DOMContentLoaded", () => {
     if (!signupSection) console.error("Signup section not found");
     if (!passwordResetSection) console.error("Password reset section not found");
     if (!newPasswordSection) console.error("New password section not found");
+    if (!linkActionSection) console.error("Link action section not found");
     if (!signupLink) console.error("Signup link not found");
     if (!twoFALoginLink) console.error("2FA login link not found");
     if (!signupLoginLink) console.error("Signup login link not found");
@@ -25,7 +27,7 @@ document.addEventListener("DOMContentLoaded", () => {
     if (!passwordResetLoginLink) console.error("Password reset login link not found");
 
     function showSection(sectionToShow) {
-        [loginSection, twoFASection, signupSection, passwordResetSection, newPasswordSection].forEach(section => {
+        [loginSection, twoFASection, signupSection, passwordResetSection, newPasswordSection, linkActionSection].forEach(section => {
             if (section) section.style.display = section === sectionToShow ? "block" : "none";
         });
     }
@@ -65,11 +67,22 @@ document.addEventListener("DOMContentLoaded", () => {
         });
     }
 
-    // Check if there's a reset token in the URL
+    // Check if there's a reset token, or a token from another emailed link, in the URL
     const urlParams = new URLSearchParams(window.location.search);
-    const resetToken = urlParams.get('token');
-    if (resetToken) {
-        document.getElementById('reset-token').value = resetToken;
+    const urlToken = urlParams.get('token');
+    const linkActionHeadings = {
+        "change-email/confirm": "Confirm Email Change",
+        "change-email/revert": "Undo Email Change",
+        "account/restore": "Restore Account",
+        "account/unlock": "Unlock Account",
+    };
+    const linkAction = window.location.pathname.match(/\/(change-email\/(?:confirm|revert)|account\/(?:restore|unlock))$/);
+    if (urlToken && linkAction) {
+        document.getElementById('link-action-token').value = urlToken;
+        document.getElementById('link-action-heading').textContent = linkActionHeadings[linkAction[1]];
+        showSection(linkActionSection);
+    } else if (urlToken) {
+        document.getElementById('reset-token').value = urlToken;
         showSection(newPasswordSection);
     }
 
@@ -290,4 +303,41 @@ document.addEventListener("DOMContentLoaded", () => {
             });
         });
     }
+
+    // Emailed Link Form Handling
+    const linkActionForm = document.getElementById("link-action-form");
+    const linkActionButton = document.getElementById("link-action-form-submit");
+    const linkActionErrAlert = document.getElementById("link-action-err-alert");
+
+    if (linkActionButton && linkAction) {
+        linkActionButton.addEventListener("click", (e) => {
+            e.preventDefault();
+
+            const token = linkActionForm.token.value;
+
+            fetch(`/auth/${linkAction[1]}`, {
+                method: 'POST',
+                headers: {
+                    'Content-Type': 'application/json',
+                },
+                body: JSON.stringify({ token }),
+            }).then(response => {
+                response.json().then(data => {
+                    if (response.ok) {
+                        linkActionErrAlert.style.display = "none";
+                        alert(data.message);
+                        showSection(loginSection);
+                    } else {
+                        let error_msg = data.error;
+                        if (error_msg !== undefined && error_msg !== null && error_msg !== "") {
+                            linkActionErrAlert.innerHTML = `<span><strong>Error: </strong>${error_msg}</span>`;
+                            linkActionErrAlert.style.display = "block";
+                        } else {
+                            linkActionErrAlert.style.display = "none";
+                        }
+                    }
+                });
+            });
+        });
+    }
 });
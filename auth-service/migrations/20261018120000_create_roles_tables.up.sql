@@ -0,0 +1,19 @@
+CREATE TABLE IF NOT EXISTS roles(
+   name TEXT NOT NULL PRIMARY KEY,
+   scopes TEXT[] NOT NULL DEFAULT '{}'
+);
+
+CREATE TABLE IF NOT EXISTS user_roles(
+   email TEXT NOT NULL REFERENCES users(email) ON DELETE CASCADE ON UPDATE CASCADE,
+   role TEXT NOT NULL REFERENCES roles(name) ON DELETE CASCADE,
+   PRIMARY KEY (email, role)
+);
+
+INSERT INTO roles (name, scopes) VALUES
+   ('user', '{"users:self"}'),
+   ('admin', '{"users:self", "users:admin"}')
+ON CONFLICT (name) DO NOTHING;
+
+INSERT INTO user_roles (email, role)
+SELECT email, 'user' FROM users
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,13 @@
+ALTER TABLE users ADD COLUMN IF NOT EXISTS two_fa_method TEXT NOT NULL DEFAULT 'none'
+   CHECK (two_fa_method IN ('none', 'email', 'totp'));
+
+UPDATE users SET two_fa_method = 'email' WHERE requires_2fa;
+
+ALTER TABLE users DROP COLUMN IF EXISTS requires_2fa;
+
+CREATE TABLE IF NOT EXISTS totp_credentials(
+   email TEXT NOT NULL PRIMARY KEY REFERENCES users(email) ON DELETE CASCADE ON UPDATE CASCADE,
+   secret TEXT,
+   pending_secret TEXT,
+   last_used_step BIGINT
+);
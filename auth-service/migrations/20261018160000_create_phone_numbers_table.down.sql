@@ -0,0 +1,7 @@
+DROP TABLE IF EXISTS phone_numbers;
+
+UPDATE users SET two_fa_method = 'none' WHERE two_fa_method = 'sms';
+
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_two_fa_method_check;
+ALTER TABLE users ADD CONSTRAINT users_two_fa_method_check
+   CHECK (two_fa_method IN ('none', 'email', 'totp'));
@@ -0,0 +1,10 @@
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_two_fa_method_check;
+ALTER TABLE users ADD CONSTRAINT users_two_fa_method_check
+   CHECK (two_fa_method IN ('none', 'email', 'totp', 'sms'));
+
+CREATE TABLE IF NOT EXISTS phone_numbers(
+   email TEXT NOT NULL PRIMARY KEY REFERENCES users(email) ON DELETE CASCADE ON UPDATE CASCADE,
+   phone_number TEXT,
+   pending_phone_number TEXT,
+   pending_verification_id TEXT
+);
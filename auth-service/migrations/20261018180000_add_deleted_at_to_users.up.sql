@@ -0,0 +1,2 @@
+-- Set while a deleted account waits out its grace period
+ALTER TABLE users ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
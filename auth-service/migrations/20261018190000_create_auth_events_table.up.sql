@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS auth_events(
+   id BIGSERIAL PRIMARY KEY,
+   email TEXT NOT NULL REFERENCES users(email) ON DELETE CASCADE ON UPDATE CASCADE,
+   kind TEXT NOT NULL,
+   ip_address TEXT,
+   user_agent TEXT,
+   occurred_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS auth_events_email_idx ON auth_events(email);
@@ -36,3 +36,34 @@ impl std::fmt::Display for VerifyTokenResponse {
         write!(f, "VerifyTokenResponse {{ is_valid: {} }}", self.is_valid)
     }
 }
+
+impl std::fmt::Display for RefreshTokenRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "RefreshTokenRequest {{ refresh_token: REDACTED }}")
+    }
+}
+
+impl std::fmt::Display for RefreshTokenResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "RefreshTokenResponse {{ access_token: REDACTED, refresh_token: REDACTED }}"
+        )
+    }
+}
+
+impl std::fmt::Display for IntrospectTokenRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "IntrospectTokenRequest {{ token: REDACTED }}")
+    }
+}
+
+impl std::fmt::Display for IntrospectTokenResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "IntrospectTokenResponse {{ active: {}, sub: {}, exp: {}, purpose: {} }}",
+            self.active, self.sub, self.exp, self.purpose
+        )
+    }
+}
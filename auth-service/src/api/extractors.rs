@@ -0,0 +1,268 @@
+use std::{marker::PhantomData, net::SocketAddr, str::FromStr, sync::Arc};
+
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts},
+    http::{
+        header::{AUTHORIZATION, USER_AGENT},
+        request::Parts,
+        HeaderMap,
+    },
+};
+use axum_extra::extract::CookieJar;
+use secrecy::Secret;
+use uuid::Uuid;
+
+use crate::domain::{email::Email, error::AuthAPIError};
+use crate::services::app_state::{AppServices, AppState};
+use crate::utils::{
+    auth::{validate_auth_token, Claims},
+    constants::{AUTH_TOKEN_PRECEDENCE, JWT_COOKIE_NAME},
+};
+
+/// Where a request came from, as recorded on the sessions it starts. Behind nginx
+/// the client address arrives in `X-Forwarded-For` / `X-Real-IP`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ClientInfo {
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ClientInfo {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let ip_address = forwarded_ip(&parts.headers).or_else(|| {
+            parts
+                .extensions
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(address)| address.ip().to_string())
+        });
+        let user_agent = header_value(&parts.headers, USER_AGENT.as_str());
+
+        Ok(Self { ip_address, user_agent })
+    }
+}
+
+/// Which token wins when a request carries both an `Authorization` header and a `jwt` cookie.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum TokenPrecedence {
+    #[default]
+    Header,
+    Cookie,
+}
+
+impl FromStr for TokenPrecedence {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "header" => Ok(Self::Header),
+            "cookie" => Ok(Self::Cookie),
+            _ => Err(format!("Unknown auth token precedence: {value}")),
+        }
+    }
+}
+
+impl TokenPrecedence {
+    fn from_env() -> Self {
+        TokenPrecedence::from_str(&AUTH_TOKEN_PRECEDENCE).unwrap_or_else(|e| {
+            tracing::warn!("{e}, falling back to the header");
+            Self::default()
+        })
+    }
+}
+
+/// The raw auth token, taken from `Authorization: Bearer` or the `jwt` cookie.
+#[derive(Clone, Debug)]
+pub struct BearerToken(pub Secret<String>);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for BearerToken {
+    type Rejection = AuthAPIError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        select_token(&parts.headers, TokenPrecedence::from_env()).map(Self)
+    }
+}
+
+/// A caller holding a valid, unrevoked auth token.
+#[derive(Clone, Debug)]
+pub struct AuthenticatedUser {
+    pub token: Secret<String>,
+    pub claims: Claims,
+    pub email: Email,
+    pub session_id: Option<Uuid>,
+}
+
+#[async_trait]
+impl<S: AppServices> FromRequestParts<Arc<AppState<S>>> for AuthenticatedUser {
+    type Rejection = AuthAPIError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &Arc<AppState<S>>) -> Result<Self, Self::Rejection> {
+        let BearerToken(token) = BearerToken::from_request_parts(parts, state).await?;
+
+        let claims = validate_auth_token(state.banned_token_store.clone(), token.clone())
+            .await
+            .map_err(|_| AuthAPIError::InvalidToken)?;
+        let email = Email::parse(claims.sub.clone()).map_err(|_| AuthAPIError::InvalidToken)?;
+        let session_id = claims.sid.as_deref().and_then(|sid| Uuid::parse_str(sid).ok());
+
+        Ok(Self {
+            token,
+            claims,
+            email,
+            session_id,
+        })
+    }
+}
+
+/// A scope a route can demand through [`RequireScope`].
+pub trait Scope: Send + Sync {
+    const NAME: &'static str;
+}
+
+pub mod scopes {
+    use super::Scope;
+
+    /// Manage other users' accounts and roles.
+    pub struct UsersAdmin;
+
+    impl Scope for UsersAdmin {
+        const NAME: &'static str = "users:admin";
+    }
+}
+
+/// An [`AuthenticatedUser`] whose token grants the scope `T`, e.g. `RequireScope<scopes::UsersAdmin>`.
+pub struct RequireScope<T: Scope> {
+    pub user: AuthenticatedUser,
+    scope: PhantomData<T>,
+}
+
+#[async_trait]
+impl<S: AppServices, T: Scope> FromRequestParts<Arc<AppState<S>>> for RequireScope<T> {
+    type Rejection = AuthAPIError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &Arc<AppState<S>>) -> Result<Self, Self::Rejection> {
+        let user = AuthenticatedUser::from_request_parts(parts, state).await?;
+        if !user.claims.has_scope(T::NAME) {
+            return Err(AuthAPIError::InsufficientScope);
+        }
+
+        Ok(Self {
+            user,
+            scope: PhantomData,
+        })
+    }
+}
+
+fn select_token(headers: &HeaderMap, precedence: TokenPrecedence) -> Result<Secret<String>, AuthAPIError> {
+    let cookie_token = || {
+        CookieJar::from_headers(headers)
+            .get(JWT_COOKIE_NAME)
+            .map(|cookie| cookie.value().to_owned())
+            .filter(|token| !token.is_empty())
+            .map(Ok)
+    };
+
+    let token = match precedence {
+        TokenPrecedence::Header => bearer_token(headers).or_else(cookie_token),
+        TokenPrecedence::Cookie => cookie_token().or_else(|| bearer_token(headers)),
+    };
+
+    token.ok_or(AuthAPIError::MissingToken)?.map(Secret::new)
+}
+
+/// Other schemes, such as the Basic credentials used for introspection, count as no token.
+fn bearer_token(headers: &HeaderMap) -> Option<Result<String, AuthAPIError>> {
+    let value = header_value(headers, AUTHORIZATION.as_str())?;
+    let (scheme, token) = value.split_once(' ').unwrap_or((value.as_str(), ""));
+    if !scheme.eq_ignore_ascii_case("bearer") {
+        return None;
+    }
+
+    match token.trim() {
+        "" => Some(Err(AuthAPIError::InvalidToken)),
+        token => Some(Ok(token.to_owned())),
+    }
+}
+
+fn forwarded_ip(headers: &HeaderMap) -> Option<String> {
+    header_value(headers, "x-forwarded-for")
+        .and_then(|forwarded| forwarded.split(',').next().map(|ip| ip.trim().to_string()))
+        .filter(|ip| !ip.is_empty())
+        .or_else(|| header_value(headers, "x-real-ip"))
+}
+
+fn header_value(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{header::COOKIE, HeaderValue};
+    use secrecy::ExposeSecret;
+
+    use super::*;
+
+    fn headers(authorization: Option<&str>, cookie: Option<&str>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Some(authorization) = authorization {
+            headers.insert(AUTHORIZATION, HeaderValue::from_str(authorization).unwrap());
+        }
+        if let Some(cookie) = cookie {
+            let cookie = format!("{JWT_COOKIE_NAME}={cookie}");
+            headers.insert(COOKIE, HeaderValue::from_str(&cookie).unwrap());
+        }
+        headers
+    }
+
+    fn selected(headers: &HeaderMap, precedence: TokenPrecedence) -> String {
+        select_token(headers, precedence).unwrap().expose_secret().clone()
+    }
+
+    #[test]
+    fn test_select_token_from_either_source() {
+        let header_only = headers(Some("Bearer header-token"), None);
+        let cookie_only = headers(None, Some("cookie-token"));
+
+        for precedence in [TokenPrecedence::Header, TokenPrecedence::Cookie] {
+            assert_eq!(selected(&header_only, precedence), "header-token");
+            assert_eq!(selected(&cookie_only, precedence), "cookie-token");
+        }
+    }
+
+    #[test]
+    fn test_select_token_follows_precedence() {
+        let both = headers(Some("bearer header-token"), Some("cookie-token"));
+
+        assert_eq!(selected(&both, TokenPrecedence::Header), "header-token");
+        assert_eq!(selected(&both, TokenPrecedence::Cookie), "cookie-token");
+    }
+
+    #[test]
+    fn test_select_token_missing() {
+        let result = select_token(&headers(Some("Basic dXNlcjpwYXNz"), None), TokenPrecedence::Header);
+        assert!(matches!(result, Err(AuthAPIError::MissingToken)));
+
+        let result = select_token(&headers(None, Some("")), TokenPrecedence::Cookie);
+        assert!(matches!(result, Err(AuthAPIError::MissingToken)));
+    }
+
+    #[test]
+    fn test_select_token_empty_bearer_is_invalid() {
+        let result = select_token(&headers(Some("Bearer "), None), TokenPrecedence::Header);
+        assert!(matches!(result, Err(AuthAPIError::InvalidToken)));
+    }
+
+    #[test]
+    fn test_token_precedence_from_str() {
+        assert_eq!(TokenPrecedence::from_str("Cookie"), Ok(TokenPrecedence::Cookie));
+        assert_eq!(TokenPrecedence::from_str("header"), Ok(TokenPrecedence::Header));
+        assert!(TokenPrecedence::from_str("query").is_err());
+    }
+}
@@ -2,20 +2,25 @@ use std::sync::Arc;
 use std::{error::Error, net::SocketAddr};
 
 use log::info;
-use secrecy::Secret;
+use secrecy::{ExposeSecret, Secret};
 use tonic::{Request, Response, Status};
 
-use crate::domain::user::NewUser;
+use crate::domain::user::{NewUser, TwoFAMethod};
 use crate::domain::{
-    data_stores::{UserStore, UserStoreError},
+    data_stores::{RefreshToken, UserStore, UserStoreError},
     email::Email,
     error::AuthAPIError,
     password::Password,
 };
+use crate::routes::verify_email::send_verification_email;
 use crate::services::app_state::{AppServices, AppState};
+use crate::utils::auth::{
+    authenticate_introspection_client, generate_auth_token, rotate_refresh_token, touch_session, validate_auth_token,
+};
 use auth_proto::{
     auth_service_server::{AuthService, AuthServiceServer},
-    SignupRequest, SignupResponse, VerifyTokenRequest, VerifyTokenResponse,
+    IntrospectTokenRequest, IntrospectTokenResponse, RefreshTokenRequest, RefreshTokenResponse, SignupRequest,
+    SignupResponse, VerifyTokenRequest, VerifyTokenResponse,
 };
 
 pub struct GRPCAuthService<S: AppServices + 'static> {
@@ -39,13 +44,22 @@ impl<S: AppServices + 'static> AuthService for GRPCAuthService<S> {
             .await
             .map_err(AuthAPIError::InvalidPassword)?;
 
-        let user = NewUser::new(email, password, req.requires_2fa);
+        let user = NewUser::new(
+            email.clone(),
+            password,
+            TwoFAMethod::from_requires_2fa(req.requires_2fa),
+        );
 
         let mut user_store = self.app_state.user_store.write().await;
         user_store.add_user(user).await.map_err(|e| match e {
             UserStoreError::UserAlreadyExists => AuthAPIError::UserAlreadyExists,
             _ => AuthAPIError::UnexpectedError(e.into()),
         })?;
+        drop(user_store);
+
+        if let Err(e) = send_verification_email(&self.app_state, &email).await {
+            tracing::error!("Error sending verification email: {e:?}");
+        }
 
         Ok(Response::new(SignupResponse {
             message: "User created successfully".to_string(),
@@ -62,6 +76,71 @@ impl<S: AppServices + 'static> AuthService for GRPCAuthService<S> {
         // TODO: Implement token verification logic
         Ok(Response::new(VerifyTokenResponse { is_valid: true }))
     }
+
+    async fn refresh_token(
+        &self,
+        request: Request<RefreshTokenRequest>,
+    ) -> Result<Response<RefreshTokenResponse>, Status> {
+        info!("Received refresh_token request");
+
+        let req = request.into_inner();
+        let token = RefreshToken::parse(Secret::new(req.refresh_token)).map_err(|_| AuthAPIError::InvalidToken)?;
+
+        let (record, new_token) = rotate_refresh_token(self.app_state.refresh_token_store.clone(), &token)
+            .await
+            .map_err(|_| AuthAPIError::InvalidToken)?;
+        touch_session(self.app_state.session_store.clone(), &record.family_id)
+            .await
+            .map_err(|_| AuthAPIError::InvalidToken)?;
+        let access = self
+            .app_state
+            .user_store
+            .read()
+            .await
+            .get_access(&record.email)
+            .await
+            .map_err(|e| match e {
+                UserStoreError::UserNotFound => AuthAPIError::InvalidToken,
+                _ => AuthAPIError::UnexpectedError(e.into()),
+            })?;
+        let access_token = generate_auth_token(&record.email, &record.family_id, &access)
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+        Ok(Response::new(RefreshTokenResponse {
+            access_token: access_token.expose_secret().to_owned(),
+            refresh_token: new_token.expose_secret_string(),
+        }))
+    }
+
+    async fn introspect_token(
+        &self,
+        request: Request<IntrospectTokenRequest>,
+    ) -> Result<Response<IntrospectTokenResponse>, Status> {
+        info!("Received introspect_token request");
+
+        let authorization = request
+            .metadata()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok());
+        authenticate_introspection_client(authorization)?;
+
+        let req = request.into_inner();
+        let response =
+            match validate_auth_token(self.app_state.banned_token_store.clone(), Secret::new(req.token)).await {
+                Ok(claims) => IntrospectTokenResponse {
+                    active: true,
+                    sub: claims.sub.expose_secret().to_owned(),
+                    exp: claims.exp.into(),
+                    iat: claims.iat.into(),
+                    purpose: format!("{:?}", claims.purpose),
+                    jti: claims.jti,
+                    scopes: claims.scopes,
+                },
+                Err(_) => IntrospectTokenResponse::default(),
+            };
+
+        Ok(Response::new(response))
+    }
 }
 
 pub struct GRPCApp<S: AppServices + 'static> {
@@ -7,7 +7,7 @@ use axum::{
     http::StatusCode,
     middleware::{from_fn, Next},
     response::{IntoResponse, Response},
-    routing::{get, post},
+    routing::{delete, get, post, put},
     serve::Serve,
     Json, Router,
 };
@@ -49,7 +49,7 @@ impl RESTApp {
         ];
 
         let cors = CorsLayer::new()
-            .allow_methods([Method::GET, Method::POST])
+            .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
             .allow_credentials(true)
             .allow_origin(allowed_origins);
 
@@ -58,14 +58,60 @@ impl RESTApp {
             .layer(from_fn(log_request))
             .nest_service("/", ServeDir::new("assets"))
             .route("/health", post(health_check))
+            .route("/.well-known/jwks.json", get(routes::jwks::get))
             .route("/signup", post(routes::signup::post))
+            .route("/verify-email", get(routes::verify_email::get))
+            .route("/resend-verification", post(routes::verify_email::post_resend))
             .route("/login", post(routes::login::post))
             .route("/logout", post(routes::logout::post))
+            .route("/logout-all", post(routes::logout_all::post))
+            .route("/sessions", get(routes::sessions::get))
+            .route("/sessions/:id", delete(routes::sessions::delete))
+            .route("/trusted-devices", get(routes::trusted_devices::get))
+            .route("/trusted-devices/:id", delete(routes::trusted_devices::delete))
             .route("/verify-2fa", post(routes::verify_2fa::post))
+            .route("/resend-2fa", post(routes::resend_2fa::post))
+            .route("/2fa/enable", post(routes::two_fa_settings::post_enable))
+            .route("/2fa/disable", post(routes::two_fa_settings::post_disable))
+            .route("/phone", get(routes::phone::get).post(routes::phone::post))
+            .route("/phone/verify", post(routes::phone::post_verify))
+            .route("/totp/enroll", post(routes::totp::post_enroll))
+            .route("/totp/confirm", post(routes::totp::post_confirm))
+            .route(
+                "/recovery-codes",
+                get(routes::recovery_codes::get).post(routes::recovery_codes::post),
+            )
+            .route("/webauthn/register/start", post(routes::webauthn::post_register_start))
+            .route(
+                "/webauthn/register/finish",
+                post(routes::webauthn::post_register_finish),
+            )
+            .route("/webauthn/login/start", post(routes::webauthn::post_login_start))
+            .route("/webauthn/login/finish", post(routes::webauthn::post_login_finish))
+            .route("/webauthn/2fa/start", post(routes::webauthn::post_2fa_start))
+            .route("/webauthn/2fa/finish", post(routes::webauthn::post_2fa_finish))
             .route("/verify-token", post(routes::verify_token::post))
+            .route("/introspect", post(routes::introspect::post))
+            .route("/token/refresh", post(routes::refresh_token::post))
             .route("/initiate-password-reset", post(routes::initiate_password_reset::post))
             .route("/reset-password", post(routes::reset_password::post))
             .route("/reset-password", get(routes::reset_password::get))
+            .route("/change-password", post(routes::change_password::post))
+            .route("/change-email", post(routes::change_email::post))
+            .route("/change-email/confirm", post(routes::change_email::post_confirm))
+            .route("/change-email/confirm", get(routes::change_email::get))
+            .route("/change-email/revert", post(routes::change_email::post_revert))
+            .route("/change-email/revert", get(routes::change_email::get))
+            .route("/account", delete(routes::account::delete))
+            .route("/account/export", get(routes::account::get_export))
+            .route("/account/restore", post(routes::account::post_restore))
+            .route("/account/restore", get(routes::account::get))
+            .route("/account/unlock", post(routes::account::post_unlock))
+            .route("/account/unlock", get(routes::account::get))
+            .route("/admin/password-hashes", get(routes::admin::get_password_hashes))
+            .route("/admin/users/import", post(routes::admin::post_import_users))
+            .route("/admin/users/roles", put(routes::admin::put_user_roles))
+            .route("/admin/users/unlock", post(routes::admin::post_unlock_user))
             .with_state(app_state)
             .layer(cors)
             .layer(
@@ -101,9 +147,53 @@ impl IntoResponse for AuthAPIError {
         let (status, error_message) = match self {
             AuthAPIError::UserAlreadyExists => (StatusCode::CONFLICT, "User already exists".to_string()),
             AuthAPIError::InvalidCredentials => (StatusCode::UNAUTHORIZED, "Invalid credentials".to_string()),
+            AuthAPIError::AccountLocked => (
+                StatusCode::LOCKED,
+                "Account temporarily locked after too many failed logins".to_string(),
+            ),
+            AuthAPIError::TooManyLoginAttempts => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "Too many failed logins, please wait before trying again".to_string(),
+            ),
+            AuthAPIError::InvalidClientCredentials => {
+                (StatusCode::UNAUTHORIZED, "Invalid client credentials".to_string())
+            }
+            AuthAPIError::InsufficientScope => (StatusCode::FORBIDDEN, "Insufficient scope".to_string()),
+            AuthAPIError::EmailNotVerified => (StatusCode::FORBIDDEN, "Email not verified".to_string()),
             AuthAPIError::InvalidEmail(msg) => (StatusCode::BAD_REQUEST, msg),
             AuthAPIError::InvalidPassword(report) => (StatusCode::BAD_REQUEST, report.to_string()),
+            AuthAPIError::InvalidPhoneNumber(msg) => (StatusCode::BAD_REQUEST, msg),
             AuthAPIError::UserNotFound => (StatusCode::NOT_FOUND, "User not found".to_string()),
+            AuthAPIError::SessionNotFound => (StatusCode::NOT_FOUND, "Session not found".to_string()),
+            AuthAPIError::TrustedDeviceNotFound => (StatusCode::NOT_FOUND, "Trusted device not found".to_string()),
+            AuthAPIError::RoleNotFound => (StatusCode::BAD_REQUEST, "Role not found".to_string()),
+            AuthAPIError::TotpNotEnrolled => (StatusCode::BAD_REQUEST, "TOTP not enrolled".to_string()),
+            AuthAPIError::TwoFAAlreadyEnabled => (StatusCode::CONFLICT, "2FA already enabled".to_string()),
+            AuthAPIError::TwoFANotEnabled => (StatusCode::BAD_REQUEST, "2FA not enabled".to_string()),
+            AuthAPIError::PhoneNumberNotVerified => (StatusCode::BAD_REQUEST, "Phone number not verified".to_string()),
+            AuthAPIError::TwoFACodeNotResendable => {
+                (StatusCode::BAD_REQUEST, "2FA method does not send codes".to_string())
+            }
+            AuthAPIError::TwoFACodeResendCooldown => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "Please wait before requesting another code".to_string(),
+            ),
+            AuthAPIError::TwoFACodeResendLimitReached => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "Too many codes requested, please log in again".to_string(),
+            ),
+            AuthAPIError::PhoneVerificationLimitReached => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "Too many verification codes requested, please try again later".to_string(),
+            ),
+            AuthAPIError::InvalidWebAuthnResponse => (StatusCode::BAD_REQUEST, "Invalid WebAuthn response".to_string()),
+            AuthAPIError::UnsupportedImportFormat => (
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "Send users as text/csv or application/x-ndjson".to_string(),
+            ),
+            AuthAPIError::WebAuthnCredentialAlreadyExists => {
+                (StatusCode::CONFLICT, "WebAuthn credential already exists".to_string())
+            }
             AuthAPIError::UnexpectedError(e) => {
                 error!("UnexpectedError: {:?}", e);
                 (
@@ -1,7 +1,11 @@
-use std::fmt;
+use std::{fmt, hash::Hash};
 
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
 use color_eyre::eyre;
-use rand::Rng;
+use data_encoding::HEXLOWER;
+use rand::{distributions::Alphanumeric, Rng};
+use ring::digest::{digest, SHA256};
 use secrecy::{ExposeSecret, Secret};
 use serde::{ser::SerializeStruct, Deserialize, Serialize};
 use thiserror;
@@ -9,28 +13,114 @@ use uuid::Uuid;
 
 use macros::SecretString;
 
-use super::user::{NewUser, User};
+use super::user::{
+    AuthEvent, ImportedUser, NewUser, PasswordHashCounts, TotpCredential, TwoFAMethod, User, UserAccess,
+    UserPhoneNumber, WebAuthnCredential,
+};
 
-use crate::domain::{email::Email, password::Password};
+use crate::domain::{email::Email, password::Password, phone_number::PhoneNumber};
+use crate::utils::constants::{Epoch, TRUSTED_DEVICE_TTL_SECONDS};
 
 //************************  Traits  ************************//
 
 #[async_trait::async_trait]
 pub trait UserStore: Clone + Send + Sync + 'static + fmt::Debug {
+    /// Adds the user, replacing one scheduled for deletion under the same address.
     async fn add_user(&mut self, user: NewUser) -> Result<(), UserStoreError>;
+    /// Adds a user moved in from another system, keeping the password hash they arrived with.
+    async fn import_user(&mut self, user: ImportedUser) -> Result<(), UserStoreError>;
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError>;
     async fn update_password(&mut self, email: &Email, password: Password) -> Result<(), UserStoreError>;
-    async fn validate_user(&self, email: &Email, password: &Password) -> eyre::Result<User>;
+    async fn update_2fa_settings(&mut self, email: &Email, two_fa_method: TwoFAMethod) -> Result<(), UserStoreError>;
+    /// Turns 2FA off and removes the authenticator-app secret and recovery codes, so turning it back on starts
+    /// from scratch.
+    async fn disable_2fa(&mut self, email: &Email) -> Result<(), UserStoreError>;
+    /// Records that the user confirmed they own their address. Verifying twice is not an error.
+    async fn mark_email_verified(&mut self, email: &Email) -> Result<(), UserStoreError>;
+    /// Moves the account, and everything stored under its address, to `new_email` in one step. Fails with
+    /// `UserAlreadyExists` if `new_email` is taken.
+    async fn change_email(&mut self, email: &Email, new_email: &Email) -> Result<(), UserStoreError>;
+    /// Removes the user and everything stored under their address.
+    async fn delete_user(&mut self, email: &Email) -> Result<(), UserStoreError>;
+    /// Hides the user from every lookup until `restore_user` brings them back or `purge_deleted_users` removes
+    /// them. Signing up again with the address removes them straight away.
+    async fn schedule_deletion(&mut self, email: &Email) -> Result<(), UserStoreError>;
+    async fn restore_user(&mut self, email: &Email) -> Result<(), UserStoreError>;
+    /// Deletes the users scheduled for deletion more than `grace_period_seconds` ago and returns how many.
+    async fn purge_deleted_users(&mut self, grace_period_seconds: u64) -> Result<u64, UserStoreError>;
+    /// Checks the password as submitted. Strength rules only apply when a password is set, so imported users
+    /// whose old password would fail them can still sign in.
+    async fn validate_user(&self, email: &Email, password: &Secret<String>) -> eyre::Result<User>;
+    /// The stored hash, if it was made under an older hashing policy.
+    async fn get_outdated_password_hash(&self, email: &Email) -> Result<Option<Secret<String>>, UserStoreError>;
+    /// Swaps in `password_hash` if `outdated_hash` is still the stored one, leaving a password changed in the
+    /// meantime alone. Returns whether the hash was replaced.
+    async fn replace_password_hash(
+        &mut self,
+        email: &Email,
+        outdated_hash: &Secret<String>,
+        password_hash: Secret<String>,
+    ) -> Result<bool, UserStoreError>;
+    async fn count_password_hashes(&self) -> Result<PasswordHashCounts, UserStoreError>;
+    async fn get_access(&self, email: &Email) -> Result<UserAccess, UserStoreError>;
+    /// Replaces the user's roles. Every role must already exist.
+    async fn set_roles(&mut self, email: &Email, roles: &[String]) -> Result<(), UserStoreError>;
+    async fn get_totp_credential(&self, email: &Email) -> Result<TotpCredential, UserStoreError>;
+    /// Stores a secret awaiting confirmation, leaving any active secret in place.
+    async fn set_pending_totp_secret(&mut self, email: &Email, secret: Secret<String>) -> Result<(), UserStoreError>;
+    /// Activates `secret` if it is still the pending one and switches the user to TOTP.
+    async fn confirm_totp_secret(
+        &mut self,
+        email: &Email,
+        secret: &Secret<String>,
+        step: u64,
+    ) -> Result<(), UserStoreError>;
+    /// Records `step` as used, failing with `TotpCodeReused` unless it is newer than the last one.
+    async fn use_totp_step(&mut self, email: &Email, step: u64) -> Result<(), UserStoreError>;
+    /// Replaces the user's recovery codes with the given hashes.
+    async fn set_recovery_codes(&mut self, email: &Email, code_hashes: Vec<String>) -> Result<(), UserStoreError>;
+    /// Consumes the code, failing with `RecoveryCodeNotFound` if it was never issued or already used.
+    async fn use_recovery_code(&mut self, email: &Email, code_hash: &str) -> Result<(), UserStoreError>;
+    async fn count_recovery_codes(&self, email: &Email) -> Result<usize, UserStoreError>;
+    /// Appends to the user's account history, which keeps only the latest `MAX_AUTH_EVENTS` entries.
+    async fn add_auth_event(&mut self, email: &Email, event: AuthEvent) -> Result<(), UserStoreError>;
+    /// The user's account history, newest first.
+    async fn get_auth_events(&self, email: &Email) -> Result<Vec<AuthEvent>, UserStoreError>;
+    async fn add_webauthn_credential(
+        &mut self,
+        email: &Email,
+        credential: WebAuthnCredential,
+    ) -> Result<(), UserStoreError>;
+    async fn get_webauthn_credentials(&self, email: &Email) -> Result<Vec<WebAuthnCredential>, UserStoreError>;
+    async fn update_webauthn_sign_count(&mut self, credential_id: &str, sign_count: u32) -> Result<(), UserStoreError>;
+    async fn get_phone_number(&self, email: &Email) -> Result<UserPhoneNumber, UserStoreError>;
+    /// Stores a number awaiting verification, leaving any verified number in place.
+    async fn set_pending_phone_number(
+        &mut self,
+        email: &Email,
+        phone_number: PhoneNumber,
+        verification_id: &str,
+    ) -> Result<(), UserStoreError>;
+    /// Makes the pending number the verified one if `verification_id` is still the pending verification.
+    async fn confirm_phone_number(&mut self, email: &Email, verification_id: &str) -> Result<(), UserStoreError>;
 }
 
 #[async_trait::async_trait]
 pub trait BannedTokenStore: Clone + Send + Sync + 'static + fmt::Debug {
     async fn add_token(&mut self, token: Secret<String>) -> Result<(), TokenStoreError>;
     async fn check_token(&self, token: Secret<String>) -> Result<(), TokenStoreError>;
+    /// Bans every auth token issued for the session.
+    async fn ban_session(&mut self, session_id: &Uuid) -> Result<(), TokenStoreError>;
+    async fn check_session(&self, session_id: &Uuid) -> Result<(), TokenStoreError>;
+    /// Bans every auth token issued to the user before `not_before`.
+    async fn ban_tokens_before(&mut self, email: &Email, not_before: Epoch) -> Result<(), TokenStoreError>;
+    async fn check_issued_at(&self, email: &Email, issued_at: Epoch) -> Result<(), TokenStoreError>;
 }
 
 #[async_trait::async_trait]
 pub trait TwoFACodeStore: Clone + Send + Sync + 'static + fmt::Debug {
+    /// Adds a pending code for a new login attempt. A user can have several attempts in flight, one per
+    /// device or tab; past `MAX_PENDING_TWO_FA_ATTEMPTS` the oldest is evicted.
     async fn add_code(
         &mut self,
         email: Email,
@@ -38,9 +128,49 @@ pub trait TwoFACodeStore: Clone + Send + Sync + 'static + fmt::Debug {
         code: TwoFACode,
     ) -> Result<(), TwoFACodeStoreError>;
 
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError>;
+    /// The remaining methods look attempts up by id and fail with `LoginAttemptIdNotFound` when the attempt
+    /// belongs to a different email.
+    async fn remove_code(
+        &mut self,
+        email: &Email,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(), TwoFACodeStoreError>;
+
+    async fn get_code(
+        &self,
+        email: &Email,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<TwoFACode, TwoFACodeStoreError>;
+
+    /// Counts a failed verification against the login attempt and returns the failures so far.
+    async fn record_failed_attempt(
+        &mut self,
+        email: &Email,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<u32, TwoFACodeStoreError>;
+
+    /// Swaps in a new code for the login attempt, keeping its id and restarting its TTL. Refused within
+    /// `cooldown_seconds` of the previous resend or once `max_resends` codes have been resent.
+    async fn resend_code(
+        &mut self,
+        email: &Email,
+        login_attempt_id: &LoginAttemptId,
+        code: TwoFACode,
+        cooldown_seconds: u64,
+        max_resends: u32,
+    ) -> Result<(), TwoFACodeStoreError>;
+
+    /// Drops every pending attempt of the user.
+    async fn remove_codes(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError>;
 
-    async fn get_code(&self, email: &Email) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError>;
+    /// Counts a newly texted code against the user. Refused once `max_sends` codes have been sent in the
+    /// current `window_seconds` window, which starts with the first send and outlives `remove_codes`.
+    async fn record_code_sent(
+        &mut self,
+        email: &Email,
+        max_sends: u32,
+        window_seconds: u64,
+    ) -> Result<(), TwoFACodeStoreError>;
 }
 
 #[async_trait::async_trait]
@@ -50,6 +180,71 @@ pub trait PasswordResetTokenStore: Clone + Send + Sync + 'static + fmt::Debug {
     async fn remove_token(&mut self, email: &Email) -> Result<(), TokenStoreError>;
 }
 
+#[async_trait::async_trait]
+pub trait RefreshTokenStore: Clone + Send + Sync + 'static + fmt::Debug {
+    async fn add_token(
+        &mut self,
+        token: RefreshToken,
+        record: RefreshTokenRecord,
+    ) -> Result<(), RefreshTokenStoreError>;
+    async fn get_token(&self, token: &RefreshToken) -> Result<RefreshTokenRecord, RefreshTokenStoreError>;
+    /// Marks the token as used, failing with `TokenReused` if it was already used.
+    async fn mark_token_used(&mut self, token: &RefreshToken) -> Result<(), RefreshTokenStoreError>;
+    /// Removes every token issued in the family, including the ones not yet used.
+    async fn revoke_family(&mut self, family_id: &Uuid) -> Result<(), RefreshTokenStoreError>;
+}
+
+#[async_trait::async_trait]
+pub trait SessionStore: Clone + Send + Sync + 'static + fmt::Debug {
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError>;
+    async fn get_session(&self, session_id: &Uuid) -> Result<Session, SessionStoreError>;
+    async fn get_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError>;
+    async fn touch_session(&mut self, session_id: &Uuid, last_seen_at: DateTime<Utc>) -> Result<(), SessionStoreError>;
+    async fn remove_session(&mut self, session_id: &Uuid) -> Result<(), SessionStoreError>;
+}
+
+#[async_trait::async_trait]
+pub trait WebAuthnChallengeStore: Clone + Send + Sync + 'static + fmt::Debug {
+    async fn add_challenge(&mut self, challenge: WebAuthnChallenge) -> Result<(), WebAuthnChallengeStoreError>;
+    /// Removes and returns the challenge, so each one can be answered only once.
+    async fn take_challenge(&mut self, challenge: &str) -> Result<WebAuthnChallenge, WebAuthnChallengeStoreError>;
+}
+
+#[async_trait::async_trait]
+pub trait TrustedDeviceStore: Clone + Send + Sync + 'static + fmt::Debug {
+    async fn add_device(&mut self, device: TrustedDevice) -> Result<(), TrustedDeviceStoreError>;
+    async fn get_device(&self, device_id: &Uuid) -> Result<TrustedDevice, TrustedDeviceStoreError>;
+    async fn get_devices(&self, email: &Email) -> Result<Vec<TrustedDevice>, TrustedDeviceStoreError>;
+    async fn touch_device(
+        &mut self,
+        device_id: &Uuid,
+        last_used_at: DateTime<Utc>,
+    ) -> Result<(), TrustedDeviceStoreError>;
+    async fn remove_device(&mut self, device_id: &Uuid) -> Result<(), TrustedDeviceStoreError>;
+    async fn remove_devices(&mut self, email: &Email) -> Result<(), TrustedDeviceStoreError>;
+}
+
+#[async_trait::async_trait]
+pub trait LoginFailureStore: Clone + Send + Sync + 'static + fmt::Debug {
+    /// Counts a failed password login. The count is forgotten `ttl_seconds` after the latest failure.
+    async fn record_failure(
+        &mut self,
+        email: &Email,
+        ttl_seconds: u64,
+    ) -> Result<LoginFailures, LoginFailureStoreError>;
+    /// Registers a password check that is about to run and returns the failures to throttle it on, counting the
+    /// checks already running as failures. Registering first means a burst of parallel attempts can't all pass
+    /// the throttle on the same count. Every reservation is ended with `release_attempt`, or by `clear_failures`.
+    async fn reserve_attempt(
+        &mut self,
+        email: &Email,
+        ttl_seconds: u64,
+    ) -> Result<LoginFailures, LoginFailureStoreError>;
+    async fn release_attempt(&mut self, email: &Email, ttl_seconds: u64) -> Result<(), LoginFailureStoreError>;
+    async fn get_failures(&self, email: &Email) -> Result<Option<LoginFailures>, LoginFailureStoreError>;
+    async fn clear_failures(&mut self, email: &Email) -> Result<(), LoginFailureStoreError>;
+}
+
 //************************  Traits  ************************//
 
 //************************  Enums   ************************//
@@ -62,6 +257,18 @@ pub enum UserStoreError {
     UserNotFound,
     #[error("Invalid credentials")]
     InvalidCredentials,
+    #[error("Role not found")]
+    RoleNotFound,
+    #[error("TOTP not enrolled")]
+    TotpNotEnrolled,
+    #[error("TOTP code already used")]
+    TotpCodeReused,
+    #[error("Recovery code not found")]
+    RecoveryCodeNotFound,
+    #[error("WebAuthn credential already exists")]
+    WebAuthnCredentialAlreadyExists,
+    #[error("Phone verification not found")]
+    PhoneVerificationNotFound,
     #[error("Unexpected error")]
     UnexpectedError(#[source] eyre::Report),
 }
@@ -78,14 +285,69 @@ pub enum TokenStoreError {
     UnexpectedError(#[source] eyre::Report),
 }
 
+#[derive(Debug, thiserror::Error)]
+pub enum RefreshTokenStoreError {
+    #[error("Refresh token not found")]
+    TokenNotFound,
+    #[error("Refresh token already used")]
+    TokenReused,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] eyre::Report),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SessionStoreError {
+    #[error("Session not found")]
+    SessionNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] eyre::Report),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TrustedDeviceStoreError {
+    #[error("Trusted device not found")]
+    TrustedDeviceNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] eyre::Report),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum LoginFailureStoreError {
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] eyre::Report),
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum TwoFACodeStoreError {
     #[error("Login attempt id not found")]
     LoginAttemptIdNotFound,
+    #[error("Code resent too recently")]
+    ResendCooldown,
+    #[error("Code resend limit reached")]
+    ResendLimitReached,
+    #[error("Code send limit reached")]
+    SendLimitReached,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] eyre::Report),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum WebAuthnChallengeStoreError {
+    #[error("Challenge not found")]
+    ChallengeNotFound,
     #[error("Unexpected error")]
     UnexpectedError(#[source] eyre::Report),
 }
 
+/// The ceremony a challenge was issued for. A challenge is only accepted by the matching finish endpoint.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WebAuthnCeremony {
+    Registration,
+    Login,
+    SecondFactor { login_attempt_id: String },
+}
+
 //************************  Enums   ************************//
 
 //***********************  Structs  ************************//
@@ -108,6 +370,14 @@ impl Default for LoginAttemptId {
     }
 }
 
+impl Hash for LoginAttemptId {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.expose_secret().hash(state)
+    }
+}
+
+impl Eq for LoginAttemptId {}
+
 #[derive(Clone, Debug, Deserialize, SecretString)]
 #[secret_string(field_name = "2FACode")]
 pub struct TwoFACode(Secret<String>);
@@ -129,6 +399,235 @@ impl Default for TwoFACode {
     }
 }
 
+/// One-time code that stands in for a `TwoFACode`. Only its `hash` is stored.
+#[derive(Clone, Debug, Deserialize, SecretString)]
+pub struct RecoveryCode(Secret<String>);
+
+impl RecoveryCode {
+    /// Accepts codes with or without the dash and in any case.
+    pub fn parse(code: Secret<String>) -> Result<Self, String> {
+        let normalized: String = code
+            .expose_secret()
+            .chars()
+            .filter(|c| *c != '-' && !c.is_whitespace())
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+        match normalized.len() == RECOVERY_CODE_LENGTH
+            && normalized.bytes().all(|b| RECOVERY_CODE_ALPHABET.contains(&b))
+        {
+            false => Err("Invalid Recovery Code".to_string()),
+            true => Ok(Self(Secret::new(normalized))),
+        }
+    }
+
+    pub fn hash(&self) -> String {
+        HEXLOWER.encode(digest(&SHA256, self.0.expose_secret().as_bytes()).as_ref())
+    }
+
+    /// The code as shown to the user, e.g. `abcde-fghjk`.
+    pub fn formatted(&self) -> String {
+        let code = self.0.expose_secret();
+        let (first, second) = code.split_at(RECOVERY_CODE_LENGTH / 2);
+        format!("{first}-{second}")
+    }
+}
+
+impl Default for RecoveryCode {
+    fn default() -> Self {
+        let mut rng = rand::thread_rng();
+        let code = (0..RECOVERY_CODE_LENGTH)
+            .map(|_| RECOVERY_CODE_ALPHABET[rng.gen_range(0..RECOVERY_CODE_ALPHABET.len())] as char)
+            .collect();
+        Self(Secret::new(code))
+    }
+}
+
+const RECOVERY_CODE_LENGTH: usize = 10;
+// Lowercase letters and digits without the easily confused 0, 1, i, l and o
+const RECOVERY_CODE_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+#[derive(Clone, Debug, Deserialize, SecretString)]
+pub struct RefreshToken(Secret<String>);
+
+impl RefreshToken {
+    pub fn parse(token: Secret<String>) -> Result<Self, String> {
+        let token_str = token.expose_secret();
+        match token_str.len() == REFRESH_TOKEN_LENGTH && token_str.chars().all(|c| c.is_ascii_alphanumeric()) {
+            false => Err("Invalid Refresh Token".to_string()),
+            true => Ok(Self(token)),
+        }
+    }
+}
+
+impl Default for RefreshToken {
+    fn default() -> Self {
+        let token = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(REFRESH_TOKEN_LENGTH)
+            .map(char::from)
+            .collect();
+        Self(Secret::new(token))
+    }
+}
+
+const REFRESH_TOKEN_LENGTH: usize = 64;
+
+/// Every refresh token issued from the same login shares a `family_id`, so a
+/// replayed token can revoke the whole chain of rotations.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RefreshTokenRecord {
+    pub email: Email,
+    pub family_id: Uuid,
+}
+
+impl RefreshTokenRecord {
+    pub fn new(email: Email) -> Self {
+        Self {
+            email,
+            family_id: Uuid::new_v4(),
+        }
+    }
+
+    /// Refresh tokens issued for a session use the session id as their family, so
+    /// revoking the session also revokes its refresh tokens.
+    pub fn for_session(session: &Session) -> Self {
+        Self {
+            email: session.email.clone(),
+            family_id: session.id,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Session {
+    pub id: Uuid,
+    pub email: Email,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+impl Session {
+    pub fn new(email: Email, ip_address: Option<String>, user_agent: Option<String>) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            email,
+            created_at: now,
+            last_seen_at: now,
+            ip_address,
+            user_agent,
+        }
+    }
+}
+
+/// A browser the user chose to remember after passing 2FA. Its cookie only lets logins skip the second factor
+/// while this record exists.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrustedDevice {
+    pub id: Uuid,
+    pub email: Email,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+impl TrustedDevice {
+    pub fn new(email: Email, ip_address: Option<String>, user_agent: Option<String>) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            email,
+            created_at: now,
+            last_used_at: now,
+            expires_at: now + chrono::Duration::seconds(TRUSTED_DEVICE_TTL_SECONDS),
+            ip_address,
+            user_agent,
+        }
+    }
+}
+
+/// Server-side state of an in-flight WebAuthn ceremony, keyed by the base64url challenge the client signs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WebAuthnChallenge {
+    pub challenge: String,
+    pub email: Email,
+    pub ceremony: WebAuthnCeremony,
+}
+
+impl WebAuthnChallenge {
+    pub fn new(email: Email, ceremony: WebAuthnCeremony) -> Self {
+        let mut bytes = [0u8; WEBAUTHN_CHALLENGE_BYTES];
+        rand::thread_rng().fill(&mut bytes);
+        Self {
+            challenge: URL_SAFE_NO_PAD.encode(bytes),
+            email,
+            ceremony,
+        }
+    }
+}
+
+const WEBAUTHN_CHALLENGE_BYTES: usize = 32;
+
+/// Failed password logins for an address since its last successful one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoginFailures {
+    pub count: u32,
+    pub last_failed_at: DateTime<Utc>,
+}
+
+/// What a run of failures means for the next login.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LoginThrottle {
+    Allowed,
+    /// Too soon after the last failure. Each failure past the free ones doubles the wait.
+    Delayed(DateTime<Utc>),
+    /// The threshold was reached; no logins until the lockout runs out or the account is unlocked.
+    Locked(DateTime<Utc>),
+}
+
+impl LoginFailures {
+    /// The failures a new attempt is throttled on. Attempts still being checked count as failing just now.
+    pub fn with_attempts_in_flight(failures: Option<LoginFailures>, in_flight: u32) -> LoginFailures {
+        match failures {
+            Some(failures) if in_flight == 0 => failures,
+            failures => LoginFailures {
+                count: failures.map_or(0, |failures| failures.count) + in_flight,
+                last_failed_at: Utc::now(),
+            },
+        }
+    }
+
+    pub fn throttle(&self, lockout_threshold: u32, lockout_seconds: u64, base_delay_seconds: u64) -> LoginThrottle {
+        let now = Utc::now();
+        if self.count >= lockout_threshold {
+            let locked_until = self.last_failed_at + chrono::Duration::seconds(lockout_seconds as i64);
+            return match locked_until > now {
+                true => LoginThrottle::Locked(locked_until),
+                false => LoginThrottle::Allowed,
+            };
+        }
+        if self.count <= FREE_LOGIN_FAILURES {
+            return LoginThrottle::Allowed;
+        }
+
+        let doublings = (self.count - FREE_LOGIN_FAILURES - 1).min(MAX_LOGIN_DELAY_DOUBLINGS);
+        let delay_seconds = (base_delay_seconds << doublings).min(lockout_seconds);
+        let retry_at = self.last_failed_at + chrono::Duration::seconds(delay_seconds as i64);
+        match retry_at > now {
+            true => LoginThrottle::Delayed(retry_at),
+            false => LoginThrottle::Allowed,
+        }
+    }
+}
+
+/// A mistyped password or two costs nothing.
+const FREE_LOGIN_FAILURES: u32 = 2;
+const MAX_LOGIN_DELAY_DOUBLINGS: u32 = 16;
+
 //***********************  Structs  ************************//
 
 //***********************   Tests   ************************//
@@ -193,3 +692,104 @@ mod two_fa_code_tests {
         assert!(code.0.expose_secret().chars().all(char::is_numeric));
     }
 }
+
+#[cfg(test)]
+mod refresh_token_tests {
+    use super::*;
+
+    #[test]
+    fn test_refresh_token_parse_valid() {
+        let token = RefreshToken::default();
+        let result = RefreshToken::parse(token.as_ref().clone());
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), token);
+    }
+
+    #[test]
+    fn test_refresh_token_parse_invalid_length() {
+        let result = RefreshToken::parse(Secret::new("abc123".to_string()));
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err(), "Invalid Refresh Token");
+    }
+
+    #[test]
+    fn test_refresh_token_parse_invalid_characters() {
+        let invalid_token = "!".repeat(REFRESH_TOKEN_LENGTH);
+        let result = RefreshToken::parse(Secret::new(invalid_token));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_refresh_token_default_is_unique() {
+        let token1 = RefreshToken::default();
+        let token2 = RefreshToken::default();
+        assert_eq!(token1.expose_secret_string().len(), REFRESH_TOKEN_LENGTH);
+        assert_ne!(token1, token2);
+    }
+}
+
+#[cfg(test)]
+mod login_failures_tests {
+    use super::*;
+
+    fn failures(count: u32, seconds_ago: i64) -> LoginFailures {
+        LoginFailures {
+            count,
+            last_failed_at: Utc::now() - chrono::Duration::seconds(seconds_ago),
+        }
+    }
+
+    #[test]
+    fn test_first_failures_are_not_delayed() {
+        for count in 1..=FREE_LOGIN_FAILURES {
+            assert_eq!(failures(count, 0).throttle(5, 900, 1), LoginThrottle::Allowed);
+        }
+    }
+
+    #[test]
+    fn test_delay_doubles_with_each_failure() {
+        assert!(matches!(failures(3, 0).throttle(10, 900, 1), LoginThrottle::Delayed(_)));
+        assert_eq!(failures(3, 1).throttle(10, 900, 1), LoginThrottle::Allowed);
+        assert!(matches!(failures(4, 1).throttle(10, 900, 1), LoginThrottle::Delayed(_)));
+        assert_eq!(failures(4, 2).throttle(10, 900, 1), LoginThrottle::Allowed);
+        assert!(matches!(failures(6, 7).throttle(10, 900, 1), LoginThrottle::Delayed(_)));
+        assert_eq!(failures(6, 8).throttle(10, 900, 1), LoginThrottle::Allowed);
+    }
+
+    #[test]
+    fn test_lockout_after_threshold() {
+        let LoginThrottle::Locked(locked_until) = failures(5, 0).throttle(5, 900, 1) else {
+            panic!("Expected a lockout");
+        };
+        assert!(locked_until > Utc::now() + chrono::Duration::seconds(899));
+        assert_eq!(failures(5, 900).throttle(5, 900, 1), LoginThrottle::Allowed);
+    }
+}
+
+#[cfg(test)]
+mod recovery_code_tests {
+    use super::*;
+
+    #[test]
+    fn test_recovery_code_parse_normalizes_formatting() {
+        let code = RecoveryCode::default();
+        let typed = Secret::new(format!(" {} ", code.formatted().to_uppercase()));
+        let parsed = RecoveryCode::parse(typed).unwrap();
+        assert_eq!(parsed, code);
+        assert_eq!(parsed.hash(), code.hash());
+    }
+
+    #[test]
+    fn test_recovery_code_parse_rejects_invalid_codes() {
+        for code in ["123456", "abcde-fghj", "abcde-fgh0k", "abcde-fghjkm"] {
+            assert!(RecoveryCode::parse(Secret::new(code.to_string())).is_err(), "{code}");
+        }
+    }
+
+    #[test]
+    fn test_recovery_code_hash_is_sha256_hex() {
+        let code = RecoveryCode::parse(Secret::new("abcde-fghjk".to_string())).unwrap();
+        assert_eq!(code.hash().len(), 64);
+        assert_ne!(code.hash(), RecoveryCode::default().hash());
+    }
+}
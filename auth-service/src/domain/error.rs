@@ -4,6 +4,14 @@ use tonic;
 
 #[derive(Debug, Error)]
 pub enum AuthAPIError {
+    #[error("Account locked")]
+    AccountLocked,
+    #[error("Email not verified")]
+    EmailNotVerified,
+    #[error("Invalid client credentials")]
+    InvalidClientCredentials,
+    #[error("Insufficient scope")]
+    InsufficientScope,
     #[error("Invalid credentials")]
     InvalidCredentials,
     #[error("Invalid email")]
@@ -12,29 +20,81 @@ pub enum AuthAPIError {
     InvalidLoginAttemptId,
     #[error("Invalid password")]
     InvalidPassword(#[source] Report),
+    #[error("Invalid phone number")]
+    InvalidPhoneNumber(String),
     #[error("Invalid auth token")]
     InvalidToken,
     #[error("Invalid two factor authentication code")]
     InvalidTwoFactorAuthCode,
+    #[error("Invalid WebAuthn response")]
+    InvalidWebAuthnResponse,
     #[error("Missing auth token")]
     MissingToken,
+    #[error("Phone number not verified")]
+    PhoneNumberNotVerified,
+    #[error("Role not found")]
+    RoleNotFound,
+    #[error("Session not found")]
+    SessionNotFound,
+    #[error("TOTP not enrolled")]
+    TotpNotEnrolled,
+    #[error("Trusted device not found")]
+    TrustedDeviceNotFound,
+    #[error("2FA already enabled")]
+    TwoFAAlreadyEnabled,
+    #[error("2FA not enabled")]
+    TwoFANotEnabled,
+    #[error("Too many failed logins")]
+    TooManyLoginAttempts,
+    #[error("2FA code resent too recently")]
+    TwoFACodeResendCooldown,
+    #[error("2FA code resend limit reached")]
+    TwoFACodeResendLimitReached,
+    #[error("2FA method does not send codes")]
+    TwoFACodeNotResendable,
+    #[error("Phone verification send limit reached")]
+    PhoneVerificationLimitReached,
     #[error("User already exists")]
     UserAlreadyExists,
     #[error("Unexpected error")]
     UnexpectedError(#[source] Report),
     #[error("User not found")]
     UserNotFound,
+    #[error("Unsupported import format")]
+    UnsupportedImportFormat,
+    #[error("WebAuthn credential already exists")]
+    WebAuthnCredentialAlreadyExists,
 }
 
 impl From<AuthAPIError> for tonic::Status {
     fn from(error: AuthAPIError) -> Self {
         match error {
-            AuthAPIError::UserAlreadyExists => tonic::Status::already_exists(error.to_string()),
+            AuthAPIError::UserAlreadyExists | AuthAPIError::WebAuthnCredentialAlreadyExists => {
+                tonic::Status::already_exists(error.to_string())
+            }
             AuthAPIError::InvalidCredentials => tonic::Status::unauthenticated(error.to_string()),
-            AuthAPIError::InvalidEmail(_) | AuthAPIError::InvalidPassword(_) => {
-                tonic::Status::invalid_argument(error.to_string())
+            AuthAPIError::InvalidClientCredentials => tonic::Status::unauthenticated(error.to_string()),
+            AuthAPIError::InsufficientScope => tonic::Status::permission_denied(error.to_string()),
+            AuthAPIError::RoleNotFound => tonic::Status::invalid_argument(error.to_string()),
+            AuthAPIError::TotpNotEnrolled
+            | AuthAPIError::TwoFACodeNotResendable
+            | AuthAPIError::TwoFAAlreadyEnabled
+            | AuthAPIError::TwoFANotEnabled
+            | AuthAPIError::PhoneNumberNotVerified
+            | AuthAPIError::EmailNotVerified => tonic::Status::failed_precondition(error.to_string()),
+            AuthAPIError::AccountLocked
+            | AuthAPIError::TooManyLoginAttempts
+            | AuthAPIError::TwoFACodeResendCooldown
+            | AuthAPIError::TwoFACodeResendLimitReached
+            | AuthAPIError::PhoneVerificationLimitReached => tonic::Status::resource_exhausted(error.to_string()),
+            AuthAPIError::InvalidEmail(_)
+            | AuthAPIError::InvalidPassword(_)
+            | AuthAPIError::InvalidPhoneNumber(_)
+            | AuthAPIError::InvalidWebAuthnResponse
+            | AuthAPIError::UnsupportedImportFormat => tonic::Status::invalid_argument(error.to_string()),
+            AuthAPIError::UserNotFound | AuthAPIError::SessionNotFound | AuthAPIError::TrustedDeviceNotFound => {
+                tonic::Status::not_found(error.to_string())
             }
-            AuthAPIError::UserNotFound => tonic::Status::not_found(error.to_string()),
             AuthAPIError::UnexpectedError(report) => tonic::Status::internal(report.to_string()),
             AuthAPIError::MissingToken => tonic::Status::unauthenticated(error.to_string()),
             AuthAPIError::InvalidToken => tonic::Status::unauthenticated(error.to_string()),
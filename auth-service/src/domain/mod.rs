@@ -3,4 +3,6 @@ pub mod email;
 pub mod email_client;
 pub mod error;
 pub mod password;
+pub mod phone_number;
+pub mod sms_client;
 pub mod user;
@@ -0,0 +1,75 @@
+use std::hash::Hash;
+
+use secrecy::{ExposeSecret, Secret};
+use serde::{ser::SerializeStruct, Serialize};
+
+use macros::SecretString;
+
+/// A phone number in E.164 form, e.g. `+14155552671`.
+#[derive(Clone, Debug, SecretString)]
+pub struct PhoneNumber(Secret<String>);
+
+impl PhoneNumber {
+    /// Accepts common separators (spaces, dashes, dots and parentheses) and stores the number without them.
+    pub fn parse(phone_number: Secret<String>) -> Result<Self, String> {
+        let normalized: String = phone_number
+            .expose_secret()
+            .chars()
+            .filter(|c| !matches!(c, ' ' | '-' | '.' | '(' | ')'))
+            .collect();
+
+        let digits = normalized.strip_prefix('+').unwrap_or_default();
+        let is_e164 =
+            (8..=15).contains(&digits.len()) && digits.chars().all(|c| c.is_ascii_digit()) && !digits.starts_with('0');
+
+        match is_e164 {
+            true => Ok(Self(Secret::new(normalized))),
+            false => Err("Invalid phone number".to_string()),
+        }
+    }
+}
+
+impl Hash for PhoneNumber {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.expose_secret().hash(state)
+    }
+}
+
+impl Eq for PhoneNumber {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(phone_number: &str) -> Result<PhoneNumber, String> {
+        PhoneNumber::parse(Secret::new(phone_number.to_string()))
+    }
+
+    #[test]
+    fn test_valid_phone_numbers() {
+        assert_eq!(parse("+14155552671").unwrap().expose_secret_string(), "+14155552671");
+        assert_eq!(
+            parse("+44 20 7946 0958").unwrap().expose_secret_string(),
+            "+442079460958"
+        );
+        assert_eq!(
+            parse("+1 (415) 555-2671").unwrap().expose_secret_string(),
+            "+14155552671"
+        );
+    }
+
+    #[test]
+    fn test_invalid_phone_numbers() {
+        for phone_number in [
+            "",
+            "+",
+            "4155552671",
+            "+0155552671",
+            "+1415555",
+            "+1415555267123456",
+            "+1415abc2671",
+        ] {
+            assert_eq!(parse(phone_number).unwrap_err(), "Invalid phone number");
+        }
+    }
+}
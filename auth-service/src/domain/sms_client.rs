@@ -0,0 +1,21 @@
+use core::fmt::Debug;
+
+use color_eyre::eyre::Result;
+
+use crate::{domain::data_stores::TwoFACode, utils::constants::Time};
+
+use super::phone_number::PhoneNumber;
+
+#[async_trait::async_trait]
+pub trait SmsClient: Clone + Send + Sync + Debug + 'static {
+    async fn send_sms(&self, recipient: &PhoneNumber, body: &str) -> Result<()>;
+}
+
+/// The text sent for 2FA and phone verification codes.
+pub fn code_message(code: &TwoFACode, expires_in: Time) -> String {
+    format!(
+        "Your verification code is {}. It expires in {}.",
+        code.expose_secret_string(),
+        expires_in.to_string().to_lowercase()
+    )
+}
@@ -1,56 +1,260 @@
-use argon2::{Argon2, PasswordHash, PasswordVerifier};
-use color_eyre::eyre::{Context, Result};
+use std::{fmt, str::FromStr};
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Report, Result};
 use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    domain::{email::Email, password::Password},
-    utils::auth::async_compute_password_hash,
+    domain::{email::Email, password::Password, phone_number::PhoneNumber},
+    utils::{
+        auth::async_compute_password_hash,
+        password_hash::{is_supported_hash, verify_password_hash},
+    },
 };
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct NewUser {
     pub email: Email,
     pub password: Password,
-    pub requires_2fa: bool,
+    pub two_fa_method: TwoFAMethod,
+}
+
+/// A user moved in from another system. `password_hash` is stored as it came, and is replaced with an Argon2id
+/// hash the first time the user logs in.
+#[derive(Clone, Debug)]
+pub struct ImportedUser {
+    pub email: Email,
+    pub password_hash: Secret<String>,
+    pub email_verified: bool,
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct User {
     pub email: Email,
-    pub requires_2fa: bool,
+    pub two_fa_method: TwoFAMethod,
+    pub email_verified: bool,
+}
+
+/// The second factor a user is challenged with after a successful password check.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TwoFAMethod {
+    #[default]
+    None,
+    Email,
+    Totp,
+    Sms,
+}
+
+impl TwoFAMethod {
+    /// Maps the `requires2FA` signup flag. TOTP needs an enrolled secret, so it can't be chosen at signup.
+    pub fn from_requires_2fa(requires_2fa: bool) -> Self {
+        match requires_2fa {
+            true => TwoFAMethod::Email,
+            false => TwoFAMethod::None,
+        }
+    }
+
+    /// Whether the method's codes are generated here and sent to the user, rather than by their device.
+    pub fn sends_codes(&self) -> bool {
+        matches!(self, TwoFAMethod::Email | TwoFAMethod::Sms)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TwoFAMethod::None => "none",
+            TwoFAMethod::Email => "email",
+            TwoFAMethod::Totp => "totp",
+            TwoFAMethod::Sms => "sms",
+        }
+    }
+}
+
+impl fmt::Display for TwoFAMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for TwoFAMethod {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(TwoFAMethod::None),
+            "email" => Ok(TwoFAMethod::Email),
+            "totp" => Ok(TwoFAMethod::Totp),
+            "sms" => Ok(TwoFAMethod::Sms),
+            _ => Err(eyre!("Unknown 2FA method: {s}")),
+        }
+    }
+}
+
+/// A user's authenticator-app secret. `pending_secret` holds a new secret until it is confirmed with a first code.
+#[derive(Clone, Debug, Default)]
+pub struct TotpCredential {
+    pub secret: Option<Secret<String>>,
+    pub pending_secret: Option<Secret<String>>,
+    pub last_used_step: Option<u64>,
+}
+
+/// A user's SMS number. `pending_phone_number` holds a new number until it is confirmed with the code texted for
+/// `pending_verification_id`; only the verified `phone_number` is used for SMS 2FA.
+#[derive(Clone, Debug, Default)]
+pub struct UserPhoneNumber {
+    pub phone_number: Option<PhoneNumber>,
+    pub pending_phone_number: Option<PhoneNumber>,
+    pub pending_verification_id: Option<String>,
+}
+
+/// Something that happened to the user's sign-in credentials, kept for their account history.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AuthEvent {
+    pub kind: AuthEventKind,
+    pub occurred_at: DateTime<Utc>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+impl AuthEvent {
+    pub fn new(kind: AuthEventKind, ip_address: Option<String>, user_agent: Option<String>) -> Self {
+        Self {
+            kind,
+            occurred_at: Utc::now(),
+            ip_address,
+            user_agent,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthEventKind {
+    SignIn,
+    SignInFailed,
+    PasswordChanged,
+    PasswordReset,
+    EmailChanged,
+}
+
+impl AuthEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuthEventKind::SignIn => "sign_in",
+            AuthEventKind::SignInFailed => "sign_in_failed",
+            AuthEventKind::PasswordChanged => "password_changed",
+            AuthEventKind::PasswordReset => "password_reset",
+            AuthEventKind::EmailChanged => "email_changed",
+        }
+    }
+}
+
+impl fmt::Display for AuthEventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for AuthEventKind {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sign_in" => Ok(AuthEventKind::SignIn),
+            "sign_in_failed" => Ok(AuthEventKind::SignInFailed),
+            "password_changed" => Ok(AuthEventKind::PasswordChanged),
+            "password_reset" => Ok(AuthEventKind::PasswordReset),
+            "email_changed" => Ok(AuthEventKind::EmailChanged),
+            _ => Err(eyre!("Unknown auth event: {s}")),
+        }
+    }
+}
+
+/// Role every new user is given. Roles and their scopes live in the `roles` table.
+pub const DEFAULT_ROLE: &str = "user";
+
+/// How many stored password hashes there are, and how many were made under an older hashing policy.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PasswordHashCounts {
+    pub total: u64,
+    pub outdated: u64,
+}
+
+/// The roles a user holds and the union of the scopes those roles grant.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct UserAccess {
+    pub roles: Vec<String>,
+    pub scopes: Vec<String>,
+}
+
+impl UserAccess {
+    pub fn new(roles: Vec<String>, scopes: impl IntoIterator<Item = String>) -> Self {
+        let mut scopes: Vec<String> = scopes.into_iter().collect();
+        scopes.sort();
+        scopes.dedup();
+        Self { roles, scopes }
+    }
+}
+
+/// A registered passkey. `public_key` is the COSE_Key taken from the registration's authenticator data.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WebAuthnCredential {
+    /// Base64url credential id
+    pub id: String,
+    pub name: String,
+    pub public_key: Vec<u8>,
+    pub sign_count: u32,
 }
 
 #[derive(Clone, Debug)]
 pub struct DbUser {
     pub email: Secret<String>,
     pub password_hash: Secret<String>,
-    pub requires_2fa: bool,
+    pub two_fa_method: String,
+    pub email_verified: bool,
 }
 
 impl NewUser {
-    pub fn new(email: Email, password: Password, requires_2fa: bool) -> Self {
+    pub fn new(email: Email, password: Password, two_fa_method: TwoFAMethod) -> Self {
         Self {
             email,
             password,
-            requires_2fa,
+            two_fa_method,
         }
     }
 }
 
+impl ImportedUser {
+    pub fn parse(email: Email, password_hash: Secret<String>, email_verified: bool) -> Result<Self> {
+        if !is_supported_hash(password_hash.expose_secret()) {
+            return Err(eyre!("Unsupported password hash format"));
+        }
+        Ok(Self {
+            email,
+            password_hash,
+            email_verified,
+        })
+    }
+}
+
 impl DbUser {
     #[tracing::instrument(name = "Verify User Password", skip_all)]
-    pub fn verify_password(&self, password_attempt: &Password) -> Result<()> {
-        let parsed_hash = PasswordHash::new(self.password_hash.expose_secret())?;
-
-        Argon2::default()
-            .verify_password(password_attempt.as_ref().expose_secret().as_bytes(), &parsed_hash)
-            .wrap_err("Failed to verify password hash")
+    pub fn verify_password(&self, password_attempt: &Secret<String>) -> Result<()> {
+        verify_password_hash(
+            self.password_hash.expose_secret(),
+            password_attempt.expose_secret().as_bytes(),
+        )
     }
 
     pub fn to_user(&self) -> User {
         User {
             email: Email::parse(self.email.clone()).expect("[ERROR] Invalid email in database"),
-            requires_2fa: self.requires_2fa,
+            two_fa_method: self
+                .two_fa_method
+                .parse()
+                .expect("[ERROR] Invalid 2FA method in database"),
+            email_verified: self.email_verified,
         }
     }
 
@@ -71,7 +275,10 @@ impl PartialEq for DbUser {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use argon2::password_hash::{PasswordHasher, SaltString};
+    use argon2::{
+        password_hash::{PasswordHasher, SaltString},
+        Argon2,
+    };
     use color_eyre::eyre;
 
     fn str_to_valid_email(email: &str) -> Email {
@@ -103,13 +310,13 @@ mod tests {
     async fn test_new_user_creation() {
         let email = str_to_valid_email("test@example.com");
         let password = str_to_valid_password("P@ssw0rd123").await;
-        let requires_2fa = true;
+        let two_fa_method = TwoFAMethod::Email;
 
-        let new_user = NewUser::new(email.clone(), password.clone(), requires_2fa);
+        let new_user = NewUser::new(email.clone(), password.clone(), two_fa_method);
 
         assert_eq!(new_user.email, email);
         assert_eq!(new_user.password, password);
-        assert_eq!(new_user.requires_2fa, requires_2fa);
+        assert_eq!(new_user.two_fa_method, two_fa_method);
     }
 
     #[tokio::test]
@@ -120,10 +327,11 @@ mod tests {
         let db_user = DbUser {
             email: str_to_email_secret("test@example.com"),
             password_hash,
-            requires_2fa: false,
+            two_fa_method: "none".to_string(),
+            email_verified: false,
         };
 
-        let password_attempt = Password::parse(Secret::new(password.to_string())).await.unwrap();
+        let password_attempt = Secret::new(password.to_string());
         assert!(db_user.verify_password(&password_attempt).is_ok());
     }
 
@@ -135,10 +343,11 @@ mod tests {
         let db_user = DbUser {
             email: str_to_email_secret("test@example.com"),
             password_hash,
-            requires_2fa: false,
+            two_fa_method: "none".to_string(),
+            email_verified: false,
         };
 
-        let wrong_password = Password::parse(Secret::new("Wr0ngP@ssw0rd".to_string())).await.unwrap();
+        let wrong_password = Secret::new("Wr0ngP@ssw0rd".to_string());
         let result = db_user.verify_password(&wrong_password);
 
         println!("Verify Password Result: {:?}", result);
@@ -150,13 +359,15 @@ mod tests {
         let db_user = DbUser {
             email: str_to_email_secret("test@example.com"),
             password_hash: Secret::new("some_hash".to_string()),
-            requires_2fa: true,
+            two_fa_method: "totp".to_string(),
+            email_verified: true,
         };
 
         let user = db_user.to_user();
 
         assert_eq!(user.email.as_ref().expose_secret(), "test@example.com");
-        assert_eq!(user.requires_2fa, true);
+        assert_eq!(user.two_fa_method, TwoFAMethod::Totp);
+        assert!(user.email_verified);
     }
 
     #[tokio::test]
@@ -165,9 +376,59 @@ mod tests {
         let db_user = DbUser {
             email: Secret::new("invalid_email".to_string()),
             password_hash: Secret::new("some_hash".to_string()),
-            requires_2fa: false,
+            two_fa_method: "none".to_string(),
+            email_verified: false,
         };
 
         db_user.to_user();
     }
+
+    #[test]
+    fn test_two_fa_method_round_trips_through_str() {
+        for method in [
+            TwoFAMethod::None,
+            TwoFAMethod::Email,
+            TwoFAMethod::Totp,
+            TwoFAMethod::Sms,
+        ] {
+            assert_eq!(method.as_str().parse::<TwoFAMethod>().unwrap(), method);
+        }
+        assert!("push".parse::<TwoFAMethod>().is_err());
+    }
+
+    #[test]
+    fn test_user_access_dedups_scopes() {
+        let access = UserAccess::new(
+            vec!["admin".to_string(), "user".to_string()],
+            ["users:self", "users:admin", "users:self"].map(String::from),
+        );
+
+        assert_eq!(access.roles, vec!["admin", "user"]);
+        assert_eq!(access.scopes, vec!["users:admin", "users:self"]);
+    }
+
+    #[tokio::test]
+    async fn test_db_user_verify_legacy_password_hash() {
+        let db_user = DbUser {
+            email: str_to_email_secret("test@example.com"),
+            password_hash: Secret::new(bcrypt::hash("P@ssw0rd123", 4).unwrap()),
+            two_fa_method: "none".to_string(),
+            email_verified: false,
+        };
+
+        assert!(db_user.verify_password(&Secret::new("P@ssw0rd123".to_string())).is_ok());
+        assert!(db_user
+            .verify_password(&Secret::new("Wr0ngP@ssw0rd".to_string()))
+            .is_err());
+    }
+
+    #[test]
+    fn test_imported_user_requires_supported_hash() {
+        let email = str_to_valid_email("test@example.com");
+
+        let hash = Secret::new(bcrypt::hash("P@ssw0rd123", 4).unwrap());
+        assert!(ImportedUser::parse(email.clone(), hash, true).is_ok());
+        let hash = Secret::new("P@ssw0rd123".to_string());
+        assert!(ImportedUser::parse(email, hash, true).is_err());
+    }
 }
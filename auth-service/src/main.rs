@@ -3,28 +3,57 @@ use std::{ops::Deref, sync::Arc};
 use redis::aio::ConnectionManager;
 use reqwest::Client;
 use sqlx::PgPool;
-use tokio::sync::RwLock;
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    sync::RwLock,
+    time::{interval, Duration},
+};
 
 use auth_service::{
-    domain::email::Email,
+    domain::{data_stores::UserStore, email::Email, phone_number::PhoneNumber},
     get_postgres_pool, get_redis_client,
     services::{
         app_state::AppState,
         concrete_app_services::PersistentAppStateType,
         data_stores::{
-            postgres_user_store::PostgresUserStore, redis_banned_token_store::RedisBannedTokenStore,
+            postgres_user_store::PostgresUserStore,
+            redis_banned_token_store::RedisBannedTokenStore,
+            redis_login_failure_store::RedisLoginFailureStore,
             redis_password_reset_token_store::RedisPasswordResetTokenStore,
-            redis_two_fa_code_store::RedisTwoFACodeStore,
+            redis_refresh_token_store::RedisRefreshTokenStore,
+            redis_session_store::RedisSessionStore,
+            redis_trusted_device_store::RedisTrustedDeviceStore,
+            redis_two_fa_code_store::{RedisTwoFACodeStore, PHONE_VERIFICATION_NAMESPACE},
+            redis_webauthn_challenge_store::RedisWebAuthnChallengeStore,
         },
         postmark_email_client::PostmarkEmailClient,
+        twilio_sms_client::TwilioSmsClient,
     },
     utils::{
-        constants::{prod, DATABASE_URL, POSTMARK_AUTH_TOKEN, REDIS_HOST_NAME, REDIS_PASSWORD},
+        auth::{init_signing_key, reload_key_ring},
+        constants::{
+            prod, Time, ACCOUNT_DELETION_GRACE_DAYS, DATABASE_URL, INTROSPECTION_CLIENT_SECRET, JWT_KEY_RING_PATH,
+            POSTMARK_AUTH_TOKEN, REDIS_HOST_NAME, REDIS_PASSWORD, TWILIO_ACCOUNT_SID, TWILIO_AUTH_TOKEN,
+            TWILIO_PHONE_NUMBER,
+        },
         tracing::init_tracing,
     },
     GRPCApp, RESTApp,
 };
 
+/// Required settings are read lazily, so one that is missing would otherwise only panic on the first request that
+/// needs it. `JWT_SECRET` is left out as it is only needed without an asymmetric key, which `init_signing_key`
+/// checks.
+fn init_required_env_vars() {
+    lazy_static::initialize(&DATABASE_URL);
+    lazy_static::initialize(&INTROSPECTION_CLIENT_SECRET);
+    lazy_static::initialize(&POSTMARK_AUTH_TOKEN);
+    lazy_static::initialize(&REDIS_PASSWORD);
+    lazy_static::initialize(&TWILIO_ACCOUNT_SID);
+    lazy_static::initialize(&TWILIO_AUTH_TOKEN);
+    lazy_static::initialize(&TWILIO_PHONE_NUMBER);
+}
+
 #[tracing::instrument(name = "Configure PostgreSQL")]
 async fn configure_postgresql() -> PgPool {
     #[allow(clippy::to_string_in_format_args, clippy::unnecessary_to_owned)]
@@ -63,6 +92,55 @@ fn configure_postmark_email_client() -> PostmarkEmailClient {
     )
 }
 
+fn configure_twilio_sms_client() -> TwilioSmsClient {
+    let http_client = Client::builder()
+        .timeout(prod::sms_client::TIMEOUT)
+        .build()
+        .expect("Failed to build HTTP client");
+
+    TwilioSmsClient::new(
+        prod::sms_client::BASE_URL.to_owned(),
+        PhoneNumber::parse(TWILIO_PHONE_NUMBER.to_owned().into()).expect("TWILIO_PHONE_NUMBER must be in E.164 form."),
+        TWILIO_ACCOUNT_SID.to_owned(),
+        TWILIO_AUTH_TOKEN.to_owned(),
+        http_client,
+    )
+}
+
+/// Operators rotate signing keys by editing the key ring file and sending SIGHUP.
+fn spawn_key_ring_reloader() {
+    if JWT_KEY_RING_PATH.is_none() {
+        return;
+    }
+    tokio::spawn(async {
+        let mut hangups = signal(SignalKind::hangup()).expect("Failed to listen for SIGHUP");
+        while hangups.recv().await.is_some() {
+            if let Err(e) = reload_key_ring() {
+                tracing::error!("Failed to reload key ring: {:?}", e);
+            }
+        }
+    });
+}
+
+/// Removes accounts whose deletion grace period has run out. Nothing to do when deletions are immediate.
+fn spawn_deleted_user_purger(user_store: Arc<RwLock<PostgresUserStore>>) {
+    if *ACCOUNT_DELETION_GRACE_DAYS == 0 {
+        return;
+    }
+    let grace_period_seconds = u64::from(*ACCOUNT_DELETION_GRACE_DAYS) * Time::Hours24 as u64;
+    tokio::spawn(async move {
+        let mut ticks = interval(Duration::from_secs(3600));
+        loop {
+            ticks.tick().await;
+            match user_store.write().await.purge_deleted_users(grace_period_seconds).await {
+                Ok(0) => {}
+                Ok(purged) => tracing::info!("Purged {purged} deleted accounts"),
+                Err(e) => tracing::error!("Failed to purge deleted accounts: {:?}", e),
+            }
+        }
+    });
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     color_eyre::install().expect("Failed to install color_eyre");
@@ -71,6 +149,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     tracing::info!("Tracing initialized successfully");
     tracing::info!("Starting auth service");
 
+    init_required_env_vars();
+    init_signing_key();
+    spawn_key_ring_reloader();
+
     let pg_pool = configure_postgresql().await;
     let redis_conn = configure_redis().await;
 
@@ -78,10 +160,19 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         RedisBannedTokenStore::new(redis_conn.clone()),
         PostgresUserStore::new(pg_pool),
         RedisTwoFACodeStore::new(redis_conn.clone()),
+        RedisTwoFACodeStore::with_namespace(redis_conn.clone(), PHONE_VERIFICATION_NAMESPACE),
         configure_postmark_email_client(),
         RedisPasswordResetTokenStore::new(redis_conn.clone()),
+        RedisRefreshTokenStore::new(redis_conn.clone()),
+        RedisSessionStore::new(redis_conn.clone()),
+        RedisWebAuthnChallengeStore::new(redis_conn.clone()),
+        RedisTrustedDeviceStore::new(redis_conn.clone()),
+        configure_twilio_sms_client(),
+        RedisLoginFailureStore::new(redis_conn.clone()),
     );
 
+    spawn_deleted_user_purger(app_state.user_store.clone());
+
     let address = prod::APP_GRPC_ADDRESS.to_string();
     let grpc_app = GRPCApp::new(app_state.clone(), address)
         .await
@@ -0,0 +1,344 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    response::{Html, IntoResponse},
+    Json,
+};
+use axum_extra::extract::{cookie, CookieJar};
+use chrono::Utc;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::api::extractors::{AuthenticatedUser, ClientInfo};
+use crate::domain::{
+    data_stores::{BannedTokenStore, LoginFailureStore, SessionStore, TrustedDeviceStore, UserStore, UserStoreError},
+    email::Email,
+    email_client::EmailClient,
+    error::AuthAPIError,
+    user::{AuthEvent, AuthEventKind},
+};
+use crate::routes::{
+    change_email::clear_login_state,
+    phone::PhoneNumberResponse,
+    sessions::SessionResponse,
+    trusted_devices::TrustedDeviceResponse,
+    two_fa_settings::{get_two_fa_method, reauthenticate, Reauthentication},
+};
+use crate::services::app_state::{AppServices, AppState};
+use crate::services::postmark_email_client::PostmarkTemplate;
+use crate::utils::{
+    auth::{validate_email_token, AccountRestoreToken, TokenPurpose},
+    constants::{Time, ACCOUNT_DELETION_GRACE_DAYS, JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME},
+};
+
+#[derive(Debug, Deserialize)]
+pub struct AccountTokenRequest {
+    token: Secret<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct AccountResponse {
+    pub message: String,
+}
+
+/// Everything held about a user, for data-access requests. Secrets such as the password hash, TOTP secret and
+/// recovery code hashes are left out.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountExport {
+    pub exported_at: String,
+    pub email: String,
+    pub email_verified: bool,
+    pub roles: Vec<String>,
+    pub scopes: Vec<String>,
+    pub two_factor: TwoFactorExport,
+    /// Only the sessions still signed in. Past sign-ins and other account activity are in `auth_events`.
+    pub sessions: Vec<SessionResponse>,
+    pub trusted_devices: Vec<TrustedDeviceResponse>,
+    pub auth_events: Vec<AuthEventResponse>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthEventResponse {
+    pub kind: AuthEventKind,
+    pub occurred_at: String,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+impl From<AuthEvent> for AuthEventResponse {
+    fn from(event: AuthEvent) -> Self {
+        Self {
+            kind: event.kind,
+            occurred_at: event.occurred_at.to_rfc3339(),
+            ip_address: event.ip_address,
+            user_agent: event.user_agent,
+        }
+    }
+}
+
+/// Adds an entry to the user's account history. The history is informational, so a failure to record it is
+/// logged rather than failing the request, and unknown addresses are skipped.
+pub async fn record_auth_event<S: AppServices>(
+    state: &AppState<S>,
+    email: &Email,
+    kind: AuthEventKind,
+    client: &ClientInfo,
+) {
+    let event = AuthEvent::new(kind, client.ip_address.clone(), client.user_agent.clone());
+    match state.user_store.write().await.add_auth_event(email, event).await {
+        Ok(()) | Err(UserStoreError::UserNotFound) => {}
+        Err(e) => tracing::error!("Error recording {kind} auth event: {e:?}"),
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TwoFactorExport {
+    pub method: String,
+    pub totp_enabled: bool,
+    pub recovery_codes_remaining: usize,
+    pub webauthn_credentials: Vec<WebAuthnCredentialExport>,
+    pub phone: PhoneNumberResponse,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct WebAuthnCredentialExport {
+    pub id: String,
+    pub name: String,
+}
+
+#[tracing::instrument(name = "Account Export GET Request", skip_all)]
+pub async fn get_export<S: AppServices>(
+    State(state): State<Arc<AppState<S>>>,
+    user: AuthenticatedUser,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let map_user_store_error = |e: UserStoreError| match e {
+        UserStoreError::UserNotFound => AuthAPIError::UserNotFound,
+        _ => AuthAPIError::UnexpectedError(e.into()),
+    };
+
+    let user_store = state.user_store.read().await;
+    let db_user = user_store.get_user(&user.email).await.map_err(map_user_store_error)?;
+    let access = user_store.get_access(&user.email).await.map_err(map_user_store_error)?;
+    let totp_credential = user_store
+        .get_totp_credential(&user.email)
+        .await
+        .map_err(map_user_store_error)?;
+    let recovery_codes_remaining = user_store
+        .count_recovery_codes(&user.email)
+        .await
+        .map_err(map_user_store_error)?;
+    let webauthn_credentials = user_store
+        .get_webauthn_credentials(&user.email)
+        .await
+        .map_err(map_user_store_error)?;
+    let phone_number = user_store
+        .get_phone_number(&user.email)
+        .await
+        .map_err(map_user_store_error)?;
+    let auth_events = user_store
+        .get_auth_events(&user.email)
+        .await
+        .map_err(map_user_store_error)?;
+    drop(user_store);
+
+    let sessions = state
+        .session_store
+        .read()
+        .await
+        .get_sessions(&user.email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    let trusted_devices = state
+        .trusted_device_store
+        .read()
+        .await
+        .get_devices(&user.email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let export = AccountExport {
+        exported_at: Utc::now().to_rfc3339(),
+        email: db_user.email.as_ref().expose_secret().to_string(),
+        email_verified: db_user.email_verified,
+        roles: access.roles,
+        scopes: access.scopes,
+        two_factor: TwoFactorExport {
+            method: db_user.two_fa_method.to_string(),
+            totp_enabled: totp_credential.secret.is_some(),
+            recovery_codes_remaining,
+            webauthn_credentials: webauthn_credentials
+                .into_iter()
+                .map(|credential| WebAuthnCredentialExport {
+                    id: credential.id,
+                    name: credential.name,
+                })
+                .collect(),
+            phone: PhoneNumberResponse::from(phone_number),
+        },
+        sessions: sessions
+            .into_iter()
+            .map(|session| SessionResponse::new(session, user.session_id.as_ref()))
+            .collect(),
+        trusted_devices: trusted_devices
+            .into_iter()
+            .map(|device| TrustedDeviceResponse::new(device, None))
+            .collect(),
+        auth_events: auth_events.into_iter().map(AuthEventResponse::from).collect(),
+    };
+
+    let headers = [(
+        header::CONTENT_DISPOSITION,
+        "attachment; filename=\"account-export.json\"",
+    )];
+    Ok((StatusCode::OK, headers, Json(export)))
+}
+
+/// Deletes the signed-in user's account. With a grace period configured the account is only hidden, and a link
+/// to restore it is emailed; otherwise it is removed straight away.
+#[tracing::instrument(name = "Account DELETE Request", skip_all)]
+pub async fn delete<S: AppServices>(
+    State(state): State<Arc<AppState<S>>>,
+    jar: CookieJar,
+    user: AuthenticatedUser,
+    Json(request): Json<Reauthentication>,
+) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
+    let current_method = get_two_fa_method(&state, &user.email).await?;
+    reauthenticate(&state, &user.email, current_method, request).await?;
+
+    let grace_days = *ACCOUNT_DELETION_GRACE_DAYS;
+    let mut user_store = state.user_store.write().await;
+    let result = match grace_days {
+        0 => user_store.delete_user(&user.email).await,
+        _ => user_store.schedule_deletion(&user.email).await,
+    };
+    result.map_err(|e| match e {
+        UserStoreError::UserNotFound => AuthAPIError::UserNotFound,
+        _ => AuthAPIError::UnexpectedError(e.into()),
+    })?;
+    drop(user_store);
+
+    clear_login_state(&state, &user.email).await?;
+
+    // The change has already been made, so a failed notification is logged rather than reported to the client
+    let message = match grace_days {
+        0 => {
+            tracing::info!("Account deleted");
+            if let Err(e) = state
+                .email_client
+                .send_email(&user.email, PostmarkTemplate::AccountDeleted)
+                .await
+            {
+                tracing::error!("Error sending account deleted notification: {e:?}");
+            }
+            "Account deleted.".to_string()
+        }
+        _ => {
+            tracing::info!("Account scheduled for deletion");
+            let ttl_seconds = u64::from(grace_days) * Time::Hours24 as u64;
+            match AccountRestoreToken::new(&user.email, ttl_seconds) {
+                Ok(token) => {
+                    let template_model = PostmarkTemplate::AccountDeletionScheduled(grace_days, token);
+                    if let Err(e) = state.email_client.send_email(&user.email, template_model).await {
+                        tracing::error!("Error sending account deletion notification: {e:?}");
+                    }
+                }
+                Err(e) => tracing::error!("Error generating account restore token: {e:?}"),
+            }
+            format!("Account will be deleted in {grace_days} days. Use the emailed link to restore it before then.")
+        }
+    };
+
+    let jar = jar
+        .remove(cookie::Cookie::from(JWT_COOKIE_NAME))
+        .remove(cookie::Cookie::from(REFRESH_TOKEN_COOKIE_NAME));
+
+    Ok((jar, (StatusCode::OK, Json(AccountResponse { message }))))
+}
+
+/// Serves the page behind the restore and unlock links. As with the email change links, opening one changes
+/// nothing; the page posts the token back once the user confirms.
+pub async fn get() -> impl IntoResponse {
+    Html(include_str!("../../assets/index.html"))
+}
+
+/// Posted from the link sent when a deletion was scheduled. Brings the account back as it was, minus the
+/// sessions that were ended at deletion.
+#[tracing::instrument(name = "Account Restore POST Request", skip_all)]
+pub async fn post_restore<S: AppServices>(
+    State(state): State<Arc<AppState<S>>>,
+    Json(request): Json<AccountTokenRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = validate_email_token(
+        state.banned_token_store.clone(),
+        request.token.clone(),
+        TokenPurpose::AccountRestore,
+    )
+    .await
+    .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    state
+        .user_store
+        .write()
+        .await
+        .restore_user(&email)
+        .await
+        .map_err(|e| match e {
+            // Already restored, or purged once the grace period ran out
+            UserStoreError::UserNotFound => AuthAPIError::InvalidToken,
+            _ => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+    state
+        .banned_token_store
+        .write()
+        .await
+        .add_token(request.token)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    tracing::info!("Account restored");
+
+    let response = AccountResponse {
+        message: "Account restored successfully.".to_string(),
+    };
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// Posted from the link sent when repeated failed logins locked the account. Lifts the lockout early.
+#[tracing::instrument(name = "Account Unlock POST Request", skip_all)]
+pub async fn post_unlock<S: AppServices>(
+    State(state): State<Arc<AppState<S>>>,
+    Json(request): Json<AccountTokenRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = validate_email_token(
+        state.banned_token_store.clone(),
+        request.token.clone(),
+        TokenPurpose::AccountUnlock,
+    )
+    .await
+    .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    state
+        .login_failure_store
+        .write()
+        .await
+        .clear_failures(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    state
+        .banned_token_store
+        .write()
+        .await
+        .add_token(request.token)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    tracing::info!("Account unlocked");
+
+    let response = AccountResponse {
+        message: "Account unlocked. If you did not try to log in, reset your password.".to_string(),
+    };
+    Ok((StatusCode::OK, Json(response)))
+}
@@ -0,0 +1,234 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+
+use crate::api::extractors::{scopes::UsersAdmin, RequireScope};
+use crate::domain::{
+    data_stores::{LoginFailureStore, UserStore, UserStoreError},
+    email::Email,
+    error::AuthAPIError,
+    user::ImportedUser,
+};
+use crate::services::app_state::{AppServices, AppState};
+
+#[derive(Debug, Deserialize)]
+pub struct SetUserRolesRequest {
+    pub email: Secret<String>,
+    pub roles: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UnlockUserRequest {
+    pub email: Secret<String>,
+}
+
+/// One user to import, as a JSON line or a CSV row with `email,passwordHash,emailVerified` headers.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportUserRecord {
+    pub email: Secret<String>,
+    pub password_hash: Secret<String>,
+    #[serde(default)]
+    pub email_verified: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct ImportUsersResponse {
+    pub imported: usize,
+    pub skipped: Vec<SkippedImport>,
+}
+
+/// A record that wasn't imported. `line` counts from 1, and for CSV includes the header line.
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct SkippedImport {
+    pub line: u64,
+    pub error: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct PasswordHashesResponse {
+    pub total: u64,
+    pub outdated: u64,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct UserRolesResponse {
+    pub roles: Vec<String>,
+    pub scopes: Vec<String>,
+}
+
+/// The new roles show up in the user's tokens from their next login or refresh.
+#[tracing::instrument(name = "Set User Roles PUT Request", skip_all)]
+pub async fn put_user_roles<S: AppServices>(
+    State(state): State<Arc<AppState<S>>>,
+    _: RequireScope<UsersAdmin>,
+    Json(request): Json<SetUserRolesRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = Email::parse(request.email).map_err(AuthAPIError::InvalidEmail)?;
+
+    let mut user_store = state.user_store.write().await;
+    user_store
+        .set_roles(&email, &request.roles)
+        .await
+        .map_err(|e| match e {
+            UserStoreError::UserNotFound => AuthAPIError::UserNotFound,
+            UserStoreError::RoleNotFound => AuthAPIError::RoleNotFound,
+            _ => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+    let access = user_store
+        .get_access(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let response = UserRolesResponse {
+        roles: access.roles,
+        scopes: access.scopes,
+    };
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// Lifts a lockout from repeated failed logins before it runs out.
+#[tracing::instrument(name = "Unlock User POST Request", skip_all)]
+pub async fn post_unlock_user<S: AppServices>(
+    State(state): State<Arc<AppState<S>>>,
+    _: RequireScope<UsersAdmin>,
+    Json(request): Json<UnlockUserRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = Email::parse(request.email).map_err(AuthAPIError::InvalidEmail)?;
+
+    state
+        .user_store
+        .read()
+        .await
+        .get_user(&email)
+        .await
+        .map_err(|e| match e {
+            UserStoreError::UserNotFound => AuthAPIError::UserNotFound,
+            _ => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+    state
+        .login_failure_store
+        .write()
+        .await
+        .clear_failures(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    tracing::info!("Account unlocked by admin");
+
+    Ok(StatusCode::OK)
+}
+
+/// How many stored password hashes still use older Argon2 settings or a legacy algorithm. Each is upgraded the
+/// next time its owner logs in, so the count shows how far a cost change or an import has rolled out.
+#[tracing::instrument(name = "Password Hashes GET Request", skip_all)]
+pub async fn get_password_hashes<S: AppServices>(
+    State(state): State<Arc<AppState<S>>>,
+    _: RequireScope<UsersAdmin>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let counts = state
+        .user_store
+        .read()
+        .await
+        .count_password_hashes()
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let response = PasswordHashesResponse {
+        total: counts.total,
+        outdated: counts.outdated,
+    };
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// Adds users moved in from another system, keeping their bcrypt, PBKDF2, scrypt or Argon2 hashes as they are.
+/// Records that can't be imported, including users who already exist, are skipped and reported, so a file can
+/// be sent again after fixing them. Bodies are capped at axum's default 2 MB, so large exports go in batches.
+#[tracing::instrument(name = "Import Users POST Request", skip_all)]
+pub async fn post_import_users<S: AppServices>(
+    State(state): State<Arc<AppState<S>>>,
+    _: RequireScope<UsersAdmin>,
+    headers: HeaderMap,
+    body: String,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let records = match content_type.split(';').next().unwrap_or_default().trim() {
+        "text/csv" => parse_csv_records(&body),
+        "application/x-ndjson" | "application/jsonl" => parse_jsonl_records(&body),
+        _ => return Err(AuthAPIError::UnsupportedImportFormat),
+    };
+
+    let mut imported = 0;
+    let mut skipped = Vec::new();
+    for (line, record) in records {
+        match import_user(&state, record).await {
+            Ok(()) => imported += 1,
+            Err(error) => skipped.push(SkippedImport { line, error }),
+        }
+    }
+    tracing::info!(imported, skipped = skipped.len(), "Users imported");
+
+    Ok((StatusCode::OK, Json(ImportUsersResponse { imported, skipped })))
+}
+
+async fn import_user<S: AppServices>(
+    state: &AppState<S>,
+    record: Result<ImportUserRecord, String>,
+) -> Result<(), String> {
+    let record = record?;
+    let email = Email::parse(record.email)?;
+    let user = ImportedUser::parse(email, record.password_hash, record.email_verified).map_err(|e| e.to_string())?;
+
+    state
+        .user_store
+        .write()
+        .await
+        .import_user(user)
+        .await
+        .map_err(|e| match e {
+            UserStoreError::UserAlreadyExists => "User already exists".to_string(),
+            _ => {
+                tracing::error!("Error importing user: {e:?}");
+                "An unexpected error occurred".to_string()
+            }
+        })
+}
+
+fn parse_jsonl_records(body: &str) -> Vec<(u64, Result<ImportUserRecord, String>)> {
+    body.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| (index as u64 + 1, serde_json::from_str(line).map_err(|e| e.to_string())))
+        .collect()
+}
+
+fn parse_csv_records(body: &str) -> Vec<(u64, Result<ImportUserRecord, String>)> {
+    let mut reader = csv::Reader::from_reader(body.as_bytes());
+    let headers = match reader.headers() {
+        Ok(headers) => headers.clone(),
+        Err(e) => return vec![(1, Err(e.to_string()))],
+    };
+    reader
+        .records()
+        .map(|result| {
+            let position = match &result {
+                Ok(record) => record.position(),
+                Err(e) => e.position(),
+            };
+            let line = position.map(|position| position.line()).unwrap_or_default();
+            let record = result
+                .and_then(|record| record.deserialize(Some(&headers)))
+                .map_err(|e| e.to_string());
+            (line, record)
+        })
+        .collect()
+}
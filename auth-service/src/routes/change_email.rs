@@ -0,0 +1,213 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{Html, IntoResponse},
+    Json,
+};
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+
+use crate::api::extractors::{AuthenticatedUser, ClientInfo};
+use crate::domain::{
+    data_stores::{
+        BannedTokenStore, PasswordResetTokenStore, TokenStoreError, TrustedDeviceStore, TwoFACodeStore, UserStore,
+        UserStoreError,
+    },
+    email::Email,
+    email_client::EmailClient,
+    error::AuthAPIError,
+    user::AuthEventKind,
+};
+use crate::routes::{account::record_auth_event, login::check_password};
+use crate::services::app_state::{AppServices, AppState};
+use crate::services::postmark_email_client::PostmarkTemplate;
+use crate::utils::{
+    auth::{revoke_all_sessions, validate_email_change_token, EmailChangeRevertToken, EmailChangeToken, TokenPurpose},
+    constants::Time,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct ChangeEmailRequest {
+    #[serde(rename = "newEmail")]
+    new_email: Secret<String>,
+    password: Secret<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangeEmailTokenRequest {
+    token: Secret<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct ChangeEmailResponse {
+    pub message: String,
+}
+
+/// Starts a change of the signed-in user's address. Nothing changes until the link sent to the new address
+/// is opened.
+#[tracing::instrument(name = "Change Email POST Request", skip_all)]
+pub async fn post<S: AppServices>(
+    State(state): State<Arc<AppState<S>>>,
+    user: AuthenticatedUser,
+    Json(request): Json<ChangeEmailRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let new_email = Email::parse(request.new_email).map_err(AuthAPIError::InvalidEmail)?;
+
+    check_password(&state, &user.email, &request.password).await?;
+    if state.user_store.read().await.get_user(&new_email).await.is_ok() {
+        return Err(AuthAPIError::UserAlreadyExists);
+    }
+
+    let token = EmailChangeToken::new(&user.email, &new_email).map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    state
+        .email_client
+        .send_email(&new_email, PostmarkTemplate::EmailChange(Time::Hours24, token))
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.wrap_err("Failed to send email change confirmation")))?;
+
+    let response = ChangeEmailResponse {
+        message: "A confirmation link has been sent to the new address.".to_string(),
+    };
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// Serves the page behind the confirm and revert links. Opening a link changes nothing, so mail scanners that
+/// follow links can't; the page posts the token back once the user confirms.
+pub async fn get() -> impl IntoResponse {
+    Html(include_str!("../../assets/index.html"))
+}
+
+/// Posted from the link sent to the new address. Signs the user out everywhere and sends the previous address
+/// a link to undo the change.
+#[tracing::instrument(name = "Confirm Email Change POST Request", skip_all)]
+pub async fn post_confirm<S: AppServices>(
+    State(state): State<Arc<AppState<S>>>,
+    client: ClientInfo,
+    Json(request): Json<ChangeEmailTokenRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let (email, new_email) = validate_email_change_token(
+        state.banned_token_store.clone(),
+        request.token.clone(),
+        TokenPurpose::EmailChange,
+    )
+    .await
+    .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    move_account(&state, &email, &new_email, request.token).await?;
+    // Opening the link proved the new address is theirs
+    state
+        .user_store
+        .write()
+        .await
+        .mark_email_verified(&new_email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    tracing::info!("Email changed");
+    record_auth_event(&state, &new_email, AuthEventKind::EmailChanged, &client).await;
+
+    // The change has already been made, so a failed notification is logged rather than reported to the client
+    match EmailChangeRevertToken::new(&new_email, &email) {
+        Ok(token) => {
+            let template_model = PostmarkTemplate::EmailChangeRevert(Time::Days7, token);
+            if let Err(e) = state.email_client.send_email(&email, template_model).await {
+                tracing::error!("Error sending email change notification: {e:?}");
+            }
+        }
+        Err(e) => tracing::error!("Error generating email change revert token: {e:?}"),
+    }
+
+    let response = ChangeEmailResponse {
+        message: "Email changed successfully.".to_string(),
+    };
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// Posted from the link sent to the previous address. Moves the account back and signs out whoever made the
+/// change.
+#[tracing::instrument(name = "Revert Email Change POST Request", skip_all)]
+pub async fn post_revert<S: AppServices>(
+    State(state): State<Arc<AppState<S>>>,
+    client: ClientInfo,
+    Json(request): Json<ChangeEmailTokenRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let (email, previous_email) = validate_email_change_token(
+        state.banned_token_store.clone(),
+        request.token.clone(),
+        TokenPurpose::EmailChangeRevert,
+    )
+    .await
+    .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    move_account(&state, &email, &previous_email, request.token).await?;
+    tracing::info!("Email change reverted");
+    record_auth_event(&state, &previous_email, AuthEventKind::EmailChanged, &client).await;
+
+    let response = ChangeEmailResponse {
+        message: "Email change reverted. Reset your password if you did not make the change.".to_string(),
+    };
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// Moves the account and uses up the token. The Redis state kept under the old address is dropped rather than
+/// moved, since it only holds short-lived login state.
+async fn move_account<S: AppServices>(
+    state: &AppState<S>,
+    email: &Email,
+    new_email: &Email,
+    token: Secret<String>,
+) -> Result<(), AuthAPIError> {
+    state
+        .user_store
+        .write()
+        .await
+        .change_email(email, new_email)
+        .await
+        .map_err(|e| match e {
+            // Already moved by this link or another one
+            UserStoreError::UserNotFound => AuthAPIError::InvalidToken,
+            UserStoreError::UserAlreadyExists => AuthAPIError::UserAlreadyExists,
+            _ => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+    state
+        .banned_token_store
+        .write()
+        .await
+        .add_token(token)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    clear_login_state(state, email).await
+}
+
+/// Signs the address out everywhere and drops the short-lived login state kept under it: trusted devices,
+/// pending 2FA codes and any password reset token.
+pub async fn clear_login_state<S: AppServices>(state: &AppState<S>, email: &Email) -> Result<(), AuthAPIError> {
+    revoke_all_sessions(
+        state.banned_token_store.clone(),
+        state.session_store.clone(),
+        state.refresh_token_store.clone(),
+        email,
+    )
+    .await
+    .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    state
+        .trusted_device_store
+        .write()
+        .await
+        .remove_devices(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    state
+        .two_fa_code_store
+        .write()
+        .await
+        .remove_codes(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    match state.password_reset_token_store.write().await.remove_token(email).await {
+        Ok(()) | Err(TokenStoreError::TokenNotFound) => Ok(()),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
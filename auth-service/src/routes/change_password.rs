@@ -0,0 +1,99 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+
+use crate::api::extractors::{AuthenticatedUser, ClientInfo};
+use crate::domain::{
+    data_stores::{PasswordResetTokenStore, TokenStoreError, TrustedDeviceStore, UserStore, UserStoreError},
+    email_client::EmailClient,
+    error::AuthAPIError,
+    password::Password,
+    user::AuthEventKind,
+};
+use crate::routes::{account::record_auth_event, login::check_password};
+use crate::services::app_state::{AppServices, AppState};
+use crate::services::postmark_email_client::PostmarkTemplate;
+use crate::utils::auth::revoke_other_sessions;
+
+#[derive(Debug, Deserialize)]
+pub struct ChangePasswordRequest {
+    #[serde(rename = "currentPassword")]
+    current_password: Secret<String>,
+    #[serde(rename = "newPassword")]
+    new_password: Secret<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct ChangePasswordResponse {
+    pub message: String,
+}
+
+/// Changes the password of a signed-in user who knows the current one. The session making the change stays
+/// signed in; every other session is ended, trusted devices are forgotten and any pending reset link stops working.
+#[tracing::instrument(name = "Change Password POST Request", skip_all)]
+pub async fn post<S: AppServices>(
+    State(state): State<Arc<AppState<S>>>,
+    user: AuthenticatedUser,
+    client: ClientInfo,
+    Json(request): Json<ChangePasswordRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let new_password = Password::parse(request.new_password)
+        .await
+        .map_err(AuthAPIError::InvalidPassword)?;
+
+    check_password(&state, &user.email, &request.current_password).await?;
+    let mut user_store = state.user_store.write().await;
+    user_store
+        .update_password(&user.email, new_password)
+        .await
+        .map_err(|e| match e {
+            UserStoreError::UserNotFound => AuthAPIError::UserNotFound,
+            _ => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+    drop(user_store);
+    tracing::info!("Password changed");
+    record_auth_event(&state, &user.email, AuthEventKind::PasswordChanged, &client).await;
+
+    revoke_other_sessions(
+        state.banned_token_store.clone(),
+        state.session_store.clone(),
+        state.refresh_token_store.clone(),
+        &user.email,
+        user.session_id.as_ref(),
+    )
+    .await
+    .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    state
+        .trusted_device_store
+        .write()
+        .await
+        .remove_devices(&user.email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    match state
+        .password_reset_token_store
+        .write()
+        .await
+        .remove_token(&user.email)
+        .await
+    {
+        Ok(()) | Err(TokenStoreError::TokenNotFound) => {}
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    // The change has already been made, so a failed notification is logged rather than reported to the client
+    if let Err(e) = state
+        .email_client
+        .send_email(&user.email, PostmarkTemplate::PasswordChanged)
+        .await
+    {
+        tracing::error!("Error sending password changed notification: {e:?}");
+    }
+
+    let response = ChangePasswordResponse {
+        message: "Password changed successfully.".to_string(),
+    };
+    Ok((StatusCode::OK, Json(response)))
+}
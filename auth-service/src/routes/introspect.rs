@@ -0,0 +1,73 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::{header::AUTHORIZATION, HeaderMap},
+    response::IntoResponse,
+    Form, Json,
+};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::domain::error::AuthAPIError;
+use crate::services::app_state::{AppServices, AppState};
+use crate::utils::{
+    auth::{authenticate_introspection_client, validate_auth_token, Claims, TokenPurpose},
+    constants::Epoch,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct IntrospectRequest {
+    token: Secret<String>,
+}
+
+/// Inactive tokens only report `active: false`, so callers can't tell an expired
+/// token from a banned or malformed one.
+#[derive(Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct IntrospectResponse {
+    pub active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp: Option<Epoch>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iat: Option<Epoch>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub purpose: Option<TokenPurpose>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>,
+    /// Space-delimited, as RFC 7662 defines it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+}
+
+impl From<Claims> for IntrospectResponse {
+    fn from(claims: Claims) -> Self {
+        Self {
+            active: true,
+            sub: Some(claims.sub.expose_secret().to_owned()),
+            exp: Some(claims.exp),
+            iat: Some(claims.iat),
+            purpose: Some(claims.purpose),
+            jti: Some(claims.jti),
+            scope: Some(claims.scopes.join(" ")).filter(|scope| !scope.is_empty()),
+        }
+    }
+}
+
+#[tracing::instrument(name = "Introspect Token POST Request", skip_all)]
+pub async fn post<S: AppServices>(
+    State(state): State<Arc<AppState<S>>>,
+    headers: HeaderMap,
+    Form(request): Form<IntrospectRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let authorization = headers.get(AUTHORIZATION).and_then(|value| value.to_str().ok());
+    authenticate_introspection_client(authorization)?;
+
+    let response = match validate_auth_token(state.banned_token_store.clone(), request.token).await {
+        Ok(claims) => IntrospectResponse::from(claims),
+        Err(_) => IntrospectResponse::default(),
+    };
+
+    Ok(Json(response))
+}
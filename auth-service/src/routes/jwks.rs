@@ -0,0 +1,8 @@
+use axum::{response::IntoResponse, Json};
+
+use crate::utils::auth::jwks;
+
+#[tracing::instrument(name = "JWKS GET Request")]
+pub async fn get() -> impl IntoResponse {
+    Json(jwks())
+}
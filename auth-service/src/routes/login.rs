@@ -4,17 +4,32 @@ use axum::http::StatusCode;
 use axum::response::IntoResponse;
 use axum::{extract::State, Json};
 use axum_extra::extract::CookieJar;
-use color_eyre::eyre::eyre;
-use secrecy::Secret;
+use color_eyre::eyre;
+use secrecy::{ExposeSecret, Secret};
 use serde::{Deserialize, Serialize};
 
-use crate::domain::data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore};
+use crate::api::extractors::ClientInfo;
+use crate::domain::data_stores::{
+    LoginAttemptId, LoginFailureStore, LoginThrottle, Session, TwoFACode, TwoFACodeStore,
+};
 use crate::domain::email_client::EmailClient;
-use crate::domain::{data_stores::UserStore, email::Email, error::AuthAPIError, password::Password};
+use crate::domain::sms_client::{code_message, SmsClient};
+use crate::domain::{
+    data_stores::UserStore,
+    email::Email,
+    error::AuthAPIError,
+    user::{AuthEventKind, TwoFAMethod, User, UserAccess},
+};
+use crate::routes::{account::record_auth_event, verify_email::EmailVerificationMode};
 use crate::services::app_state::{AppServices, AppState};
 use crate::services::postmark_email_client::PostmarkTemplate;
-use crate::utils::auth::generate_auth_cookie;
-use crate::utils::constants::Time;
+use crate::utils::auth::{
+    async_compute_password_hash, check_trusted_device, start_session, AccountUnlockToken, GenerateTokenError,
+};
+use crate::utils::constants::{
+    Time, LOGIN_FAILURE_DELAY_SECONDS, LOGIN_LOCKOUT_SECONDS, LOGIN_LOCKOUT_THRESHOLD, TRUSTED_DEVICE_COOKIE_NAME,
+};
+use crate::utils::password_hash::verify_password_hash;
 
 #[derive(Deserialize, Debug)]
 pub struct LoginRequest {
@@ -27,6 +42,8 @@ pub struct TwoFactorAuthResponse {
     pub message: String,
     #[serde(rename = "loginAttemptId")]
     pub login_attempt_id: String,
+    #[serde(rename = "twoFAMethod")]
+    pub two_fa_method: TwoFAMethod,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -40,38 +57,205 @@ pub enum LoginResponse {
 pub async fn post<S: AppServices>(
     State(state): State<Arc<AppState<S>>>,
     jar: CookieJar,
+    client: ClientInfo,
     Json(payload): Json<LoginRequest>,
 ) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
     let email = Email::parse(payload.email).map_err(AuthAPIError::InvalidEmail)?;
-    let password = Password::parse(payload.password)
+    // Strength rules are for new passwords; an imported user's old one may not meet them
+    let password = payload.password;
+    let user = match check_password(&state, &email, &password).await {
+        Ok(user) => user,
+        Err(e) => {
+            if !matches!(e, AuthAPIError::UnexpectedError(_)) {
+                record_auth_event(&state, &email, AuthEventKind::SignInFailed, &client).await;
+            }
+            return Err(e);
+        }
+    };
+    if !user.email_verified && EmailVerificationMode::from_env() == EmailVerificationMode::Required {
+        return Err(AuthAPIError::EmailNotVerified);
+    }
+
+    // The login itself doesn't depend on the upgrade, so a failure is only logged
+    match upgrade_password_hash(&state, &email, &password).await {
+        Ok(true) => tracing::info!("Password hash upgraded to the current policy"),
+        Ok(false) => {}
+        Err(e) => tracing::error!("Error upgrading password hash: {e:?}"),
+    }
+
+    let skip_2fa = user.two_fa_method == TwoFAMethod::None || is_trusted_device(&state, &jar, &email).await?;
+    if skip_2fa {
+        let access = state
+            .user_store
+            .read()
+            .await
+            .get_access(&email)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+        handle_no_2fa(&email, &access, &state, jar, client).await
+    } else {
+        handle_2fa(&email, user.two_fa_method, &state, jar).await
+    }
+}
+
+/// Rehashes a password whose stored hash predates the current policy. The hashing is done without holding the
+/// user store lock, which is only taken for writing to swap the new hash in. Returns whether it was replaced.
+async fn upgrade_password_hash<S: AppServices>(
+    state: &AppState<S>,
+    email: &Email,
+    password: &Secret<String>,
+) -> eyre::Result<bool> {
+    let outdated_hash = state.user_store.read().await.get_outdated_password_hash(email).await?;
+    let Some(outdated_hash) = outdated_hash else {
+        return Ok(false);
+    };
+    // The hash may have changed since the password was checked, so it is checked against this one again
+    verify_password_hash(outdated_hash.expose_secret(), password.expose_secret().as_bytes())?;
+    let password_hash = async_compute_password_hash(password.clone()).await?;
+
+    let replaced = state
+        .user_store
+        .write()
+        .await
+        .replace_password_hash(email, &outdated_hash, password_hash)
+        .await?;
+    Ok(replaced)
+}
+
+/// Checks a password for the address, for logins and for the password re-entered before sensitive changes. An
+/// address that is locked out, or that failed too recently, is turned away without checking. The attempt is
+/// reserved before the hash is checked, so parallel attempts are throttled on each other. A wrong password
+/// counts towards the lockout and a right one clears the count.
+pub async fn check_password<S: AppServices>(
+    state: &AppState<S>,
+    email: &Email,
+    password: &Secret<String>,
+) -> Result<User, AuthAPIError> {
+    let lockout_seconds = *LOGIN_LOCKOUT_SECONDS;
+    let failures = state
+        .login_failure_store
+        .write()
+        .await
+        .reserve_attempt(email, lockout_seconds)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    let throttled = match failures.throttle(*LOGIN_LOCKOUT_THRESHOLD, lockout_seconds, *LOGIN_FAILURE_DELAY_SECONDS) {
+        LoginThrottle::Allowed => None,
+        LoginThrottle::Delayed(_) => Some(AuthAPIError::TooManyLoginAttempts),
+        LoginThrottle::Locked(_) => Some(AuthAPIError::AccountLocked),
+    };
+    if let Some(e) = throttled {
+        release_attempt(state, email).await?;
+        return Err(e);
+    }
+
+    let validation = state.user_store.read().await.validate_user(email, password).await;
+    match validation {
+        // Clearing the failures also ends the reservation
+        Ok(user) => state
+            .login_failure_store
+            .write()
+            .await
+            .clear_failures(email)
+            .await
+            .map(|_| user)
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into())),
+        Err(_) => {
+            let e = record_login_failure(state, email).await;
+            release_attempt(state, email).await?;
+            Err(e)
+        }
+    }
+}
+
+async fn release_attempt<S: AppServices>(state: &AppState<S>, email: &Email) -> Result<(), AuthAPIError> {
+    state
+        .login_failure_store
+        .write()
         .await
-        .map_err(AuthAPIError::InvalidPassword)?;
-    let user_store = state.user_store.write().await;
+        .release_attempt(email, *LOGIN_LOCKOUT_SECONDS)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))
+}
 
-    let user = user_store
-        .validate_user(&email, &password)
+/// Counts a wrong password and returns the error to answer with. The failure that reaches the threshold locks
+/// the account and emails its owner a link to unlock it.
+async fn record_login_failure<S: AppServices>(state: &AppState<S>, email: &Email) -> AuthAPIError {
+    let lockout_seconds = *LOGIN_LOCKOUT_SECONDS;
+    let failures = match state
+        .login_failure_store
+        .write()
+        .await
+        .record_failure(email, lockout_seconds)
         .await
-        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+    {
+        Ok(failures) => failures,
+        Err(e) => return AuthAPIError::UnexpectedError(e.into()),
+    };
+    if failures.count < *LOGIN_LOCKOUT_THRESHOLD {
+        return AuthAPIError::InvalidCredentials;
+    }
+    tracing::warn!("Account locked after {} failed logins", failures.count);
 
-    match user.requires_2fa {
-        false => handle_no_2fa(&email, jar).await,
-        true => handle_2fa(&email, &state, jar).await,
+    // Unknown addresses are locked the same way so a lockout doesn't reveal which accounts exist, but only real
+    // ones get an email
+    if failures.count == *LOGIN_LOCKOUT_THRESHOLD && state.user_store.read().await.get_user(email).await.is_ok() {
+        match AccountUnlockToken::new(email, lockout_seconds) {
+            Ok(token) => {
+                let template_model = PostmarkTemplate::AccountLocked(lockout_seconds, token);
+                if let Err(e) = state.email_client.send_email(email, template_model).await {
+                    tracing::error!("Error sending account locked notification: {e:?}");
+                }
+            }
+            Err(e) => tracing::error!("Error generating account unlock token: {e:?}"),
+        }
+    }
+    AuthAPIError::AccountLocked
+}
+
+/// Whether the request carries a live trusted-device cookie issued to this user. Anything else just means the
+/// login goes through 2FA as usual.
+async fn is_trusted_device<S: AppServices>(
+    state: &AppState<S>,
+    jar: &CookieJar,
+    email: &Email,
+) -> Result<bool, AuthAPIError> {
+    let Some(cookie) = jar.get(TRUSTED_DEVICE_COOKIE_NAME) else {
+        return Ok(false);
+    };
+    match check_trusted_device(state.trusted_device_store.clone(), cookie.value(), email).await {
+        Ok(()) => Ok(true),
+        Err(GenerateTokenError::UnexpectedError(e)) => Err(AuthAPIError::UnexpectedError(e)),
+        Err(_) => Ok(false),
     }
 }
 
 #[tracing::instrument(name = "Handle no 2fa path")]
-async fn handle_no_2fa(
+async fn handle_no_2fa<S: AppServices>(
     email: &Email,
+    access: &UserAccess,
+    state: &AppState<S>,
     jar: CookieJar,
+    client: ClientInfo,
 ) -> Result<(CookieJar, (StatusCode, Json<LoginResponse>)), AuthAPIError> {
-    let auth_cookie = generate_auth_cookie(email).map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
-    let updated_jar = jar.add(auth_cookie);
+    record_auth_event(state, email, AuthEventKind::SignIn, &client).await;
+    let session = Session::new(email.clone(), client.ip_address, client.user_agent);
+    let (auth_cookie, refresh_cookie) = start_session(
+        state.session_store.clone(),
+        state.refresh_token_store.clone(),
+        session,
+        access,
+    )
+    .await
+    .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    let updated_jar = jar.add(auth_cookie).add(refresh_cookie);
     Ok((updated_jar, (StatusCode::OK, Json(LoginResponse::RegularAuth))))
 }
 
 #[tracing::instrument(name = "Handle 2fa path")]
 async fn handle_2fa<S: AppServices>(
     email: &Email,
+    two_fa_method: TwoFAMethod,
     state: &AppState<S>,
     jar: CookieJar,
 ) -> Result<(CookieJar, (StatusCode, Json<LoginResponse>)), AuthAPIError> {
@@ -89,14 +273,10 @@ async fn handle_2fa<S: AppServices>(
     let response = TwoFactorAuthResponse {
         message: "2FA required".to_string(),
         login_attempt_id: login_attempt_id.expose_secret_string(),
+        two_fa_method,
     };
 
-    let template_model = PostmarkTemplate::TwoFACode(Time::Minutes10, two_fa_code);
-
-    if state.email_client.send_email(&email, template_model).await.is_err() {
-        tracing::info!("Error sending 2FA email");
-        return Err(AuthAPIError::UnexpectedError(eyre!("Failed to send 2FA email")));
-    };
+    send_two_fa_code(state, &email, two_fa_method, two_fa_code).await?;
 
     Ok((
         jar,
@@ -106,3 +286,38 @@ async fn handle_2fa<S: AppServices>(
         ),
     ))
 }
+
+/// Sends the code by email or text, depending on the user's method. TOTP codes come from the user's
+/// authenticator app, so the stored code is never sent or checked.
+pub async fn send_two_fa_code<S: AppServices>(
+    state: &AppState<S>,
+    email: &Email,
+    two_fa_method: TwoFAMethod,
+    two_fa_code: TwoFACode,
+) -> Result<(), AuthAPIError> {
+    let result = match two_fa_method {
+        TwoFAMethod::Email => {
+            let template_model = PostmarkTemplate::TwoFACode(Time::Minutes10, two_fa_code);
+            state.email_client.send_email(email, template_model).await
+        }
+        TwoFAMethod::Sms => {
+            let phone_number = state
+                .user_store
+                .read()
+                .await
+                .get_phone_number(email)
+                .await
+                .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?
+                .phone_number
+                .ok_or(AuthAPIError::PhoneNumberNotVerified)?;
+            let body = code_message(&two_fa_code, Time::Minutes10);
+            state.sms_client.send_sms(&phone_number, &body).await
+        }
+        TwoFAMethod::None | TwoFAMethod::Totp => Ok(()),
+    };
+
+    result.map_err(|e| {
+        tracing::info!("Error sending 2FA code");
+        AuthAPIError::UnexpectedError(e.wrap_err("Failed to send 2FA code"))
+    })
+}
@@ -4,30 +4,49 @@ use axum::{extract::State, http::StatusCode, response::IntoResponse};
 use axum_extra::extract::{cookie, CookieJar};
 use secrecy::Secret;
 
-use crate::domain::{data_stores::BannedTokenStore, error::AuthAPIError};
+use crate::api::extractors::AuthenticatedUser;
+use crate::domain::{
+    data_stores::{BannedTokenStore, RefreshToken},
+    error::AuthAPIError,
+};
 use crate::services::app_state::{AppServices, AppState};
-use crate::utils::{auth::validate_token, constants::JWT_COOKIE_NAME};
+use crate::utils::{
+    auth::{revoke_refresh_token, revoke_session},
+    constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME},
+};
 
-#[tracing::instrument(name = "Logout POST Request")]
+#[tracing::instrument(name = "Logout POST Request", skip_all)]
 pub async fn post<S: AppServices>(
     State(state): State<Arc<AppState<S>>>,
     jar: CookieJar,
+    user: AuthenticatedUser,
 ) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
-    let cookie = match jar.get(JWT_COOKIE_NAME) {
-        Some(cookie) => cookie,
-        None => return Err(AuthAPIError::MissingToken),
-    };
-
-    let token = Secret::new(cookie.value().to_owned());
-    validate_token(state.banned_token_store.clone(), token.clone())
+    if let Some(session_id) = user.session_id {
+        revoke_session(
+            state.banned_token_store.clone(),
+            state.session_store.clone(),
+            state.refresh_token_store.clone(),
+            &session_id,
+        )
         .await
-        .map_err(|_| AuthAPIError::InvalidToken)?;
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    }
+
+    if let Some(refresh_cookie) = jar.get(REFRESH_TOKEN_COOKIE_NAME) {
+        if let Ok(refresh_token) = RefreshToken::parse(Secret::new(refresh_cookie.value().to_owned())) {
+            revoke_refresh_token(state.refresh_token_store.clone(), &refresh_token)
+                .await
+                .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+        }
+    }
 
-    let jar = jar.remove(cookie::Cookie::from(JWT_COOKIE_NAME));
+    let jar = jar
+        .remove(cookie::Cookie::from(JWT_COOKIE_NAME))
+        .remove(cookie::Cookie::from(REFRESH_TOKEN_COOKIE_NAME));
 
     let mut banned_token_store = state.banned_token_store.write().await;
     banned_token_store
-        .add_token(token)
+        .add_token(user.token)
         .await
         .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
 
@@ -0,0 +1,33 @@
+use std::sync::Arc;
+
+use crate::api::extractors::AuthenticatedUser;
+use crate::domain::error::AuthAPIError;
+use crate::services::app_state::{AppServices, AppState};
+use crate::utils::{
+    auth::revoke_all_sessions,
+    constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME},
+};
+use axum::{extract::State, http::StatusCode, response::IntoResponse};
+use axum_extra::extract::{cookie, CookieJar};
+
+#[tracing::instrument(name = "Logout All POST Request", skip_all)]
+pub async fn post<S: AppServices>(
+    State(state): State<Arc<AppState<S>>>,
+    jar: CookieJar,
+    user: AuthenticatedUser,
+) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
+    revoke_all_sessions(
+        state.banned_token_store.clone(),
+        state.session_store.clone(),
+        state.refresh_token_store.clone(),
+        &user.email,
+    )
+    .await
+    .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let jar = jar
+        .remove(cookie::Cookie::from(JWT_COOKIE_NAME))
+        .remove(cookie::Cookie::from(REFRESH_TOKEN_COOKIE_NAME));
+
+    Ok((jar, StatusCode::OK))
+}
@@ -1,7 +1,24 @@
+pub mod account;
+pub mod admin;
+pub mod change_email;
+pub mod change_password;
 pub mod initiate_password_reset;
+pub mod introspect;
+pub mod jwks;
 pub mod login;
 pub mod logout;
+pub mod logout_all;
+pub mod phone;
+pub mod recovery_codes;
+pub mod refresh_token;
+pub mod resend_2fa;
 pub mod reset_password;
+pub mod sessions;
 pub mod signup;
+pub mod totp;
+pub mod trusted_devices;
+pub mod two_fa_settings;
 pub mod verify_2fa;
+pub mod verify_email;
 pub mod verify_token;
+pub mod webauthn;
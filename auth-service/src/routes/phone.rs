@@ -0,0 +1,202 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+
+use crate::api::extractors::AuthenticatedUser;
+use crate::domain::{
+    data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError, UserStore, UserStoreError},
+    email::Email,
+    email_client::EmailClient,
+    error::AuthAPIError,
+    phone_number::PhoneNumber,
+    sms_client::{code_message, SmsClient},
+    user::{TwoFAMethod, UserPhoneNumber},
+};
+use crate::routes::two_fa_settings::{get_two_fa_method, reauthenticate, Reauthentication};
+use crate::routes::verify_2fa::record_failed_attempt;
+use crate::services::app_state::{AppServices, AppState};
+use crate::services::postmark_email_client::PostmarkTemplate;
+use crate::utils::constants::{Time, PHONE_VERIFICATION_MAX_SENDS, PHONE_VERIFICATION_SEND_WINDOW_SECONDS};
+
+#[derive(Debug, Deserialize)]
+pub struct SetPhoneNumberRequest {
+    #[serde(rename = "phoneNumber")]
+    phone_number: Secret<String>,
+    #[serde(flatten)]
+    reauthentication: Reauthentication,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PhoneVerificationResponse {
+    #[serde(rename = "verificationId")]
+    pub verification_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyPhoneNumberRequest {
+    #[serde(rename = "verificationId")]
+    verification_id: Secret<String>,
+    code: Secret<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PhoneNumberResponse {
+    #[serde(rename = "phoneNumber")]
+    pub phone_number: Option<String>,
+    #[serde(rename = "pendingPhoneNumber")]
+    pub pending_phone_number: Option<String>,
+}
+
+impl From<UserPhoneNumber> for PhoneNumberResponse {
+    fn from(user_phone_number: UserPhoneNumber) -> Self {
+        Self {
+            phone_number: user_phone_number.phone_number.map(|p| p.expose_secret_string()),
+            pending_phone_number: user_phone_number.pending_phone_number.map(|p| p.expose_secret_string()),
+        }
+    }
+}
+
+#[tracing::instrument(name = "Phone Number GET Request", skip_all)]
+pub async fn get<S: AppServices>(
+    State(state): State<Arc<AppState<S>>>,
+    user: AuthenticatedUser,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let user_phone_number = get_phone_number(&state, &user.email).await?;
+    Ok((StatusCode::OK, Json(PhoneNumberResponse::from(user_phone_number))))
+}
+
+/// Texts a verification code to a new number. The number is only used for SMS 2FA once it is verified, so a
+/// verified number stays in use until then. When SMS is the user's second factor, replacing the number takes
+/// the password or a recovery code, and only `PHONE_VERIFICATION_MAX_SENDS` codes are texted per user a day.
+#[tracing::instrument(name = "Phone Number POST Request", skip_all)]
+pub async fn post<S: AppServices>(
+    State(state): State<Arc<AppState<S>>>,
+    user: AuthenticatedUser,
+    Json(request): Json<SetPhoneNumberRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let phone_number = PhoneNumber::parse(request.phone_number).map_err(AuthAPIError::InvalidPhoneNumber)?;
+
+    let current_method = get_two_fa_method(&state, &user.email).await?;
+    if current_method == TwoFAMethod::Sms {
+        reauthenticate(&state, &user.email, current_method, request.reauthentication).await?;
+    }
+
+    let verification_id = LoginAttemptId::default();
+    let code = TwoFACode::default();
+    let mut code_store = state.phone_verification_code_store.write().await;
+    code_store
+        .record_code_sent(
+            &user.email,
+            *PHONE_VERIFICATION_MAX_SENDS,
+            PHONE_VERIFICATION_SEND_WINDOW_SECONDS,
+        )
+        .await
+        .map_err(|e| match e {
+            TwoFACodeStoreError::SendLimitReached => AuthAPIError::PhoneVerificationLimitReached,
+            _ => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+    code_store
+        .add_code(user.email.clone(), verification_id.clone(), code.clone())
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    drop(code_store);
+
+    state
+        .user_store
+        .write()
+        .await
+        .set_pending_phone_number(
+            &user.email,
+            phone_number.clone(),
+            &verification_id.expose_secret_string(),
+        )
+        .await
+        .map_err(|e| match e {
+            UserStoreError::UserNotFound => AuthAPIError::UserNotFound,
+            _ => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+
+    let body = code_message(&code, Time::Minutes10);
+    state
+        .sms_client
+        .send_sms(&phone_number, &body)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.wrap_err("Failed to send phone verification code")))?;
+
+    let response = PhoneVerificationResponse {
+        verification_id: verification_id.expose_secret_string(),
+    };
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// Confirms the pending number with the texted code and emails the owner. Wrong codes count against the
+/// verification the same way they count against a login attempt.
+#[tracing::instrument(name = "Verify Phone Number POST Request", skip_all)]
+pub async fn post_verify<S: AppServices>(
+    State(state): State<Arc<AppState<S>>>,
+    user: AuthenticatedUser,
+    Json(request): Json<VerifyPhoneNumberRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let verification_id =
+        LoginAttemptId::parse(request.verification_id).map_err(|_| AuthAPIError::InvalidLoginAttemptId)?;
+    let code = TwoFACode::parse(request.code).map_err(|_| AuthAPIError::InvalidTwoFactorAuthCode)?;
+
+    let mut code_store = state.phone_verification_code_store.write().await;
+    let stored_code = code_store
+        .get_code(&user.email, &verification_id)
+        .await
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+    if code != stored_code {
+        record_failed_attempt(&mut *code_store, &user.email, &verification_id).await?;
+        return Err(AuthAPIError::InvalidCredentials);
+    }
+    code_store
+        .remove_code(&user.email, &verification_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let mut user_store = state.user_store.write().await;
+    user_store
+        .confirm_phone_number(&user.email, &verification_id.expose_secret_string())
+        .await
+        .map_err(|e| match e {
+            // A newer number was submitted after this code was sent
+            UserStoreError::PhoneVerificationNotFound => AuthAPIError::InvalidCredentials,
+            _ => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+    let user_phone_number = user_store
+        .get_phone_number(&user.email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    drop(user_store);
+    tracing::info!("Phone number verified");
+
+    // The change has already been made, so a failed notification is logged rather than reported to the client
+    if let Err(e) = state
+        .email_client
+        .send_email(&user.email, PostmarkTemplate::PhoneNumberChanged)
+        .await
+    {
+        tracing::error!("Error sending phone number notification: {e:?}");
+    }
+
+    Ok((StatusCode::OK, Json(PhoneNumberResponse::from(user_phone_number))))
+}
+
+pub async fn get_phone_number<S: AppServices>(
+    state: &AppState<S>,
+    email: &Email,
+) -> Result<UserPhoneNumber, AuthAPIError> {
+    state
+        .user_store
+        .read()
+        .await
+        .get_phone_number(email)
+        .await
+        .map_err(|e| match e {
+            UserStoreError::UserNotFound => AuthAPIError::UserNotFound,
+            _ => AuthAPIError::UnexpectedError(e.into()),
+        })
+}
@@ -0,0 +1,93 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use serde::{Deserialize, Serialize};
+
+use crate::api::extractors::AuthenticatedUser;
+use crate::domain::{
+    data_stores::{RecoveryCode, UserStore, UserStoreError},
+    email::Email,
+    email_client::EmailClient,
+    error::AuthAPIError,
+};
+use crate::routes::two_fa_settings::{get_two_fa_method, reauthenticate, Reauthentication};
+use crate::services::app_state::{AppServices, AppState};
+use crate::services::postmark_email_client::PostmarkTemplate;
+use crate::utils::constants::RECOVERY_CODE_COUNT;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RecoveryCodesResponse {
+    #[serde(rename = "recoveryCodes")]
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RecoveryCodesStatusResponse {
+    pub remaining: usize,
+}
+
+/// Replaces the user's recovery codes. The plain codes are only ever returned here.
+pub async fn issue_recovery_codes<U: UserStore>(
+    user_store: &mut U,
+    email: &Email,
+) -> Result<RecoveryCodesResponse, AuthAPIError> {
+    let codes: Vec<RecoveryCode> = (0..RECOVERY_CODE_COUNT).map(|_| RecoveryCode::default()).collect();
+    user_store
+        .set_recovery_codes(email, codes.iter().map(RecoveryCode::hash).collect())
+        .await
+        .map_err(|e| match e {
+            UserStoreError::UserNotFound => AuthAPIError::UserNotFound,
+            _ => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+
+    Ok(RecoveryCodesResponse {
+        recovery_codes: codes.iter().map(RecoveryCode::formatted).collect(),
+    })
+}
+
+#[tracing::instrument(name = "Recovery Codes GET Request", skip_all)]
+pub async fn get<S: AppServices>(
+    State(state): State<Arc<AppState<S>>>,
+    user: AuthenticatedUser,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let remaining = state
+        .user_store
+        .read()
+        .await
+        .count_recovery_codes(&user.email)
+        .await
+        .map_err(|e| match e {
+            UserStoreError::UserNotFound => AuthAPIError::UserNotFound,
+            _ => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+
+    Ok((StatusCode::OK, Json(RecoveryCodesStatusResponse { remaining })))
+}
+
+/// Regenerates the codes, invalidating any that are left from the previous set. The codes get past 2FA, so
+/// this takes the password or a current code, and the owner is emailed.
+#[tracing::instrument(name = "Recovery Codes POST Request", skip_all)]
+pub async fn post<S: AppServices>(
+    State(state): State<Arc<AppState<S>>>,
+    user: AuthenticatedUser,
+    Json(request): Json<Reauthentication>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let current_method = get_two_fa_method(&state, &user.email).await?;
+    reauthenticate(&state, &user.email, current_method, request).await?;
+
+    let mut user_store = state.user_store.write().await;
+    let response = issue_recovery_codes(&mut *user_store, &user.email).await?;
+    drop(user_store);
+    tracing::info!("Recovery codes regenerated");
+
+    // The change has already been made, so a failed notification is logged rather than reported to the client
+    if let Err(e) = state
+        .email_client
+        .send_email(&user.email, PostmarkTemplate::RecoveryCodesRegenerated)
+        .await
+    {
+        tracing::error!("Error sending recovery codes notification: {e:?}");
+    }
+
+    Ok((StatusCode::OK, Json(response)))
+}
@@ -0,0 +1,67 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse};
+use axum_extra::extract::{cookie, CookieJar};
+use secrecy::Secret;
+
+use crate::domain::{
+    data_stores::{RefreshToken, UserStore, UserStoreError},
+    error::AuthAPIError,
+};
+use crate::services::app_state::{AppServices, AppState};
+use crate::utils::{
+    auth::{generate_auth_cookie, generate_refresh_cookie, rotate_refresh_token, touch_session},
+    constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME},
+};
+
+#[tracing::instrument(name = "Refresh Token POST Request", skip_all)]
+pub async fn post<S: AppServices>(
+    State(state): State<Arc<AppState<S>>>,
+    jar: CookieJar,
+) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
+    let cookie = match jar.get(REFRESH_TOKEN_COOKIE_NAME) {
+        Some(cookie) => cookie,
+        None => return Err(AuthAPIError::MissingToken),
+    };
+
+    let token = RefreshToken::parse(Secret::new(cookie.value().to_owned())).map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let (record, new_token) = match rotate_refresh_token(state.refresh_token_store.clone(), &token).await {
+        Ok(rotated) => rotated,
+        Err(_) => {
+            let jar = jar
+                .remove(cookie::Cookie::from(JWT_COOKIE_NAME))
+                .remove(cookie::Cookie::from(REFRESH_TOKEN_COOKIE_NAME));
+            return Ok((jar, AuthAPIError::InvalidToken.into_response()));
+        }
+    };
+
+    // The session was revoked while the refresh token was still live
+    if touch_session(state.session_store.clone(), &record.family_id)
+        .await
+        .is_err()
+    {
+        let jar = jar
+            .remove(cookie::Cookie::from(JWT_COOKIE_NAME))
+            .remove(cookie::Cookie::from(REFRESH_TOKEN_COOKIE_NAME));
+        return Ok((jar, AuthAPIError::InvalidToken.into_response()));
+    }
+
+    // Roles are read again on every refresh, so role changes reach the next token
+    let access = match state.user_store.read().await.get_access(&record.email).await {
+        Ok(access) => access,
+        Err(UserStoreError::UserNotFound) => {
+            let jar = jar
+                .remove(cookie::Cookie::from(JWT_COOKIE_NAME))
+                .remove(cookie::Cookie::from(REFRESH_TOKEN_COOKIE_NAME));
+            return Ok((jar, AuthAPIError::InvalidToken.into_response()));
+        }
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    let auth_cookie = generate_auth_cookie(&record.email, &record.family_id, &access)
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    let updated_jar = jar.add(auth_cookie).add(generate_refresh_cookie(&new_token));
+
+    Ok((updated_jar, StatusCode::OK.into_response()))
+}
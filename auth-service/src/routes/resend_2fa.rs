@@ -0,0 +1,77 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use secrecy::Secret;
+use serde::Deserialize;
+
+use crate::domain::{
+    data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError, UserStore},
+    email::Email,
+    error::AuthAPIError,
+};
+use crate::routes::login::{send_two_fa_code, TwoFactorAuthResponse};
+use crate::services::app_state::{AppServices, AppState};
+use crate::utils::constants::{TWO_FA_MAX_RESENDS, TWO_FA_RESEND_COOLDOWN_SECONDS};
+
+#[derive(Debug, Deserialize)]
+pub struct Resend2FARequest {
+    email: Secret<String>,
+    #[serde(rename = "loginAttemptId")]
+    login_attempt_id: Secret<String>,
+}
+
+/// Sends a fresh code for a pending login attempt. The attempt id stays the same, so the client carries on
+/// with `/verify-2fa` as before.
+#[tracing::instrument(name = "Resend 2FA POST Request", skip_all)]
+pub async fn post<S: AppServices>(
+    State(state): State<Arc<AppState<S>>>,
+    Json(payload): Json<Resend2FARequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = Email::parse(payload.email).map_err(AuthAPIError::InvalidEmail)?;
+    let login_attempt_id =
+        LoginAttemptId::parse(payload.login_attempt_id).map_err(|_| AuthAPIError::InvalidLoginAttemptId)?;
+
+    let mut two_fa_code_store = state.two_fa_code_store.write().await;
+    two_fa_code_store
+        .get_code(&email, &login_attempt_id)
+        .await
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let user = state
+        .user_store
+        .read()
+        .await
+        .get_user(&email)
+        .await
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+    if !user.two_fa_method.sends_codes() {
+        return Err(AuthAPIError::TwoFACodeNotResendable);
+    }
+
+    let two_fa_code = TwoFACode::default();
+    two_fa_code_store
+        .resend_code(
+            &email,
+            &login_attempt_id,
+            two_fa_code.clone(),
+            *TWO_FA_RESEND_COOLDOWN_SECONDS,
+            *TWO_FA_MAX_RESENDS,
+        )
+        .await
+        .map_err(|e| match e {
+            TwoFACodeStoreError::LoginAttemptIdNotFound => AuthAPIError::InvalidCredentials,
+            TwoFACodeStoreError::ResendCooldown => AuthAPIError::TwoFACodeResendCooldown,
+            TwoFACodeStoreError::ResendLimitReached => AuthAPIError::TwoFACodeResendLimitReached,
+            _ => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+    drop(two_fa_code_store);
+
+    send_two_fa_code(&state, &email, user.two_fa_method, two_fa_code).await?;
+
+    let response = TwoFactorAuthResponse {
+        message: "2FA code resent".to_string(),
+        login_attempt_id: login_attempt_id.expose_secret_string(),
+        two_fa_method: user.two_fa_method,
+    };
+    Ok((StatusCode::OK, Json(response)))
+}
@@ -4,18 +4,21 @@ use axum::response::{Html, IntoResponse};
 use axum::{extract::State, Json};
 use axum_extra::extract::CookieJar;
 use hyper::StatusCode;
-use secrecy::Secret;
+use secrecy::{ExposeSecret, Secret};
 use serde::{Deserialize, Serialize};
 
+use crate::api::extractors::ClientInfo;
 use crate::services::app_state::{AppServices, AppState};
 use crate::utils::auth::validate_password_reset_token;
 use crate::{
     domain::{
-        data_stores::{PasswordResetTokenStore, UserStore},
+        data_stores::{PasswordResetTokenStore, Session, TrustedDeviceStore, UserStore},
         error::AuthAPIError,
         password::Password,
+        user::AuthEventKind,
     },
-    utils::auth::{generate_auth_cookie, TokenPurpose},
+    routes::account::record_auth_event,
+    utils::auth::{revoke_all_sessions, start_session, TokenPurpose},
 };
 
 #[derive(Debug, Deserialize)]
@@ -44,12 +47,13 @@ pub async fn get() -> impl IntoResponse {
 pub async fn post<S: AppServices>(
     State(state): State<Arc<AppState<S>>>,
     jar: CookieJar,
+    client: ClientInfo,
     Json(payload): Json<ResetPasswordRequest>,
 ) -> Result<(CookieJar, (StatusCode, Json<ResetPasswordResponse>)), AuthAPIError> {
     let new_password = Password::parse(payload.new_password)
         .await
         .map_err(AuthAPIError::InvalidPassword)?;
-    let (email, claims) = validate_password_reset_token(state.banned_token_store.clone(), payload.token)
+    let (email, claims) = validate_password_reset_token(state.banned_token_store.clone(), payload.token.clone())
         .await
         .map_err(|_| AuthAPIError::InvalidToken)?;
 
@@ -57,7 +61,15 @@ pub async fn post<S: AppServices>(
         return Err(AuthAPIError::InvalidToken);
     }
 
+    // Only the most recently issued link works, and none does once the password has been changed another way
     let mut token_store = state.password_reset_token_store.write().await;
+    let stored_token = token_store
+        .get_token(&email)
+        .await
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+    if stored_token != *payload.token.expose_secret() {
+        return Err(AuthAPIError::InvalidToken);
+    }
     token_store
         .remove_token(&email)
         .await
@@ -68,9 +80,45 @@ pub async fn post<S: AppServices>(
         .update_password(&email, new_password)
         .await
         .map_err(|_| AuthAPIError::UserNotFound)?;
+    // The reset link was delivered to the address, which proves the user controls it
+    user_store
+        .mark_email_verified(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    let access = user_store
+        .get_access(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    drop(user_store);
+    record_auth_event(&state, &email, AuthEventKind::PasswordReset, &client).await;
+
+    // Whoever knew the old password may still hold tokens or a trusted browser, so sign out and forget every device
+    revoke_all_sessions(
+        state.banned_token_store.clone(),
+        state.session_store.clone(),
+        state.refresh_token_store.clone(),
+        &email,
+    )
+    .await
+    .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    state
+        .trusted_device_store
+        .write()
+        .await
+        .remove_devices(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
 
-    let auth_cookie = generate_auth_cookie(&email).map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
-    let updated_jar = jar.add(auth_cookie);
+    let session = Session::new(email, client.ip_address, client.user_agent);
+    let (auth_cookie, refresh_cookie) = start_session(
+        state.session_store.clone(),
+        state.refresh_token_store.clone(),
+        session,
+        &access,
+    )
+    .await
+    .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    let updated_jar = jar.add(auth_cookie).add(refresh_cookie);
 
     let response = ResetPasswordResponse {
         message: "Password has been reset successfully.".to_string(),
@@ -0,0 +1,99 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::api::extractors::AuthenticatedUser;
+use crate::domain::{
+    data_stores::{Session, SessionStore, SessionStoreError},
+    error::AuthAPIError,
+};
+use crate::services::app_state::{AppServices, AppState};
+use crate::utils::auth::{revoke_session, touch_session};
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionResponse {
+    pub id: String,
+    pub created_at: String,
+    pub last_seen_at: String,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub current: bool,
+}
+
+impl SessionResponse {
+    pub fn new(session: Session, current_session_id: Option<&Uuid>) -> Self {
+        Self {
+            current: current_session_id == Some(&session.id),
+            id: session.id.to_string(),
+            created_at: session.created_at.to_rfc3339(),
+            last_seen_at: session.last_seen_at.to_rfc3339(),
+            ip_address: session.ip_address,
+            user_agent: session.user_agent,
+        }
+    }
+}
+
+#[tracing::instrument(name = "Sessions GET Request", skip_all)]
+pub async fn get<S: AppServices>(
+    State(state): State<Arc<AppState<S>>>,
+    user: AuthenticatedUser,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    if let Some(session_id) = &user.session_id {
+        touch_session(state.session_store.clone(), session_id)
+            .await
+            .map_err(|_| AuthAPIError::InvalidToken)?;
+    }
+
+    let sessions = state
+        .session_store
+        .read()
+        .await
+        .get_sessions(&user.email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let response: Vec<SessionResponse> = sessions
+        .into_iter()
+        .map(|session| SessionResponse::new(session, user.session_id.as_ref()))
+        .collect();
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+#[tracing::instrument(name = "Sessions DELETE Request", skip_all)]
+pub async fn delete<S: AppServices>(
+    State(state): State<Arc<AppState<S>>>,
+    user: AuthenticatedUser,
+    Path(session_id): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let session_id = Uuid::parse_str(&session_id).map_err(|_| AuthAPIError::SessionNotFound)?;
+
+    let session = match state.session_store.read().await.get_session(&session_id).await {
+        Ok(session) => session,
+        Err(SessionStoreError::SessionNotFound) => return Err(AuthAPIError::SessionNotFound),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+    // Other users' sessions are reported as missing rather than forbidden
+    if session.email != user.email {
+        return Err(AuthAPIError::SessionNotFound);
+    }
+
+    revoke_session(
+        state.banned_token_store.clone(),
+        state.session_store.clone(),
+        state.refresh_token_store.clone(),
+        &session_id,
+    )
+    .await
+    .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
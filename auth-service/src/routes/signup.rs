@@ -5,13 +5,14 @@ use axum::{extract::State, Json};
 use secrecy::Secret;
 use serde::{Deserialize, Serialize};
 
-use crate::domain::user::NewUser;
+use crate::domain::user::{NewUser, TwoFAMethod};
 use crate::domain::{
     data_stores::{UserStore, UserStoreError},
     email::Email,
     error::AuthAPIError,
     password::Password,
 };
+use crate::routes::{recovery_codes::issue_recovery_codes, verify_email::send_verification_email};
 use crate::services::app_state::{AppServices, AppState};
 
 #[derive(Deserialize, Debug)]
@@ -25,6 +26,8 @@ pub struct SignupRequest {
 #[derive(Serialize)]
 pub struct SignupResponse {
     message: String,
+    #[serde(rename = "recoveryCodes", skip_serializing_if = "Option::is_none")]
+    recovery_codes: Option<Vec<String>>,
 }
 
 #[tracing::instrument(name = "Signup POST Request", skip_all, err(Debug))]
@@ -37,7 +40,8 @@ pub async fn post<S: AppServices>(
         .await
         .map_err(AuthAPIError::InvalidPassword)?;
 
-    let user = NewUser::new(email, password, payload.requires_2fa);
+    let two_fa_method = TwoFAMethod::from_requires_2fa(payload.requires_2fa);
+    let user = NewUser::new(email.clone(), password, two_fa_method);
 
     let mut user_store = state.user_store.write().await;
     user_store.add_user(user).await.map_err(|e| match e {
@@ -45,10 +49,22 @@ pub async fn post<S: AppServices>(
         _ => AuthAPIError::UnexpectedError(e.into()),
     })?;
 
+    let recovery_codes = match two_fa_method {
+        TwoFAMethod::None => None,
+        _ => Some(issue_recovery_codes(&mut *user_store, &email).await?.recovery_codes),
+    };
+    drop(user_store);
+
+    // The account exists either way, and the user can ask for another link
+    if let Err(e) = send_verification_email(&state, &email).await {
+        tracing::error!("Error sending verification email: {e:?}");
+    }
+
     Ok((
         StatusCode::CREATED,
         Json(SignupResponse {
             message: "User created successfully".to_string(),
+            recovery_codes,
         }),
     ))
 }
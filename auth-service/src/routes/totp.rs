@@ -0,0 +1,116 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use chrono::Utc;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::api::extractors::AuthenticatedUser;
+use crate::domain::{
+    data_stores::{TwoFACode, UserStore, UserStoreError},
+    email_client::EmailClient,
+    error::AuthAPIError,
+    user::TwoFAMethod,
+};
+use crate::routes::{
+    recovery_codes::issue_recovery_codes,
+    two_fa_settings::{get_two_fa_method, reauthenticate, Reauthentication},
+};
+use crate::services::app_state::{AppServices, AppState};
+use crate::services::postmark_email_client::PostmarkTemplate;
+use crate::utils::{
+    constants::{TOTP_ISSUER, TOTP_SKEW_STEPS},
+    totp,
+};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TotpEnrollmentResponse {
+    pub secret: String,
+    #[serde(rename = "otpauthUri")]
+    pub otpauth_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConfirmTotpRequest {
+    pub code: Secret<String>,
+    #[serde(flatten)]
+    reauthentication: Reauthentication,
+}
+
+/// Starts enrollment with a fresh secret. Login keeps using the current method until the secret is confirmed.
+#[tracing::instrument(name = "TOTP Enroll POST Request", skip_all)]
+pub async fn post_enroll<S: AppServices>(
+    State(state): State<Arc<AppState<S>>>,
+    user: AuthenticatedUser,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let secret = totp::generate_secret();
+    let otpauth_uri = totp::otpauth_uri(&secret, &user.email, &TOTP_ISSUER);
+
+    state
+        .user_store
+        .write()
+        .await
+        .set_pending_totp_secret(&user.email, secret.clone())
+        .await
+        .map_err(|e| match e {
+            UserStoreError::UserNotFound => AuthAPIError::UserNotFound,
+            _ => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+
+    let response = TotpEnrollmentResponse {
+        secret: secret.expose_secret().to_string(),
+        otpauth_uri: otpauth_uri.expose_secret().to_string(),
+    };
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// Activates the pending secret once the user proves their app generates matching codes, and issues a fresh
+/// set of recovery codes. This is where an existing factor gets replaced, so it also takes the password or a
+/// code from the current factor, and the owner is emailed.
+#[tracing::instrument(name = "TOTP Confirm POST Request", skip_all)]
+pub async fn post_confirm<S: AppServices>(
+    State(state): State<Arc<AppState<S>>>,
+    user: AuthenticatedUser,
+    Json(request): Json<ConfirmTotpRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let code = TwoFACode::parse(request.code).map_err(|_| AuthAPIError::InvalidTwoFactorAuthCode)?;
+    let current_method = get_two_fa_method(&state, &user.email).await?;
+    reauthenticate(&state, &user.email, current_method, request.reauthentication).await?;
+
+    let mut user_store = state.user_store.write().await;
+    let pending_secret = user_store
+        .get_totp_credential(&user.email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?
+        .pending_secret
+        .ok_or(AuthAPIError::TotpNotEnrolled)?;
+
+    let step = totp::verify_code(
+        &pending_secret,
+        code.as_ref().expose_secret(),
+        Utc::now().timestamp() as u64,
+        *TOTP_SKEW_STEPS,
+    )
+    .map_err(AuthAPIError::UnexpectedError)?
+    .ok_or(AuthAPIError::InvalidTwoFactorAuthCode)?;
+
+    user_store
+        .confirm_totp_secret(&user.email, &pending_secret, step)
+        .await
+        .map_err(|e| match e {
+            UserStoreError::TotpNotEnrolled => AuthAPIError::TotpNotEnrolled,
+            _ => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+
+    let response = issue_recovery_codes(&mut *user_store, &user.email).await?;
+    drop(user_store);
+    tracing::info!("TOTP confirmed");
+
+    // The change has already been made, so a failed notification is logged rather than reported to the client
+    let template_model = PostmarkTemplate::TwoFASettingsChanged(TwoFAMethod::Totp);
+    if let Err(e) = state.email_client.send_email(&user.email, template_model).await {
+        tracing::error!("Error sending 2FA settings notification: {e:?}");
+    }
+
+    Ok((StatusCode::OK, Json(response)))
+}
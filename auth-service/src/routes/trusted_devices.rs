@@ -0,0 +1,103 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use axum_extra::extract::CookieJar;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::api::extractors::AuthenticatedUser;
+use crate::domain::{
+    data_stores::{TrustedDevice, TrustedDeviceStore, TrustedDeviceStoreError},
+    error::AuthAPIError,
+};
+use crate::services::app_state::{AppServices, AppState};
+use crate::utils::{auth::parse_trusted_device_token, constants::TRUSTED_DEVICE_COOKIE_NAME};
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TrustedDeviceResponse {
+    pub id: String,
+    pub created_at: String,
+    pub last_used_at: String,
+    pub expires_at: String,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub current: bool,
+}
+
+impl TrustedDeviceResponse {
+    pub fn new(device: TrustedDevice, current_device_id: Option<&Uuid>) -> Self {
+        Self {
+            current: current_device_id == Some(&device.id),
+            id: device.id.to_string(),
+            created_at: device.created_at.to_rfc3339(),
+            last_used_at: device.last_used_at.to_rfc3339(),
+            expires_at: device.expires_at.to_rfc3339(),
+            ip_address: device.ip_address,
+            user_agent: device.user_agent,
+        }
+    }
+}
+
+#[tracing::instrument(name = "Trusted Devices GET Request", skip_all)]
+pub async fn get<S: AppServices>(
+    State(state): State<Arc<AppState<S>>>,
+    user: AuthenticatedUser,
+    jar: CookieJar,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let current_device_id = match jar.get(TRUSTED_DEVICE_COOKIE_NAME) {
+        Some(cookie) => parse_trusted_device_token(cookie.value())
+            .await
+            .ok()
+            .filter(|(email, _)| *email == user.email)
+            .map(|(_, device_id)| device_id),
+        None => None,
+    };
+
+    let devices = state
+        .trusted_device_store
+        .read()
+        .await
+        .get_devices(&user.email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let response: Vec<TrustedDeviceResponse> = devices
+        .into_iter()
+        .map(|device| TrustedDeviceResponse::new(device, current_device_id.as_ref()))
+        .collect();
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+#[tracing::instrument(name = "Trusted Devices DELETE Request", skip_all)]
+pub async fn delete<S: AppServices>(
+    State(state): State<Arc<AppState<S>>>,
+    user: AuthenticatedUser,
+    Path(device_id): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let device_id = Uuid::parse_str(&device_id).map_err(|_| AuthAPIError::TrustedDeviceNotFound)?;
+
+    let mut trusted_device_store = state.trusted_device_store.write().await;
+    let device = match trusted_device_store.get_device(&device_id).await {
+        Ok(device) => device,
+        Err(TrustedDeviceStoreError::TrustedDeviceNotFound) => return Err(AuthAPIError::TrustedDeviceNotFound),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+    // Other users' devices are reported as missing rather than forbidden
+    if device.email != user.email {
+        return Err(AuthAPIError::TrustedDeviceNotFound);
+    }
+
+    trusted_device_store
+        .remove_device(&device_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
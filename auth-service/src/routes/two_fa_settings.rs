@@ -0,0 +1,165 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+
+use crate::api::extractors::AuthenticatedUser;
+use crate::domain::{
+    data_stores::{UserStore, UserStoreError},
+    email::Email,
+    email_client::EmailClient,
+    error::AuthAPIError,
+    user::TwoFAMethod,
+};
+use crate::routes::login::check_password;
+use crate::routes::phone::get_phone_number;
+use crate::routes::verify_2fa::{verify_recovery_code, verify_totp_code, SecondFactor};
+use crate::services::app_state::{AppServices, AppState};
+use crate::services::postmark_email_client::PostmarkTemplate;
+
+/// Proof of identity for a sensitive change, on top of the auth token: the password, or a code from the
+/// current second factor.
+#[derive(Debug, Deserialize)]
+pub struct Reauthentication {
+    password: Option<Secret<String>>,
+    #[serde(rename = "2FACode")]
+    two_factor_code: Option<Secret<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TwoFASettingsRequest {
+    #[serde(flatten)]
+    reauthentication: Reauthentication,
+    /// Only read when enabling.
+    #[serde(default, rename = "twoFAMethod")]
+    two_fa_method: SentCodeMethod,
+}
+
+/// The methods that can be turned on directly; the rest have their own enrollment flow.
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SentCodeMethod {
+    #[default]
+    Email,
+    Sms,
+}
+
+impl From<SentCodeMethod> for TwoFAMethod {
+    fn from(method: SentCodeMethod) -> Self {
+        match method {
+            SentCodeMethod::Email => TwoFAMethod::Email,
+            SentCodeMethod::Sms => TwoFAMethod::Sms,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TwoFASettingsResponse {
+    #[serde(rename = "twoFAMethod")]
+    pub two_fa_method: TwoFAMethod,
+}
+
+/// Turns on emailed or texted 2FA codes. Texted codes need a verified phone number. Authenticator apps are set
+/// up through TOTP enrollment instead.
+#[tracing::instrument(name = "Enable 2FA POST Request", skip_all)]
+pub async fn post_enable<S: AppServices>(
+    State(state): State<Arc<AppState<S>>>,
+    user: AuthenticatedUser,
+    Json(request): Json<TwoFASettingsRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let current_method = get_two_fa_method(&state, &user.email).await?;
+    if current_method != TwoFAMethod::None {
+        return Err(AuthAPIError::TwoFAAlreadyEnabled);
+    }
+
+    let two_fa_method = TwoFAMethod::from(request.two_fa_method);
+    if two_fa_method == TwoFAMethod::Sms {
+        get_phone_number(&state, &user.email)
+            .await?
+            .phone_number
+            .ok_or(AuthAPIError::PhoneNumberNotVerified)?;
+    }
+
+    reauthenticate(&state, &user.email, current_method, request.reauthentication).await?;
+    update_two_fa_method(&state, &user.email, two_fa_method).await
+}
+
+#[tracing::instrument(name = "Disable 2FA POST Request", skip_all)]
+pub async fn post_disable<S: AppServices>(
+    State(state): State<Arc<AppState<S>>>,
+    user: AuthenticatedUser,
+    Json(request): Json<TwoFASettingsRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let current_method = get_two_fa_method(&state, &user.email).await?;
+    if current_method == TwoFAMethod::None {
+        return Err(AuthAPIError::TwoFANotEnabled);
+    }
+
+    reauthenticate(&state, &user.email, current_method, request.reauthentication).await?;
+    update_two_fa_method(&state, &user.email, TwoFAMethod::None).await
+}
+
+pub async fn get_two_fa_method<S: AppServices>(
+    state: &AppState<S>,
+    email: &Email,
+) -> Result<TwoFAMethod, AuthAPIError> {
+    state
+        .user_store
+        .read()
+        .await
+        .get_user(email)
+        .await
+        .map(|user| user.two_fa_method)
+        .map_err(|e| match e {
+            UserStoreError::UserNotFound => AuthAPIError::UserNotFound,
+            _ => AuthAPIError::UnexpectedError(e.into()),
+        })
+}
+
+/// Emailed and texted codes are only sent during login, so the code option is limited to authenticator-app and
+/// recovery codes.
+pub async fn reauthenticate<S: AppServices>(
+    state: &AppState<S>,
+    email: &Email,
+    current_method: TwoFAMethod,
+    reauthentication: Reauthentication,
+) -> Result<(), AuthAPIError> {
+    match (reauthentication.password, reauthentication.two_factor_code) {
+        (Some(password), _) => check_password(state, email, &password).await.map(|_| ()),
+        (None, Some(code)) => match SecondFactor::parse(code)? {
+            SecondFactor::Code(code) if current_method == TwoFAMethod::Totp => {
+                verify_totp_code(state, email, &code).await
+            }
+            SecondFactor::Code(_) => Err(AuthAPIError::InvalidCredentials),
+            SecondFactor::Recovery(recovery_code) => verify_recovery_code(state, email, &recovery_code).await,
+        },
+        (None, None) => Err(AuthAPIError::InvalidCredentials),
+    }
+}
+
+async fn update_two_fa_method<S: AppServices>(
+    state: &AppState<S>,
+    email: &Email,
+    two_fa_method: TwoFAMethod,
+) -> Result<(StatusCode, Json<TwoFASettingsResponse>), AuthAPIError> {
+    let mut user_store = state.user_store.write().await;
+    let result = match two_fa_method {
+        TwoFAMethod::None => user_store.disable_2fa(email).await,
+        _ => user_store.update_2fa_settings(email, two_fa_method).await,
+    };
+    result.map_err(|e| match e {
+        UserStoreError::UserNotFound => AuthAPIError::UserNotFound,
+        _ => AuthAPIError::UnexpectedError(e.into()),
+    })?;
+    drop(user_store);
+    tracing::info!(%two_fa_method, "2FA settings changed");
+
+    // The change has already been made, so a failed notification is logged rather than reported to the client
+    let template_model = PostmarkTemplate::TwoFASettingsChanged(two_fa_method);
+    if let Err(e) = state.email_client.send_email(email, template_model).await {
+        tracing::error!("Error sending 2FA settings notification: {e:?}");
+    }
+
+    Ok((StatusCode::OK, Json(TwoFASettingsResponse { two_fa_method })))
+}
@@ -2,18 +2,28 @@ use std::{sync::Arc, time::Duration};
 
 use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
 use axum_extra::extract::CookieJar;
-use secrecy::Secret;
+use chrono::Utc;
+use secrecy::{ExposeSecret, Secret};
 use serde::Deserialize;
 use tokio::time::timeout;
 use tracing::debug;
 
+use crate::api::extractors::ClientInfo;
 use crate::domain::{
-    data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore},
+    data_stores::{
+        LoginAttemptId, RecoveryCode, Session, TrustedDevice, TwoFACode, TwoFACodeStore, UserStore, UserStoreError,
+    },
     email::Email,
     error::AuthAPIError,
+    user::{AuthEventKind, TwoFAMethod},
 };
+use crate::routes::account::record_auth_event;
 use crate::services::app_state::{AppServices, AppState};
-use crate::utils::auth::generate_auth_cookie;
+use crate::utils::{
+    auth::{start_session, trust_device},
+    constants::{TOTP_SKEW_STEPS, TWO_FA_MAX_ATTEMPTS},
+    totp,
+};
 
 #[derive(Debug, Deserialize)]
 pub struct Verify2FARequest {
@@ -22,19 +32,37 @@ pub struct Verify2FARequest {
     login_attempt_id: Secret<String>,
     #[serde(rename = "2FACode")]
     two_factor_code: Secret<String>,
+    /// Opts into a trusted-device cookie so later logins from this browser skip 2FA.
+    #[serde(default, rename = "rememberDevice")]
+    remember_device: bool,
+}
+
+/// What the client sent in the `2FACode` field.
+pub enum SecondFactor {
+    Code(TwoFACode),
+    Recovery(RecoveryCode),
+}
+
+impl SecondFactor {
+    pub fn parse(code: Secret<String>) -> Result<Self, AuthAPIError> {
+        TwoFACode::parse(code.clone())
+            .map(SecondFactor::Code)
+            .or_else(|_| RecoveryCode::parse(code).map(SecondFactor::Recovery))
+            .map_err(|_| AuthAPIError::InvalidTwoFactorAuthCode)
+    }
 }
 
 #[tracing::instrument(name = "Verify 2FA POST Request")]
 pub async fn post<S: AppServices>(
     State(state): State<Arc<AppState<S>>>,
     jar: CookieJar,
+    client: ClientInfo,
     Json(payload): Json<Verify2FARequest>,
 ) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
     let email = Email::parse(payload.email).map_err(AuthAPIError::InvalidEmail)?;
     let login_attempt_id =
         LoginAttemptId::parse(payload.login_attempt_id).map_err(|_| AuthAPIError::InvalidLoginAttemptId)?;
-    let two_factor_code =
-        TwoFACode::parse(payload.two_factor_code).map_err(|_| AuthAPIError::InvalidTwoFactorAuthCode)?;
+    let second_factor = SecondFactor::parse(payload.two_factor_code)?;
 
     debug!("payload successfully parsed");
 
@@ -44,28 +72,164 @@ pub async fn post<S: AppServices>(
         Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
     };
 
-    let (stored_attempt_id, stored_2fa_code) = match two_fa_code_store.get_code(&email).await {
-        Ok(result) => result,
-        Err(_) => return Err(AuthAPIError::InvalidCredentials),
+    let stored_2fa_code = match two_fa_code_store.get_code(&email, &login_attempt_id).await {
+        Ok(code) => code,
+        Err(_) => {
+            debug!("Unknown login_attempt_id");
+            return Err(AuthAPIError::InvalidCredentials);
+        }
     };
     debug!("Two factor code retrieved from store");
 
-    if login_attempt_id != stored_attempt_id || two_factor_code != stored_2fa_code {
-        debug!("Incorrect login_attempt_id and two_factor_code");
-        return Err(AuthAPIError::InvalidCredentials);
+    if let Err(e) = check_second_factor(&state, &email, second_factor, &stored_2fa_code).await {
+        if matches!(e, AuthAPIError::InvalidCredentials) {
+            record_failed_attempt(&mut *two_fa_code_store, &email, &login_attempt_id).await?;
+        }
+        return Err(e);
     }
 
     two_fa_code_store
-        .remove_code(&email)
+        .remove_code(&email, &login_attempt_id)
         .await
         .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
     debug!("Two factor auth code successfully removed from store");
 
     drop(two_fa_code_store);
 
-    let auth_cookie = generate_auth_cookie(&email).map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
-    let updated_jar = jar.add(auth_cookie);
-    debug!("Auth cookie successfully created");
+    let access = state
+        .user_store
+        .read()
+        .await
+        .get_access(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    let mut updated_jar = jar;
+    if payload.remember_device {
+        let device = TrustedDevice::new(email.clone(), client.ip_address.clone(), client.user_agent.clone());
+        let trusted_device_cookie = trust_device(state.trusted_device_store.clone(), device)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+        updated_jar = updated_jar.add(trusted_device_cookie);
+        debug!("Trusted device cookie successfully created");
+    }
+
+    record_auth_event(&state, &email, AuthEventKind::SignIn, &client).await;
+    let session = Session::new(email, client.ip_address, client.user_agent);
+    let (auth_cookie, refresh_cookie) = start_session(
+        state.session_store.clone(),
+        state.refresh_token_store.clone(),
+        session,
+        &access,
+    )
+    .await
+    .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    let updated_jar = updated_jar.add(auth_cookie).add(refresh_cookie);
+    debug!("Auth and refresh cookies successfully created");
 
     Ok((updated_jar, StatusCode::OK.into_response()))
 }
+
+async fn check_second_factor<S: AppServices>(
+    state: &AppState<S>,
+    email: &Email,
+    second_factor: SecondFactor,
+    stored_2fa_code: &TwoFACode,
+) -> Result<(), AuthAPIError> {
+    match second_factor {
+        SecondFactor::Recovery(recovery_code) => verify_recovery_code(state, email, &recovery_code).await?,
+        SecondFactor::Code(two_factor_code) => {
+            let user = state
+                .user_store
+                .read()
+                .await
+                .get_user(email)
+                .await
+                .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+            match user.two_fa_method {
+                TwoFAMethod::Totp => verify_totp_code(state, email, &two_factor_code).await?,
+                _ if two_factor_code != *stored_2fa_code => {
+                    debug!("Incorrect two_factor_code");
+                    return Err(AuthAPIError::InvalidCredentials);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Counts a wrong code against the login attempt. Once the limit is reached the attempt is dropped, so the
+/// user has to log in again to get a new code.
+pub async fn record_failed_attempt<T: TwoFACodeStore>(
+    two_fa_code_store: &mut T,
+    email: &Email,
+    login_attempt_id: &LoginAttemptId,
+) -> Result<(), AuthAPIError> {
+    let failed_attempts = two_fa_code_store
+        .record_failed_attempt(email, login_attempt_id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    if failed_attempts >= *TWO_FA_MAX_ATTEMPTS {
+        tracing::warn!(
+            failed_attempts,
+            "Too many failed 2FA attempts, login attempt invalidated"
+        );
+        two_fa_code_store
+            .remove_code(email, login_attempt_id)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    }
+    Ok(())
+}
+
+/// Consumes the recovery code, so it can't be used again.
+pub async fn verify_recovery_code<S: AppServices>(
+    state: &AppState<S>,
+    email: &Email,
+    recovery_code: &RecoveryCode,
+) -> Result<(), AuthAPIError> {
+    state
+        .user_store
+        .write()
+        .await
+        .use_recovery_code(email, &recovery_code.hash())
+        .await
+        .map_err(|e| match e {
+            UserStoreError::RecoveryCodeNotFound => AuthAPIError::InvalidCredentials,
+            _ => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+    tracing::info!("Recovery code used in place of a 2FA code");
+    Ok(())
+}
+
+/// Checks the code against the user's authenticator secret and burns its time step so it can't be replayed.
+pub async fn verify_totp_code<S: AppServices>(
+    state: &AppState<S>,
+    email: &Email,
+    code: &TwoFACode,
+) -> Result<(), AuthAPIError> {
+    let mut user_store = state.user_store.write().await;
+    let secret = user_store
+        .get_totp_credential(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?
+        .secret
+        .ok_or(AuthAPIError::TotpNotEnrolled)?;
+
+    let step = totp::verify_code(
+        &secret,
+        code.as_ref().expose_secret(),
+        Utc::now().timestamp() as u64,
+        *TOTP_SKEW_STEPS,
+    )
+    .map_err(AuthAPIError::UnexpectedError)?
+    .ok_or(AuthAPIError::InvalidCredentials)?;
+
+    user_store.use_totp_step(email, step).await.map_err(|e| match e {
+        UserStoreError::TotpCodeReused => AuthAPIError::InvalidCredentials,
+        _ => AuthAPIError::UnexpectedError(e.into()),
+    })
+}
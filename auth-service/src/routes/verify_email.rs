@@ -0,0 +1,133 @@
+use std::{str::FromStr, sync::Arc};
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use lazy_static::lazy_static;
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::{
+    data_stores::{UserStore, UserStoreError},
+    email::Email,
+    email_client::EmailClient,
+    error::AuthAPIError,
+};
+use crate::services::app_state::{AppServices, AppState};
+use crate::services::postmark_email_client::PostmarkTemplate;
+use crate::utils::{
+    auth::{validate_email_token, EmailVerificationToken, TokenPurpose},
+    constants::{Time, EMAIL_VERIFICATION_MODE},
+};
+
+lazy_static! {
+    static ref RESEND_VERIFICATION_RESPONSE: VerifyEmailResponse = VerifyEmailResponse {
+        message: "If the email belongs to an unverified account, a verification link has been sent.".to_string(),
+    };
+}
+
+/// Whether an unverified user can log in. `Grace` lets them in anyway, for rolling verification out without
+/// locking out users who haven't confirmed yet.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum EmailVerificationMode {
+    #[default]
+    Required,
+    Grace,
+}
+
+impl FromStr for EmailVerificationMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "required" => Ok(Self::Required),
+            "grace" => Ok(Self::Grace),
+            _ => Err(format!("Unknown email verification mode: {value}")),
+        }
+    }
+}
+
+impl EmailVerificationMode {
+    pub fn from_env() -> Self {
+        EmailVerificationMode::from_str(&EMAIL_VERIFICATION_MODE).unwrap_or_else(|e| {
+            tracing::warn!("{e}, falling back to requiring verification");
+            Self::default()
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyEmailQuery {
+    token: Secret<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResendVerificationRequest {
+    email: Secret<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct VerifyEmailResponse {
+    pub message: String,
+}
+
+/// Opened from the emailed link.
+#[tracing::instrument(name = "Verify Email GET Request", skip_all)]
+pub async fn get<S: AppServices>(
+    State(state): State<Arc<AppState<S>>>,
+    Query(query): Query<VerifyEmailQuery>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = validate_email_token(
+        state.banned_token_store.clone(),
+        query.token,
+        TokenPurpose::EmailVerification,
+    )
+    .await
+    .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    state
+        .user_store
+        .write()
+        .await
+        .mark_email_verified(&email)
+        .await
+        .map_err(|e| match e {
+            UserStoreError::UserNotFound => AuthAPIError::InvalidToken,
+            _ => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+    tracing::info!("Email verified");
+
+    let response = VerifyEmailResponse {
+        message: "Email verified successfully.".to_string(),
+    };
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// Sends a new link. The response is the same whether or not the account exists or is already verified.
+#[tracing::instrument(name = "Resend Verification POST Request", skip_all)]
+pub async fn post_resend<S: AppServices>(
+    State(state): State<Arc<AppState<S>>>,
+    Json(payload): Json<ResendVerificationRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = Email::parse(payload.email).map_err(AuthAPIError::InvalidEmail)?;
+
+    let user = state.user_store.read().await.get_user(&email).await;
+    if user.is_ok_and(|user| !user.email_verified) {
+        send_verification_email(&state, &email).await?;
+    }
+
+    Ok((StatusCode::OK, Json(RESEND_VERIFICATION_RESPONSE.clone())))
+}
+
+pub async fn send_verification_email<S: AppServices>(state: &AppState<S>, email: &Email) -> Result<(), AuthAPIError> {
+    let token = EmailVerificationToken::new(email).map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    let template_model = PostmarkTemplate::EmailVerification(Time::Hours24, token);
+    state
+        .email_client
+        .send_email(email, template_model)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.wrap_err("Failed to send verification email")))
+}
@@ -1,25 +1,41 @@
 use std::sync::Arc;
 
-use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum::{
+    extract::{rejection::JsonRejection, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
 use secrecy::Secret;
 use serde::Deserialize;
 
+use crate::api::extractors::BearerToken;
 use crate::domain::error::AuthAPIError;
 use crate::services::app_state::{AppServices, AppState};
-use crate::utils::auth::validate_token;
+use crate::utils::auth::validate_auth_token;
 
 #[derive(Debug, Deserialize)]
 pub struct VerifyTokenRequest {
     token: Secret<String>,
 }
 
-#[tracing::instrument(name = "Verify Auth Token POST Request")]
+/// The token comes from the JSON body when one is sent, otherwise from the
+/// `Authorization` header or the auth cookie.
+#[tracing::instrument(name = "Verify Auth Token POST Request", skip_all)]
 pub async fn post<S: AppServices>(
     State(state): State<Arc<AppState<S>>>,
-    Json(request): Json<VerifyTokenRequest>,
-) -> impl IntoResponse {
-    match validate_token(state.banned_token_store.clone(), request.token).await {
-        Ok(_) => Ok(StatusCode::OK),
-        Err(_) => Err(AuthAPIError::InvalidCredentials),
+    bearer: Result<BearerToken, AuthAPIError>,
+    request: Result<Json<VerifyTokenRequest>, JsonRejection>,
+) -> Response {
+    let token = match (request, bearer) {
+        (Ok(Json(request)), _) => request.token,
+        (Err(JsonRejection::MissingJsonContentType(_)), Ok(BearerToken(token))) => token,
+        (Err(JsonRejection::MissingJsonContentType(_)), Err(e)) => return e.into_response(),
+        (Err(rejection), _) => return rejection.into_response(),
+    };
+
+    match validate_auth_token(state.banned_token_store.clone(), token).await {
+        Ok(_) => StatusCode::OK.into_response(),
+        Err(_) => AuthAPIError::InvalidCredentials.into_response(),
     }
 }
@@ -0,0 +1,519 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum_extra::extract::CookieJar;
+use rand::RngCore;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::api::extractors::{AuthenticatedUser, ClientInfo};
+use crate::domain::{
+    data_stores::{
+        LoginAttemptId, Session, TwoFACodeStore, UserStore, UserStoreError, WebAuthnCeremony, WebAuthnChallenge,
+        WebAuthnChallengeStore, WebAuthnChallengeStoreError,
+    },
+    email::Email,
+    error::AuthAPIError,
+    user::{AuthEventKind, WebAuthnCredential},
+};
+use crate::routes::{account::record_auth_event, verify_email::EmailVerificationMode};
+use crate::services::app_state::{AppServices, AppState};
+use crate::utils::{
+    auth::start_session,
+    constants::{WEBAUTHN_CHALLENGE_TTL_SECONDS, WEBAUTHN_ORIGIN, WEBAUTHN_RP_ID, WEBAUTHN_RP_NAME},
+    webauthn::{
+        decode_base64url, encode_base64url, verify_assertion_signature, AuthenticatorData, ClientData, CosePublicKey,
+        COSE_ALG_ES256, COSE_ALG_RS256,
+    },
+};
+
+const PUBLIC_KEY_CREDENTIAL_TYPE: &str = "public-key";
+const DEFAULT_CREDENTIAL_NAME: &str = "Passkey";
+const USER_HANDLE_BYTES: usize = 16;
+
+//**********************  Options  ************************//
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CreationOptionsResponse {
+    #[serde(rename = "publicKey")]
+    pub public_key: CreationOptions,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreationOptions {
+    pub challenge: String,
+    pub rp: RelyingParty,
+    pub user: UserEntity,
+    pub pub_key_cred_params: Vec<CredentialParameters>,
+    pub timeout: u64,
+    pub exclude_credentials: Vec<CredentialDescriptor>,
+    pub authenticator_selection: AuthenticatorSelection,
+    pub attestation: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RequestOptionsResponse {
+    #[serde(rename = "publicKey")]
+    pub public_key: RequestOptions,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestOptions {
+    pub challenge: String,
+    pub rp_id: String,
+    pub timeout: u64,
+    pub allow_credentials: Vec<CredentialDescriptor>,
+    pub user_verification: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RelyingParty {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserEntity {
+    pub id: String,
+    pub name: String,
+    pub display_name: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CredentialParameters {
+    #[serde(rename = "type")]
+    pub credential_type: String,
+    pub alg: i64,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CredentialDescriptor {
+    #[serde(rename = "type")]
+    pub credential_type: String,
+    pub id: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthenticatorSelection {
+    pub resident_key: String,
+    pub user_verification: String,
+}
+
+//**********************  Requests  ************************//
+
+#[derive(Debug, Deserialize)]
+pub struct RegisterFinishRequest {
+    pub name: Option<String>,
+    pub credential: RegistrationCredential,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RegistrationCredential {
+    pub response: AttestationResponse,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AttestationResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "attestationObject")]
+    pub attestation_object: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AssertionCredential {
+    pub id: String,
+    pub response: AssertionResponse,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AssertionResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "authenticatorData")]
+    pub authenticator_data: String,
+    pub signature: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoginStartRequest {
+    pub email: Secret<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SecondFactorStartRequest {
+    pub email: Secret<String>,
+    #[serde(rename = "loginAttemptId")]
+    pub login_attempt_id: Secret<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct WebAuthnCredentialResponse {
+    pub id: String,
+    pub name: String,
+}
+
+//**********************  Handlers  ************************//
+
+#[tracing::instrument(name = "WebAuthn Register Start POST Request", skip_all)]
+pub async fn post_register_start<S: AppServices>(
+    State(state): State<Arc<AppState<S>>>,
+    user: AuthenticatedUser,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let exclude_credentials = credential_descriptors(&state, &user.email).await?;
+    let challenge = issue_challenge(&state, user.email.clone(), WebAuthnCeremony::Registration).await?;
+
+    // Credentials are not discoverable, so the user handle only has to be opaque
+    let mut user_handle = [0u8; USER_HANDLE_BYTES];
+    rand::thread_rng().fill_bytes(&mut user_handle);
+    let email = user.email.as_ref().expose_secret().to_string();
+
+    let response = CreationOptionsResponse {
+        public_key: CreationOptions {
+            challenge,
+            rp: RelyingParty {
+                id: WEBAUTHN_RP_ID.to_string(),
+                name: WEBAUTHN_RP_NAME.to_string(),
+            },
+            user: UserEntity {
+                id: encode_base64url(&user_handle),
+                name: email.clone(),
+                display_name: email,
+            },
+            pub_key_cred_params: [COSE_ALG_ES256, COSE_ALG_RS256]
+                .into_iter()
+                .map(|alg| CredentialParameters {
+                    credential_type: PUBLIC_KEY_CREDENTIAL_TYPE.to_string(),
+                    alg,
+                })
+                .collect(),
+            timeout: timeout_millis(),
+            exclude_credentials,
+            authenticator_selection: AuthenticatorSelection {
+                resident_key: "discouraged".to_string(),
+                user_verification: "preferred".to_string(),
+            },
+            attestation: "none".to_string(),
+        },
+    };
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// Stores the credential created for a registration challenge. Attestation is not verified.
+#[tracing::instrument(name = "WebAuthn Register Finish POST Request", skip_all)]
+pub async fn post_register_finish<S: AppServices>(
+    State(state): State<Arc<AppState<S>>>,
+    user: AuthenticatedUser,
+    Json(request): Json<RegisterFinishRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let response = request.credential.response;
+    let client_data_json = decode(&response.client_data_json)?;
+    let challenge = take_challenge(&state, &client_data_json, "webauthn.create").await?;
+    if challenge.ceremony != WebAuthnCeremony::Registration || challenge.email != user.email {
+        return Err(AuthAPIError::InvalidCredentials);
+    }
+
+    let authenticator_data = AuthenticatorData::from_attestation_object(&decode(&response.attestation_object)?)
+        .map_err(|_| AuthAPIError::InvalidWebAuthnResponse)?;
+    authenticator_data
+        .verify(&WEBAUTHN_RP_ID, false)
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let attested_credential = authenticator_data
+        .attested_credential
+        .ok_or(AuthAPIError::InvalidWebAuthnResponse)?;
+    CosePublicKey::parse(&attested_credential.public_key).map_err(|_| AuthAPIError::InvalidWebAuthnResponse)?;
+
+    let credential = WebAuthnCredential {
+        id: encode_base64url(&attested_credential.credential_id),
+        name: request
+            .name
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_CREDENTIAL_NAME.to_string()),
+        public_key: attested_credential.public_key,
+        sign_count: authenticator_data.sign_count,
+    };
+    state
+        .user_store
+        .write()
+        .await
+        .add_webauthn_credential(&user.email, credential.clone())
+        .await
+        .map_err(|e| match e {
+            UserStoreError::WebAuthnCredentialAlreadyExists => AuthAPIError::WebAuthnCredentialAlreadyExists,
+            UserStoreError::UserNotFound => AuthAPIError::UserNotFound,
+            _ => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+
+    let response = WebAuthnCredentialResponse {
+        id: credential.id,
+        name: credential.name,
+    };
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+#[tracing::instrument(name = "WebAuthn Login Start POST Request", skip_all)]
+pub async fn post_login_start<S: AppServices>(
+    State(state): State<Arc<AppState<S>>>,
+    Json(request): Json<LoginStartRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = Email::parse(request.email).map_err(AuthAPIError::InvalidEmail)?;
+    let response = request_options(&state, email, WebAuthnCeremony::Login, "required").await?;
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// Passwordless login. The passkey stands in for both factors, so user verification is required.
+#[tracing::instrument(name = "WebAuthn Login Finish POST Request", skip_all)]
+pub async fn post_login_finish<S: AppServices>(
+    State(state): State<Arc<AppState<S>>>,
+    jar: CookieJar,
+    client: ClientInfo,
+    Json(credential): Json<AssertionCredential>,
+) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
+    let challenge = verify_assertion(&state, &credential, true).await?;
+    if challenge.ceremony != WebAuthnCeremony::Login {
+        return Err(AuthAPIError::InvalidCredentials);
+    }
+
+    let jar = start_user_session(&state, jar, client, challenge.email).await?;
+    Ok((jar, StatusCode::OK))
+}
+
+/// Offers a passkey in place of the code for a login attempt that is waiting on its second factor.
+#[tracing::instrument(name = "WebAuthn 2FA Start POST Request", skip_all)]
+pub async fn post_2fa_start<S: AppServices>(
+    State(state): State<Arc<AppState<S>>>,
+    Json(request): Json<SecondFactorStartRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = Email::parse(request.email).map_err(AuthAPIError::InvalidEmail)?;
+    let login_attempt_id =
+        LoginAttemptId::parse(request.login_attempt_id).map_err(|_| AuthAPIError::InvalidLoginAttemptId)?;
+
+    state
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&email, &login_attempt_id)
+        .await
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let ceremony = WebAuthnCeremony::SecondFactor {
+        login_attempt_id: login_attempt_id.expose_secret_string(),
+    };
+    let response = request_options(&state, email, ceremony, "preferred").await?;
+    Ok((StatusCode::OK, Json(response)))
+}
+
+#[tracing::instrument(name = "WebAuthn 2FA Finish POST Request", skip_all)]
+pub async fn post_2fa_finish<S: AppServices>(
+    State(state): State<Arc<AppState<S>>>,
+    jar: CookieJar,
+    client: ClientInfo,
+    Json(credential): Json<AssertionCredential>,
+) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
+    // Same lock order as verify-2fa: the code store before the user store
+    let mut two_fa_code_store = state.two_fa_code_store.write().await;
+
+    let challenge = verify_assertion(&state, &credential, false).await?;
+    let WebAuthnCeremony::SecondFactor { login_attempt_id } = challenge.ceremony else {
+        return Err(AuthAPIError::InvalidCredentials);
+    };
+
+    // The login attempt may have expired or been evicted since the challenge was issued
+    let login_attempt_id =
+        LoginAttemptId::parse(Secret::new(login_attempt_id)).map_err(|_| AuthAPIError::InvalidLoginAttemptId)?;
+    two_fa_code_store
+        .remove_code(&challenge.email, &login_attempt_id)
+        .await
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+    drop(two_fa_code_store);
+
+    let jar = start_user_session(&state, jar, client, challenge.email).await?;
+    Ok((jar, StatusCode::OK))
+}
+
+//**********************  Helpers  ************************//
+
+fn decode(value: &str) -> Result<Vec<u8>, AuthAPIError> {
+    decode_base64url(value).map_err(|_| AuthAPIError::InvalidWebAuthnResponse)
+}
+
+fn timeout_millis() -> u64 {
+    WEBAUTHN_CHALLENGE_TTL_SECONDS as u64 * 1000
+}
+
+async fn credential_descriptors<S: AppServices>(
+    state: &AppState<S>,
+    email: &Email,
+) -> Result<Vec<CredentialDescriptor>, AuthAPIError> {
+    let credentials = state
+        .user_store
+        .read()
+        .await
+        .get_webauthn_credentials(email)
+        .await
+        .map_err(|e| match e {
+            // Answered like a user without passkeys, so the response doesn't reveal whether the account exists
+            UserStoreError::UserNotFound => AuthAPIError::InvalidCredentials,
+            _ => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+
+    Ok(credentials
+        .into_iter()
+        .map(|credential| CredentialDescriptor {
+            credential_type: PUBLIC_KEY_CREDENTIAL_TYPE.to_string(),
+            id: credential.id,
+        })
+        .collect())
+}
+
+async fn issue_challenge<S: AppServices>(
+    state: &AppState<S>,
+    email: Email,
+    ceremony: WebAuthnCeremony,
+) -> Result<String, AuthAPIError> {
+    let challenge = WebAuthnChallenge::new(email, ceremony);
+    let value = challenge.challenge.clone();
+    state
+        .webauthn_challenge_store
+        .write()
+        .await
+        .add_challenge(challenge)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    Ok(value)
+}
+
+async fn request_options<S: AppServices>(
+    state: &AppState<S>,
+    email: Email,
+    ceremony: WebAuthnCeremony,
+    user_verification: &str,
+) -> Result<RequestOptionsResponse, AuthAPIError> {
+    let allow_credentials = credential_descriptors(state, &email).await?;
+    if allow_credentials.is_empty() {
+        return Err(AuthAPIError::InvalidCredentials);
+    }
+    let challenge = issue_challenge(state, email, ceremony).await?;
+
+    Ok(RequestOptionsResponse {
+        public_key: RequestOptions {
+            challenge,
+            rp_id: WEBAUTHN_RP_ID.to_string(),
+            timeout: timeout_millis(),
+            allow_credentials,
+            user_verification: user_verification.to_string(),
+        },
+    })
+}
+
+/// Checks the client data and consumes the challenge it names, so each challenge can only be answered once.
+async fn take_challenge<S: AppServices>(
+    state: &AppState<S>,
+    client_data_json: &[u8],
+    ceremony_type: &str,
+) -> Result<WebAuthnChallenge, AuthAPIError> {
+    let client_data = ClientData::parse(client_data_json).map_err(|_| AuthAPIError::InvalidWebAuthnResponse)?;
+    client_data
+        .verify(ceremony_type, &WEBAUTHN_ORIGIN)
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    state
+        .webauthn_challenge_store
+        .write()
+        .await
+        .take_challenge(&client_data.challenge)
+        .await
+        .map_err(|e| match e {
+            WebAuthnChallengeStoreError::ChallengeNotFound => AuthAPIError::InvalidCredentials,
+            _ => AuthAPIError::UnexpectedError(e.into()),
+        })
+}
+
+/// Verifies an assertion against the stored credential and records its new signature counter.
+async fn verify_assertion<S: AppServices>(
+    state: &AppState<S>,
+    credential: &AssertionCredential,
+    require_user_verification: bool,
+) -> Result<WebAuthnChallenge, AuthAPIError> {
+    let client_data_json = decode(&credential.response.client_data_json)?;
+    let authenticator_data_bytes = decode(&credential.response.authenticator_data)?;
+    let signature = decode(&credential.response.signature)?;
+    let challenge = take_challenge(state, &client_data_json, "webauthn.get").await?;
+
+    let mut user_store = state.user_store.write().await;
+    let stored_credential = user_store
+        .get_webauthn_credentials(&challenge.email)
+        .await
+        .map_err(|e| match e {
+            // Deleted since the challenge was issued
+            UserStoreError::UserNotFound => AuthAPIError::InvalidCredentials,
+            _ => AuthAPIError::UnexpectedError(e.into()),
+        })?
+        .into_iter()
+        .find(|stored| stored.id == credential.id.trim_end_matches('='))
+        .ok_or(AuthAPIError::InvalidCredentials)?;
+
+    let authenticator_data =
+        AuthenticatorData::parse(&authenticator_data_bytes).map_err(|_| AuthAPIError::InvalidWebAuthnResponse)?;
+    authenticator_data
+        .verify(&WEBAUTHN_RP_ID, require_user_verification)
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+    verify_assertion_signature(
+        &stored_credential.public_key,
+        &authenticator_data_bytes,
+        &client_data_json,
+        &signature,
+    )
+    .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    // Authenticators without a counter always report 0; otherwise it must move forward or the key was cloned
+    let sign_count = authenticator_data.sign_count;
+    if sign_count != 0 || stored_credential.sign_count != 0 {
+        if sign_count <= stored_credential.sign_count {
+            tracing::warn!("WebAuthn signature counter did not increase");
+            return Err(AuthAPIError::InvalidCredentials);
+        }
+        user_store
+            .update_webauthn_sign_count(&stored_credential.id, sign_count)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    }
+
+    Ok(challenge)
+}
+
+async fn start_user_session<S: AppServices>(
+    state: &AppState<S>,
+    jar: CookieJar,
+    client: ClientInfo,
+    email: Email,
+) -> Result<CookieJar, AuthAPIError> {
+    let user_store = state.user_store.read().await;
+    let user = user_store.get_user(&email).await.map_err(|e| match e {
+        UserStoreError::UserNotFound => AuthAPIError::InvalidCredentials,
+        _ => AuthAPIError::UnexpectedError(e.into()),
+    })?;
+    if !user.email_verified && EmailVerificationMode::from_env() == EmailVerificationMode::Required {
+        return Err(AuthAPIError::EmailNotVerified);
+    }
+    let access = user_store
+        .get_access(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    drop(user_store);
+    record_auth_event(state, &email, AuthEventKind::SignIn, &client).await;
+    let session = Session::new(email, client.ip_address, client.user_agent);
+    let (auth_cookie, refresh_cookie) = start_session(
+        state.session_store.clone(),
+        state.refresh_token_store.clone(),
+        session,
+        &access,
+    )
+    .await
+    .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    Ok(jar.add(auth_cookie).add(refresh_cookie))
+}
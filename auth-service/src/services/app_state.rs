@@ -3,8 +3,12 @@ use std::{fmt, sync::Arc};
 use tokio::sync::RwLock;
 
 use crate::domain::{
-    data_stores::{BannedTokenStore, PasswordResetTokenStore, TwoFACodeStore, UserStore},
+    data_stores::{
+        BannedTokenStore, LoginFailureStore, PasswordResetTokenStore, RefreshTokenStore, SessionStore,
+        TrustedDeviceStore, TwoFACodeStore, UserStore, WebAuthnChallengeStore,
+    },
     email_client::EmailClient,
+    sms_client::SmsClient,
 };
 
 pub trait AppServices: fmt::Debug {
@@ -12,7 +16,13 @@ pub trait AppServices: fmt::Debug {
     type UserStore: UserStore + fmt::Debug + 'static;
     type TwoFACodeStore: TwoFACodeStore + fmt::Debug + 'static;
     type PasswordResetTokenStore: PasswordResetTokenStore + fmt::Debug + 'static;
+    type RefreshTokenStore: RefreshTokenStore + fmt::Debug + 'static;
+    type SessionStore: SessionStore + fmt::Debug + 'static;
     type EmailClient: EmailClient + fmt::Debug + 'static;
+    type WebAuthnChallengeStore: WebAuthnChallengeStore + fmt::Debug + 'static;
+    type TrustedDeviceStore: TrustedDeviceStore + fmt::Debug + 'static;
+    type SmsClient: SmsClient + fmt::Debug + 'static;
+    type LoginFailureStore: LoginFailureStore + fmt::Debug + 'static;
 }
 
 #[derive(Clone, Debug)]
@@ -20,40 +30,78 @@ pub struct AppState<S: AppServices> {
     pub banned_token_store: Arc<RwLock<S::BannedTokenStore>>,
     pub user_store: Arc<RwLock<S::UserStore>>,
     pub two_fa_code_store: Arc<RwLock<S::TwoFACodeStore>>,
+    /// Codes texted to confirm a phone number, kept apart from the login codes
+    pub phone_verification_code_store: Arc<RwLock<S::TwoFACodeStore>>,
     pub email_client: Arc<S::EmailClient>,
     pub password_reset_token_store: Arc<RwLock<S::PasswordResetTokenStore>>,
+    pub refresh_token_store: Arc<RwLock<S::RefreshTokenStore>>,
+    pub session_store: Arc<RwLock<S::SessionStore>>,
+    pub webauthn_challenge_store: Arc<RwLock<S::WebAuthnChallengeStore>>,
+    pub trusted_device_store: Arc<RwLock<S::TrustedDeviceStore>>,
+    pub sms_client: Arc<S::SmsClient>,
+    pub login_failure_store: Arc<RwLock<S::LoginFailureStore>>,
 }
 
 impl<S: AppServices> AppState<S> {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         banned_token_store: S::BannedTokenStore,
         user_store: S::UserStore,
         two_factor_code_store: S::TwoFACodeStore,
+        phone_verification_code_store: S::TwoFACodeStore,
         email_client: S::EmailClient,
         password_reset_token_store: S::PasswordResetTokenStore,
+        refresh_token_store: S::RefreshTokenStore,
+        session_store: S::SessionStore,
+        webauthn_challenge_store: S::WebAuthnChallengeStore,
+        trusted_device_store: S::TrustedDeviceStore,
+        sms_client: S::SmsClient,
+        login_failure_store: S::LoginFailureStore,
     ) -> Self {
         Self {
             banned_token_store: Arc::new(RwLock::new(banned_token_store)),
             user_store: Arc::new(RwLock::new(user_store)),
             two_fa_code_store: Arc::new(RwLock::new(two_factor_code_store)),
+            phone_verification_code_store: Arc::new(RwLock::new(phone_verification_code_store)),
             email_client: Arc::new(email_client),
             password_reset_token_store: Arc::new(RwLock::new(password_reset_token_store)),
+            refresh_token_store: Arc::new(RwLock::new(refresh_token_store)),
+            session_store: Arc::new(RwLock::new(session_store)),
+            webauthn_challenge_store: Arc::new(RwLock::new(webauthn_challenge_store)),
+            trusted_device_store: Arc::new(RwLock::new(trusted_device_store)),
+            sms_client: Arc::new(sms_client),
+            login_failure_store: Arc::new(RwLock::new(login_failure_store)),
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn new_arc(
         banned_token_store: S::BannedTokenStore,
         user_store: S::UserStore,
         two_factor_code_store: S::TwoFACodeStore,
+        phone_verification_code_store: S::TwoFACodeStore,
         email_client: S::EmailClient,
         password_reset_token_store: S::PasswordResetTokenStore,
+        refresh_token_store: S::RefreshTokenStore,
+        session_store: S::SessionStore,
+        webauthn_challenge_store: S::WebAuthnChallengeStore,
+        trusted_device_store: S::TrustedDeviceStore,
+        sms_client: S::SmsClient,
+        login_failure_store: S::LoginFailureStore,
     ) -> Arc<Self> {
         Arc::new(Self::new(
             banned_token_store,
             user_store,
             two_factor_code_store,
+            phone_verification_code_store,
             email_client,
             password_reset_token_store,
+            refresh_token_store,
+            session_store,
+            webauthn_challenge_store,
+            trusted_device_store,
+            sms_client,
+            login_failure_store,
         ))
     }
 }
@@ -4,14 +4,25 @@ use super::{
     app_state::{AppServices, AppState},
     data_stores::{
         postgres_user_store::PostgresUserStore, redis_banned_token_store::RedisBannedTokenStore,
-        redis_password_reset_token_store::RedisPasswordResetTokenStore, redis_two_fa_code_store::RedisTwoFACodeStore,
+        redis_login_failure_store::RedisLoginFailureStore,
+        redis_password_reset_token_store::RedisPasswordResetTokenStore,
+        redis_refresh_token_store::RedisRefreshTokenStore, redis_session_store::RedisSessionStore,
+        redis_trusted_device_store::RedisTrustedDeviceStore, redis_two_fa_code_store::RedisTwoFACodeStore,
+        redis_webauthn_challenge_store::RedisWebAuthnChallengeStore,
     },
     hashmap_banned_token_store::HashMapBannedTokenStore,
+    hashmap_login_failure_store::HashMapLoginFailureStore,
     hashmap_password_reset_token_store::HashMapPasswordResetTokenStore,
+    hashmap_refresh_token_store::HashMapRefreshTokenStore,
+    hashmap_session_store::HashMapSessionStore,
+    hashmap_trusted_device_store::HashMapTrustedDeviceStore,
     hashmap_two_fa_code_store::HashMapTwoFACodeStore,
     hashmap_user_store::HashmapUserStore,
+    hashmap_webauthn_challenge_store::HashMapWebAuthnChallengeStore,
     mock_email_client::MockEmailClient,
+    mock_sms_client::MockSmsClient,
     postmark_email_client::PostmarkEmailClient,
+    twilio_sms_client::TwilioSmsClient,
 };
 
 #[derive(Debug)]
@@ -22,7 +33,13 @@ impl AppServices for MemoryServices {
     type UserStore = HashmapUserStore;
     type TwoFACodeStore = HashMapTwoFACodeStore;
     type PasswordResetTokenStore = HashMapPasswordResetTokenStore;
+    type RefreshTokenStore = HashMapRefreshTokenStore;
+    type SessionStore = HashMapSessionStore;
     type EmailClient = MockEmailClient;
+    type WebAuthnChallengeStore = HashMapWebAuthnChallengeStore;
+    type TrustedDeviceStore = HashMapTrustedDeviceStore;
+    type SmsClient = MockSmsClient;
+    type LoginFailureStore = HashMapLoginFailureStore;
 }
 
 #[derive(Debug)]
@@ -33,7 +50,13 @@ impl AppServices for PersistentServices {
     type UserStore = PostgresUserStore;
     type TwoFACodeStore = RedisTwoFACodeStore;
     type PasswordResetTokenStore = RedisPasswordResetTokenStore;
+    type RefreshTokenStore = RedisRefreshTokenStore;
+    type SessionStore = RedisSessionStore;
     type EmailClient = PostmarkEmailClient;
+    type WebAuthnChallengeStore = RedisWebAuthnChallengeStore;
+    type TrustedDeviceStore = RedisTrustedDeviceStore;
+    type SmsClient = TwilioSmsClient;
+    type LoginFailureStore = RedisLoginFailureStore;
 }
 
 pub type MemoryAppStateType = Arc<AppState<MemoryServices>>;
@@ -1,4 +1,9 @@
 pub mod postgres_user_store;
 pub mod redis_banned_token_store;
+pub mod redis_login_failure_store;
 pub mod redis_password_reset_token_store;
+pub mod redis_refresh_token_store;
+pub mod redis_session_store;
+pub mod redis_trusted_device_store;
 pub mod redis_two_fa_code_store;
+pub mod redis_webauthn_challenge_store;
@@ -1,5 +1,6 @@
+use chrono::DateTime;
 use color_eyre::eyre;
-use secrecy::ExposeSecret;
+use secrecy::{ExposeSecret, Secret};
 use sqlx::PgPool;
 
 use crate::{
@@ -7,9 +8,13 @@ use crate::{
         data_stores::{UserStore, UserStoreError},
         email::Email,
         password::Password,
-        user::{DbUser, NewUser, User},
+        phone_number::PhoneNumber,
+        user::{
+            AuthEvent, DbUser, ImportedUser, NewUser, PasswordHashCounts, TotpCredential, TwoFAMethod, User,
+            UserAccess, UserPhoneNumber, WebAuthnCredential, DEFAULT_ROLE,
+        },
     },
-    utils::auth::async_compute_password_hash,
+    utils::{auth::async_compute_password_hash, constants::MAX_AUTH_EVENTS, password_hash::PasswordHashPolicy},
 };
 
 #[derive(Clone, Debug)]
@@ -21,35 +26,88 @@ impl PostgresUserStore {
     pub fn new(pool: PgPool) -> Self {
         Self { pool }
     }
-}
 
-#[async_trait::async_trait]
-impl UserStore for PostgresUserStore {
-    #[tracing::instrument(name = "Adding user to PostgreSQL", skip_all)]
-    async fn add_user(&mut self, user: NewUser) -> Result<(), UserStoreError> {
-        let password_hash = async_compute_password_hash(user.password.as_ref().clone())
+    /// Inserts the user with their default role, storing `password_hash` as given. A user scheduled for deletion
+    /// under the same address is removed first.
+    async fn insert_user(
+        &self,
+        email: &Email,
+        password_hash: &Secret<String>,
+        two_fa_method: TwoFAMethod,
+        email_verified: bool,
+    ) -> Result<(), UserStoreError> {
+        let mut transaction = self
+            .pool
+            .begin()
             .await
-            .map_err(UserStoreError::UnexpectedError)?;
-        let result = sqlx::query_as!(
-            DbUser,
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+        // An account waiting out its deletion grace period doesn't hold on to the address
+        sqlx::query!(
             r#"
-            INSERT INTO users (email, password_hash, requires_2fa)
-            VALUES ($1, $2, $3)
+            DELETE FROM users
+            WHERE email = $1 AND deleted_at IS NOT NULL
             "#,
-            user.email.as_ref().expose_secret(),
+            email.as_ref().expose_secret(),
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO users (email, password_hash, two_fa_method, verified_at)
+            VALUES ($1, $2, $3, CASE WHEN $4 THEN NOW() END)
+            "#,
+            email.as_ref().expose_secret(),
             password_hash.expose_secret(),
-            user.requires_2fa
+            two_fa_method.as_str(),
+            email_verified
         )
-        .execute(&self.pool)
+        .execute(&mut *transaction)
         .await;
 
         match result {
-            Ok(_) => Ok(()),
+            Ok(_) => {}
             Err(sqlx::Error::Database(db_err)) if db_err.is_unique_violation() => {
-                Err(UserStoreError::UserAlreadyExists)
+                return Err(UserStoreError::UserAlreadyExists)
             }
-            Err(e) => Err(UserStoreError::UnexpectedError(e.into())),
+            Err(e) => return Err(UserStoreError::UnexpectedError(e.into())),
         }
+
+        sqlx::query!(
+            r#"
+            INSERT INTO user_roles (email, role)
+            VALUES ($1, $2)
+            "#,
+            email.as_ref().expose_secret(),
+            DEFAULT_ROLE,
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))
+    }
+}
+
+#[async_trait::async_trait]
+impl UserStore for PostgresUserStore {
+    #[tracing::instrument(name = "Adding user to PostgreSQL", skip_all)]
+    async fn add_user(&mut self, user: NewUser) -> Result<(), UserStoreError> {
+        let password_hash = async_compute_password_hash(user.password.as_ref().clone())
+            .await
+            .map_err(UserStoreError::UnexpectedError)?;
+        self.insert_user(&user.email, &password_hash, user.two_fa_method, false)
+            .await
+    }
+
+    #[tracing::instrument(name = "Importing user into PostgreSQL", skip_all)]
+    async fn import_user(&mut self, user: ImportedUser) -> Result<(), UserStoreError> {
+        self.insert_user(&user.email, &user.password_hash, TwoFAMethod::None, user.email_verified)
+            .await
     }
 
     #[tracing::instrument(name = "Retrieving user from PostgreSQL", skip_all)]
@@ -57,9 +115,9 @@ impl UserStore for PostgresUserStore {
         let user = sqlx::query_as!(
             DbUser,
             r#"
-            SELECT *
+            SELECT email, password_hash, two_fa_method, verified_at IS NOT NULL AS "email_verified!"
             FROM users
-            WHERE email = $1
+            WHERE email = $1 AND deleted_at IS NULL
             "#,
             email.as_ref().expose_secret(),
         )
@@ -98,14 +156,209 @@ impl UserStore for PostgresUserStore {
         }
     }
 
+    #[tracing::instrument(name = "Marking email verified in PostgreSQL", skip_all)]
+    async fn mark_email_verified(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET verified_at = COALESCE(verified_at, NOW())
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        match result.rows_affected() {
+            0 => Err(UserStoreError::UserNotFound),
+            _ => Ok(()),
+        }
+    }
+
+    /// The tables keyed by email go with the user through `ON DELETE CASCADE`.
+    #[tracing::instrument(name = "Deleting user from PostgreSQL", skip_all)]
+    async fn delete_user(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM users
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        match result.rows_affected() {
+            0 => Err(UserStoreError::UserNotFound),
+            _ => Ok(()),
+        }
+    }
+
+    #[tracing::instrument(name = "Scheduling user deletion in PostgreSQL", skip_all)]
+    async fn schedule_deletion(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET deleted_at = NOW()
+            WHERE email = $1 AND deleted_at IS NULL
+            "#,
+            email.as_ref().expose_secret(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        match result.rows_affected() {
+            0 => Err(UserStoreError::UserNotFound),
+            _ => Ok(()),
+        }
+    }
+
+    #[tracing::instrument(name = "Restoring user in PostgreSQL", skip_all)]
+    async fn restore_user(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET deleted_at = NULL
+            WHERE email = $1 AND deleted_at IS NOT NULL
+            "#,
+            email.as_ref().expose_secret(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        match result.rows_affected() {
+            0 => Err(UserStoreError::UserNotFound),
+            _ => Ok(()),
+        }
+    }
+
+    #[tracing::instrument(name = "Purging deleted users from PostgreSQL", skip_all)]
+    async fn purge_deleted_users(&mut self, grace_period_seconds: u64) -> Result<u64, UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM users
+            WHERE deleted_at < NOW() - make_interval(secs => $1)
+            "#,
+            grace_period_seconds as f64,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// The tables keyed by email follow the user through `ON UPDATE CASCADE`.
+    #[tracing::instrument(name = "Changing user email in PostgreSQL", skip_all)]
+    async fn change_email(&mut self, email: &Email, new_email: &Email) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET email = $2
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret(),
+            new_email.as_ref().expose_secret(),
+        )
+        .execute(&self.pool)
+        .await;
+
+        match result {
+            Ok(pg_result) if pg_result.rows_affected() == 0 => Err(UserStoreError::UserNotFound),
+            Ok(_) => Ok(()),
+            Err(sqlx::Error::Database(db_err)) if db_err.is_unique_violation() => {
+                Err(UserStoreError::UserAlreadyExists)
+            }
+            Err(e) => Err(UserStoreError::UnexpectedError(e.into())),
+        }
+    }
+
+    #[tracing::instrument(name = "Updating 2FA settings in PostgreSQL", skip_all)]
+    async fn update_2fa_settings(&mut self, email: &Email, two_fa_method: TwoFAMethod) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET two_fa_method = $2
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret(),
+            two_fa_method.as_str(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        match result.rows_affected() {
+            0 => Err(UserStoreError::UserNotFound),
+            _ => Ok(()),
+        }
+    }
+
+    #[tracing::instrument(name = "Disabling 2FA in PostgreSQL", skip_all)]
+    async fn disable_2fa(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET two_fa_method = $2
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret(),
+            TwoFAMethod::None.as_str(),
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        sqlx::query!(
+            r#"
+            DELETE FROM totp_credentials
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret(),
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        sqlx::query!(
+            r#"
+            DELETE FROM recovery_codes
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret(),
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))
+    }
+
     #[tracing::instrument(name = "Verify password hash", skip_all)]
-    async fn validate_user(&self, email: &Email, password: &Password) -> eyre::Result<User> {
+    async fn validate_user(&self, email: &Email, password: &Secret<String>) -> eyre::Result<User> {
         let user = sqlx::query_as!(
             DbUser,
             r#"
-            SELECT *
+            SELECT email, password_hash, two_fa_method, verified_at IS NOT NULL AS "email_verified!"
             FROM users
-            WHERE email = $1
+            WHERE email = $1 AND deleted_at IS NULL
             "#,
             email.as_ref().expose_secret(),
         )
@@ -114,4 +367,630 @@ impl UserStore for PostgresUserStore {
         user.verify_password(password)?;
         Ok(user.to_user())
     }
+
+    #[tracing::instrument(name = "Retrieving outdated password hash from PostgreSQL", skip_all)]
+    async fn get_outdated_password_hash(&self, email: &Email) -> Result<Option<Secret<String>>, UserStoreError> {
+        let password_hash = sqlx::query_scalar!(
+            r#"
+            SELECT password_hash
+            FROM users
+            WHERE email = $1 AND deleted_at IS NULL
+            "#,
+            email.as_ref().expose_secret(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .ok_or(UserStoreError::UserNotFound)?;
+
+        let policy = PasswordHashPolicy::from_env().map_err(UserStoreError::UnexpectedError)?;
+        match policy.is_current(&password_hash) {
+            true => Ok(None),
+            false => Ok(Some(Secret::new(password_hash))),
+        }
+    }
+
+    #[tracing::instrument(name = "Replacing password hash in PostgreSQL", skip_all)]
+    async fn replace_password_hash(
+        &mut self,
+        email: &Email,
+        outdated_hash: &Secret<String>,
+        password_hash: Secret<String>,
+    ) -> Result<bool, UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET password_hash = $1
+            WHERE email = $2 AND password_hash = $3
+            "#,
+            password_hash.expose_secret(),
+            email.as_ref().expose_secret(),
+            outdated_hash.expose_secret(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[tracing::instrument(name = "Counting password hashes in PostgreSQL", skip_all)]
+    async fn count_password_hashes(&self) -> Result<PasswordHashCounts, UserStoreError> {
+        let policy = PasswordHashPolicy::from_env().map_err(UserStoreError::UnexpectedError)?;
+        let counts = sqlx::query!(
+            r#"
+            SELECT
+                COUNT(*) AS "total!",
+                COUNT(*) FILTER (WHERE LEFT(password_hash, LENGTH($1)) <> $1) AS "outdated!"
+            FROM users
+            "#,
+            policy.hash_prefix(),
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        Ok(PasswordHashCounts {
+            total: counts.total as u64,
+            outdated: counts.outdated as u64,
+        })
+    }
+
+    #[tracing::instrument(name = "Retrieving user roles from PostgreSQL", skip_all)]
+    async fn get_access(&self, email: &Email) -> Result<UserAccess, UserStoreError> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT user_roles.role AS "role?", roles.scopes AS "scopes?"
+            FROM users
+            LEFT JOIN user_roles ON user_roles.email = users.email
+            LEFT JOIN roles ON roles.name = user_roles.role
+            WHERE users.email = $1 AND users.deleted_at IS NULL
+            ORDER BY user_roles.role
+            "#,
+            email.as_ref().expose_secret(),
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        // Every user yields a row, even one without roles
+        if rows.is_empty() {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        let mut roles = Vec::new();
+        let mut scopes = Vec::new();
+        for row in rows {
+            roles.extend(row.role);
+            scopes.extend(row.scopes.unwrap_or_default());
+        }
+        Ok(UserAccess::new(roles, scopes))
+    }
+
+    #[tracing::instrument(name = "Updating user roles in PostgreSQL", skip_all)]
+    async fn set_roles(&mut self, email: &Email, roles: &[String]) -> Result<(), UserStoreError> {
+        let mut roles = roles.to_vec();
+        roles.sort();
+        roles.dedup();
+
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        sqlx::query!(
+            r#"
+            SELECT email
+            FROM users
+            WHERE email = $1
+            FOR UPDATE
+            "#,
+            email.as_ref().expose_secret(),
+        )
+        .fetch_optional(&mut *transaction)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .ok_or(UserStoreError::UserNotFound)?;
+
+        sqlx::query!(
+            r#"
+            DELETE FROM user_roles
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret(),
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO user_roles (email, role)
+            SELECT $1, UNNEST($2::TEXT[])
+            "#,
+            email.as_ref().expose_secret(),
+            &roles,
+        )
+        .execute(&mut *transaction)
+        .await;
+
+        match result {
+            Ok(_) => {}
+            Err(sqlx::Error::Database(db_err)) if db_err.is_foreign_key_violation() => {
+                return Err(UserStoreError::RoleNotFound)
+            }
+            Err(e) => return Err(UserStoreError::UnexpectedError(e.into())),
+        }
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))
+    }
+
+    #[tracing::instrument(name = "Retrieving TOTP credential from PostgreSQL", skip_all)]
+    async fn get_totp_credential(&self, email: &Email) -> Result<TotpCredential, UserStoreError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT totp_credentials.secret, totp_credentials.pending_secret, totp_credentials.last_used_step
+            FROM users
+            LEFT JOIN totp_credentials ON totp_credentials.email = users.email
+            WHERE users.email = $1 AND users.deleted_at IS NULL
+            "#,
+            email.as_ref().expose_secret(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .ok_or(UserStoreError::UserNotFound)?;
+
+        Ok(TotpCredential {
+            secret: row.secret.map(Secret::new),
+            pending_secret: row.pending_secret.map(Secret::new),
+            last_used_step: row.last_used_step.map(|step| step as u64),
+        })
+    }
+
+    #[tracing::instrument(name = "Storing pending TOTP secret in PostgreSQL", skip_all)]
+    async fn set_pending_totp_secret(&mut self, email: &Email, secret: Secret<String>) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO totp_credentials (email, pending_secret)
+            VALUES ($1, $2)
+            ON CONFLICT (email) DO UPDATE SET pending_secret = EXCLUDED.pending_secret
+            "#,
+            email.as_ref().expose_secret(),
+            secret.expose_secret(),
+        )
+        .execute(&self.pool)
+        .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(sqlx::Error::Database(db_err)) if db_err.is_foreign_key_violation() => {
+                Err(UserStoreError::UserNotFound)
+            }
+            Err(e) => Err(UserStoreError::UnexpectedError(e.into())),
+        }
+    }
+
+    #[tracing::instrument(name = "Confirming TOTP secret in PostgreSQL", skip_all)]
+    async fn confirm_totp_secret(
+        &mut self,
+        email: &Email,
+        secret: &Secret<String>,
+        step: u64,
+    ) -> Result<(), UserStoreError> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        let result = sqlx::query!(
+            r#"
+            UPDATE totp_credentials
+            SET secret = pending_secret, pending_secret = NULL, last_used_step = $3
+            WHERE email = $1 AND pending_secret = $2
+            "#,
+            email.as_ref().expose_secret(),
+            secret.expose_secret(),
+            step as i64,
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::TotpNotEnrolled);
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE users
+            SET two_fa_method = $2
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret(),
+            TwoFAMethod::Totp.as_str(),
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))
+    }
+
+    #[tracing::instrument(name = "Recording TOTP step in PostgreSQL", skip_all)]
+    async fn use_totp_step(&mut self, email: &Email, step: u64) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE totp_credentials
+            SET last_used_step = $2
+            WHERE email = $1
+            AND secret IS NOT NULL
+            AND (last_used_step IS NULL OR last_used_step < $2)
+            "#,
+            email.as_ref().expose_secret(),
+            step as i64,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() > 0 {
+            return Ok(());
+        }
+
+        match self.get_totp_credential(email).await?.secret {
+            Some(_) => Err(UserStoreError::TotpCodeReused),
+            None => Err(UserStoreError::TotpNotEnrolled),
+        }
+    }
+
+    #[tracing::instrument(name = "Replacing recovery codes in PostgreSQL", skip_all)]
+    async fn set_recovery_codes(&mut self, email: &Email, code_hashes: Vec<String>) -> Result<(), UserStoreError> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        sqlx::query!(
+            r#"
+            DELETE FROM recovery_codes
+            WHERE email = $1
+            "#,
+            email.as_ref().expose_secret(),
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO recovery_codes (email, code_hash)
+            SELECT $1, UNNEST($2::TEXT[])
+            "#,
+            email.as_ref().expose_secret(),
+            &code_hashes,
+        )
+        .execute(&mut *transaction)
+        .await;
+
+        match result {
+            Ok(_) => {}
+            Err(sqlx::Error::Database(db_err)) if db_err.is_foreign_key_violation() => {
+                return Err(UserStoreError::UserNotFound)
+            }
+            Err(e) => return Err(UserStoreError::UnexpectedError(e.into())),
+        }
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))
+    }
+
+    #[tracing::instrument(name = "Using recovery code in PostgreSQL", skip_all)]
+    async fn use_recovery_code(&mut self, email: &Email, code_hash: &str) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM recovery_codes
+            WHERE email = $1 AND code_hash = $2
+            "#,
+            email.as_ref().expose_secret(),
+            code_hash,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        match result.rows_affected() {
+            0 => Err(UserStoreError::RecoveryCodeNotFound),
+            _ => Ok(()),
+        }
+    }
+
+    #[tracing::instrument(name = "Counting recovery codes in PostgreSQL", skip_all)]
+    async fn count_recovery_codes(&self, email: &Email) -> Result<usize, UserStoreError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT COUNT(recovery_codes.code_hash) AS "count!"
+            FROM users
+            LEFT JOIN recovery_codes ON recovery_codes.email = users.email
+            WHERE users.email = $1 AND users.deleted_at IS NULL
+            GROUP BY users.email
+            "#,
+            email.as_ref().expose_secret(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .ok_or(UserStoreError::UserNotFound)?;
+
+        Ok(row.count as usize)
+    }
+
+    #[tracing::instrument(name = "Adding auth event to PostgreSQL", skip_all)]
+    async fn add_auth_event(&mut self, email: &Email, event: AuthEvent) -> Result<(), UserStoreError> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO auth_events (email, kind, ip_address, user_agent, occurred_at)
+            SELECT email, $2, $3, $4, to_timestamp($5::BIGINT / 1000000.0)
+            FROM users
+            WHERE email = $1 AND deleted_at IS NULL
+            "#,
+            email.as_ref().expose_secret(),
+            event.kind.as_str(),
+            event.ip_address,
+            event.user_agent,
+            event.occurred_at.timestamp_micros(),
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        sqlx::query!(
+            r#"
+            DELETE FROM auth_events
+            WHERE email = $1 AND id NOT IN (
+                SELECT id FROM auth_events WHERE email = $1 ORDER BY id DESC LIMIT $2
+            )
+            "#,
+            email.as_ref().expose_secret(),
+            MAX_AUTH_EVENTS as i64,
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))
+    }
+
+    #[tracing::instrument(name = "Retrieving auth events from PostgreSQL", skip_all)]
+    async fn get_auth_events(&self, email: &Email) -> Result<Vec<AuthEvent>, UserStoreError> {
+        let user = sqlx::query!(
+            r#"SELECT email FROM users WHERE email = $1 AND deleted_at IS NULL"#,
+            email.as_ref().expose_secret(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if user.is_none() {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT kind, ip_address, user_agent,
+                (EXTRACT(EPOCH FROM occurred_at) * 1000000)::BIGINT AS "occurred_at!"
+            FROM auth_events
+            WHERE email = $1
+            ORDER BY id DESC
+            "#,
+            email.as_ref().expose_secret(),
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(AuthEvent {
+                    kind: row.kind.parse().map_err(UserStoreError::UnexpectedError)?,
+                    occurred_at: DateTime::from_timestamp_micros(row.occurred_at)
+                        .ok_or_else(|| UserStoreError::UnexpectedError(eyre::eyre!("Invalid auth event timestamp")))?,
+                    ip_address: row.ip_address,
+                    user_agent: row.user_agent,
+                })
+            })
+            .collect()
+    }
+
+    #[tracing::instrument(name = "Adding WebAuthn credential to PostgreSQL", skip_all)]
+    async fn add_webauthn_credential(
+        &mut self,
+        email: &Email,
+        credential: WebAuthnCredential,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO webauthn_credentials (id, email, name, public_key, sign_count)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+            credential.id,
+            email.as_ref().expose_secret(),
+            credential.name,
+            credential.public_key,
+            credential.sign_count as i64,
+        )
+        .execute(&self.pool)
+        .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(sqlx::Error::Database(db_err)) if db_err.is_unique_violation() => {
+                Err(UserStoreError::WebAuthnCredentialAlreadyExists)
+            }
+            Err(sqlx::Error::Database(db_err)) if db_err.is_foreign_key_violation() => {
+                Err(UserStoreError::UserNotFound)
+            }
+            Err(e) => Err(UserStoreError::UnexpectedError(e.into())),
+        }
+    }
+
+    #[tracing::instrument(name = "Retrieving WebAuthn credentials from PostgreSQL", skip_all)]
+    async fn get_webauthn_credentials(&self, email: &Email) -> Result<Vec<WebAuthnCredential>, UserStoreError> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT webauthn_credentials.id AS "id?",
+                webauthn_credentials.name AS "name?",
+                webauthn_credentials.public_key AS "public_key?",
+                webauthn_credentials.sign_count AS "sign_count?"
+            FROM users
+            LEFT JOIN webauthn_credentials ON webauthn_credentials.email = users.email
+            WHERE users.email = $1 AND users.deleted_at IS NULL
+            ORDER BY webauthn_credentials.created_at
+            "#,
+            email.as_ref().expose_secret(),
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        // Every user yields a row, even one without credentials
+        if rows.is_empty() {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| {
+                Some(WebAuthnCredential {
+                    id: row.id?,
+                    name: row.name?,
+                    public_key: row.public_key?,
+                    sign_count: row.sign_count? as u32,
+                })
+            })
+            .collect())
+    }
+
+    #[tracing::instrument(name = "Updating WebAuthn sign count in PostgreSQL", skip_all)]
+    async fn update_webauthn_sign_count(&mut self, credential_id: &str, sign_count: u32) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE webauthn_credentials
+            SET sign_count = $2
+            WHERE id = $1
+            "#,
+            credential_id,
+            sign_count as i64,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        match result.rows_affected() {
+            0 => Err(UserStoreError::UserNotFound),
+            _ => Ok(()),
+        }
+    }
+
+    #[tracing::instrument(name = "Retrieving phone number from PostgreSQL", skip_all)]
+    async fn get_phone_number(&self, email: &Email) -> Result<UserPhoneNumber, UserStoreError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT phone_numbers.phone_number, phone_numbers.pending_phone_number, phone_numbers.pending_verification_id
+            FROM users
+            LEFT JOIN phone_numbers ON phone_numbers.email = users.email
+            WHERE users.email = $1 AND users.deleted_at IS NULL
+            "#,
+            email.as_ref().expose_secret(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .ok_or(UserStoreError::UserNotFound)?;
+
+        let parse = |phone_number: String| {
+            PhoneNumber::parse(Secret::new(phone_number)).map_err(|e| UserStoreError::UnexpectedError(eyre::eyre!(e)))
+        };
+        Ok(UserPhoneNumber {
+            phone_number: row.phone_number.map(parse).transpose()?,
+            pending_phone_number: row.pending_phone_number.map(parse).transpose()?,
+            pending_verification_id: row.pending_verification_id,
+        })
+    }
+
+    #[tracing::instrument(name = "Storing pending phone number in PostgreSQL", skip_all)]
+    async fn set_pending_phone_number(
+        &mut self,
+        email: &Email,
+        phone_number: PhoneNumber,
+        verification_id: &str,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO phone_numbers (email, pending_phone_number, pending_verification_id)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (email) DO UPDATE
+            SET pending_phone_number = EXCLUDED.pending_phone_number,
+                pending_verification_id = EXCLUDED.pending_verification_id
+            "#,
+            email.as_ref().expose_secret(),
+            phone_number.as_ref().expose_secret(),
+            verification_id,
+        )
+        .execute(&self.pool)
+        .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(sqlx::Error::Database(db_err)) if db_err.is_foreign_key_violation() => {
+                Err(UserStoreError::UserNotFound)
+            }
+            Err(e) => Err(UserStoreError::UnexpectedError(e.into())),
+        }
+    }
+
+    #[tracing::instrument(name = "Confirming phone number in PostgreSQL", skip_all)]
+    async fn confirm_phone_number(&mut self, email: &Email, verification_id: &str) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE phone_numbers
+            SET phone_number = pending_phone_number, pending_phone_number = NULL, pending_verification_id = NULL
+            WHERE email = $1 AND pending_verification_id = $2
+            "#,
+            email.as_ref().expose_secret(),
+            verification_id,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        match result.rows_affected() {
+            0 => Err(UserStoreError::PhoneVerificationNotFound),
+            _ => Ok(()),
+        }
+    }
 }
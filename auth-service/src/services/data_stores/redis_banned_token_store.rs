@@ -1,12 +1,20 @@
 use std::{fmt, sync::Arc};
 
+use chrono::Utc;
 use redis::{aio::ConnectionManager, AsyncCommands};
 use secrecy::{ExposeSecret, Secret};
 use tokio::sync::RwLock;
+use uuid::Uuid;
 
 use crate::{
-    domain::data_stores::{BannedTokenStore, TokenStoreError},
-    utils::constants::TOKEN_TTL_SECONDS,
+    domain::{
+        data_stores::{BannedTokenStore, TokenStoreError},
+        email::Email,
+    },
+    utils::{
+        auth::validate_token_structure,
+        constants::{Epoch, MAX_TOKEN_TTL_SECONDS, TOKEN_TTL_SECONDS},
+    },
 };
 
 #[derive(Clone)]
@@ -30,10 +38,18 @@ impl RedisBannedTokenStore {
 impl BannedTokenStore for RedisBannedTokenStore {
     #[tracing::instrument(name = "RedisBannedTokenStore Add Token")]
     async fn add_token(&mut self, token: Secret<String>) -> Result<(), TokenStoreError> {
+        // Emailed links live far longer than auth tokens, so the ban lasts for whatever is left of the token
+        let claims = validate_token_structure(token.expose_secret())
+            .await
+            .map_err(|_| TokenStoreError::InvalidToken)?;
+        let ttl_seconds = u64::from(claims.exp)
+            .saturating_sub(Utc::now().timestamp() as u64)
+            .max(1);
+
         let mut conn = self.conn.write().await;
         let key = get_key(&token);
 
-        conn.set_ex(key, true, TOKEN_TTL_SECONDS as u64)
+        conn.set_ex(key, true, ttl_seconds)
             .await
             .map_err(|e| TokenStoreError::UnexpectedError(e.into()))?;
 
@@ -54,10 +70,73 @@ impl BannedTokenStore for RedisBannedTokenStore {
             false => Ok(()),
         }
     }
+
+    #[tracing::instrument(name = "RedisBannedTokenStore Ban Session")]
+    async fn ban_session(&mut self, session_id: &Uuid) -> Result<(), TokenStoreError> {
+        let mut conn = self.conn.write().await;
+
+        // Auth tokens outlive their session by at most one token lifetime
+        conn.set_ex(get_session_key(session_id), true, TOKEN_TTL_SECONDS as u64)
+            .await
+            .map_err(|e| TokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "RedisBannedTokenStore Check Session")]
+    async fn check_session(&self, session_id: &Uuid) -> Result<(), TokenStoreError> {
+        let mut conn = self.conn.write().await;
+
+        match conn
+            .exists(get_session_key(session_id))
+            .await
+            .map_err(|e| TokenStoreError::UnexpectedError(e.into()))?
+        {
+            true => Err(TokenStoreError::BannedToken),
+            false => Ok(()),
+        }
+    }
+
+    #[tracing::instrument(name = "RedisBannedTokenStore Ban Tokens Before", skip_all)]
+    async fn ban_tokens_before(&mut self, email: &Email, not_before: Epoch) -> Result<(), TokenStoreError> {
+        let mut conn = self.conn.write().await;
+
+        // Once the longest-lived token issued before the cutoff has expired the cutoff is moot
+        conn.set_ex(get_not_before_key(email), not_before, *MAX_TOKEN_TTL_SECONDS)
+            .await
+            .map_err(|e| TokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "RedisBannedTokenStore Check Issued At", skip_all)]
+    async fn check_issued_at(&self, email: &Email, issued_at: Epoch) -> Result<(), TokenStoreError> {
+        let mut conn = self.conn.write().await;
+
+        let not_before: Option<Epoch> = conn
+            .get(get_not_before_key(email))
+            .await
+            .map_err(|e| TokenStoreError::UnexpectedError(e.into()))?;
+
+        match not_before {
+            Some(not_before) if issued_at < not_before => Err(TokenStoreError::BannedToken),
+            _ => Ok(()),
+        }
+    }
 }
 
 const BANNED_TOKEN_KEY_PREFIX: &str = "banned_token:";
+const BANNED_SESSION_KEY_PREFIX: &str = "banned_session:";
+const TOKENS_NOT_BEFORE_KEY_PREFIX: &str = "tokens_not_before:";
 
 fn get_key(token: &Secret<String>) -> String {
     format!("{}{}", BANNED_TOKEN_KEY_PREFIX, token.expose_secret())
 }
+
+fn get_session_key(session_id: &Uuid) -> String {
+    format!("{}{}", BANNED_SESSION_KEY_PREFIX, session_id)
+}
+
+fn get_not_before_key(email: &Email) -> String {
+    format!("{}{}", TOKENS_NOT_BEFORE_KEY_PREFIX, email.expose_secret_string())
+}
@@ -0,0 +1,140 @@
+use std::{collections::HashMap, fmt, sync::Arc};
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::eyre;
+use redis::{aio::ConnectionManager, AsyncCommands};
+use tokio::sync::RwLock;
+
+use crate::domain::{
+    data_stores::{LoginFailureStore, LoginFailureStoreError, LoginFailures},
+    email::Email,
+};
+
+#[derive(Clone)]
+pub struct RedisLoginFailureStore {
+    conn: Arc<RwLock<ConnectionManager>>,
+}
+
+impl RedisLoginFailureStore {
+    pub fn new(conn: Arc<RwLock<ConnectionManager>>) -> Self {
+        Self { conn }
+    }
+}
+
+impl fmt::Debug for RedisLoginFailureStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RedisLoginFailureStore")
+    }
+}
+
+#[async_trait::async_trait]
+impl LoginFailureStore for RedisLoginFailureStore {
+    #[tracing::instrument(name = "RedisLoginFailureStore Record Failure", skip_all)]
+    async fn record_failure(
+        &mut self,
+        email: &Email,
+        ttl_seconds: u64,
+    ) -> Result<LoginFailures, LoginFailureStoreError> {
+        let mut conn = self.conn.write().await;
+        let key = get_key(email);
+        let last_failed_at = Utc::now();
+
+        let (count,): (u32,) = redis::pipe()
+            .atomic()
+            .hincr(&key, COUNT_FIELD, 1)
+            .hset(&key, LAST_FAILED_AT_FIELD, last_failed_at.timestamp_millis())
+            .ignore()
+            .expire(&key, ttl_seconds as i64)
+            .ignore()
+            .query_async(&mut *conn)
+            .await
+            .map_err(|e| LoginFailureStoreError::UnexpectedError(e.into()))?;
+
+        Ok(LoginFailures { count, last_failed_at })
+    }
+
+    #[tracing::instrument(name = "RedisLoginFailureStore Reserve Attempt", skip_all)]
+    async fn reserve_attempt(
+        &mut self,
+        email: &Email,
+        ttl_seconds: u64,
+    ) -> Result<LoginFailures, LoginFailureStoreError> {
+        let mut conn = self.conn.write().await;
+        let key = get_key(email);
+
+        let (in_flight, count, last_failed_at): (i64, Option<u32>, Option<i64>) = redis::pipe()
+            .atomic()
+            .hincr(&key, IN_FLIGHT_FIELD, 1)
+            .hget(&key, COUNT_FIELD)
+            .hget(&key, LAST_FAILED_AT_FIELD)
+            .expire(&key, ttl_seconds as i64)
+            .ignore()
+            .query_async(&mut *conn)
+            .await
+            .map_err(|e| LoginFailureStoreError::UnexpectedError(e.into()))?;
+
+        let failures = match (count, last_failed_at) {
+            (Some(count), Some(last_failed_at)) => Some(LoginFailures {
+                count,
+                last_failed_at: DateTime::<Utc>::from_timestamp_millis(last_failed_at)
+                    .ok_or_else(|| LoginFailureStoreError::UnexpectedError(eyre!("Invalid last failure time")))?,
+            }),
+            _ => None,
+        };
+        // The count includes this attempt, and goes negative if a release outlives a clear
+        let others_in_flight = (in_flight - 1).max(0) as u32;
+        Ok(LoginFailures::with_attempts_in_flight(failures, others_in_flight))
+    }
+
+    #[tracing::instrument(name = "RedisLoginFailureStore Release Attempt", skip_all)]
+    async fn release_attempt(&mut self, email: &Email, ttl_seconds: u64) -> Result<(), LoginFailureStoreError> {
+        let mut conn = self.conn.write().await;
+        let key = get_key(email);
+
+        redis::pipe()
+            .atomic()
+            .hincr(&key, IN_FLIGHT_FIELD, -1)
+            .ignore()
+            .expire(&key, ttl_seconds as i64)
+            .ignore()
+            .query_async(&mut *conn)
+            .await
+            .map_err(|e| LoginFailureStoreError::UnexpectedError(e.into()))
+    }
+
+    #[tracing::instrument(name = "RedisLoginFailureStore Get Failures", skip_all)]
+    async fn get_failures(&self, email: &Email) -> Result<Option<LoginFailures>, LoginFailureStoreError> {
+        let mut conn = self.conn.write().await;
+        let fields: HashMap<String, i64> = conn
+            .hgetall(get_key(email))
+            .await
+            .map_err(|e| LoginFailureStoreError::UnexpectedError(e.into()))?;
+
+        let (Some(count), Some(last_failed_at)) = (fields.get(COUNT_FIELD), fields.get(LAST_FAILED_AT_FIELD)) else {
+            return Ok(None);
+        };
+        let last_failed_at = DateTime::<Utc>::from_timestamp_millis(*last_failed_at)
+            .ok_or_else(|| LoginFailureStoreError::UnexpectedError(eyre!("Invalid last failure time")))?;
+        Ok(Some(LoginFailures {
+            count: *count as u32,
+            last_failed_at,
+        }))
+    }
+
+    #[tracing::instrument(name = "RedisLoginFailureStore Clear Failures", skip_all)]
+    async fn clear_failures(&mut self, email: &Email) -> Result<(), LoginFailureStoreError> {
+        let mut conn = self.conn.write().await;
+        conn.del(get_key(email))
+            .await
+            .map_err(|e| LoginFailureStoreError::UnexpectedError(e.into()))
+    }
+}
+
+const LOGIN_FAILURES_PREFIX: &str = "login_failures:";
+const COUNT_FIELD: &str = "count";
+const LAST_FAILED_AT_FIELD: &str = "last_failed_at";
+const IN_FLIGHT_FIELD: &str = "in_flight";
+
+fn get_key(email: &Email) -> String {
+    format!("{}{}", LOGIN_FAILURES_PREFIX, email.expose_secret_string())
+}
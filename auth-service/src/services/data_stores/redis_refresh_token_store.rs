@@ -0,0 +1,160 @@
+use std::{fmt, sync::Arc};
+
+use color_eyre::eyre::eyre;
+use redis::{aio::ConnectionManager, AsyncCommands};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use serde_json::{from_str, json};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::{
+    domain::{
+        data_stores::{RefreshToken, RefreshTokenRecord, RefreshTokenStore, RefreshTokenStoreError},
+        email::Email,
+    },
+    utils::constants::REFRESH_TOKEN_TTL_SECONDS,
+};
+
+#[derive(Clone)]
+pub struct RedisRefreshTokenStore {
+    conn: Arc<RwLock<ConnectionManager>>,
+}
+
+impl RedisRefreshTokenStore {
+    pub fn new(conn: Arc<RwLock<ConnectionManager>>) -> Self {
+        Self { conn }
+    }
+}
+
+impl fmt::Debug for RedisRefreshTokenStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RedisRefreshTokenStore")
+    }
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenStore for RedisRefreshTokenStore {
+    #[tracing::instrument(name = "RedisRefreshTokenStore Add Token", skip_all)]
+    async fn add_token(
+        &mut self,
+        token: RefreshToken,
+        record: RefreshTokenRecord,
+    ) -> Result<(), RefreshTokenStoreError> {
+        let mut conn = self.conn.write().await;
+        let token_key = get_token_key(&token);
+        let family_key = get_family_key(&record.family_id);
+        let record_tuple = RefreshTokenTuple(record.email.expose_secret_string(), record.family_id.to_string());
+        let record_json = json!(record_tuple).to_string();
+
+        let _: () = redis::pipe()
+            .atomic()
+            .set_ex(&token_key, record_json, REFRESH_TOKEN_TTL_SECONDS as u64)
+            .ignore()
+            .sadd(&family_key, token.expose_secret_string())
+            .ignore()
+            .expire(&family_key, REFRESH_TOKEN_TTL_SECONDS)
+            .ignore()
+            .query_async(&mut *conn)
+            .await
+            .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "RedisRefreshTokenStore Get Token", skip_all)]
+    async fn get_token(&self, token: &RefreshToken) -> Result<RefreshTokenRecord, RefreshTokenStoreError> {
+        let mut conn = self.conn.write().await;
+        let key = get_token_key(token);
+
+        let record_json: String = conn.get(key).await.map_err(|_| RefreshTokenStoreError::TokenNotFound)?;
+
+        let record_tuple: RefreshTokenTuple =
+            from_str(&record_json).map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        record_tuple
+            .destructure()
+            .map_err(|err_msg| RefreshTokenStoreError::UnexpectedError(eyre!(err_msg)))
+    }
+
+    #[tracing::instrument(name = "RedisRefreshTokenStore Mark Token Used", skip_all)]
+    async fn mark_token_used(&mut self, token: &RefreshToken) -> Result<(), RefreshTokenStoreError> {
+        let mut conn = self.conn.write().await;
+
+        let exists: bool = conn
+            .exists(get_token_key(token))
+            .await
+            .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+        if !exists {
+            return Err(RefreshTokenStoreError::TokenNotFound);
+        }
+
+        // SET NX is atomic, so only the first caller to present a token gets to rotate it
+        let newly_used: Option<String> = redis::cmd("SET")
+            .arg(get_used_key(token))
+            .arg(true)
+            .arg("NX")
+            .arg("EX")
+            .arg(REFRESH_TOKEN_TTL_SECONDS)
+            .query_async(&mut *conn)
+            .await
+            .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        match newly_used {
+            Some(_) => Ok(()),
+            None => Err(RefreshTokenStoreError::TokenReused),
+        }
+    }
+
+    #[tracing::instrument(name = "RedisRefreshTokenStore Revoke Family", skip_all)]
+    async fn revoke_family(&mut self, family_id: &Uuid) -> Result<(), RefreshTokenStoreError> {
+        let mut conn = self.conn.write().await;
+        let family_key = get_family_key(family_id);
+
+        let tokens: Vec<String> = conn
+            .smembers(&family_key)
+            .await
+            .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        let mut keys: Vec<String> = tokens
+            .into_iter()
+            .map(|token| RefreshToken::parse(Secret::new(token)))
+            .filter_map(Result::ok)
+            .flat_map(|token| [get_token_key(&token), get_used_key(&token)])
+            .collect();
+        keys.push(family_key);
+
+        conn.del::<_, ()>(keys)
+            .await
+            .map_err(|e| RefreshTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct RefreshTokenTuple(pub String, pub String);
+
+impl RefreshTokenTuple {
+    fn destructure(&self) -> Result<RefreshTokenRecord, String> {
+        let email = Email::parse(Secret::new(self.0.clone()))?;
+        let family_id = Uuid::parse_str(&self.1).map_err(|e| e.to_string())?;
+        Ok(RefreshTokenRecord { email, family_id })
+    }
+}
+
+const REFRESH_TOKEN_PREFIX: &str = "refresh_token:";
+const USED_REFRESH_TOKEN_PREFIX: &str = "refresh_token_used:";
+const REFRESH_TOKEN_FAMILY_PREFIX: &str = "refresh_token_family:";
+
+fn get_token_key(token: &RefreshToken) -> String {
+    format!("{}{}", REFRESH_TOKEN_PREFIX, token.as_ref().expose_secret())
+}
+
+fn get_used_key(token: &RefreshToken) -> String {
+    format!("{}{}", USED_REFRESH_TOKEN_PREFIX, token.as_ref().expose_secret())
+}
+
+fn get_family_key(family_id: &Uuid) -> String {
+    format!("{}{}", REFRESH_TOKEN_FAMILY_PREFIX, family_id)
+}
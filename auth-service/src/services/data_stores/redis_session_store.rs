@@ -0,0 +1,204 @@
+use std::{fmt, sync::Arc};
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::eyre;
+use redis::{aio::ConnectionManager, AsyncCommands};
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+use serde_json::{from_str, json};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::{
+    domain::{
+        data_stores::{Session, SessionStore, SessionStoreError},
+        email::Email,
+    },
+    utils::constants::REFRESH_TOKEN_TTL_SECONDS,
+};
+
+#[derive(Clone)]
+pub struct RedisSessionStore {
+    conn: Arc<RwLock<ConnectionManager>>,
+}
+
+impl RedisSessionStore {
+    pub fn new(conn: Arc<RwLock<ConnectionManager>>) -> Self {
+        Self { conn }
+    }
+}
+
+impl fmt::Debug for RedisSessionStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RedisSessionStore")
+    }
+}
+
+#[async_trait::async_trait]
+impl SessionStore for RedisSessionStore {
+    #[tracing::instrument(name = "RedisSessionStore Add Session", skip_all)]
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError> {
+        let mut conn = self.conn.write().await;
+        let user_key = get_user_key(&session.email);
+        let session_json = json!(SessionRecord::from(&session)).to_string();
+
+        let _: () = redis::pipe()
+            .atomic()
+            .set_ex(
+                get_session_key(&session.id),
+                session_json,
+                REFRESH_TOKEN_TTL_SECONDS as u64,
+            )
+            .ignore()
+            .sadd(&user_key, session.id.to_string())
+            .ignore()
+            .expire(&user_key, REFRESH_TOKEN_TTL_SECONDS)
+            .ignore()
+            .query_async(&mut *conn)
+            .await
+            .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "RedisSessionStore Get Session", skip_all)]
+    async fn get_session(&self, session_id: &Uuid) -> Result<Session, SessionStoreError> {
+        let mut conn = self.conn.write().await;
+        get_session(&mut conn, session_id).await
+    }
+
+    #[tracing::instrument(name = "RedisSessionStore Get Sessions", skip_all)]
+    async fn get_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError> {
+        let mut conn = self.conn.write().await;
+        let user_key = get_user_key(email);
+
+        let session_ids: Vec<String> = conn
+            .smembers(&user_key)
+            .await
+            .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+
+        let mut sessions = Vec::new();
+        for session_id in session_ids {
+            let Ok(id) = Uuid::parse_str(&session_id) else {
+                continue;
+            };
+            match get_session(&mut conn, &id).await {
+                Ok(session) => sessions.push(session),
+                // The session expired, so drop it from the user's index as well
+                Err(SessionStoreError::SessionNotFound) => conn
+                    .srem::<_, _, ()>(&user_key, session_id)
+                    .await
+                    .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?,
+                Err(e) => return Err(e),
+            }
+        }
+        sessions.sort_by_key(|session| session.created_at);
+
+        Ok(sessions)
+    }
+
+    #[tracing::instrument(name = "RedisSessionStore Touch Session", skip_all)]
+    async fn touch_session(&mut self, session_id: &Uuid, last_seen_at: DateTime<Utc>) -> Result<(), SessionStoreError> {
+        let mut conn = self.conn.write().await;
+        let mut session = get_session(&mut conn, session_id).await?;
+        session.last_seen_at = last_seen_at;
+
+        let user_key = get_user_key(&session.email);
+        let session_json = json!(SessionRecord::from(&session)).to_string();
+
+        let _: () = redis::pipe()
+            .atomic()
+            .set_ex(
+                get_session_key(session_id),
+                session_json,
+                REFRESH_TOKEN_TTL_SECONDS as u64,
+            )
+            .ignore()
+            .expire(&user_key, REFRESH_TOKEN_TTL_SECONDS)
+            .ignore()
+            .query_async(&mut *conn)
+            .await
+            .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "RedisSessionStore Remove Session", skip_all)]
+    async fn remove_session(&mut self, session_id: &Uuid) -> Result<(), SessionStoreError> {
+        let mut conn = self.conn.write().await;
+        let session = get_session(&mut conn, session_id).await?;
+
+        let _: () = redis::pipe()
+            .atomic()
+            .del(get_session_key(session_id))
+            .ignore()
+            .srem(get_user_key(&session.email), session_id.to_string())
+            .ignore()
+            .query_async(&mut *conn)
+            .await
+            .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+}
+
+async fn get_session(conn: &mut ConnectionManager, session_id: &Uuid) -> Result<Session, SessionStoreError> {
+    let session_json: Option<String> = conn
+        .get(get_session_key(session_id))
+        .await
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+    let session_json = session_json.ok_or(SessionStoreError::SessionNotFound)?;
+
+    let record: SessionRecord = from_str(&session_json).map_err(|e| SessionStoreError::UnexpectedError(e.into()))?;
+    record
+        .into_session(*session_id)
+        .map_err(|err_msg| SessionStoreError::UnexpectedError(eyre!(err_msg)))
+}
+
+#[derive(Serialize, Deserialize)]
+struct SessionRecord {
+    email: String,
+    created_at: i64,
+    last_seen_at: i64,
+    ip_address: Option<String>,
+    user_agent: Option<String>,
+}
+
+impl From<&Session> for SessionRecord {
+    fn from(session: &Session) -> Self {
+        Self {
+            email: session.email.expose_secret_string(),
+            created_at: session.created_at.timestamp(),
+            last_seen_at: session.last_seen_at.timestamp(),
+            ip_address: session.ip_address.clone(),
+            user_agent: session.user_agent.clone(),
+        }
+    }
+}
+
+impl SessionRecord {
+    fn into_session(self, id: Uuid) -> Result<Session, String> {
+        let email = Email::parse(Secret::new(self.email))?;
+        let created_at = DateTime::from_timestamp(self.created_at, 0).ok_or("Invalid session creation time")?;
+        let last_seen_at = DateTime::from_timestamp(self.last_seen_at, 0).ok_or("Invalid session last seen time")?;
+        Ok(Session {
+            id,
+            email,
+            created_at,
+            last_seen_at,
+            ip_address: self.ip_address,
+            user_agent: self.user_agent,
+        })
+    }
+}
+
+const SESSION_PREFIX: &str = "session:";
+const USER_SESSIONS_PREFIX: &str = "user_sessions:";
+
+fn get_session_key(session_id: &Uuid) -> String {
+    format!("{}{}", SESSION_PREFIX, session_id)
+}
+
+fn get_user_key(email: &Email) -> String {
+    format!("{}{}", USER_SESSIONS_PREFIX, email.expose_secret_string())
+}
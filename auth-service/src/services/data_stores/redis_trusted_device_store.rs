@@ -0,0 +1,226 @@
+use std::{fmt, sync::Arc};
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::eyre;
+use redis::{aio::ConnectionManager, AsyncCommands};
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+use serde_json::{from_str, json};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::domain::{
+    data_stores::{TrustedDevice, TrustedDeviceStore, TrustedDeviceStoreError},
+    email::Email,
+};
+
+#[derive(Clone)]
+pub struct RedisTrustedDeviceStore {
+    conn: Arc<RwLock<ConnectionManager>>,
+}
+
+impl RedisTrustedDeviceStore {
+    pub fn new(conn: Arc<RwLock<ConnectionManager>>) -> Self {
+        Self { conn }
+    }
+}
+
+impl fmt::Debug for RedisTrustedDeviceStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RedisTrustedDeviceStore")
+    }
+}
+
+#[async_trait::async_trait]
+impl TrustedDeviceStore for RedisTrustedDeviceStore {
+    #[tracing::instrument(name = "RedisTrustedDeviceStore Add Device", skip_all)]
+    async fn add_device(&mut self, device: TrustedDevice) -> Result<(), TrustedDeviceStoreError> {
+        let mut conn = self.conn.write().await;
+        let user_key = get_user_key(&device.email);
+        let ttl_seconds = remaining_seconds(&device)?;
+        let device_json = json!(TrustedDeviceRecord::from(&device)).to_string();
+
+        let _: () = redis::pipe()
+            .atomic()
+            .set_ex(get_device_key(&device.id), device_json, ttl_seconds)
+            .ignore()
+            .sadd(&user_key, device.id.to_string())
+            .ignore()
+            .expire(&user_key, ttl_seconds as i64)
+            .ignore()
+            .query_async(&mut *conn)
+            .await
+            .map_err(|e| TrustedDeviceStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "RedisTrustedDeviceStore Get Device", skip_all)]
+    async fn get_device(&self, device_id: &Uuid) -> Result<TrustedDevice, TrustedDeviceStoreError> {
+        let mut conn = self.conn.write().await;
+        get_device(&mut conn, device_id).await
+    }
+
+    #[tracing::instrument(name = "RedisTrustedDeviceStore Get Devices", skip_all)]
+    async fn get_devices(&self, email: &Email) -> Result<Vec<TrustedDevice>, TrustedDeviceStoreError> {
+        let mut conn = self.conn.write().await;
+        let user_key = get_user_key(email);
+
+        let device_ids: Vec<String> = conn
+            .smembers(&user_key)
+            .await
+            .map_err(|e| TrustedDeviceStoreError::UnexpectedError(e.into()))?;
+
+        let mut devices = Vec::new();
+        for device_id in device_ids {
+            let Ok(id) = Uuid::parse_str(&device_id) else {
+                continue;
+            };
+            match get_device(&mut conn, &id).await {
+                Ok(device) => devices.push(device),
+                // The device expired, so drop it from the user's index as well
+                Err(TrustedDeviceStoreError::TrustedDeviceNotFound) => conn
+                    .srem::<_, _, ()>(&user_key, device_id)
+                    .await
+                    .map_err(|e| TrustedDeviceStoreError::UnexpectedError(e.into()))?,
+                Err(e) => return Err(e),
+            }
+        }
+        devices.sort_by_key(|device| device.created_at);
+
+        Ok(devices)
+    }
+
+    #[tracing::instrument(name = "RedisTrustedDeviceStore Touch Device", skip_all)]
+    async fn touch_device(
+        &mut self,
+        device_id: &Uuid,
+        last_used_at: DateTime<Utc>,
+    ) -> Result<(), TrustedDeviceStoreError> {
+        let mut conn = self.conn.write().await;
+        let mut device = get_device(&mut conn, device_id).await?;
+        device.last_used_at = last_used_at;
+
+        // Using a device never extends its trust, so the record keeps its original expiry
+        let device_json = json!(TrustedDeviceRecord::from(&device)).to_string();
+        conn.set_ex(get_device_key(device_id), device_json, remaining_seconds(&device)?)
+            .await
+            .map_err(|e| TrustedDeviceStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "RedisTrustedDeviceStore Remove Device", skip_all)]
+    async fn remove_device(&mut self, device_id: &Uuid) -> Result<(), TrustedDeviceStoreError> {
+        let mut conn = self.conn.write().await;
+        let device = get_device(&mut conn, device_id).await?;
+
+        let _: () = redis::pipe()
+            .atomic()
+            .del(get_device_key(device_id))
+            .ignore()
+            .srem(get_user_key(&device.email), device_id.to_string())
+            .ignore()
+            .query_async(&mut *conn)
+            .await
+            .map_err(|e| TrustedDeviceStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "RedisTrustedDeviceStore Remove Devices", skip_all)]
+    async fn remove_devices(&mut self, email: &Email) -> Result<(), TrustedDeviceStoreError> {
+        let mut conn = self.conn.write().await;
+        let user_key = get_user_key(email);
+
+        let device_ids: Vec<String> = conn
+            .smembers(&user_key)
+            .await
+            .map_err(|e| TrustedDeviceStoreError::UnexpectedError(e.into()))?;
+
+        let mut keys: Vec<String> = device_ids
+            .iter()
+            .filter_map(|device_id| Uuid::parse_str(device_id).ok())
+            .map(|device_id| get_device_key(&device_id))
+            .collect();
+        keys.push(user_key);
+        conn.del(keys)
+            .await
+            .map_err(|e| TrustedDeviceStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+}
+
+async fn get_device(conn: &mut ConnectionManager, device_id: &Uuid) -> Result<TrustedDevice, TrustedDeviceStoreError> {
+    let device_json: Option<String> = conn
+        .get(get_device_key(device_id))
+        .await
+        .map_err(|e| TrustedDeviceStoreError::UnexpectedError(e.into()))?;
+    let device_json = device_json.ok_or(TrustedDeviceStoreError::TrustedDeviceNotFound)?;
+
+    let record: TrustedDeviceRecord =
+        from_str(&device_json).map_err(|e| TrustedDeviceStoreError::UnexpectedError(e.into()))?;
+    record
+        .into_device(*device_id)
+        .map_err(|err_msg| TrustedDeviceStoreError::UnexpectedError(eyre!(err_msg)))
+}
+
+fn remaining_seconds(device: &TrustedDevice) -> Result<u64, TrustedDeviceStoreError> {
+    u64::try_from((device.expires_at - Utc::now()).num_seconds())
+        .ok()
+        .filter(|seconds| *seconds > 0)
+        .ok_or(TrustedDeviceStoreError::TrustedDeviceNotFound)
+}
+
+#[derive(Serialize, Deserialize)]
+struct TrustedDeviceRecord {
+    email: String,
+    created_at: i64,
+    last_used_at: i64,
+    expires_at: i64,
+    ip_address: Option<String>,
+    user_agent: Option<String>,
+}
+
+impl From<&TrustedDevice> for TrustedDeviceRecord {
+    fn from(device: &TrustedDevice) -> Self {
+        Self {
+            email: device.email.expose_secret_string(),
+            created_at: device.created_at.timestamp(),
+            last_used_at: device.last_used_at.timestamp(),
+            expires_at: device.expires_at.timestamp(),
+            ip_address: device.ip_address.clone(),
+            user_agent: device.user_agent.clone(),
+        }
+    }
+}
+
+impl TrustedDeviceRecord {
+    fn into_device(self, id: Uuid) -> Result<TrustedDevice, String> {
+        let email = Email::parse(Secret::new(self.email))?;
+        let created_at = DateTime::from_timestamp(self.created_at, 0).ok_or("Invalid device creation time")?;
+        let last_used_at = DateTime::from_timestamp(self.last_used_at, 0).ok_or("Invalid device last used time")?;
+        let expires_at = DateTime::from_timestamp(self.expires_at, 0).ok_or("Invalid device expiry time")?;
+        Ok(TrustedDevice {
+            id,
+            email,
+            created_at,
+            last_used_at,
+            expires_at,
+            ip_address: self.ip_address,
+            user_agent: self.user_agent,
+        })
+    }
+}
+
+const TRUSTED_DEVICE_PREFIX: &str = "trusted_device:";
+const USER_TRUSTED_DEVICES_PREFIX: &str = "user_trusted_devices:";
+
+fn get_device_key(device_id: &Uuid) -> String {
+    format!("{}{}", TRUSTED_DEVICE_PREFIX, device_id)
+}
+
+fn get_user_key(email: &Email) -> String {
+    format!("{}{}", USER_TRUSTED_DEVICES_PREFIX, email.expose_secret_string())
+}
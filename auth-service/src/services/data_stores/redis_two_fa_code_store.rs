@@ -12,17 +12,27 @@ use crate::{
         data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
         email::Email,
     },
-    utils::constants::Time,
+    utils::constants::{Time, MAX_PENDING_TWO_FA_ATTEMPTS},
 };
 
+/// Namespace for the codes that confirm a new phone number.
+pub const PHONE_VERIFICATION_NAMESPACE: &str = "phone_verification:";
+
 #[derive(Clone)]
 pub struct RedisTwoFACodeStore {
     conn: Arc<RwLock<ConnectionManager>>,
+    namespace: &'static str,
 }
 
 impl RedisTwoFACodeStore {
     pub fn new(conn: Arc<RwLock<ConnectionManager>>) -> Self {
-        Self { conn }
+        Self { conn, namespace: "" }
+    }
+
+    /// A store whose keys are prefixed with `namespace`, so its codes can never be looked up, counted or
+    /// evicted through a store with a different namespace.
+    pub fn with_namespace(conn: Arc<RwLock<ConnectionManager>>, namespace: &'static str) -> Self {
+        Self { conn, namespace }
     }
 }
 
@@ -41,59 +51,293 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
         code: TwoFACode,
     ) -> Result<(), TwoFACodeStoreError> {
         let mut conn = self.conn.write().await;
-        let key = get_key(&email);
-        let two_fa_tuple = TwoFATuple(login_attempt_id.expose_secret_string(), code.expose_secret_string());
-        let two_fa_json = json!(two_fa_tuple).to_string();
+        let attempt_id = login_attempt_id.expose_secret_string();
+        let record = TwoFARecord {
+            email: email.as_ref().expose_secret().to_string(),
+            code: code.expose_secret_string(),
+        };
+
+        conn.set_ex(
+            self.get_key(&attempt_id),
+            json!(record).to_string(),
+            Time::Minutes10 as u64,
+        )
+        .await
+        .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+
+        // The index lists the user's attempts oldest first. Expired attempts are always older than live ones,
+        // so they are the first to go when it overflows.
+        let index_key = self.get_index_key(&email);
+        let pending: usize = conn
+            .rpush(&index_key, &attempt_id)
+            .await
+            .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+        conn.expire(&index_key, Time::Minutes10 as i64)
+            .await
+            .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+
+        for _ in MAX_PENDING_TWO_FA_ATTEMPTS..pending {
+            let evicted: Option<String> = conn
+                .lpop(&index_key, None)
+                .await
+                .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+            if let Some(evicted) = evicted {
+                conn.del(self.get_attempt_keys(&evicted))
+                    .await
+                    .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn remove_code(
+        &mut self,
+        email: &Email,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let mut conn = self.conn.write().await;
+        self.get_record(&mut conn, email, login_attempt_id).await?;
+        let attempt_id = login_attempt_id.expose_secret_string();
 
-        conn.set_ex(key, two_fa_json, Time::Minutes10 as u64)
+        conn.del(self.get_attempt_keys(&attempt_id))
+            .await
+            .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+        conn.lrem(self.get_index_key(email), 0, &attempt_id)
             .await
             .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
 
         Ok(())
     }
 
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
-        let key = get_key(email);
+    async fn get_code(
+        &self,
+        email: &Email,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<TwoFACode, TwoFACodeStoreError> {
+        let mut conn = self.conn.write().await;
+        let record = self.get_record(&mut conn, email, login_attempt_id).await?;
+
+        TwoFACode::parse(Secret::new(record.code)).map_err(|e| TwoFACodeStoreError::UnexpectedError(eyre!(e)))
+    }
+
+    async fn record_failed_attempt(
+        &mut self,
+        email: &Email,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<u32, TwoFACodeStoreError> {
+        let mut conn = self.conn.write().await;
+        self.get_record(&mut conn, email, login_attempt_id).await?;
+
+        let attempts_key = self.get_attempts_key(&login_attempt_id.expose_secret_string());
+        let failed_attempts: u32 = conn
+            .incr(&attempts_key, 1)
+            .await
+            .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+        // The counter never needs to outlive the code it belongs to
+        if failed_attempts == 1 {
+            conn.expire(&attempts_key, Time::Minutes10 as i64)
+                .await
+                .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+        }
+
+        Ok(failed_attempts)
+    }
+
+    async fn resend_code(
+        &mut self,
+        email: &Email,
+        login_attempt_id: &LoginAttemptId,
+        code: TwoFACode,
+        cooldown_seconds: u64,
+        max_resends: u32,
+    ) -> Result<(), TwoFACodeStoreError> {
         let mut conn = self.conn.write().await;
+        let record = self.get_record(&mut conn, email, login_attempt_id).await?;
+        let attempt_id = login_attempt_id.expose_secret_string();
+
+        let resends_key = self.get_resends_key(&attempt_id);
+        let resends: Option<u32> = conn
+            .get(&resends_key)
+            .await
+            .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+        if resends.unwrap_or(0) >= max_resends {
+            return Err(TwoFACodeStoreError::ResendLimitReached);
+        }
+
+        // SET NX is atomic, so concurrent resends can't both get past the cooldown
+        if cooldown_seconds > 0 {
+            let cooldown_started: Option<String> = redis::cmd("SET")
+                .arg(self.get_resend_cooldown_key(&attempt_id))
+                .arg(true)
+                .arg("NX")
+                .arg("EX")
+                .arg(cooldown_seconds)
+                .query_async(&mut *conn)
+                .await
+                .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+            if cooldown_started.is_none() {
+                return Err(TwoFACodeStoreError::ResendCooldown);
+            }
+        }
 
-        conn.del(key)
+        let record = TwoFARecord {
+            code: code.expose_secret_string(),
+            ..record
+        };
+        conn.set_ex(
+            self.get_key(&attempt_id),
+            json!(record).to_string(),
+            Time::Minutes10 as u64,
+        )
+        .await
+        .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+        conn.incr(&resends_key, 1)
             .await
             .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
 
+        // Keep the bookkeeping for this attempt alive as long as its refreshed code
+        for bookkeeping_key in [
+            resends_key,
+            self.get_attempts_key(&attempt_id),
+            self.get_index_key(email),
+        ] {
+            conn.expire(bookkeeping_key, Time::Minutes10 as i64)
+                .await
+                .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+        }
+
         Ok(())
     }
 
-    async fn get_code(&self, email: &Email) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
+    async fn remove_codes(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
         let mut conn = self.conn.write().await;
-        let key = get_key(email);
+        let index_key = self.get_index_key(email);
+        let attempt_ids: Vec<String> = conn
+            .lrange(&index_key, 0, -1)
+            .await
+            .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
 
-        let two_fa_json: String = conn
-            .get(key)
+        let mut keys: Vec<String> = attempt_ids.iter().flat_map(|id| self.get_attempt_keys(id)).collect();
+        keys.push(index_key);
+        conn.del(keys)
             .await
-            .map_err(|_| TwoFACodeStoreError::LoginAttemptIdNotFound)?;
+            .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
 
-        let two_fa_tuple: TwoFATuple =
-            from_str(&two_fa_json).map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+    async fn record_code_sent(
+        &mut self,
+        email: &Email,
+        max_sends: u32,
+        window_seconds: u64,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let mut conn = self.conn.write().await;
+        let sends_key = self.get_sends_key(email);
 
-        two_fa_tuple
-            .destructure()
-            .map_err(|err_msg| TwoFACodeStoreError::UnexpectedError(eyre!(err_msg)))
+        // The window starts with the first send; SET NX leaves a running window's TTL alone
+        let (sent,): (u32,) = redis::pipe()
+            .atomic()
+            .cmd("SET")
+            .arg(&sends_key)
+            .arg(0)
+            .arg("NX")
+            .arg("EX")
+            .arg(window_seconds.max(1))
+            .ignore()
+            .incr(&sends_key, 1)
+            .query_async(&mut *conn)
+            .await
+            .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+
+        match sent > max_sends {
+            true => Err(TwoFACodeStoreError::SendLimitReached),
+            false => Ok(()),
+        }
     }
 }
 
 #[derive(Serialize, Deserialize)]
-struct TwoFATuple(pub String, pub String);
+struct TwoFARecord {
+    email: String,
+    code: String,
+}
+
+impl RedisTwoFACodeStore {
+    /// Loads the attempt, treating one that belongs to a different email as missing.
+    async fn get_record(
+        &self,
+        conn: &mut ConnectionManager,
+        email: &Email,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<TwoFARecord, TwoFACodeStoreError> {
+        let two_fa_json: Option<String> = conn
+            .get(self.get_key(&login_attempt_id.expose_secret_string()))
+            .await
+            .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+        let record: TwoFARecord = match two_fa_json {
+            None => return Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
+            Some(json) => from_str(&json).map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?,
+        };
 
-impl TwoFATuple {
-    fn destructure(&self) -> Result<(LoginAttemptId, TwoFACode), String> {
-        let attempt_id = LoginAttemptId::parse(Secret::new(self.0.clone()))?;
-        let code = TwoFACode::parse(Secret::new(self.1.clone()))?;
-        Ok((attempt_id, code))
+        match record.email == *email.as_ref().expose_secret() {
+            true => Ok(record),
+            false => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
+        }
     }
-}
 
-const TWO_FA_CODE_PREFIX: &str = "two_fa_code:";
+    fn get_key(&self, login_attempt_id: &str) -> String {
+        format!("{}{}{}", self.namespace, TWO_FA_CODE_PREFIX, login_attempt_id)
+    }
+
+    fn get_attempts_key(&self, login_attempt_id: &str) -> String {
+        format!("{}{}{}", self.namespace, TWO_FA_ATTEMPTS_PREFIX, login_attempt_id)
+    }
+
+    fn get_resends_key(&self, login_attempt_id: &str) -> String {
+        format!("{}{}{}", self.namespace, TWO_FA_RESENDS_PREFIX, login_attempt_id)
+    }
 
-fn get_key(email: &Email) -> String {
-    format!("{}{}", TWO_FA_CODE_PREFIX, email.as_ref().expose_secret())
+    fn get_resend_cooldown_key(&self, login_attempt_id: &str) -> String {
+        format!(
+            "{}{}{}",
+            self.namespace, TWO_FA_RESEND_COOLDOWN_PREFIX, login_attempt_id
+        )
+    }
+
+    /// Every key holding state for the attempt.
+    fn get_attempt_keys(&self, login_attempt_id: &str) -> Vec<String> {
+        vec![
+            self.get_key(login_attempt_id),
+            self.get_attempts_key(login_attempt_id),
+            self.get_resends_key(login_attempt_id),
+            self.get_resend_cooldown_key(login_attempt_id),
+        ]
+    }
+
+    fn get_sends_key(&self, email: &Email) -> String {
+        format!(
+            "{}{}{}",
+            self.namespace,
+            TWO_FA_SENDS_PREFIX,
+            email.as_ref().expose_secret()
+        )
+    }
+
+    fn get_index_key(&self, email: &Email) -> String {
+        format!(
+            "{}{}{}",
+            self.namespace,
+            TWO_FA_INDEX_PREFIX,
+            email.as_ref().expose_secret()
+        )
+    }
 }
+
+const TWO_FA_CODE_PREFIX: &str = "two_fa_code:";
+const TWO_FA_ATTEMPTS_PREFIX: &str = "two_fa_attempts:";
+const TWO_FA_RESENDS_PREFIX: &str = "two_fa_resends:";
+const TWO_FA_RESEND_COOLDOWN_PREFIX: &str = "two_fa_resend_cooldown:";
+const TWO_FA_INDEX_PREFIX: &str = "two_fa_pending:";
+const TWO_FA_SENDS_PREFIX: &str = "two_fa_sends:";
@@ -0,0 +1,100 @@
+use std::{fmt, sync::Arc};
+
+use color_eyre::eyre::eyre;
+use redis::{aio::ConnectionManager, AsyncCommands};
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+use serde_json::{from_str, json};
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{
+        data_stores::{WebAuthnCeremony, WebAuthnChallenge, WebAuthnChallengeStore, WebAuthnChallengeStoreError},
+        email::Email,
+    },
+    utils::constants::WEBAUTHN_CHALLENGE_TTL_SECONDS,
+};
+
+#[derive(Clone)]
+pub struct RedisWebAuthnChallengeStore {
+    conn: Arc<RwLock<ConnectionManager>>,
+}
+
+impl RedisWebAuthnChallengeStore {
+    pub fn new(conn: Arc<RwLock<ConnectionManager>>) -> Self {
+        Self { conn }
+    }
+}
+
+impl fmt::Debug for RedisWebAuthnChallengeStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RedisWebAuthnChallengeStore")
+    }
+}
+
+#[async_trait::async_trait]
+impl WebAuthnChallengeStore for RedisWebAuthnChallengeStore {
+    #[tracing::instrument(name = "RedisWebAuthnChallengeStore Add Challenge", skip_all)]
+    async fn add_challenge(&mut self, challenge: WebAuthnChallenge) -> Result<(), WebAuthnChallengeStoreError> {
+        let mut conn = self.conn.write().await;
+        let record_json = json!(ChallengeRecord::from(&challenge)).to_string();
+
+        conn.set_ex(
+            get_key(&challenge.challenge),
+            record_json,
+            WEBAUTHN_CHALLENGE_TTL_SECONDS as u64,
+        )
+        .await
+        .map_err(|e| WebAuthnChallengeStoreError::UnexpectedError(e.into()))
+    }
+
+    #[tracing::instrument(name = "RedisWebAuthnChallengeStore Take Challenge", skip_all)]
+    async fn take_challenge(&mut self, challenge: &str) -> Result<WebAuthnChallenge, WebAuthnChallengeStoreError> {
+        let mut conn = self.conn.write().await;
+
+        // GETDEL makes the lookup and removal atomic, so concurrent responses can't share a challenge
+        let record_json: Option<String> = conn
+            .get_del(get_key(challenge))
+            .await
+            .map_err(|e| WebAuthnChallengeStoreError::UnexpectedError(e.into()))?;
+        let record_json = record_json.ok_or(WebAuthnChallengeStoreError::ChallengeNotFound)?;
+
+        let record: ChallengeRecord =
+            from_str(&record_json).map_err(|e| WebAuthnChallengeStoreError::UnexpectedError(e.into()))?;
+        record
+            .into_challenge(challenge.to_string())
+            .map_err(|err_msg| WebAuthnChallengeStoreError::UnexpectedError(eyre!(err_msg)))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct ChallengeRecord {
+    email: String,
+    ceremony: WebAuthnCeremony,
+}
+
+impl From<&WebAuthnChallenge> for ChallengeRecord {
+    fn from(challenge: &WebAuthnChallenge) -> Self {
+        Self {
+            email: challenge.email.expose_secret_string(),
+            ceremony: challenge.ceremony.clone(),
+        }
+    }
+}
+
+impl ChallengeRecord {
+    fn into_challenge(self, challenge: String) -> Result<WebAuthnChallenge, String> {
+        let email = Email::parse(Secret::new(self.email))?;
+        Ok(WebAuthnChallenge {
+            challenge,
+            email,
+            ceremony: self.ceremony,
+        })
+    }
+}
+
+const WEBAUTHN_CHALLENGE_PREFIX: &str = "webauthn_challenge:";
+
+fn get_key(challenge: &str) -> String {
+    format!("{}{}", WEBAUTHN_CHALLENGE_PREFIX, challenge)
+}
@@ -1,18 +1,28 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use secrecy::{ExposeSecret, Secret};
+use uuid::Uuid;
 
-use crate::domain::data_stores::{BannedTokenStore, TokenStoreError};
+use crate::domain::{
+    data_stores::{BannedTokenStore, TokenStoreError},
+    email::Email,
+};
 use crate::utils::{auth::validate_token_structure, constants::Epoch};
 
 #[derive(Clone, Debug)]
 pub struct HashMapBannedTokenStore {
     tokens: HashMap<String, Epoch>,
+    sessions: HashSet<Uuid>,
+    not_before: HashMap<String, Epoch>,
 }
 
 impl HashMapBannedTokenStore {
     pub fn new() -> Self {
-        Self { tokens: HashMap::new() }
+        Self {
+            tokens: HashMap::new(),
+            sessions: HashSet::new(),
+            not_before: HashMap::new(),
+        }
     }
 }
 
@@ -39,19 +49,43 @@ impl BannedTokenStore for HashMapBannedTokenStore {
         };
         response
     }
+
+    async fn ban_session(&mut self, session_id: &Uuid) -> Result<(), TokenStoreError> {
+        self.sessions.insert(*session_id);
+        Ok(())
+    }
+
+    async fn check_session(&self, session_id: &Uuid) -> Result<(), TokenStoreError> {
+        match self.sessions.contains(session_id) {
+            true => Err(TokenStoreError::BannedToken),
+            false => Ok(()),
+        }
+    }
+
+    async fn ban_tokens_before(&mut self, email: &Email, not_before: Epoch) -> Result<(), TokenStoreError> {
+        self.not_before.insert(email.expose_secret_string(), not_before);
+        Ok(())
+    }
+
+    async fn check_issued_at(&self, email: &Email, issued_at: Epoch) -> Result<(), TokenStoreError> {
+        match self.not_before.get(&email.expose_secret_string()) {
+            Some(not_before) if issued_at < *not_before => Err(TokenStoreError::BannedToken),
+            _ => Ok(()),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use secrecy::Secret;
 
-    use crate::{domain::email::Email, utils::auth::generate_auth_token};
+    use crate::utils::auth::generate_auth_token;
 
     use super::*;
 
     fn create_token(email: &str) -> Secret<String> {
         let email = Email::parse(Secret::new(email.to_string())).unwrap();
-        generate_auth_token(&email).unwrap()
+        generate_auth_token(&email, &uuid::Uuid::new_v4(), &Default::default()).unwrap()
     }
 
     #[tokio::test]
@@ -82,4 +116,25 @@ mod tests {
         let result = store.check_token(token).await;
         assert!(result.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_check_session_banned() {
+        let mut store = HashMapBannedTokenStore::new();
+        let session_id = Uuid::new_v4();
+        assert!(store.check_session(&session_id).await.is_ok());
+
+        store.ban_session(&session_id).await.unwrap();
+        assert!(store.check_session(&session_id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_check_issued_at_before_cutoff() {
+        let mut store = HashMapBannedTokenStore::new();
+        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
+        assert!(store.check_issued_at(&email, 100).await.is_ok());
+
+        store.ban_tokens_before(&email, 200).await.unwrap();
+        assert!(store.check_issued_at(&email, 199).await.is_err());
+        assert!(store.check_issued_at(&email, 200).await.is_ok());
+    }
 }
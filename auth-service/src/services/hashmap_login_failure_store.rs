@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+
+use crate::domain::{
+    data_stores::{LoginFailureStore, LoginFailureStoreError, LoginFailures},
+    email::Email,
+};
+
+#[derive(Clone, Debug)]
+pub struct HashMapLoginFailureStore {
+    failures: HashMap<Email, (LoginFailures, DateTime<Utc>)>,
+    /// Password checks still running, and when they stop counting
+    in_flight: HashMap<Email, (u32, DateTime<Utc>)>,
+}
+
+impl HashMapLoginFailureStore {
+    pub fn new() -> Self {
+        Self {
+            failures: HashMap::new(),
+            in_flight: HashMap::new(),
+        }
+    }
+
+    fn get_in_flight(&self, email: &Email) -> u32 {
+        self.in_flight
+            .get(email)
+            .filter(|(_, expires_at)| *expires_at > Utc::now())
+            .map_or(0, |(count, _)| *count)
+    }
+}
+
+impl Default for HashMapLoginFailureStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait::async_trait]
+impl LoginFailureStore for HashMapLoginFailureStore {
+    async fn record_failure(
+        &mut self,
+        email: &Email,
+        ttl_seconds: u64,
+    ) -> Result<LoginFailures, LoginFailureStoreError> {
+        let count = self.get_failures(email).await?.map_or(0, |failures| failures.count) + 1;
+        let now = Utc::now();
+        let failures = LoginFailures {
+            count,
+            last_failed_at: now,
+        };
+        let expires_at = now + chrono::Duration::seconds(ttl_seconds as i64);
+        self.failures.insert(email.clone(), (failures.clone(), expires_at));
+        Ok(failures)
+    }
+
+    async fn reserve_attempt(
+        &mut self,
+        email: &Email,
+        ttl_seconds: u64,
+    ) -> Result<LoginFailures, LoginFailureStoreError> {
+        let failures = self.get_failures(email).await?;
+        let in_flight = self.get_in_flight(email);
+        let expires_at = Utc::now() + chrono::Duration::seconds(ttl_seconds as i64);
+        self.in_flight.insert(email.clone(), (in_flight + 1, expires_at));
+        Ok(LoginFailures::with_attempts_in_flight(failures, in_flight))
+    }
+
+    async fn release_attempt(&mut self, email: &Email, _ttl_seconds: u64) -> Result<(), LoginFailureStoreError> {
+        match self.get_in_flight(email) {
+            0 | 1 => {
+                self.in_flight.remove(email);
+            }
+            in_flight => {
+                if let Some(entry) = self.in_flight.get_mut(email) {
+                    entry.0 = in_flight - 1;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn get_failures(&self, email: &Email) -> Result<Option<LoginFailures>, LoginFailureStoreError> {
+        Ok(self
+            .failures
+            .get(email)
+            .filter(|(_, expires_at)| *expires_at > Utc::now())
+            .map(|(failures, _)| failures.clone()))
+    }
+
+    async fn clear_failures(&mut self, email: &Email) -> Result<(), LoginFailureStoreError> {
+        self.failures.remove(email);
+        self.in_flight.remove(email);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+
+    fn get_test_email() -> Email {
+        Email::parse(Secret::new("test@example.com".to_string())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_record_failure_counts_up() {
+        let mut store = HashMapLoginFailureStore::new();
+        let email = get_test_email();
+
+        assert_eq!(store.get_failures(&email).await.unwrap(), None);
+        assert_eq!(store.record_failure(&email, 60).await.unwrap().count, 1);
+        assert_eq!(store.record_failure(&email, 60).await.unwrap().count, 2);
+        assert_eq!(store.get_failures(&email).await.unwrap().unwrap().count, 2);
+    }
+
+    #[tokio::test]
+    async fn test_failures_expire() {
+        let mut store = HashMapLoginFailureStore::new();
+        let email = get_test_email();
+
+        store.record_failure(&email, 0).await.unwrap();
+        assert_eq!(store.get_failures(&email).await.unwrap(), None);
+        assert_eq!(store.record_failure(&email, 60).await.unwrap().count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_reserve_attempt_counts_attempts_in_flight() {
+        let mut store = HashMapLoginFailureStore::new();
+        let email = get_test_email();
+
+        store.record_failure(&email, 60).await.unwrap();
+        let failures = store.reserve_attempt(&email, 60).await.unwrap();
+        assert_eq!(failures.count, 1);
+        assert_eq!(store.reserve_attempt(&email, 60).await.unwrap().count, 2);
+        assert_eq!(store.reserve_attempt(&email, 60).await.unwrap().count, 3);
+
+        store.release_attempt(&email, 60).await.unwrap();
+        store.release_attempt(&email, 60).await.unwrap();
+        assert_eq!(store.reserve_attempt(&email, 60).await.unwrap().count, 2);
+        assert_eq!(store.get_failures(&email).await.unwrap().unwrap().count, 1);
+
+        store.clear_failures(&email).await.unwrap();
+        assert_eq!(store.reserve_attempt(&email, 60).await.unwrap().count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_clear_failures() {
+        let mut store = HashMapLoginFailureStore::new();
+        let email = get_test_email();
+
+        store.record_failure(&email, 60).await.unwrap();
+        store.clear_failures(&email).await.unwrap();
+        assert_eq!(store.get_failures(&email).await.unwrap(), None);
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::{HashMap, HashSet};
+
+use secrecy::ExposeSecret;
+use uuid::Uuid;
+
+use crate::domain::data_stores::{RefreshToken, RefreshTokenRecord, RefreshTokenStore, RefreshTokenStoreError};
+
+#[derive(Clone, Debug)]
+pub struct HashMapRefreshTokenStore {
+    tokens: HashMap<String, RefreshTokenRecord>,
+    used_tokens: HashSet<String>,
+}
+
+impl HashMapRefreshTokenStore {
+    pub fn new() -> Self {
+        Self {
+            tokens: HashMap::new(),
+            used_tokens: HashSet::new(),
+        }
+    }
+}
+
+impl Default for HashMapRefreshTokenStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenStore for HashMapRefreshTokenStore {
+    async fn add_token(
+        &mut self,
+        token: RefreshToken,
+        record: RefreshTokenRecord,
+    ) -> Result<(), RefreshTokenStoreError> {
+        self.tokens.insert(token.expose_secret_string(), record);
+        Ok(())
+    }
+
+    async fn get_token(&self, token: &RefreshToken) -> Result<RefreshTokenRecord, RefreshTokenStoreError> {
+        match self.tokens.get(token.as_ref().expose_secret()) {
+            Some(record) => Ok(record.clone()),
+            None => Err(RefreshTokenStoreError::TokenNotFound),
+        }
+    }
+
+    async fn mark_token_used(&mut self, token: &RefreshToken) -> Result<(), RefreshTokenStoreError> {
+        let token = token.expose_secret_string();
+        if !self.tokens.contains_key(&token) {
+            return Err(RefreshTokenStoreError::TokenNotFound);
+        }
+        match self.used_tokens.insert(token) {
+            true => Ok(()),
+            false => Err(RefreshTokenStoreError::TokenReused),
+        }
+    }
+
+    async fn revoke_family(&mut self, family_id: &Uuid) -> Result<(), RefreshTokenStoreError> {
+        let revoked_tokens: Vec<String> = self
+            .tokens
+            .iter()
+            .filter(|(_, record)| record.family_id == *family_id)
+            .map(|(token, _)| token.clone())
+            .collect();
+
+        for token in revoked_tokens {
+            self.tokens.remove(&token);
+            self.used_tokens.remove(&token);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+    use crate::domain::email::Email;
+
+    fn str_to_valid_email(email: &str) -> Email {
+        Email::parse(Secret::new(email.to_string())).unwrap()
+    }
+
+    async fn get_store_with_token() -> (HashMapRefreshTokenStore, RefreshToken, RefreshTokenRecord) {
+        let mut store = HashMapRefreshTokenStore::new();
+        let token = RefreshToken::default();
+        let record = RefreshTokenRecord::new(str_to_valid_email("test@example.com"));
+        store.add_token(token.clone(), record.clone()).await.unwrap();
+        (store, token, record)
+    }
+
+    #[tokio::test]
+    async fn test_add_token() {
+        let (store, token, record) = get_store_with_token().await;
+
+        assert_eq!(store.tokens.len(), 1);
+        assert_eq!(store.tokens.get(token.as_ref().expose_secret()), Some(&record));
+    }
+
+    #[tokio::test]
+    async fn test_get_token_existing() {
+        let (store, token, record) = get_store_with_token().await;
+
+        let result = store.get_token(&token).await;
+
+        assert_eq!(result.unwrap(), record);
+    }
+
+    #[tokio::test]
+    async fn test_get_token_non_existing() {
+        let store = HashMapRefreshTokenStore::new();
+
+        let result = store.get_token(&RefreshToken::default()).await;
+
+        assert!(matches!(result, Err(RefreshTokenStoreError::TokenNotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_mark_token_used_twice_returns_token_reused() {
+        let (mut store, token, _) = get_store_with_token().await;
+
+        assert!(store.mark_token_used(&token).await.is_ok());
+
+        let result = store.mark_token_used(&token).await;
+        assert!(matches!(result, Err(RefreshTokenStoreError::TokenReused)));
+    }
+
+    #[tokio::test]
+    async fn test_mark_token_used_non_existing() {
+        let mut store = HashMapRefreshTokenStore::new();
+
+        let result = store.mark_token_used(&RefreshToken::default()).await;
+
+        assert!(matches!(result, Err(RefreshTokenStoreError::TokenNotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_revoke_family_removes_only_family_tokens() {
+        let (mut store, token, record) = get_store_with_token().await;
+        let rotated_token = RefreshToken::default();
+        store.add_token(rotated_token.clone(), record.clone()).await.unwrap();
+        store.mark_token_used(&token).await.unwrap();
+
+        let other_token = RefreshToken::default();
+        let other_record = RefreshTokenRecord::new(str_to_valid_email("other@example.com"));
+        store.add_token(other_token.clone(), other_record).await.unwrap();
+
+        store.revoke_family(&record.family_id).await.unwrap();
+
+        assert!(store.get_token(&token).await.is_err());
+        assert!(store.get_token(&rotated_token).await.is_err());
+        assert!(store.get_token(&other_token).await.is_ok());
+        assert!(store.used_tokens.is_empty());
+    }
+}
@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::domain::{
+    data_stores::{Session, SessionStore, SessionStoreError},
+    email::Email,
+};
+
+#[derive(Clone, Debug)]
+pub struct HashMapSessionStore {
+    sessions: HashMap<Uuid, Session>,
+}
+
+impl HashMapSessionStore {
+    pub fn new() -> Self {
+        Self {
+            sessions: HashMap::new(),
+        }
+    }
+}
+
+impl Default for HashMapSessionStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait::async_trait]
+impl SessionStore for HashMapSessionStore {
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError> {
+        self.sessions.insert(session.id, session);
+        Ok(())
+    }
+
+    async fn get_session(&self, session_id: &Uuid) -> Result<Session, SessionStoreError> {
+        self.sessions
+            .get(session_id)
+            .cloned()
+            .ok_or(SessionStoreError::SessionNotFound)
+    }
+
+    async fn get_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError> {
+        let mut sessions: Vec<Session> = self
+            .sessions
+            .values()
+            .filter(|session| session.email == *email)
+            .cloned()
+            .collect();
+        sessions.sort_by_key(|session| session.created_at);
+        Ok(sessions)
+    }
+
+    async fn touch_session(&mut self, session_id: &Uuid, last_seen_at: DateTime<Utc>) -> Result<(), SessionStoreError> {
+        let session = self
+            .sessions
+            .get_mut(session_id)
+            .ok_or(SessionStoreError::SessionNotFound)?;
+        session.last_seen_at = last_seen_at;
+        Ok(())
+    }
+
+    async fn remove_session(&mut self, session_id: &Uuid) -> Result<(), SessionStoreError> {
+        self.sessions
+            .remove(session_id)
+            .map(|_| ())
+            .ok_or(SessionStoreError::SessionNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+
+    fn str_to_valid_email(email: &str) -> Email {
+        Email::parse(Secret::new(email.to_string())).unwrap()
+    }
+
+    fn new_session(email: &str) -> Session {
+        Session::new(
+            str_to_valid_email(email),
+            Some("127.0.0.1".to_string()),
+            Some("test-agent".to_string()),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_add_and_get_session() {
+        let mut store = HashMapSessionStore::new();
+        let session = new_session("test@example.com");
+        store.add_session(session.clone()).await.unwrap();
+
+        assert_eq!(store.get_session(&session.id).await.unwrap(), session);
+    }
+
+    #[tokio::test]
+    async fn test_get_session_non_existing() {
+        let store = HashMapSessionStore::new();
+
+        let result = store.get_session(&Uuid::new_v4()).await;
+
+        assert!(matches!(result, Err(SessionStoreError::SessionNotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_get_sessions_only_returns_users_sessions() {
+        let mut store = HashMapSessionStore::new();
+        let first = new_session("test@example.com");
+        let second = new_session("test@example.com");
+        store.add_session(first.clone()).await.unwrap();
+        store.add_session(second.clone()).await.unwrap();
+        store.add_session(new_session("other@example.com")).await.unwrap();
+
+        let sessions = store.get_sessions(&first.email).await.unwrap();
+
+        assert_eq!(sessions.len(), 2);
+        assert!(sessions.contains(&first));
+        assert!(sessions.contains(&second));
+    }
+
+    #[tokio::test]
+    async fn test_touch_session_updates_last_seen() {
+        let mut store = HashMapSessionStore::new();
+        let session = new_session("test@example.com");
+        store.add_session(session.clone()).await.unwrap();
+        let last_seen_at = session.last_seen_at + chrono::Duration::minutes(5);
+
+        store.touch_session(&session.id, last_seen_at).await.unwrap();
+
+        let stored = store.get_session(&session.id).await.unwrap();
+        assert_eq!(stored.last_seen_at, last_seen_at);
+        assert_eq!(stored.created_at, session.created_at);
+    }
+
+    #[tokio::test]
+    async fn test_remove_session() {
+        let mut store = HashMapSessionStore::new();
+        let session = new_session("test@example.com");
+        store.add_session(session.clone()).await.unwrap();
+
+        store.remove_session(&session.id).await.unwrap();
+
+        assert!(store.get_session(&session.id).await.is_err());
+        assert!(matches!(
+            store.remove_session(&session.id).await,
+            Err(SessionStoreError::SessionNotFound)
+        ));
+    }
+}
@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::domain::{
+    data_stores::{TrustedDevice, TrustedDeviceStore, TrustedDeviceStoreError},
+    email::Email,
+};
+
+#[derive(Clone, Debug)]
+pub struct HashMapTrustedDeviceStore {
+    devices: HashMap<Uuid, TrustedDevice>,
+}
+
+impl HashMapTrustedDeviceStore {
+    pub fn new() -> Self {
+        Self {
+            devices: HashMap::new(),
+        }
+    }
+}
+
+impl Default for HashMapTrustedDeviceStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait::async_trait]
+impl TrustedDeviceStore for HashMapTrustedDeviceStore {
+    async fn add_device(&mut self, device: TrustedDevice) -> Result<(), TrustedDeviceStoreError> {
+        self.devices.insert(device.id, device);
+        Ok(())
+    }
+
+    async fn get_device(&self, device_id: &Uuid) -> Result<TrustedDevice, TrustedDeviceStoreError> {
+        self.devices
+            .get(device_id)
+            .filter(|device| device.expires_at > Utc::now())
+            .cloned()
+            .ok_or(TrustedDeviceStoreError::TrustedDeviceNotFound)
+    }
+
+    async fn get_devices(&self, email: &Email) -> Result<Vec<TrustedDevice>, TrustedDeviceStoreError> {
+        let now = Utc::now();
+        let mut devices: Vec<TrustedDevice> = self
+            .devices
+            .values()
+            .filter(|device| device.email == *email && device.expires_at > now)
+            .cloned()
+            .collect();
+        devices.sort_by_key(|device| device.created_at);
+        Ok(devices)
+    }
+
+    async fn touch_device(
+        &mut self,
+        device_id: &Uuid,
+        last_used_at: DateTime<Utc>,
+    ) -> Result<(), TrustedDeviceStoreError> {
+        let device = self
+            .devices
+            .get_mut(device_id)
+            .ok_or(TrustedDeviceStoreError::TrustedDeviceNotFound)?;
+        device.last_used_at = last_used_at;
+        Ok(())
+    }
+
+    async fn remove_device(&mut self, device_id: &Uuid) -> Result<(), TrustedDeviceStoreError> {
+        self.devices
+            .remove(device_id)
+            .map(|_| ())
+            .ok_or(TrustedDeviceStoreError::TrustedDeviceNotFound)
+    }
+
+    async fn remove_devices(&mut self, email: &Email) -> Result<(), TrustedDeviceStoreError> {
+        self.devices.retain(|_, device| device.email != *email);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+
+    fn str_to_valid_email(email: &str) -> Email {
+        Email::parse(Secret::new(email.to_string())).unwrap()
+    }
+
+    fn new_device(email: &str) -> TrustedDevice {
+        TrustedDevice::new(
+            str_to_valid_email(email),
+            Some("127.0.0.1".to_string()),
+            Some("test-agent".to_string()),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_add_and_get_device() {
+        let mut store = HashMapTrustedDeviceStore::new();
+        let device = new_device("test@example.com");
+        store.add_device(device.clone()).await.unwrap();
+
+        assert_eq!(store.get_device(&device.id).await.unwrap(), device);
+    }
+
+    #[tokio::test]
+    async fn test_get_device_non_existing() {
+        let store = HashMapTrustedDeviceStore::new();
+
+        let result = store.get_device(&Uuid::new_v4()).await;
+
+        assert!(matches!(result, Err(TrustedDeviceStoreError::TrustedDeviceNotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_get_device_expired() {
+        let mut store = HashMapTrustedDeviceStore::new();
+        let mut device = new_device("test@example.com");
+        device.expires_at = Utc::now() - chrono::Duration::seconds(1);
+        store.add_device(device.clone()).await.unwrap();
+
+        assert!(store.get_device(&device.id).await.is_err());
+        assert!(store.get_devices(&device.email).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_devices_only_returns_users_devices() {
+        let mut store = HashMapTrustedDeviceStore::new();
+        let first = new_device("test@example.com");
+        let second = new_device("test@example.com");
+        store.add_device(first.clone()).await.unwrap();
+        store.add_device(second.clone()).await.unwrap();
+        store.add_device(new_device("other@example.com")).await.unwrap();
+
+        let devices = store.get_devices(&first.email).await.unwrap();
+
+        assert_eq!(devices.len(), 2);
+        assert!(devices.contains(&first));
+        assert!(devices.contains(&second));
+    }
+
+    #[tokio::test]
+    async fn test_touch_device_updates_last_used() {
+        let mut store = HashMapTrustedDeviceStore::new();
+        let device = new_device("test@example.com");
+        store.add_device(device.clone()).await.unwrap();
+        let last_used_at = device.last_used_at + chrono::Duration::minutes(5);
+
+        store.touch_device(&device.id, last_used_at).await.unwrap();
+
+        let stored = store.get_device(&device.id).await.unwrap();
+        assert_eq!(stored.last_used_at, last_used_at);
+        assert_eq!(stored.expires_at, device.expires_at);
+    }
+
+    #[tokio::test]
+    async fn test_remove_device() {
+        let mut store = HashMapTrustedDeviceStore::new();
+        let device = new_device("test@example.com");
+        store.add_device(device.clone()).await.unwrap();
+
+        store.remove_device(&device.id).await.unwrap();
+
+        assert!(store.get_device(&device.id).await.is_err());
+        assert!(matches!(
+            store.remove_device(&device.id).await,
+            Err(TrustedDeviceStoreError::TrustedDeviceNotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_remove_devices_only_removes_users_devices() {
+        let mut store = HashMapTrustedDeviceStore::new();
+        let device = new_device("test@example.com");
+        let other = new_device("other@example.com");
+        store.add_device(device.clone()).await.unwrap();
+        store.add_device(new_device("test@example.com")).await.unwrap();
+        store.add_device(other.clone()).await.unwrap();
+
+        store.remove_devices(&device.email).await.unwrap();
+
+        assert!(store.get_devices(&device.email).await.unwrap().is_empty());
+        assert_eq!(store.get_device(&other.id).await.unwrap(), other);
+    }
+}
@@ -1,18 +1,49 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+
+use chrono::{DateTime, Duration, Utc};
 
 use crate::domain::{
     data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
     email::Email,
 };
+use crate::utils::constants::MAX_PENDING_TWO_FA_ATTEMPTS;
+
+#[derive(Clone, Debug)]
+struct PendingCode {
+    email: Email,
+    code: TwoFACode,
+    failed_attempts: u32,
+    resends: u32,
+    last_resent_at: Option<DateTime<Utc>>,
+}
 
 #[derive(Clone, Default, Debug)]
 pub struct HashMapTwoFACodeStore {
-    codes: HashMap<Email, (LoginAttemptId, TwoFACode)>,
+    codes: HashMap<LoginAttemptId, PendingCode>,
+    /// Each user's pending attempts, oldest first
+    attempts: HashMap<Email, VecDeque<LoginAttemptId>>,
+    /// Codes sent to each user in the current window, and when that window started
+    sends: HashMap<Email, (u32, DateTime<Utc>)>,
 }
 
 impl HashMapTwoFACodeStore {
     pub fn new() -> Self {
-        Self { codes: HashMap::new() }
+        Self {
+            codes: HashMap::new(),
+            attempts: HashMap::new(),
+            sends: HashMap::new(),
+        }
+    }
+
+    fn pending_code(
+        &mut self,
+        email: &Email,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<&mut PendingCode, TwoFACodeStoreError> {
+        self.codes
+            .get_mut(login_attempt_id)
+            .filter(|pending| pending.email == *email)
+            .ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)
     }
 }
 
@@ -24,23 +55,116 @@ impl TwoFACodeStore for HashMapTwoFACodeStore {
         login_attempt_id: LoginAttemptId,
         code: TwoFACode,
     ) -> Result<(), TwoFACodeStoreError> {
-        self.codes
-            .insert(email.clone(), (login_attempt_id.clone(), code.clone()));
+        let attempts = self.attempts.entry(email.clone()).or_default();
+        attempts.push_back(login_attempt_id.clone());
+        while attempts.len() > MAX_PENDING_TWO_FA_ATTEMPTS {
+            if let Some(evicted) = attempts.pop_front() {
+                self.codes.remove(&evicted);
+            }
+        }
+
+        self.codes.insert(
+            login_attempt_id,
+            PendingCode {
+                email,
+                code,
+                failed_attempts: 0,
+                resends: 0,
+                last_resent_at: None,
+            },
+        );
+        Ok(())
+    }
+
+    async fn get_code(
+        &self,
+        email: &Email,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<TwoFACode, TwoFACodeStoreError> {
+        match self.codes.get(login_attempt_id) {
+            Some(pending) if pending.email == *email => Ok(pending.code.clone()),
+            _ => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
+        }
+    }
+
+    async fn remove_code(
+        &mut self,
+        email: &Email,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(), TwoFACodeStoreError> {
+        self.pending_code(email, login_attempt_id)?;
+        self.codes.remove(login_attempt_id);
+
+        if let Some(attempts) = self.attempts.get_mut(email) {
+            attempts.retain(|id| id != login_attempt_id);
+            if attempts.is_empty() {
+                self.attempts.remove(email);
+            }
+        }
         Ok(())
     }
 
-    async fn get_code(&self, email: &Email) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
-        match self.codes.get(email) {
-            None => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
-            Some(code_ref) => Ok((*code_ref).clone()),
+    async fn record_failed_attempt(
+        &mut self,
+        email: &Email,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<u32, TwoFACodeStoreError> {
+        let pending = self.pending_code(email, login_attempt_id)?;
+        pending.failed_attempts += 1;
+        Ok(pending.failed_attempts)
+    }
+
+    async fn resend_code(
+        &mut self,
+        email: &Email,
+        login_attempt_id: &LoginAttemptId,
+        code: TwoFACode,
+        cooldown_seconds: u64,
+        max_resends: u32,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let pending = self.pending_code(email, login_attempt_id)?;
+
+        let now = Utc::now();
+        if pending.resends >= max_resends {
+            return Err(TwoFACodeStoreError::ResendLimitReached);
+        }
+        if pending
+            .last_resent_at
+            .is_some_and(|last_resent_at| now - last_resent_at < Duration::seconds(cooldown_seconds as i64))
+        {
+            return Err(TwoFACodeStoreError::ResendCooldown);
         }
+
+        pending.code = code;
+        pending.resends += 1;
+        pending.last_resent_at = Some(now);
+        Ok(())
     }
 
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
-        match self.codes.remove(email) {
-            None => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
-            Some(_) => Ok(()),
+    async fn remove_codes(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
+        for login_attempt_id in self.attempts.remove(email).unwrap_or_default() {
+            self.codes.remove(&login_attempt_id);
         }
+        Ok(())
+    }
+
+    async fn record_code_sent(
+        &mut self,
+        email: &Email,
+        max_sends: u32,
+        window_seconds: u64,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let now = Utc::now();
+        let (sent, window_started_at) = self.sends.entry(email.clone()).or_insert((0, now));
+        if now - *window_started_at >= Duration::seconds(window_seconds as i64) {
+            (*sent, *window_started_at) = (0, now);
+        }
+        if *sent >= max_sends {
+            return Err(TwoFACodeStoreError::SendLimitReached);
+        }
+
+        *sent += 1;
+        Ok(())
     }
 }
 
@@ -56,7 +180,7 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_add_code() {
+    async fn test_add_and_get_code() {
         let mut store = HashMapTwoFACodeStore::new();
         let email = str_to_valid_email("test@example.com");
         let login_attempt_id = LoginAttemptId::default();
@@ -67,35 +191,62 @@ mod tests {
             .await;
 
         assert!(result.is_ok());
-        assert_eq!(store.codes.len(), 1);
-        assert_eq!(store.codes.get(&email), Some(&(login_attempt_id, code)));
+        assert_eq!(store.get_code(&email, &login_attempt_id).await.unwrap(), code);
+    }
+
+    #[tokio::test]
+    async fn test_get_code_non_existing() {
+        let store = HashMapTwoFACodeStore::new();
+        let email = str_to_valid_email("test@example.com");
+
+        let result = store.get_code(&email, &LoginAttemptId::default()).await;
+
+        assert!(result.is_err());
     }
 
     #[tokio::test]
-    async fn test_get_code_existing() {
+    async fn test_get_code_for_other_email() {
         let mut store = HashMapTwoFACodeStore::new();
         let email = str_to_valid_email("test@example.com");
+        let other_email = str_to_valid_email("other@example.com");
         let login_attempt_id = LoginAttemptId::default();
-        let code = TwoFACode::default();
 
         store
-            .codes
-            .insert(email.clone(), (login_attempt_id.clone(), code.clone()));
-
-        let result = store.get_code(&email).await;
+            .add_code(email, login_attempt_id.clone(), TwoFACode::default())
+            .await
+            .unwrap();
 
-        assert!(result.is_ok());
-        assert_eq!(result.unwrap(), (login_attempt_id, code));
+        assert!(store.get_code(&other_email, &login_attempt_id).await.is_err());
     }
 
     #[tokio::test]
-    async fn test_get_code_non_existing() {
-        let store = HashMapTwoFACodeStore::new();
+    async fn test_remove_codes_only_for_user() {
+        let mut store = HashMapTwoFACodeStore::new();
         let email = str_to_valid_email("test@example.com");
+        let other_email = str_to_valid_email("other@example.com");
+        let login_attempt_ids = [LoginAttemptId::default(), LoginAttemptId::default()];
+        let other_login_attempt_id = LoginAttemptId::default();
+        for login_attempt_id in &login_attempt_ids {
+            store
+                .add_code(email.clone(), login_attempt_id.clone(), TwoFACode::default())
+                .await
+                .unwrap();
+        }
+        store
+            .add_code(
+                other_email.clone(),
+                other_login_attempt_id.clone(),
+                TwoFACode::default(),
+            )
+            .await
+            .unwrap();
 
-        let result = store.get_code(&email).await;
+        store.remove_codes(&email).await.unwrap();
 
-        assert!(result.is_err());
+        for login_attempt_id in &login_attempt_ids {
+            assert!(store.get_code(&email, login_attempt_id).await.is_err());
+        }
+        assert!(store.get_code(&other_email, &other_login_attempt_id).await.is_ok());
     }
 
     #[tokio::test]
@@ -103,15 +254,17 @@ mod tests {
         let mut store = HashMapTwoFACodeStore::new();
         let email = str_to_valid_email("test@example.com");
         let login_attempt_id = LoginAttemptId::default();
-        let code = TwoFACode::default();
 
-        store.codes.insert(email.clone(), (login_attempt_id, code));
+        store
+            .add_code(email.clone(), login_attempt_id.clone(), TwoFACode::default())
+            .await
+            .unwrap();
 
-        let result = store.remove_code(&email).await;
+        let result = store.remove_code(&email, &login_attempt_id).await;
 
         assert!(result.is_ok());
-        assert_eq!(store.codes.len(), 0);
-        assert!(store.codes.get(&email).is_none());
+        assert!(store.codes.is_empty());
+        assert!(store.attempts.is_empty());
     }
 
     #[tokio::test]
@@ -119,27 +272,176 @@ mod tests {
         let mut store = HashMapTwoFACodeStore::new();
         let email = str_to_valid_email("test@example.com");
 
-        let result = store.remove_code(&email).await;
+        let result = store.remove_code(&email, &LoginAttemptId::default()).await;
 
         assert!(result.is_err());
     }
 
     #[tokio::test]
-    async fn test_add_code_overwrites_existing() {
+    async fn test_concurrent_attempts_are_kept_separately() {
+        let mut store = HashMapTwoFACodeStore::new();
+        let email = str_to_valid_email("test@example.com");
+        let (first_id, first_code) = (LoginAttemptId::default(), TwoFACode::default());
+        let (second_id, second_code) = (LoginAttemptId::default(), TwoFACode::default());
+
+        store
+            .add_code(email.clone(), first_id.clone(), first_code.clone())
+            .await
+            .unwrap();
+        store
+            .add_code(email.clone(), second_id.clone(), second_code.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(store.get_code(&email, &first_id).await.unwrap(), first_code);
+        assert_eq!(store.get_code(&email, &second_id).await.unwrap(), second_code);
+
+        store.remove_code(&email, &first_id).await.unwrap();
+        assert!(store.get_code(&email, &first_id).await.is_err());
+        assert_eq!(store.get_code(&email, &second_id).await.unwrap(), second_code);
+    }
+
+    #[tokio::test]
+    async fn test_add_code_evicts_oldest_attempt() {
+        let mut store = HashMapTwoFACodeStore::new();
+        let email = str_to_valid_email("test@example.com");
+        let ids: Vec<LoginAttemptId> = (0..=MAX_PENDING_TWO_FA_ATTEMPTS)
+            .map(|_| LoginAttemptId::default())
+            .collect();
+
+        for id in &ids {
+            store
+                .add_code(email.clone(), id.clone(), TwoFACode::default())
+                .await
+                .unwrap();
+        }
+
+        assert!(store.get_code(&email, &ids[0]).await.is_err());
+        for id in &ids[1..] {
+            assert!(store.get_code(&email, id).await.is_ok());
+        }
+        assert_eq!(store.codes.len(), MAX_PENDING_TWO_FA_ATTEMPTS);
+    }
+
+    #[tokio::test]
+    async fn test_record_failed_attempt() {
         let mut store = HashMapTwoFACodeStore::new();
         let email = str_to_valid_email("test@example.com");
-        let login_attempt_id1 = LoginAttemptId::default();
-        let code1 = TwoFACode::default();
-        let login_attempt_id2 = LoginAttemptId::default();
-        let code2 = TwoFACode::default();
+        let login_attempt_id = LoginAttemptId::default();
+        let other_attempt_id = LoginAttemptId::default();
+
+        assert!(store.record_failed_attempt(&email, &login_attempt_id).await.is_err());
 
-        store.add_code(email.clone(), login_attempt_id1, code1).await.unwrap();
         store
-            .add_code(email.clone(), login_attempt_id2.clone(), code2.clone())
+            .add_code(email.clone(), login_attempt_id.clone(), TwoFACode::default())
             .await
             .unwrap();
+        store
+            .add_code(email.clone(), other_attempt_id.clone(), TwoFACode::default())
+            .await
+            .unwrap();
+        assert_eq!(store.record_failed_attempt(&email, &login_attempt_id).await.unwrap(), 1);
+        assert_eq!(store.record_failed_attempt(&email, &login_attempt_id).await.unwrap(), 2);
+
+        // Each login attempt keeps its own count
+        assert_eq!(store.record_failed_attempt(&email, &other_attempt_id).await.unwrap(), 1);
+
+        store.remove_code(&email, &login_attempt_id).await.unwrap();
+        assert!(store.record_failed_attempt(&email, &login_attempt_id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_resend_code_keeps_login_attempt_id() {
+        let mut store = HashMapTwoFACodeStore::new();
+        let email = str_to_valid_email("test@example.com");
+        let login_attempt_id = LoginAttemptId::default();
+        let new_code = TwoFACode::default();
+
+        assert!(matches!(
+            store
+                .resend_code(&email, &login_attempt_id, new_code.clone(), 0, 3)
+                .await,
+            Err(TwoFACodeStoreError::LoginAttemptIdNotFound)
+        ));
+
+        store
+            .add_code(email.clone(), login_attempt_id.clone(), TwoFACode::default())
+            .await
+            .unwrap();
+        store
+            .resend_code(&email, &login_attempt_id, new_code.clone(), 0, 3)
+            .await
+            .unwrap();
+
+        assert_eq!(store.get_code(&email, &login_attempt_id).await.unwrap(), new_code);
+    }
+
+    #[tokio::test]
+    async fn test_resend_code_enforces_cooldown_and_limit() {
+        let mut store = HashMapTwoFACodeStore::new();
+        let email = str_to_valid_email("test@example.com");
+        let login_attempt_id = LoginAttemptId::default();
+        store
+            .add_code(email.clone(), login_attempt_id.clone(), TwoFACode::default())
+            .await
+            .unwrap();
+
+        store
+            .resend_code(&email, &login_attempt_id, TwoFACode::default(), 60, 3)
+            .await
+            .unwrap();
+        assert!(matches!(
+            store
+                .resend_code(&email, &login_attempt_id, TwoFACode::default(), 60, 3)
+                .await,
+            Err(TwoFACodeStoreError::ResendCooldown)
+        ));
+
+        for _ in 0..2 {
+            store
+                .resend_code(&email, &login_attempt_id, TwoFACode::default(), 0, 3)
+                .await
+                .unwrap();
+        }
+        assert!(matches!(
+            store
+                .resend_code(&email, &login_attempt_id, TwoFACode::default(), 0, 3)
+                .await,
+            Err(TwoFACodeStoreError::ResendLimitReached)
+        ));
+
+        // A new login attempt gets a fresh allowance
+        let new_attempt_id = LoginAttemptId::default();
+        store
+            .add_code(email.clone(), new_attempt_id.clone(), TwoFACode::default())
+            .await
+            .unwrap();
+        store
+            .resend_code(&email, &new_attempt_id, TwoFACode::default(), 60, 3)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_record_code_sent_enforces_limit_per_user() {
+        let mut store = HashMapTwoFACodeStore::new();
+        let email = str_to_valid_email("test@example.com");
+        let other_email = str_to_valid_email("other@example.com");
+
+        for _ in 0..2 {
+            store.record_code_sent(&email, 2, 60).await.unwrap();
+        }
+        assert!(matches!(
+            store.record_code_sent(&email, 2, 60).await,
+            Err(TwoFACodeStoreError::SendLimitReached)
+        ));
+
+        // Dropping the pending codes doesn't reset the allowance
+        store.remove_codes(&email).await.unwrap();
+        assert!(store.record_code_sent(&email, 2, 60).await.is_err());
+        assert!(store.record_code_sent(&other_email, 2, 60).await.is_ok());
 
-        assert_eq!(store.codes.len(), 1);
-        assert_eq!(store.codes.get(&email), Some(&(login_attempt_id2, code2)));
+        // A new window does
+        assert!(store.record_code_sent(&email, 2, 0).await.is_ok());
     }
 }
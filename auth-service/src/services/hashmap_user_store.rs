@@ -1,34 +1,88 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 
+use chrono::{DateTime, Duration, Utc};
 use color_eyre::eyre::{self, eyre};
+use secrecy::{ExposeSecret, Secret};
 
 use crate::{
     domain::{
         data_stores::{UserStore, UserStoreError},
         email::Email,
         password::Password,
-        user::{DbUser, NewUser, User},
+        phone_number::PhoneNumber,
+        user::{
+            AuthEvent, DbUser, ImportedUser, NewUser, PasswordHashCounts, TotpCredential, TwoFAMethod, User,
+            UserAccess, UserPhoneNumber, WebAuthnCredential, DEFAULT_ROLE,
+        },
     },
-    utils::auth::async_compute_password_hash,
+    utils::{auth::async_compute_password_hash, constants::MAX_AUTH_EVENTS, password_hash::PasswordHashPolicy},
 };
 
 #[derive(Clone, Debug)]
 pub struct HashmapUserStore {
     // id: String,
     users: HashMap<Email, DbUser>,
+    user_roles: HashMap<Email, Vec<String>>,
+    /// Mirrors the roles seeded by the `create_roles_tables` migration.
+    roles: HashMap<String, Vec<String>>,
+    totp_credentials: HashMap<Email, TotpCredential>,
+    recovery_codes: HashMap<Email, HashSet<String>>,
+    webauthn_credentials: HashMap<Email, Vec<WebAuthnCredential>>,
+    phone_numbers: HashMap<Email, UserPhoneNumber>,
+    /// Each user's account history, newest first
+    auth_events: HashMap<Email, VecDeque<AuthEvent>>,
+    /// Users scheduled for deletion, with when it was requested.
+    deleted_at: HashMap<Email, DateTime<Utc>>,
 }
 
+const BUILT_IN_ROLES: [(&str, &[&str]); 2] = [("user", &["users:self"]), ("admin", &["users:self", "users:admin"])];
+
 impl HashmapUserStore {
     pub fn new() -> Self {
         HashmapUserStore {
             // id: uuid::Uuid::new_v4().to_string(),
             users: HashMap::new(),
+            user_roles: HashMap::new(),
+            roles: BUILT_IN_ROLES
+                .iter()
+                .map(|(role, scopes)| (role.to_string(), scopes.iter().map(|s| s.to_string()).collect()))
+                .collect(),
+            totp_credentials: HashMap::new(),
+            recovery_codes: HashMap::new(),
+            webauthn_credentials: HashMap::new(),
+            phone_numbers: HashMap::new(),
+            auth_events: HashMap::new(),
+            deleted_at: HashMap::new(),
         }
     }
 
     // pub fn get_id(&self) -> String {
     //     self.id.clone()
     // }
+
+    /// Whether the user exists and isn't scheduled for deletion.
+    fn is_active(&self, email: &Email) -> bool {
+        self.users.contains_key(email) && !self.deleted_at.contains_key(email)
+    }
+
+    /// Removes a user scheduled for deletion, so the address can be used again.
+    fn purge_if_deleted(&mut self, email: &Email) {
+        if self.deleted_at.contains_key(email) {
+            self.remove_user_data(email);
+        }
+    }
+
+    /// Removes everything stored under the address and returns whether there was a user.
+    fn remove_user_data(&mut self, email: &Email) -> bool {
+        self.user_roles.remove(email);
+        self.totp_credentials.remove(email);
+        self.recovery_codes.remove(email);
+        self.webauthn_credentials.remove(email);
+        self.phone_numbers.remove(email);
+        self.auth_events.remove(email);
+        self.deleted_at.remove(email);
+        self.users.remove(email).is_some()
+    }
 }
 
 #[async_trait::async_trait]
@@ -37,6 +91,7 @@ impl UserStore for HashmapUserStore {
         println!("[HashmapUserStore][add_user] {:?}", self);
         println!("[HashmapUserStore][add_user] {:?}", user);
         let email = user.email.clone();
+        self.purge_if_deleted(&email);
         match self.users.get(&email) {
             Some(_) => Err(UserStoreError::UserAlreadyExists),
             None => {
@@ -46,20 +101,38 @@ impl UserStore for HashmapUserStore {
                 let user = DbUser {
                     email: email.as_ref().clone(),
                     password_hash,
-                    requires_2fa: user.requires_2fa,
+                    two_fa_method: user.two_fa_method.to_string(),
+                    email_verified: false,
                 };
-                self.users.insert(email, user);
+                self.users.insert(email.clone(), user);
+                self.user_roles.insert(email, vec![DEFAULT_ROLE.to_string()]);
                 Ok(())
             }
         }
     }
 
+    async fn import_user(&mut self, user: ImportedUser) -> Result<(), UserStoreError> {
+        self.purge_if_deleted(&user.email);
+        if self.users.contains_key(&user.email) {
+            return Err(UserStoreError::UserAlreadyExists);
+        }
+        let db_user = DbUser {
+            email: user.email.as_ref().clone(),
+            password_hash: user.password_hash,
+            two_fa_method: TwoFAMethod::None.to_string(),
+            email_verified: user.email_verified,
+        };
+        self.users.insert(user.email.clone(), db_user);
+        self.user_roles.insert(user.email, vec![DEFAULT_ROLE.to_string()]);
+        Ok(())
+    }
+
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
         println!("[HashmapUserStore][get_user] {:?}", self);
         println!("[HashmapUserStore][get_user] {:?}", email);
         match self.users.get(email) {
-            Some(user) => Ok((*user).clone().to_user()),
-            None => Err(UserStoreError::UserNotFound),
+            Some(user) if !self.deleted_at.contains_key(email) => Ok((*user).clone().to_user()),
+            _ => Err(UserStoreError::UserNotFound),
         }
     }
 
@@ -75,16 +148,336 @@ impl UserStore for HashmapUserStore {
         Ok(())
     }
 
-    async fn validate_user(&self, email: &Email, password: &Password) -> eyre::Result<User> {
+    async fn update_2fa_settings(&mut self, email: &Email, two_fa_method: TwoFAMethod) -> Result<(), UserStoreError> {
+        let user = self.users.get_mut(email).ok_or(UserStoreError::UserNotFound)?;
+        user.two_fa_method = two_fa_method.to_string();
+        Ok(())
+    }
+
+    async fn disable_2fa(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        let user = self.users.get_mut(email).ok_or(UserStoreError::UserNotFound)?;
+        user.two_fa_method = TwoFAMethod::None.to_string();
+        self.totp_credentials.remove(email);
+        self.recovery_codes.remove(email);
+        Ok(())
+    }
+
+    async fn mark_email_verified(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        let user = self.users.get_mut(email).ok_or(UserStoreError::UserNotFound)?;
+        user.email_verified = true;
+        Ok(())
+    }
+
+    async fn change_email(&mut self, email: &Email, new_email: &Email) -> Result<(), UserStoreError> {
+        if !self.users.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+        if self.users.contains_key(new_email) {
+            return Err(UserStoreError::UserAlreadyExists);
+        }
+        let mut user = self.users.remove(email).ok_or(UserStoreError::UserNotFound)?;
+        user.email = new_email.as_ref().clone();
+        self.users.insert(new_email.clone(), user);
+
+        fn move_entry<T>(map: &mut HashMap<Email, T>, email: &Email, new_email: &Email) {
+            if let Some(value) = map.remove(email) {
+                map.insert(new_email.clone(), value);
+            }
+        }
+        move_entry(&mut self.user_roles, email, new_email);
+        move_entry(&mut self.totp_credentials, email, new_email);
+        move_entry(&mut self.recovery_codes, email, new_email);
+        move_entry(&mut self.webauthn_credentials, email, new_email);
+        move_entry(&mut self.phone_numbers, email, new_email);
+        move_entry(&mut self.auth_events, email, new_email);
+        Ok(())
+    }
+
+    async fn delete_user(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        match self.remove_user_data(email) {
+            true => Ok(()),
+            false => Err(UserStoreError::UserNotFound),
+        }
+    }
+
+    async fn schedule_deletion(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        if !self.users.contains_key(email) || self.deleted_at.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+        self.deleted_at.insert(email.clone(), Utc::now());
+        Ok(())
+    }
+
+    async fn restore_user(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        self.deleted_at.remove(email).ok_or(UserStoreError::UserNotFound)?;
+        Ok(())
+    }
+
+    async fn purge_deleted_users(&mut self, grace_period_seconds: u64) -> Result<u64, UserStoreError> {
+        let cutoff = Utc::now() - Duration::seconds(grace_period_seconds as i64);
+        let expired: Vec<Email> = self
+            .deleted_at
+            .iter()
+            .filter(|(_, deleted_at)| **deleted_at < cutoff)
+            .map(|(email, _)| email.clone())
+            .collect();
+        for email in &expired {
+            self.delete_user(email).await?;
+        }
+        Ok(expired.len() as u64)
+    }
+
+    async fn validate_user(&self, email: &Email, password: &Secret<String>) -> eyre::Result<User> {
         let db_user = match self.users.get(email) {
-            None => Err(eyre!("User Not Found")),
+            Some(db_user) if !self.deleted_at.contains_key(email) => Ok((*db_user).clone()),
+            _ => Err(eyre!("User Not Found")),
             // None => Err(UserStoreError::UserNotFound),
-            Some(db_user) => Ok((*db_user).clone()),
         }?;
         db_user.verify_password(password)?;
 
         Ok(db_user.to_user())
     }
+
+    async fn get_outdated_password_hash(&self, email: &Email) -> Result<Option<Secret<String>>, UserStoreError> {
+        let db_user = match self.users.get(email) {
+            Some(db_user) if !self.deleted_at.contains_key(email) => db_user,
+            _ => return Err(UserStoreError::UserNotFound),
+        };
+        let policy = PasswordHashPolicy::from_env().map_err(UserStoreError::UnexpectedError)?;
+        match policy.is_current(db_user.password_hash.expose_secret()) {
+            true => Ok(None),
+            false => Ok(Some(db_user.password_hash.clone())),
+        }
+    }
+
+    async fn replace_password_hash(
+        &mut self,
+        email: &Email,
+        outdated_hash: &Secret<String>,
+        password_hash: Secret<String>,
+    ) -> Result<bool, UserStoreError> {
+        match self.users.get_mut(email) {
+            Some(db_user) if db_user.password_hash.expose_secret() == outdated_hash.expose_secret() => {
+                db_user.password_hash = password_hash;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn count_password_hashes(&self) -> Result<PasswordHashCounts, UserStoreError> {
+        let policy = PasswordHashPolicy::from_env().map_err(UserStoreError::UnexpectedError)?;
+        let outdated = self
+            .users
+            .values()
+            .filter(|db_user| !policy.is_current(db_user.password_hash.expose_secret()))
+            .count();
+        Ok(PasswordHashCounts {
+            total: self.users.len() as u64,
+            outdated: outdated as u64,
+        })
+    }
+
+    async fn get_access(&self, email: &Email) -> Result<UserAccess, UserStoreError> {
+        if !self.is_active(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+        let roles = self.user_roles.get(email).cloned().unwrap_or_default();
+        let scopes = roles
+            .iter()
+            .filter_map(|role| self.roles.get(role))
+            .flatten()
+            .cloned()
+            .collect::<Vec<_>>();
+        Ok(UserAccess::new(roles, scopes))
+    }
+
+    async fn set_roles(&mut self, email: &Email, roles: &[String]) -> Result<(), UserStoreError> {
+        if !self.users.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+        if roles.iter().any(|role| !self.roles.contains_key(role)) {
+            return Err(UserStoreError::RoleNotFound);
+        }
+        let mut roles = roles.to_vec();
+        roles.sort();
+        roles.dedup();
+        self.user_roles.insert(email.clone(), roles);
+        Ok(())
+    }
+
+    async fn get_totp_credential(&self, email: &Email) -> Result<TotpCredential, UserStoreError> {
+        if !self.is_active(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+        Ok(self.totp_credentials.get(email).cloned().unwrap_or_default())
+    }
+
+    async fn set_pending_totp_secret(&mut self, email: &Email, secret: Secret<String>) -> Result<(), UserStoreError> {
+        if !self.users.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+        self.totp_credentials.entry(email.clone()).or_default().pending_secret = Some(secret);
+        Ok(())
+    }
+
+    async fn confirm_totp_secret(
+        &mut self,
+        email: &Email,
+        secret: &Secret<String>,
+        step: u64,
+    ) -> Result<(), UserStoreError> {
+        let user = self.users.get_mut(email).ok_or(UserStoreError::UserNotFound)?;
+        let credential = self
+            .totp_credentials
+            .get_mut(email)
+            .filter(|credential| {
+                credential
+                    .pending_secret
+                    .as_ref()
+                    .is_some_and(|pending| pending.expose_secret() == secret.expose_secret())
+            })
+            .ok_or(UserStoreError::TotpNotEnrolled)?;
+        credential.secret = credential.pending_secret.take();
+        credential.last_used_step = Some(step);
+        user.two_fa_method = TwoFAMethod::Totp.to_string();
+        Ok(())
+    }
+
+    async fn use_totp_step(&mut self, email: &Email, step: u64) -> Result<(), UserStoreError> {
+        let credential = self
+            .totp_credentials
+            .get_mut(email)
+            .filter(|credential| credential.secret.is_some())
+            .ok_or(UserStoreError::TotpNotEnrolled)?;
+        if credential.last_used_step.is_some_and(|last| last >= step) {
+            return Err(UserStoreError::TotpCodeReused);
+        }
+        credential.last_used_step = Some(step);
+        Ok(())
+    }
+
+    async fn set_recovery_codes(&mut self, email: &Email, code_hashes: Vec<String>) -> Result<(), UserStoreError> {
+        if !self.users.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+        self.recovery_codes
+            .insert(email.clone(), code_hashes.into_iter().collect());
+        Ok(())
+    }
+
+    async fn use_recovery_code(&mut self, email: &Email, code_hash: &str) -> Result<(), UserStoreError> {
+        let removed = self
+            .recovery_codes
+            .get_mut(email)
+            .is_some_and(|code_hashes| code_hashes.remove(code_hash));
+        match removed {
+            true => Ok(()),
+            false => Err(UserStoreError::RecoveryCodeNotFound),
+        }
+    }
+
+    async fn count_recovery_codes(&self, email: &Email) -> Result<usize, UserStoreError> {
+        if !self.is_active(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+        Ok(self.recovery_codes.get(email).map_or(0, HashSet::len))
+    }
+
+    async fn add_auth_event(&mut self, email: &Email, event: AuthEvent) -> Result<(), UserStoreError> {
+        if !self.is_active(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+        let events = self.auth_events.entry(email.clone()).or_default();
+        events.push_front(event);
+        events.truncate(MAX_AUTH_EVENTS);
+        Ok(())
+    }
+
+    async fn get_auth_events(&self, email: &Email) -> Result<Vec<AuthEvent>, UserStoreError> {
+        if !self.is_active(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+        Ok(self
+            .auth_events
+            .get(email)
+            .map(|events| events.iter().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    async fn add_webauthn_credential(
+        &mut self,
+        email: &Email,
+        credential: WebAuthnCredential,
+    ) -> Result<(), UserStoreError> {
+        if !self.users.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+        let exists = self
+            .webauthn_credentials
+            .values()
+            .flatten()
+            .any(|existing| existing.id == credential.id);
+        if exists {
+            return Err(UserStoreError::WebAuthnCredentialAlreadyExists);
+        }
+        self.webauthn_credentials
+            .entry(email.clone())
+            .or_default()
+            .push(credential);
+        Ok(())
+    }
+
+    async fn get_webauthn_credentials(&self, email: &Email) -> Result<Vec<WebAuthnCredential>, UserStoreError> {
+        if !self.is_active(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+        Ok(self.webauthn_credentials.get(email).cloned().unwrap_or_default())
+    }
+
+    async fn update_webauthn_sign_count(&mut self, credential_id: &str, sign_count: u32) -> Result<(), UserStoreError> {
+        let credential = self
+            .webauthn_credentials
+            .values_mut()
+            .flatten()
+            .find(|credential| credential.id == credential_id)
+            .ok_or(UserStoreError::UserNotFound)?;
+        credential.sign_count = sign_count;
+        Ok(())
+    }
+
+    async fn get_phone_number(&self, email: &Email) -> Result<UserPhoneNumber, UserStoreError> {
+        if !self.is_active(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+        Ok(self.phone_numbers.get(email).cloned().unwrap_or_default())
+    }
+
+    async fn set_pending_phone_number(
+        &mut self,
+        email: &Email,
+        phone_number: PhoneNumber,
+        verification_id: &str,
+    ) -> Result<(), UserStoreError> {
+        if !self.users.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+        let user_phone_number = self.phone_numbers.entry(email.clone()).or_default();
+        user_phone_number.pending_phone_number = Some(phone_number);
+        user_phone_number.pending_verification_id = Some(verification_id.to_owned());
+        Ok(())
+    }
+
+    async fn confirm_phone_number(&mut self, email: &Email, verification_id: &str) -> Result<(), UserStoreError> {
+        let user_phone_number = self
+            .phone_numbers
+            .get_mut(email)
+            .filter(|user_phone_number| user_phone_number.pending_verification_id.as_deref() == Some(verification_id))
+            .ok_or(UserStoreError::PhoneVerificationNotFound)?;
+        user_phone_number.phone_number = user_phone_number.pending_phone_number.take();
+        user_phone_number.pending_verification_id = None;
+        Ok(())
+    }
 }
 
 impl Default for HashmapUserStore {
@@ -95,9 +488,10 @@ impl Default for HashmapUserStore {
 
 #[cfg(test)]
 mod tests {
-    use secrecy::{ExposeSecret, Secret};
+    use argon2::{password_hash::SaltString, Algorithm, Argon2, Params, PasswordHasher, Version};
 
     use super::*;
+    use crate::domain::user::AuthEventKind;
 
     fn get_test_email() -> Email {
         Email::parse(Secret::new("test@email.com".to_string())).unwrap()
@@ -111,7 +505,7 @@ mod tests {
         NewUser {
             email: get_test_email(),
             password: get_test_password().await,
-            requires_2fa: false,
+            two_fa_method: TwoFAMethod::None,
         }
     }
 
@@ -119,7 +513,8 @@ mod tests {
         DbUser {
             email: get_test_email().as_ref().clone(),
             password_hash: get_test_password().await.as_ref().clone(),
-            requires_2fa: false,
+            two_fa_method: TwoFAMethod::None.to_string(),
+            email_verified: false,
         }
     }
 
@@ -152,7 +547,7 @@ mod tests {
             stored_user.email.expose_secret(),
             test_user.email.as_ref().expose_secret()
         );
-        assert_eq!(stored_user.requires_2fa, test_user.requires_2fa);
+        assert_eq!(stored_user.two_fa_method, test_user.two_fa_method.to_string());
 
         // assert!(stored_user.verify_password(&original_password).is_ok());
     }
@@ -167,7 +562,7 @@ mod tests {
             output_user.email.as_ref().expose_secret(),
             test_db_user.email.expose_secret()
         );
-        assert_eq!(output_user.requires_2fa, test_db_user.requires_2fa);
+        assert_eq!(output_user.two_fa_method.to_string(), test_db_user.two_fa_method);
     }
 
     #[tokio::test]
@@ -175,7 +570,7 @@ mod tests {
         let store = get_store_with_test_user().await;
         let email = get_test_email();
         let password = get_test_password().await;
-        assert!(store.validate_user(&email, &password).await.is_ok());
+        assert!(store.validate_user(&email, password.as_ref()).await.is_ok());
     }
 
     #[tokio::test]
@@ -183,7 +578,7 @@ mod tests {
         let store = HashmapUserStore::new();
         let email = get_test_email();
         let password = get_test_password().await;
-        let result = store.validate_user(&email, &password).await;
+        let result = store.validate_user(&email, password.as_ref()).await;
         // assert_eq!(result, Err(UserStoreError::UserNotFound));
         assert!(result.is_err())
     }
@@ -193,8 +588,454 @@ mod tests {
         let store = get_store_with_test_user().await;
         let email = get_test_email();
         let incorrect_password = Password::parse(Secret::new("Inc0rrect!".to_string())).await.unwrap();
-        let result = store.validate_user(&email, &incorrect_password).await;
+        let result = store.validate_user(&email, incorrect_password.as_ref()).await;
         // assert_eq!(result, Err(UserStoreError::InvalidCredentials));
         assert!(result.is_err())
     }
+
+    #[tokio::test]
+    async fn test_new_user_gets_default_role() {
+        let store = get_store_with_test_user().await;
+        let access = store.get_access(&get_test_email()).await.unwrap();
+        assert_eq!(access.roles, vec![DEFAULT_ROLE]);
+        assert_eq!(access.scopes, vec!["users:self"]);
+    }
+
+    #[tokio::test]
+    async fn test_set_roles() {
+        let mut store = get_store_with_test_user().await;
+        let email = get_test_email();
+
+        store.set_roles(&email, &["admin".to_string()]).await.unwrap();
+        let access = store.get_access(&email).await.unwrap();
+        assert_eq!(access.roles, vec!["admin"]);
+        assert_eq!(access.scopes, vec!["users:admin", "users:self"]);
+
+        let result = store.set_roles(&email, &["superuser".to_string()]).await;
+        assert!(matches!(result, Err(UserStoreError::RoleNotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_confirm_totp_secret_switches_method() {
+        let mut store = get_store_with_test_user().await;
+        let email = get_test_email();
+        let secret = Secret::new("JBSWY3DPEHPK3PXP".to_string());
+
+        let result = store.confirm_totp_secret(&email, &secret, 1).await;
+        assert!(matches!(result, Err(UserStoreError::TotpNotEnrolled)));
+
+        store.set_pending_totp_secret(&email, secret.clone()).await.unwrap();
+        store.confirm_totp_secret(&email, &secret, 1).await.unwrap();
+
+        let credential = store.get_totp_credential(&email).await.unwrap();
+        assert_eq!(credential.secret.unwrap().expose_secret(), secret.expose_secret());
+        assert!(credential.pending_secret.is_none());
+        assert_eq!(store.get_user(&email).await.unwrap().two_fa_method, TwoFAMethod::Totp);
+    }
+
+    #[tokio::test]
+    async fn test_update_2fa_settings() {
+        let mut store = get_store_with_test_user().await;
+        let email = get_test_email();
+
+        store.update_2fa_settings(&email, TwoFAMethod::Email).await.unwrap();
+        assert_eq!(store.get_user(&email).await.unwrap().two_fa_method, TwoFAMethod::Email);
+
+        store.update_2fa_settings(&email, TwoFAMethod::None).await.unwrap();
+        assert_eq!(store.get_user(&email).await.unwrap().two_fa_method, TwoFAMethod::None);
+
+        let other_email = Email::parse(Secret::new("other@email.com".to_string())).unwrap();
+        let result = store.update_2fa_settings(&other_email, TwoFAMethod::Email).await;
+        assert!(matches!(result, Err(UserStoreError::UserNotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_disable_2fa_removes_totp_secret_and_recovery_codes() {
+        let mut store = get_store_with_test_user().await;
+        let email = get_test_email();
+        let secret = Secret::new("JBSWY3DPEHPK3PXP".to_string());
+        store.set_pending_totp_secret(&email, secret.clone()).await.unwrap();
+        store.confirm_totp_secret(&email, &secret, 1).await.unwrap();
+        store
+            .set_recovery_codes(&email, vec!["first".to_string()])
+            .await
+            .unwrap();
+
+        store.disable_2fa(&email).await.unwrap();
+
+        assert_eq!(store.get_user(&email).await.unwrap().two_fa_method, TwoFAMethod::None);
+        assert!(store.get_totp_credential(&email).await.unwrap().secret.is_none());
+        assert_eq!(store.count_recovery_codes(&email).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_use_totp_step_rejects_replayed_steps() {
+        let mut store = get_store_with_test_user().await;
+        let email = get_test_email();
+        let secret = Secret::new("JBSWY3DPEHPK3PXP".to_string());
+        store.set_pending_totp_secret(&email, secret.clone()).await.unwrap();
+        store.confirm_totp_secret(&email, &secret, 10).await.unwrap();
+
+        assert!(matches!(
+            store.use_totp_step(&email, 10).await,
+            Err(UserStoreError::TotpCodeReused)
+        ));
+        assert!(store.use_totp_step(&email, 11).await.is_ok());
+        assert!(matches!(
+            store.use_totp_step(&email, 11).await,
+            Err(UserStoreError::TotpCodeReused)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_recovery_codes_are_single_use() {
+        let mut store = get_store_with_test_user().await;
+        let email = get_test_email();
+
+        store
+            .set_recovery_codes(&email, vec!["first".to_string(), "second".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(store.count_recovery_codes(&email).await.unwrap(), 2);
+
+        assert!(store.use_recovery_code(&email, "first").await.is_ok());
+        assert!(matches!(
+            store.use_recovery_code(&email, "first").await,
+            Err(UserStoreError::RecoveryCodeNotFound)
+        ));
+        assert_eq!(store.count_recovery_codes(&email).await.unwrap(), 1);
+
+        store
+            .set_recovery_codes(&email, vec!["third".to_string()])
+            .await
+            .unwrap();
+        assert!(matches!(
+            store.use_recovery_code(&email, "second").await,
+            Err(UserStoreError::RecoveryCodeNotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_webauthn_credentials() {
+        let mut store = get_store_with_test_user().await;
+        let email = get_test_email();
+        let credential = WebAuthnCredential {
+            id: "credential-id".to_string(),
+            name: "Laptop".to_string(),
+            public_key: vec![1, 2, 3],
+            sign_count: 0,
+        };
+
+        store.add_webauthn_credential(&email, credential.clone()).await.unwrap();
+        assert!(matches!(
+            store.add_webauthn_credential(&email, credential.clone()).await,
+            Err(UserStoreError::WebAuthnCredentialAlreadyExists)
+        ));
+
+        store.update_webauthn_sign_count(&credential.id, 5).await.unwrap();
+        let credentials = store.get_webauthn_credentials(&email).await.unwrap();
+        assert_eq!(credentials.len(), 1);
+        assert_eq!(credentials[0].sign_count, 5);
+    }
+
+    #[tokio::test]
+    async fn test_confirm_phone_number_requires_pending_verification() {
+        let mut store = get_store_with_test_user().await;
+        let email = get_test_email();
+        let phone_number = PhoneNumber::parse(Secret::new("+14155552671".to_string())).unwrap();
+
+        let result = store.confirm_phone_number(&email, "first").await;
+        assert!(matches!(result, Err(UserStoreError::PhoneVerificationNotFound)));
+
+        store
+            .set_pending_phone_number(&email, phone_number.clone(), "first")
+            .await
+            .unwrap();
+        store
+            .set_pending_phone_number(&email, phone_number.clone(), "second")
+            .await
+            .unwrap();
+        let result = store.confirm_phone_number(&email, "first").await;
+        assert!(matches!(result, Err(UserStoreError::PhoneVerificationNotFound)));
+        assert!(store.get_phone_number(&email).await.unwrap().phone_number.is_none());
+
+        store.confirm_phone_number(&email, "second").await.unwrap();
+        let user_phone_number = store.get_phone_number(&email).await.unwrap();
+        assert_eq!(user_phone_number.phone_number, Some(phone_number));
+        assert!(user_phone_number.pending_phone_number.is_none());
+        assert!(user_phone_number.pending_verification_id.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_mark_email_verified() {
+        let mut store = get_store_with_test_user().await;
+        let email = get_test_email();
+        assert!(!store.get_user(&email).await.unwrap().email_verified);
+
+        store.mark_email_verified(&email).await.unwrap();
+        store.mark_email_verified(&email).await.unwrap();
+        assert!(store.get_user(&email).await.unwrap().email_verified);
+
+        let unknown_email = Email::parse(Secret::new("unknown@example.com".to_string())).unwrap();
+        let result = store.mark_email_verified(&unknown_email).await;
+        assert!(matches!(result, Err(UserStoreError::UserNotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_change_email_moves_user_data() {
+        let mut store = get_store_with_test_user().await;
+        let email = get_test_email();
+        let new_email = Email::parse(Secret::new("new@email.com".to_string())).unwrap();
+        store
+            .set_recovery_codes(&email, vec!["code_hash".to_string()])
+            .await
+            .unwrap();
+
+        store.change_email(&email, &new_email).await.unwrap();
+        assert!(matches!(
+            store.get_user(&email).await,
+            Err(UserStoreError::UserNotFound)
+        ));
+        let user = store.get_user(&new_email).await.unwrap();
+        assert_eq!(user.email, new_email);
+        assert_eq!(
+            store.get_access(&new_email).await.unwrap().roles,
+            vec![DEFAULT_ROLE.to_string()]
+        );
+        assert_eq!(store.count_recovery_codes(&new_email).await.unwrap(), 1);
+        assert!(store
+            .validate_user(&new_email, get_test_password().await.as_ref())
+            .await
+            .is_ok());
+
+        let result = store.change_email(&email, &new_email).await;
+        assert!(matches!(result, Err(UserStoreError::UserNotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_auth_events_keep_latest_entries() {
+        let mut store = get_store_with_test_user().await;
+        let email = get_test_email();
+
+        store
+            .add_auth_event(&email, AuthEvent::new(AuthEventKind::SignIn, None, None))
+            .await
+            .unwrap();
+        for _ in 0..MAX_AUTH_EVENTS {
+            store
+                .add_auth_event(&email, AuthEvent::new(AuthEventKind::SignInFailed, None, None))
+                .await
+                .unwrap();
+        }
+        store
+            .add_auth_event(&email, AuthEvent::new(AuthEventKind::PasswordChanged, None, None))
+            .await
+            .unwrap();
+
+        let events = store.get_auth_events(&email).await.unwrap();
+        assert_eq!(events.len(), MAX_AUTH_EVENTS);
+        assert_eq!(events[0].kind, AuthEventKind::PasswordChanged);
+        assert!(events.iter().all(|event| event.kind != AuthEventKind::SignIn));
+
+        let new_email = Email::parse(Secret::new("new@email.com".to_string())).unwrap();
+        store.change_email(&email, &new_email).await.unwrap();
+        assert_eq!(store.get_auth_events(&new_email).await.unwrap().len(), MAX_AUTH_EVENTS);
+
+        store.delete_user(&new_email).await.unwrap();
+        assert!(matches!(
+            store.get_auth_events(&new_email).await,
+            Err(UserStoreError::UserNotFound)
+        ));
+        let result = store
+            .add_auth_event(&new_email, AuthEvent::new(AuthEventKind::SignIn, None, None))
+            .await;
+        assert!(matches!(result, Err(UserStoreError::UserNotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_change_email_to_existing_user() {
+        let mut store = get_store_with_test_user().await;
+        let other_email = Email::parse(Secret::new("other@email.com".to_string())).unwrap();
+        store
+            .add_user(NewUser::new(
+                other_email.clone(),
+                get_test_password().await,
+                TwoFAMethod::None,
+            ))
+            .await
+            .unwrap();
+
+        let result = store.change_email(&get_test_email(), &other_email).await;
+        assert!(matches!(result, Err(UserStoreError::UserAlreadyExists)));
+        assert!(store.get_user(&get_test_email()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_delete_user() {
+        let mut store = get_store_with_test_user().await;
+        let email = get_test_email();
+
+        store.delete_user(&email).await.unwrap();
+        assert!(matches!(
+            store.get_user(&email).await,
+            Err(UserStoreError::UserNotFound)
+        ));
+        assert!(matches!(
+            store.get_access(&email).await,
+            Err(UserStoreError::UserNotFound)
+        ));
+        assert!(matches!(
+            store.delete_user(&email).await,
+            Err(UserStoreError::UserNotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_scheduled_deletion_hides_user_until_restored() {
+        let mut store = get_store_with_test_user().await;
+        let email = get_test_email();
+
+        store.schedule_deletion(&email).await.unwrap();
+        assert!(matches!(
+            store.get_user(&email).await,
+            Err(UserStoreError::UserNotFound)
+        ));
+        assert!(store
+            .validate_user(&email, get_test_password().await.as_ref())
+            .await
+            .is_err());
+        assert!(matches!(
+            store.schedule_deletion(&email).await,
+            Err(UserStoreError::UserNotFound)
+        ));
+        assert!(matches!(
+            store.get_access(&email).await,
+            Err(UserStoreError::UserNotFound)
+        ));
+        assert!(matches!(
+            store.get_webauthn_credentials(&email).await,
+            Err(UserStoreError::UserNotFound)
+        ));
+
+        store.restore_user(&email).await.unwrap();
+        assert!(store.get_user(&email).await.is_ok());
+        assert!(matches!(
+            store.restore_user(&email).await,
+            Err(UserStoreError::UserNotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_add_user_replaces_user_scheduled_for_deletion() {
+        let mut store = get_store_with_test_user().await;
+        let email = get_test_email();
+        store.update_2fa_settings(&email, TwoFAMethod::Email).await.unwrap();
+        store.schedule_deletion(&email).await.unwrap();
+
+        store.add_user(create_new_user().await).await.unwrap();
+
+        assert_eq!(store.get_user(&email).await.unwrap().two_fa_method, TwoFAMethod::None);
+        assert!(matches!(
+            store.restore_user(&email).await,
+            Err(UserStoreError::UserNotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_purge_deleted_users_after_grace_period() {
+        let mut store = get_store_with_test_user().await;
+        let email = get_test_email();
+        store.schedule_deletion(&email).await.unwrap();
+
+        assert_eq!(store.purge_deleted_users(3600).await.unwrap(), 0);
+        assert!(store.restore_user(&email).await.is_ok());
+
+        store.schedule_deletion(&email).await.unwrap();
+        assert_eq!(store.purge_deleted_users(0).await.unwrap(), 1);
+        assert!(matches!(
+            store.restore_user(&email).await,
+            Err(UserStoreError::UserNotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_upgrade_outdated_password_hash() {
+        let mut store = get_store_with_test_user().await;
+        let email = get_test_email();
+        let password = get_test_password().await;
+        assert_eq!(
+            store.count_password_hashes().await.unwrap(),
+            PasswordHashCounts { total: 1, outdated: 0 }
+        );
+        assert!(store.get_outdated_password_hash(&email).await.unwrap().is_none());
+
+        // Plant a hash made under different Argon2 settings
+        let salt = SaltString::generate(&mut rand::thread_rng());
+        let outdated_hash = Argon2::new(
+            Algorithm::Argon2id,
+            Version::V0x13,
+            Params::new(8192, 1, 1, None).unwrap(),
+        )
+        .hash_password(password.as_ref().expose_secret().as_bytes(), &salt)
+        .unwrap()
+        .to_string();
+        store.users.get_mut(&email).unwrap().password_hash = Secret::new(outdated_hash);
+        assert_eq!(
+            store.count_password_hashes().await.unwrap(),
+            PasswordHashCounts { total: 1, outdated: 1 }
+        );
+
+        let outdated_hash = store.get_outdated_password_hash(&email).await.unwrap().unwrap();
+        let password_hash = async_compute_password_hash(password.as_ref().clone()).await.unwrap();
+        assert!(store
+            .replace_password_hash(&email, &outdated_hash, password_hash.clone())
+            .await
+            .unwrap());
+        // A hash that is no longer the stored one is left alone
+        assert!(!store
+            .replace_password_hash(&email, &outdated_hash, password_hash)
+            .await
+            .unwrap());
+        assert!(store.get_outdated_password_hash(&email).await.unwrap().is_none());
+        assert_eq!(
+            store.count_password_hashes().await.unwrap(),
+            PasswordHashCounts { total: 1, outdated: 0 }
+        );
+        assert!(store.validate_user(&email, password.as_ref()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_import_user_keeps_legacy_hash_until_login() {
+        let mut store = HashmapUserStore::new();
+        let email = get_test_email();
+        let password = get_test_password().await;
+        let password_hash = bcrypt::hash(password.as_ref().expose_secret(), 4).unwrap();
+        let user = ImportedUser::parse(email.clone(), Secret::new(password_hash.clone()), true).unwrap();
+
+        store.import_user(user.clone()).await.unwrap();
+        assert_eq!(store.users[&email].password_hash.expose_secret(), &password_hash);
+        assert!(store.get_user(&email).await.unwrap().email_verified);
+        assert_eq!(store.get_access(&email).await.unwrap().roles, vec![DEFAULT_ROLE]);
+        assert!(matches!(
+            store.import_user(user).await,
+            Err(UserStoreError::UserAlreadyExists)
+        ));
+        assert_eq!(
+            store.count_password_hashes().await.unwrap(),
+            PasswordHashCounts { total: 1, outdated: 1 }
+        );
+
+        assert!(store.validate_user(&email, password.as_ref()).await.is_ok());
+        let outdated_hash = store.get_outdated_password_hash(&email).await.unwrap().unwrap();
+        let password_hash = async_compute_password_hash(password.as_ref().clone()).await.unwrap();
+        assert!(store
+            .replace_password_hash(&email, &outdated_hash, password_hash)
+            .await
+            .unwrap());
+        assert_eq!(
+            store.count_password_hashes().await.unwrap(),
+            PasswordHashCounts { total: 1, outdated: 0 }
+        );
+        assert!(store.validate_user(&email, password.as_ref()).await.is_ok());
+    }
 }
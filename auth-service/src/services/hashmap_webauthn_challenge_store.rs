@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+
+use crate::domain::data_stores::{WebAuthnChallenge, WebAuthnChallengeStore, WebAuthnChallengeStoreError};
+
+#[derive(Clone, Debug)]
+pub struct HashMapWebAuthnChallengeStore {
+    challenges: HashMap<String, WebAuthnChallenge>,
+}
+
+impl HashMapWebAuthnChallengeStore {
+    pub fn new() -> Self {
+        Self {
+            challenges: HashMap::new(),
+        }
+    }
+}
+
+impl Default for HashMapWebAuthnChallengeStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait::async_trait]
+impl WebAuthnChallengeStore for HashMapWebAuthnChallengeStore {
+    async fn add_challenge(&mut self, challenge: WebAuthnChallenge) -> Result<(), WebAuthnChallengeStoreError> {
+        self.challenges.insert(challenge.challenge.clone(), challenge);
+        Ok(())
+    }
+
+    async fn take_challenge(&mut self, challenge: &str) -> Result<WebAuthnChallenge, WebAuthnChallengeStoreError> {
+        self.challenges
+            .remove(challenge)
+            .ok_or(WebAuthnChallengeStoreError::ChallengeNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+    use crate::domain::{data_stores::WebAuthnCeremony, email::Email};
+
+    #[tokio::test]
+    async fn test_challenge_can_only_be_taken_once() {
+        let mut store = HashMapWebAuthnChallengeStore::new();
+        let email = Email::parse(Secret::new("test@example.com".to_string())).unwrap();
+        let challenge = WebAuthnChallenge::new(email, WebAuthnCeremony::Registration);
+        store.add_challenge(challenge.clone()).await.unwrap();
+
+        assert_eq!(store.take_challenge(&challenge.challenge).await.unwrap(), challenge);
+        assert!(matches!(
+            store.take_challenge(&challenge.challenge).await,
+            Err(WebAuthnChallengeStoreError::ChallengeNotFound)
+        ));
+    }
+}
@@ -0,0 +1,15 @@
+use color_eyre::eyre::Result;
+
+use crate::domain::{phone_number::PhoneNumber, sms_client::SmsClient};
+
+#[derive(Clone, Debug)]
+pub struct MockSmsClient;
+
+#[async_trait::async_trait]
+impl SmsClient for MockSmsClient {
+    async fn send_sms(&self, recipient: &PhoneNumber, body: &str) -> Result<()> {
+        println!("Sending SMS to {} with body {body}", recipient.expose_secret_string());
+
+        Ok(())
+    }
+}
@@ -2,8 +2,15 @@ pub mod app_state;
 pub mod concrete_app_services;
 pub mod data_stores;
 pub mod hashmap_banned_token_store;
+pub mod hashmap_login_failure_store;
 pub mod hashmap_password_reset_token_store;
+pub mod hashmap_refresh_token_store;
+pub mod hashmap_session_store;
+pub mod hashmap_trusted_device_store;
 pub mod hashmap_two_fa_code_store;
 pub mod hashmap_user_store;
+pub mod hashmap_webauthn_challenge_store;
 pub mod mock_email_client;
+pub mod mock_sms_client;
 pub mod postmark_email_client;
+pub mod twilio_sms_client;
@@ -8,9 +8,13 @@ use crate::{
         data_stores::TwoFACode,
         email::Email,
         email_client::{EmailClient, TemplateModel},
+        user::TwoFAMethod,
     },
     utils::{
-        auth::PasswordResetToken,
+        auth::{
+            AccountRestoreToken, AccountUnlockToken, EmailChangeRevertToken, EmailChangeToken, EmailVerificationToken,
+            PasswordResetToken,
+        },
         constants::{Time, REST_AUTH_SERVICE_URL},
     },
 };
@@ -39,29 +43,102 @@ impl PostmarkEmailClient {
 
 #[derive(Clone, Debug, Serialize)]
 pub enum PostmarkTemplate {
+    /// Confirms the account and its data are gone.
+    AccountDeleted,
+    /// Tells the user their account will be deleted after the given number of days unless they restore it.
+    AccountDeletionScheduled(u32, AccountRestoreToken),
+    /// Tells the user their account is locked for the given number of seconds, with a link to unlock it early.
+    AccountLocked(u64, AccountUnlockToken),
+    /// Sent to the new address to confirm an email change.
+    EmailChange(Time, EmailChangeToken),
+    /// Sent to the previous address once an email change went through.
+    EmailChangeRevert(Time, EmailChangeRevertToken),
+    EmailVerification(Time, EmailVerificationToken),
+    /// Tells the user their password was changed from a signed-in session.
+    PasswordChanged,
     PasswordReset(Time, PasswordResetToken),
+    /// Tells the user a new phone number was verified for their account.
+    PhoneNumberChanged,
+    /// Tells the user a new set of recovery codes was generated and the old ones stopped working.
+    RecoveryCodesRegenerated,
     TwoFACode(Time, TwoFACode),
+    /// Tells the user their second factor was switched to the given method.
+    TwoFASettingsChanged(TwoFAMethod),
 }
 
 impl PostmarkTemplate {
     fn to_model(&self) -> TemplateModel {
         match self {
+            Self::AccountDeleted => TemplateModel::new(String::new(), String::new()),
+            Self::AccountDeletionScheduled(days, token) => {
+                let auth_base_url = REST_AUTH_SERVICE_URL.to_string();
+                let url = format!("{auth_base_url}/account/restore?token={}", token.expose_secret_string());
+                let expiration_time = match days {
+                    1 => "1 Day".to_string(),
+                    _ => format!("{days} Days"),
+                };
+                TemplateModel::new(expiration_time, url)
+            }
+            Self::AccountLocked(seconds, token) => {
+                let auth_base_url = REST_AUTH_SERVICE_URL.to_string();
+                let url = format!("{auth_base_url}/account/unlock?token={}", token.expose_secret_string());
+                let expiration_time = match seconds.div_ceil(60) {
+                    1 => "1 Minute".to_string(),
+                    minutes => format!("{minutes} Minutes"),
+                };
+                TemplateModel::new(expiration_time, url)
+            }
+            Self::EmailChange(time, token) => {
+                let auth_base_url = REST_AUTH_SERVICE_URL.to_string();
+                let url = format!(
+                    "{auth_base_url}/change-email/confirm?token={}",
+                    token.expose_secret_string()
+                );
+                TemplateModel::new(time.to_string(), url)
+            }
+            Self::EmailChangeRevert(time, token) => {
+                let auth_base_url = REST_AUTH_SERVICE_URL.to_string();
+                let url = format!(
+                    "{auth_base_url}/change-email/revert?token={}",
+                    token.expose_secret_string()
+                );
+                TemplateModel::new(time.to_string(), url)
+            }
+            Self::EmailVerification(time, token) => {
+                let auth_base_url = REST_AUTH_SERVICE_URL.to_string();
+                let url = format!("{auth_base_url}/verify-email?token={}", token.expose_secret_string());
+                TemplateModel::new(time.to_string(), url)
+            }
+            Self::PasswordChanged => TemplateModel::new(String::new(), String::new()),
             Self::PasswordReset(time, token) => {
                 let auth_base_url = REST_AUTH_SERVICE_URL.to_string();
                 let url = format!("{auth_base_url}/reset-password?token={}", token.expose_secret_string());
                 TemplateModel::new(time.to_string(), url)
             }
+            Self::PhoneNumberChanged => TemplateModel::new(String::new(), String::new()),
+            Self::RecoveryCodesRegenerated => TemplateModel::new(String::new(), String::new()),
             Self::TwoFACode(time, two_fa_code) => {
                 let model_content = two_fa_code.expose_secret_string();
                 TemplateModel::new(time.to_string(), model_content)
             }
+            Self::TwoFASettingsChanged(two_fa_method) => TemplateModel::new(String::new(), two_fa_method.to_string()),
         }
     }
 
     fn alias(&self) -> &str {
         match self {
+            Self::AccountDeleted => "account-deleted",
+            Self::AccountDeletionScheduled(_, _) => "account-deletion-scheduled",
+            Self::AccountLocked(_, _) => "account-locked",
+            Self::EmailChange(_, _) => "email-change",
+            Self::EmailChangeRevert(_, _) => "email-change-revert",
+            Self::EmailVerification(_, _) => "email-verification",
+            Self::PasswordChanged => "password-changed",
             Self::PasswordReset(_, _) => "password-reset",
+            Self::PhoneNumberChanged => "phone-number-changed",
+            Self::RecoveryCodesRegenerated => "recovery-codes-regenerated",
             Self::TwoFACode(_, _) => "two-fa-code",
+            Self::TwoFASettingsChanged(_) => "two-fa-settings-changed",
         }
     }
 }
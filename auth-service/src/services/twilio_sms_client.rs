@@ -0,0 +1,149 @@
+use color_eyre::eyre::Result;
+use reqwest::{Client, Url};
+use secrecy::{ExposeSecret, Secret};
+use serde::Serialize;
+
+use crate::domain::{phone_number::PhoneNumber, sms_client::SmsClient};
+
+#[derive(Debug, Clone)]
+pub struct TwilioSmsClient {
+    http_client: Client,
+    base_url: String,
+    sender: PhoneNumber,
+    account_sid: String,
+    auth_token: Secret<String>,
+}
+
+impl TwilioSmsClient {
+    pub fn new(
+        base_url: String,
+        sender: PhoneNumber,
+        account_sid: String,
+        auth_token: Secret<String>,
+        http_client: Client,
+    ) -> Self {
+        Self {
+            base_url,
+            sender,
+            account_sid,
+            auth_token,
+            http_client,
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct SendSmsRequest<'a> {
+    from: &'a str,
+    to: &'a str,
+    body: &'a str,
+}
+
+#[async_trait::async_trait]
+impl SmsClient for TwilioSmsClient {
+    #[tracing::instrument(name = "Sending SMS", skip_all)]
+    async fn send_sms(&self, recipient: &PhoneNumber, body: &str) -> Result<()> {
+        let base = Url::parse(&self.base_url)?;
+        let url = base.join(&format!("/2010-04-01/Accounts/{}/Messages.json", self.account_sid))?;
+
+        let request_body = SendSmsRequest {
+            from: self.sender.as_ref().expose_secret(),
+            to: recipient.as_ref().expose_secret(),
+            body,
+        };
+
+        let request = self
+            .http_client
+            .post(url)
+            .basic_auth(&self.account_sid, Some(self.auth_token.expose_secret()))
+            .form(&request_body);
+
+        request.send().await?.error_for_status()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::constants::test;
+
+    use super::*;
+    use fake::{Fake, Faker};
+    use wiremock::matchers::{any, body_string_contains, header, header_exists, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::TwilioSmsClient;
+
+    fn phone_number() -> PhoneNumber {
+        PhoneNumber::parse(Secret::new("+14155552671".to_string())).unwrap()
+    }
+
+    fn sms_client(base_url: String) -> TwilioSmsClient {
+        let http_client = Client::builder().timeout(test::sms_client::TIMEOUT).build().unwrap();
+        let sender = PhoneNumber::parse(Secret::new(test::sms_client::SENDER.to_string())).unwrap();
+        TwilioSmsClient::new(
+            base_url,
+            sender,
+            test::sms_client::ACCOUNT_SID.to_string(),
+            Secret::new(Faker.fake()),
+            http_client,
+        )
+    }
+
+    #[tokio::test]
+    async fn send_sms_sends_the_expected_request() {
+        let mock_server = MockServer::start().await;
+        let sms_client = sms_client(mock_server.uri());
+
+        let messages_path = format!("/2010-04-01/Accounts/{}/Messages.json", test::sms_client::ACCOUNT_SID);
+        Mock::given(header_exists("Authorization"))
+            .and(header("Content-Type", "application/x-www-form-urlencoded"))
+            .and(path(messages_path))
+            .and(method("POST"))
+            .and(body_string_contains("From=%2B15005550006"))
+            .and(body_string_contains("To=%2B14155552671"))
+            .and(body_string_contains("Body=Hello"))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = sms_client.send_sms(&phone_number(), "Hello").await;
+        assert!(outcome.is_ok());
+    }
+
+    #[tokio::test]
+    async fn send_sms_fails_if_the_server_returns_500() {
+        let mock_server = MockServer::start().await;
+        let sms_client = sms_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = sms_client.send_sms(&phone_number(), "Hello").await;
+
+        assert!(outcome.is_err());
+    }
+
+    #[tokio::test]
+    async fn send_sms_times_out_if_the_server_takes_too_long() {
+        let mock_server = MockServer::start().await;
+        let sms_client = sms_client(mock_server.uri());
+
+        let response = ResponseTemplate::new(201).set_delay(std::time::Duration::from_secs(180));
+        Mock::given(any())
+            .respond_with(response)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = sms_client.send_sms(&phone_number(), "Hello").await;
+
+        assert!(outcome.is_err());
+    }
+}
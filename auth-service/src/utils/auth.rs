@@ -1,20 +1,186 @@
 use core::fmt;
-use std::sync::Arc;
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, PoisonError},
+};
 
-use argon2::{password_hash::SaltString, Algorithm, Argon2, Params, PasswordHasher, Version};
+use argon2::{password_hash::SaltString, PasswordHasher};
 use axum_extra::extract::cookie::{Cookie, SameSite};
+use base64::{engine::general_purpose::STANDARD, Engine};
 use chrono::Utc;
 use color_eyre::eyre::{eyre, Report, Result};
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Validation};
+use jsonwebtoken::{decode_header, jwk::JwkSet};
+use lazy_static::lazy_static;
 use macros::SecretString;
+use ring::digest::{digest, SHA256};
 use secrecy::{ExposeSecret, Secret};
-use serde::{ser::SerializeStruct, Deserialize, Serialize};
+use serde::{de::DeserializeOwned, ser::SerializeStruct, Deserialize, Serialize};
 use tokio::sync::RwLock;
-use tracing::error;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::domain::{
+    data_stores::{
+        BannedTokenStore, RefreshToken, RefreshTokenRecord, RefreshTokenStore, RefreshTokenStoreError, Session,
+        SessionStore, SessionStoreError, TrustedDevice, TrustedDeviceStore, TrustedDeviceStoreError,
+    },
+    email::Email,
+    error::AuthAPIError,
+    user::UserAccess,
+};
+
+use super::{
+    constants::{
+        Epoch, Time, INTROSPECTION_CLIENT_ID, INTROSPECTION_CLIENT_SECRET, JWT_COOKIE_NAME, JWT_KEY_RING_PATH,
+        REFRESH_TOKEN_COOKIE_NAME, REFRESH_TOKEN_TTL_SECONDS, TOKEN_TTL_SECONDS, TRUSTED_DEVICE_COOKIE_NAME,
+    },
+    jwt_keys::{KeyRingConfig, SigningKey},
+    password_hash::PasswordHashPolicy,
+};
+
+lazy_static! {
+    static ref KEY_RING: std::sync::RwLock<KeyRing> =
+        std::sync::RwLock::new(KeyRing::from_env().expect("Failed to load JWT key ring"));
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum KeyRingError {
+    #[error("Signing key already exists")]
+    KeyAlreadyExists,
+    #[error("Signing key not found")]
+    KeyNotFound,
+    #[error("The active signing key cannot be retired")]
+    ActiveKeyRetired,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+/// One key signs new tokens; every other key in the ring still verifies the tokens
+/// it signed until it is retired.
+#[derive(Debug, Clone)]
+pub struct KeyRing {
+    active_kid: String,
+    keys: BTreeMap<String, SigningKey>,
+}
+
+impl KeyRing {
+    pub fn new(active_key: SigningKey) -> Self {
+        let active_kid = active_key.kid().to_string();
+        Self {
+            keys: BTreeMap::from([(active_kid.clone(), active_key)]),
+            active_kid,
+        }
+    }
+
+    pub fn from_config(config: &KeyRingConfig) -> Result<Self, KeyRingError> {
+        let mut keys = BTreeMap::new();
+        for key_config in &config.keys {
+            let key = key_config.load().map_err(KeyRingError::UnexpectedError)?;
+            if keys.insert(key.kid().to_string(), key).is_some() {
+                return Err(KeyRingError::KeyAlreadyExists);
+            }
+        }
+        if !keys.contains_key(&config.active_kid) {
+            return Err(KeyRingError::KeyNotFound);
+        }
+        Ok(Self {
+            active_kid: config.active_kid.clone(),
+            keys,
+        })
+    }
+
+    /// Loads the ring from `JWT_KEY_RING_PATH`, or wraps the single key configured
+    /// through `JWT_ALGORITHM` when no key ring file is set.
+    pub fn from_env() -> Result<Self, KeyRingError> {
+        match JWT_KEY_RING_PATH.as_deref() {
+            Some(path) => {
+                let config = KeyRingConfig::from_file(path).map_err(KeyRingError::UnexpectedError)?;
+                Self::from_config(&config)
+            }
+            None => Ok(Self::new(
+                SigningKey::from_env().map_err(KeyRingError::UnexpectedError)?,
+            )),
+        }
+    }
+
+    pub fn active_key(&self) -> &SigningKey {
+        &self.keys[&self.active_kid]
+    }
+
+    pub fn kids(&self) -> Vec<&str> {
+        self.keys.keys().map(String::as_str).collect()
+    }
+
+    /// Adds a verification key. It only signs tokens once promoted.
+    pub fn add_key(&mut self, key: SigningKey) -> Result<(), KeyRingError> {
+        if self.keys.contains_key(key.kid()) {
+            return Err(KeyRingError::KeyAlreadyExists);
+        }
+        self.keys.insert(key.kid().to_string(), key);
+        Ok(())
+    }
+
+    pub fn promote_key(&mut self, kid: &str) -> Result<(), KeyRingError> {
+        if !self.keys.contains_key(kid) {
+            return Err(KeyRingError::KeyNotFound);
+        }
+        self.active_kid = kid.to_string();
+        Ok(())
+    }
+
+    /// Removes a key, after which tokens it signed no longer verify.
+    pub fn retire_key(&mut self, kid: &str) -> Result<(), KeyRingError> {
+        if kid == self.active_kid {
+            return Err(KeyRingError::ActiveKeyRetired);
+        }
+        self.keys.remove(kid).map(|_| ()).ok_or(KeyRingError::KeyNotFound)
+    }
+
+    pub fn sign<T: Serialize>(&self, claims: &T) -> Result<String, jsonwebtoken::errors::Error> {
+        self.active_key().sign(claims)
+    }
+
+    pub fn verify<T: DeserializeOwned>(&self, token: &str) -> Result<T> {
+        let kid = decode_header(token)?.kid.ok_or(eyre!("Token has no kid header"))?;
+        let key = self.keys.get(&kid).ok_or(eyre!("Unknown signing key id: {kid}"))?;
+        key.verify(token)
+    }
 
-use crate::domain::{data_stores::BannedTokenStore, email::Email};
+    pub fn jwks(&self) -> JwkSet {
+        JwkSet {
+            keys: self.keys.values().filter_map(SigningKey::jwk).cloned().collect(),
+        }
+    }
+}
 
-use super::constants::{Epoch, Time, JWT_COOKIE_NAME, JWT_SECRET, TOKEN_TTL_SECONDS};
+/// Applies a change to the key ring shared by every request, e.g. to add, promote
+/// or retire a key at runtime.
+pub fn update_key_ring<R>(update: impl FnOnce(&mut KeyRing) -> R) -> R {
+    let mut key_ring = KEY_RING.write().unwrap_or_else(PoisonError::into_inner);
+    update(&mut key_ring)
+}
+
+/// Re-reads `JWT_KEY_RING_PATH` and swaps in the new ring. The current ring is kept
+/// if the file is missing or invalid.
+#[tracing::instrument(name = "Reload Key Ring", skip_all)]
+pub fn reload_key_ring() -> Result<(), KeyRingError> {
+    let path = JWT_KEY_RING_PATH
+        .as_deref()
+        .ok_or(KeyRingError::UnexpectedError(eyre!("JWT_KEY_RING_PATH is not set")))?;
+    let config = KeyRingConfig::from_file(path).map_err(KeyRingError::UnexpectedError)?;
+    let new_ring = KeyRing::from_config(&config)?;
+    info!(
+        "Reloaded key ring with keys {:?}, signing with {}",
+        new_ring.kids(),
+        new_ring.active_key().kid()
+    );
+    update_key_ring(|key_ring| *key_ring = new_ring);
+    Ok(())
+}
+
+fn read_key_ring() -> std::sync::RwLockReadGuard<'static, KeyRing> {
+    KEY_RING.read().unwrap_or_else(PoisonError::into_inner)
+}
 
 #[derive(Debug, thiserror::Error)]
 pub enum GenerateTokenError {
@@ -24,6 +190,8 @@ pub enum GenerateTokenError {
     BannedToken,
     #[error("Invalid token purpose")]
     InvalidTokenPurpose,
+    #[error("Reused refresh token")]
+    ReusedToken,
     #[error("Unexpected error")]
     UnexpectedError(#[source] Report),
 }
@@ -32,6 +200,12 @@ pub enum GenerateTokenError {
 pub enum TokenPurpose {
     Auth,
     PasswordReset,
+    TrustedDevice,
+    EmailVerification,
+    EmailChange,
+    EmailChangeRevert,
+    AccountRestore,
+    AccountUnlock,
 }
 
 impl fmt::Display for TokenPurpose {
@@ -39,15 +213,47 @@ impl fmt::Display for TokenPurpose {
         match self {
             TokenPurpose::Auth => write!(f, "auth"),
             TokenPurpose::PasswordReset => write!(f, "password reset"),
+            TokenPurpose::TrustedDevice => write!(f, "trusted device"),
+            TokenPurpose::EmailVerification => write!(f, "email verification"),
+            TokenPurpose::EmailChange => write!(f, "email change"),
+            TokenPurpose::EmailChangeRevert => write!(f, "email change revert"),
+            TokenPurpose::AccountRestore => write!(f, "account restore"),
+            TokenPurpose::AccountUnlock => write!(f, "account unlock"),
         }
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 pub struct Claims {
     pub sub: Secret<String>,
     pub exp: Epoch,
+    pub iat: Epoch,
+    pub jti: String,
     pub purpose: TokenPurpose,
+    /// The session an auth token belongs to. Password reset and email verification tokens have none.
+    pub sid: Option<String>,
+    #[serde(default)]
+    pub roles: Vec<String>,
+    /// Serialized as the space-delimited `scope` claim of RFC 9068.
+    #[serde(default, rename = "scope", deserialize_with = "deserialize_scope")]
+    pub scopes: Vec<String>,
+    /// The address an email change token moves the account to.
+    #[serde(default)]
+    pub new_email: Option<Secret<String>>,
+}
+
+impl Claims {
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|granted| granted == scope)
+    }
+}
+
+fn deserialize_scope<'de, D>(deserializer: D) -> std::result::Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let scope = String::deserialize(deserializer)?;
+    Ok(scope.split_whitespace().map(str::to_string).collect())
 }
 
 impl Serialize for Claims {
@@ -55,10 +261,24 @@ impl Serialize for Claims {
     where
         S: serde::Serializer,
     {
-        let mut state = serializer.serialize_struct("Claims", 3)?;
+        let mut state = serializer.serialize_struct("Claims", 9)?;
         state.serialize_field("sub", self.sub.expose_secret())?;
         state.serialize_field("exp", &self.exp)?;
+        state.serialize_field("iat", &self.iat)?;
+        state.serialize_field("jti", &self.jti)?;
         state.serialize_field("purpose", &self.purpose)?;
+        if let Some(sid) = &self.sid {
+            state.serialize_field("sid", sid)?;
+        }
+        if !self.roles.is_empty() {
+            state.serialize_field("roles", &self.roles)?;
+        }
+        if !self.scopes.is_empty() {
+            state.serialize_field("scope", &self.scopes.join(" "))?;
+        }
+        if let Some(new_email) = &self.new_email {
+            state.serialize_field("new_email", new_email.expose_secret())?;
+        }
         state.end()
     }
 }
@@ -67,8 +287,8 @@ impl Serialize for Claims {
 pub struct AuthToken(Secret<String>);
 
 impl AuthToken {
-    pub fn new(email: &Email) -> Result<Self, GenerateTokenError> {
-        let auth_token = generate_auth_token(email)?;
+    pub fn new(email: &Email, session_id: &Uuid, access: &UserAccess) -> Result<Self, GenerateTokenError> {
+        let auth_token = generate_auth_token(email, session_id, access)?;
         Ok(Self(auth_token))
     }
 
@@ -99,9 +319,72 @@ impl PasswordResetToken {
     }
 }
 
+#[derive(Clone, Debug, Deserialize, SecretString)]
+pub struct EmailVerificationToken(Secret<String>);
+
+impl EmailVerificationToken {
+    pub fn new(email: &Email) -> Result<Self, GenerateTokenError> {
+        let token = generate_email_token(email, TokenPurpose::EmailVerification, Time::Hours24 as u64, None)?;
+        Ok(Self(token))
+    }
+}
+
+/// Sent when an account is scheduled for deletion, valid for the grace period.
+#[derive(Clone, Debug, Deserialize, SecretString)]
+pub struct AccountRestoreToken(Secret<String>);
+
+impl AccountRestoreToken {
+    pub fn new(email: &Email, ttl_seconds: u64) -> Result<Self, GenerateTokenError> {
+        let token = generate_email_token(email, TokenPurpose::AccountRestore, ttl_seconds, None)?;
+        Ok(Self(token))
+    }
+}
+
+/// Sent when an account is locked after repeated failed logins, valid for as long as the lockout.
+#[derive(Clone, Debug, Deserialize, SecretString)]
+pub struct AccountUnlockToken(Secret<String>);
+
+impl AccountUnlockToken {
+    pub fn new(email: &Email, ttl_seconds: u64) -> Result<Self, GenerateTokenError> {
+        let token = generate_email_token(email, TokenPurpose::AccountUnlock, ttl_seconds, None)?;
+        Ok(Self(token))
+    }
+}
+
+/// Sent to the new address to confirm a change from `email` to `new_email`.
+#[derive(Clone, Debug, Deserialize, SecretString)]
+pub struct EmailChangeToken(Secret<String>);
+
+impl EmailChangeToken {
+    pub fn new(email: &Email, new_email: &Email) -> Result<Self, GenerateTokenError> {
+        let token = generate_email_token(email, TokenPurpose::EmailChange, Time::Hours24 as u64, Some(new_email))?;
+        Ok(Self(token))
+    }
+}
+
+/// Sent to the previous address after a change, so its owner can move the account at `email` back.
+#[derive(Clone, Debug, Deserialize, SecretString)]
+pub struct EmailChangeRevertToken(Secret<String>);
+
+impl EmailChangeRevertToken {
+    pub fn new(email: &Email, previous_email: &Email) -> Result<Self, GenerateTokenError> {
+        let token = generate_email_token(
+            email,
+            TokenPurpose::EmailChangeRevert,
+            Time::Days7 as u64,
+            Some(previous_email),
+        )?;
+        Ok(Self(token))
+    }
+}
+
 #[tracing::instrument(name = "Generate Auth Cookie", skip_all)]
-pub fn generate_auth_cookie(email: &Email) -> Result<Cookie<'static>, GenerateTokenError> {
-    let token = generate_auth_token(email)?;
+pub fn generate_auth_cookie(
+    email: &Email,
+    session_id: &Uuid,
+    access: &UserAccess,
+) -> Result<Cookie<'static>, GenerateTokenError> {
+    let token = generate_auth_token(email, session_id, access)?;
     let cookie = Cookie::build((JWT_COOKIE_NAME, token.expose_secret().clone()))
         .path("/")
         .http_only(true)
@@ -111,11 +394,16 @@ pub fn generate_auth_cookie(email: &Email) -> Result<Cookie<'static>, GenerateTo
 }
 
 #[tracing::instrument(name = "Generate Auth Token", skip_all)]
-pub fn generate_auth_token(email: &Email) -> Result<Secret<String>, GenerateTokenError> {
+pub fn generate_auth_token(
+    email: &Email,
+    session_id: &Uuid,
+    access: &UserAccess,
+) -> Result<Secret<String>, GenerateTokenError> {
     let delta = chrono::Duration::try_seconds(TOKEN_TTL_SECONDS).ok_or(GenerateTokenError::UnexpectedError(eyre!(
         "Failed to obtain chrono duration"
     )))?;
-    let exp: Epoch = Utc::now()
+    let now = Utc::now();
+    let exp: Epoch = now
         .checked_add_signed(delta)
         .ok_or(GenerateTokenError::UnexpectedError(eyre!(
             "Failed to generate expiration timestamp"
@@ -123,11 +411,21 @@ pub fn generate_auth_token(email: &Email) -> Result<Secret<String>, GenerateToke
         .timestamp()
         .try_into()
         .map_err(|_| GenerateTokenError::UnexpectedError(eyre!("Failed to convert to Epoch")))?;
+    let iat: Epoch = now
+        .timestamp()
+        .try_into()
+        .map_err(|_| GenerateTokenError::UnexpectedError(eyre!("Failed to convert to Epoch")))?;
     let sub = email.as_ref().to_owned();
     let claims = Claims {
         sub,
         exp,
+        iat,
+        jti: Uuid::new_v4().to_string(),
         purpose: TokenPurpose::Auth,
+        sid: Some(session_id.to_string()),
+        roles: access.roles.clone(),
+        scopes: access.scopes.clone(),
+        new_email: None,
     };
     let token = create_token(&claims).map_err(|e| GenerateTokenError::TokenError(e.into()))?;
 
@@ -136,19 +434,13 @@ pub fn generate_auth_token(email: &Email) -> Result<Secret<String>, GenerateToke
 
 #[tracing::instrument(name = "Validate Token", skip_all)]
 pub async fn validate_token_structure(token: &str) -> Result<Claims, GenerateTokenError> {
-    let data = match decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
-        &Validation::default(),
-    ) {
-        Ok(data) => data,
+    match read_key_ring().verify::<Claims>(token) {
+        Ok(claims) => Ok(claims),
         Err(error) => {
             error!("{:?}", error);
-            return Err(GenerateTokenError::TokenError(error.into()));
+            Err(GenerateTokenError::TokenError(error))
         }
-    };
-    let claims = data.claims;
-    Ok(claims)
+    }
 }
 
 #[tracing::instrument(name = "Validate Token and Check if Banned", skip_all)]
@@ -162,26 +454,324 @@ pub async fn validate_token<T: BannedTokenStore>(
         .await
         .map_err(|_| GenerateTokenError::BannedToken)?;
     let claims = validate_token_structure(token.expose_secret()).await?;
+    if let Some(session_id) = claims.sid.as_deref().and_then(|sid| Uuid::parse_str(sid).ok()) {
+        banned_token_store
+            .check_session(&session_id)
+            .await
+            .map_err(|_| GenerateTokenError::BannedToken)?;
+    }
+    if let Ok(email) = Email::parse(claims.sub.clone()) {
+        banned_token_store
+            .check_issued_at(&email, claims.iat)
+            .await
+            .map_err(|_| GenerateTokenError::BannedToken)?;
+    }
+    Ok(claims)
+}
+
+/// Validates a token presented as proof of a signed-in session. Tokens issued for anything else, such as
+/// trusted-device cookies or emailed links, are rejected.
+#[tracing::instrument(name = "Validate Auth Token", skip_all)]
+pub async fn validate_auth_token<T: BannedTokenStore>(
+    banned_token_store: Arc<RwLock<T>>,
+    token: Secret<String>,
+) -> Result<Claims, GenerateTokenError> {
+    let claims = validate_token(banned_token_store, token).await?;
+    if claims.purpose != TokenPurpose::Auth {
+        return Err(GenerateTokenError::InvalidTokenPurpose);
+    }
     Ok(claims)
 }
 
+/// Records a new session and issues its auth and refresh cookies.
+#[tracing::instrument(name = "Start Session", skip_all)]
+pub async fn start_session<T: SessionStore, R: RefreshTokenStore>(
+    session_store: Arc<RwLock<T>>,
+    refresh_token_store: Arc<RwLock<R>>,
+    session: Session,
+    access: &UserAccess,
+) -> Result<(Cookie<'static>, Cookie<'static>), GenerateTokenError> {
+    let auth_cookie = generate_auth_cookie(&session.email, &session.id, access)?;
+    let refresh_token = issue_refresh_token(refresh_token_store, RefreshTokenRecord::for_session(&session)).await?;
+
+    session_store
+        .write()
+        .await
+        .add_session(session)
+        .await
+        .map_err(|e| GenerateTokenError::UnexpectedError(e.into()))?;
+
+    Ok((auth_cookie, generate_refresh_cookie(&refresh_token)))
+}
+
+#[tracing::instrument(name = "Touch Session", skip_all)]
+pub async fn touch_session<T: SessionStore>(
+    session_store: Arc<RwLock<T>>,
+    session_id: &Uuid,
+) -> Result<(), GenerateTokenError> {
+    session_store
+        .write()
+        .await
+        .touch_session(session_id, Utc::now())
+        .await
+        .map_err(|e| match e {
+            SessionStoreError::SessionNotFound => GenerateTokenError::TokenError(e.into()),
+            _ => GenerateTokenError::UnexpectedError(e.into()),
+        })
+}
+
+/// Ends a session: its record and refresh tokens are removed and its auth tokens
+/// are rejected from then on.
+#[tracing::instrument(name = "Revoke Session", skip_all)]
+pub async fn revoke_session<B: BannedTokenStore, T: SessionStore, R: RefreshTokenStore>(
+    banned_token_store: Arc<RwLock<B>>,
+    session_store: Arc<RwLock<T>>,
+    refresh_token_store: Arc<RwLock<R>>,
+    session_id: &Uuid,
+) -> Result<(), GenerateTokenError> {
+    match session_store.write().await.remove_session(session_id).await {
+        Ok(()) | Err(SessionStoreError::SessionNotFound) => {}
+        Err(e) => return Err(GenerateTokenError::UnexpectedError(e.into())),
+    }
+    refresh_token_store
+        .write()
+        .await
+        .revoke_family(session_id)
+        .await
+        .map_err(|e| GenerateTokenError::UnexpectedError(e.into()))?;
+    banned_token_store
+        .write()
+        .await
+        .ban_session(session_id)
+        .await
+        .map_err(|e| GenerateTokenError::UnexpectedError(e.into()))
+}
+
+/// Ends every session of the user and rejects any auth token issued to them so far.
+#[tracing::instrument(name = "Revoke All Sessions", skip_all)]
+pub async fn revoke_all_sessions<B: BannedTokenStore, T: SessionStore, R: RefreshTokenStore>(
+    banned_token_store: Arc<RwLock<B>>,
+    session_store: Arc<RwLock<T>>,
+    refresh_token_store: Arc<RwLock<R>>,
+    email: &Email,
+) -> Result<(), GenerateTokenError> {
+    let not_before: Epoch = Utc::now()
+        .timestamp()
+        .try_into()
+        .map_err(|_| GenerateTokenError::UnexpectedError(eyre!("Failed to convert to Epoch")))?;
+    banned_token_store
+        .write()
+        .await
+        .ban_tokens_before(email, not_before)
+        .await
+        .map_err(|e| GenerateTokenError::UnexpectedError(e.into()))?;
+
+    let sessions = session_store
+        .read()
+        .await
+        .get_sessions(email)
+        .await
+        .map_err(|e| GenerateTokenError::UnexpectedError(e.into()))?;
+    for session in sessions {
+        revoke_session(
+            banned_token_store.clone(),
+            session_store.clone(),
+            refresh_token_store.clone(),
+            &session.id,
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Ends every session of the user except the current one, so the caller stays signed in.
+#[tracing::instrument(name = "Revoke Other Sessions", skip_all)]
+pub async fn revoke_other_sessions<B: BannedTokenStore, T: SessionStore, R: RefreshTokenStore>(
+    banned_token_store: Arc<RwLock<B>>,
+    session_store: Arc<RwLock<T>>,
+    refresh_token_store: Arc<RwLock<R>>,
+    email: &Email,
+    current_session_id: Option<&Uuid>,
+) -> Result<(), GenerateTokenError> {
+    let sessions = session_store
+        .read()
+        .await
+        .get_sessions(email)
+        .await
+        .map_err(|e| GenerateTokenError::UnexpectedError(e.into()))?;
+    for session in sessions
+        .iter()
+        .filter(|session| Some(&session.id) != current_session_id)
+    {
+        revoke_session(
+            banned_token_store.clone(),
+            session_store.clone(),
+            refresh_token_store.clone(),
+            &session.id,
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Records a trusted device and issues the cookie that identifies it on later logins.
+#[tracing::instrument(name = "Trust Device", skip_all)]
+pub async fn trust_device<T: TrustedDeviceStore>(
+    trusted_device_store: Arc<RwLock<T>>,
+    device: TrustedDevice,
+) -> Result<Cookie<'static>, GenerateTokenError> {
+    let iat: Epoch = device
+        .created_at
+        .timestamp()
+        .try_into()
+        .map_err(|_| GenerateTokenError::UnexpectedError(eyre!("Failed to convert to Epoch")))?;
+    let exp: Epoch = device
+        .expires_at
+        .timestamp()
+        .try_into()
+        .map_err(|_| GenerateTokenError::UnexpectedError(eyre!("Failed to convert to Epoch")))?;
+    let claims = Claims {
+        sub: device.email.as_ref().to_owned(),
+        exp,
+        iat,
+        jti: device.id.to_string(),
+        purpose: TokenPurpose::TrustedDevice,
+        sid: None,
+        roles: vec![],
+        scopes: vec![],
+        new_email: None,
+    };
+    let token = create_token(&claims).map_err(|e| GenerateTokenError::TokenError(e.into()))?;
+    let max_age = (device.expires_at - device.created_at).num_seconds();
+
+    trusted_device_store
+        .write()
+        .await
+        .add_device(device)
+        .await
+        .map_err(|e| GenerateTokenError::UnexpectedError(e.into()))?;
+
+    Ok(
+        Cookie::build((TRUSTED_DEVICE_COOKIE_NAME, token.expose_secret().clone()))
+            .path("/")
+            .http_only(true)
+            .same_site(SameSite::Lax)
+            .max_age(time::Duration::seconds(max_age))
+            .build(),
+    )
+}
+
+/// Reads the device id out of a trusted-device token. Only the signature, expiry and purpose are checked here.
+pub async fn parse_trusted_device_token(token: &str) -> Result<(Email, Uuid), GenerateTokenError> {
+    let claims = validate_token_structure(token).await?;
+    if claims.purpose != TokenPurpose::TrustedDevice {
+        return Err(GenerateTokenError::InvalidTokenPurpose);
+    }
+    let email = Email::parse(claims.sub).map_err(|err_msg| GenerateTokenError::TokenError(eyre!(err_msg)))?;
+    let device_id = Uuid::parse_str(&claims.jti).map_err(|e| GenerateTokenError::TokenError(e.into()))?;
+    Ok((email, device_id))
+}
+
+/// Checks that the token belongs to `email` and that its device has not been revoked, then records the use.
+#[tracing::instrument(name = "Check Trusted Device", skip_all)]
+pub async fn check_trusted_device<T: TrustedDeviceStore>(
+    trusted_device_store: Arc<RwLock<T>>,
+    token: &str,
+    email: &Email,
+) -> Result<(), GenerateTokenError> {
+    let (token_email, device_id) = parse_trusted_device_token(token).await?;
+    if token_email != *email {
+        return Err(GenerateTokenError::TokenError(eyre!(
+            "Trusted device belongs to another user"
+        )));
+    }
+
+    // A revoked or expired device is treated like a banned token
+    let map_store_error = |e: TrustedDeviceStoreError| match e {
+        TrustedDeviceStoreError::TrustedDeviceNotFound => GenerateTokenError::BannedToken,
+        _ => GenerateTokenError::UnexpectedError(e.into()),
+    };
+    let mut trusted_device_store = trusted_device_store.write().await;
+    let device = trusted_device_store
+        .get_device(&device_id)
+        .await
+        .map_err(map_store_error)?;
+    if device.email != *email {
+        return Err(GenerateTokenError::TokenError(eyre!(
+            "Trusted device belongs to another user"
+        )));
+    }
+    trusted_device_store
+        .touch_device(&device_id, Utc::now())
+        .await
+        .map_err(map_store_error)
+}
+
 #[tracing::instrument(name = "Create Token", skip_all)]
 pub fn create_token(claims: &Claims) -> Result<Secret<String>, jsonwebtoken::errors::Error> {
-    let token = encode(
-        &jsonwebtoken::Header::default(),
-        &claims,
-        &EncodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
-    )?;
+    let token = read_key_ring().sign(claims)?;
 
     Ok(Secret::new(token))
 }
 
+/// The public keys in the ring, for services that verify tokens offline. HMAC keys
+/// are never published.
+pub fn jwks() -> JwkSet {
+    read_key_ring().jwks()
+}
+
+/// Loads the key ring eagerly so a bad key configuration fails at startup.
+pub fn init_signing_key() {
+    let key_ring = read_key_ring();
+    let active_key = key_ring.active_key();
+    info!(
+        "Signing tokens with {:?} key {}, verifying with {:?}",
+        active_key.algorithm(),
+        active_key.kid(),
+        key_ring.kids()
+    );
+}
+
+/// Checks the HTTP Basic credentials a resource server presents to the introspection
+/// endpoint, per RFC 7662 section 2.1.
+pub fn authenticate_introspection_client(authorization: Option<&str>) -> Result<(), AuthAPIError> {
+    let credentials = authorization
+        .and_then(|header| header.strip_prefix("Basic "))
+        .and_then(|encoded| STANDARD.decode(encoded.trim()).ok())
+        .and_then(|decoded| String::from_utf8(decoded).ok())
+        .ok_or(AuthAPIError::InvalidClientCredentials)?;
+    let (client_id, client_secret) = credentials
+        .split_once(':')
+        .ok_or(AuthAPIError::InvalidClientCredentials)?;
+
+    // Compare digests so the check takes the same time however much of the secret matches
+    let secret_matches = digest(&SHA256, client_secret.as_bytes()).as_ref()
+        == digest(&SHA256, INTROSPECTION_CLIENT_SECRET.expose_secret().as_bytes()).as_ref();
+    match client_id == INTROSPECTION_CLIENT_ID.as_str() && secret_matches {
+        true => Ok(()),
+        false => Err(AuthAPIError::InvalidClientCredentials),
+    }
+}
+
 #[tracing::instrument(name = "Generate Password Reset Token", skip_all)]
 pub fn generate_password_reset_token(email: &Email) -> Result<Secret<String>, GenerateTokenError> {
-    let delta = chrono::Duration::try_seconds(Time::Minutes15 as i64).ok_or(GenerateTokenError::UnexpectedError(
-        eyre!("Failed to obtain chrono duration"),
-    ))?;
-    let exp: Epoch = Utc::now()
+    generate_email_token(email, TokenPurpose::PasswordReset, Time::Minutes15 as u64, None)
+}
+
+/// Signs a token for a link emailed to the user. It carries no session, so it can be used without logging in.
+fn generate_email_token(
+    email: &Email,
+    purpose: TokenPurpose,
+    ttl_seconds: u64,
+    new_email: Option<&Email>,
+) -> Result<Secret<String>, GenerateTokenError> {
+    let delta = chrono::Duration::try_seconds(ttl_seconds as i64).ok_or(GenerateTokenError::UnexpectedError(eyre!(
+        "Failed to obtain chrono duration"
+    )))?;
+    let now = Utc::now();
+    let exp: Epoch = now
         .checked_add_signed(delta)
         .ok_or(GenerateTokenError::UnexpectedError(eyre!(
             "Failed to generate expiration timestamp"
@@ -189,11 +779,21 @@ pub fn generate_password_reset_token(email: &Email) -> Result<Secret<String>, Ge
         .timestamp()
         .try_into()
         .map_err(|_| GenerateTokenError::UnexpectedError(eyre!("Failed to convert to Epoch")))?;
+    let iat: Epoch = now
+        .timestamp()
+        .try_into()
+        .map_err(|_| GenerateTokenError::UnexpectedError(eyre!("Failed to convert to Epoch")))?;
     let sub = email.as_ref().to_owned();
     let claims = Claims {
         sub,
         exp,
-        purpose: TokenPurpose::PasswordReset,
+        iat,
+        jti: Uuid::new_v4().to_string(),
+        purpose,
+        sid: None,
+        roles: vec![],
+        scopes: vec![],
+        new_email: new_email.map(|new_email| new_email.as_ref().to_owned()),
     };
     create_token(&claims).map_err(|e| GenerateTokenError::TokenError(e.into()))
 }
@@ -214,6 +814,120 @@ pub async fn validate_password_reset_token<T: BannedTokenStore>(
     Ok((email, claims))
 }
 
+/// Validates a token sent by email for `purpose` and returns the address it was sent to.
+#[tracing::instrument(name = "Validate Email Token", skip_all)]
+pub async fn validate_email_token<T: BannedTokenStore>(
+    banned_token_store: Arc<RwLock<T>>,
+    token: Secret<String>,
+    purpose: TokenPurpose,
+) -> Result<Email, GenerateTokenError> {
+    let claims = validate_token(banned_token_store, token).await?;
+
+    if claims.purpose != purpose {
+        return Err(GenerateTokenError::InvalidTokenPurpose);
+    }
+
+    Email::parse(claims.sub).map_err(|err_msg| GenerateTokenError::TokenError(eyre!(err_msg)))
+}
+
+/// Returns the address the account is at and the one the token moves it to.
+#[tracing::instrument(name = "Validate Email Change Token", skip_all)]
+pub async fn validate_email_change_token<T: BannedTokenStore>(
+    banned_token_store: Arc<RwLock<T>>,
+    token: Secret<String>,
+    purpose: TokenPurpose,
+) -> Result<(Email, Email), GenerateTokenError> {
+    let claims = validate_token(banned_token_store, token).await?;
+
+    if claims.purpose != purpose {
+        return Err(GenerateTokenError::InvalidTokenPurpose);
+    }
+
+    let new_email = claims
+        .new_email
+        .ok_or(GenerateTokenError::TokenError(eyre!("Missing new email")))?;
+    let email = Email::parse(claims.sub).map_err(|err_msg| GenerateTokenError::TokenError(eyre!(err_msg)))?;
+    let new_email = Email::parse(new_email).map_err(|err_msg| GenerateTokenError::TokenError(eyre!(err_msg)))?;
+    Ok((email, new_email))
+}
+
+#[tracing::instrument(name = "Generate Refresh Cookie", skip_all)]
+pub fn generate_refresh_cookie(token: &RefreshToken) -> Cookie<'static> {
+    Cookie::build((REFRESH_TOKEN_COOKIE_NAME, token.expose_secret_string()))
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .max_age(time::Duration::seconds(REFRESH_TOKEN_TTL_SECONDS))
+        .build()
+}
+
+#[tracing::instrument(name = "Issue Refresh Token", skip_all)]
+pub async fn issue_refresh_token<T: RefreshTokenStore>(
+    refresh_token_store: Arc<RwLock<T>>,
+    record: RefreshTokenRecord,
+) -> Result<RefreshToken, GenerateTokenError> {
+    let token = RefreshToken::default();
+    let mut refresh_token_store = refresh_token_store.write().await;
+    refresh_token_store
+        .add_token(token.clone(), record)
+        .await
+        .map_err(|e| GenerateTokenError::UnexpectedError(e.into()))?;
+    Ok(token)
+}
+
+#[tracing::instrument(name = "Rotate Refresh Token", skip_all)]
+pub async fn rotate_refresh_token<T: RefreshTokenStore>(
+    refresh_token_store: Arc<RwLock<T>>,
+    token: &RefreshToken,
+) -> Result<(RefreshTokenRecord, RefreshToken), GenerateTokenError> {
+    let mut refresh_token_store = refresh_token_store.write().await;
+    let record = refresh_token_store
+        .get_token(token)
+        .await
+        .map_err(|e| GenerateTokenError::TokenError(e.into()))?;
+
+    match refresh_token_store.mark_token_used(token).await {
+        Ok(()) => {}
+        Err(RefreshTokenStoreError::TokenReused) => {
+            warn!(
+                "Refresh token reuse detected, revoking token family {}",
+                record.family_id
+            );
+            refresh_token_store
+                .revoke_family(&record.family_id)
+                .await
+                .map_err(|e| GenerateTokenError::UnexpectedError(e.into()))?;
+            return Err(GenerateTokenError::ReusedToken);
+        }
+        Err(e) => return Err(GenerateTokenError::TokenError(e.into())),
+    }
+
+    let new_token = RefreshToken::default();
+    refresh_token_store
+        .add_token(new_token.clone(), record.clone())
+        .await
+        .map_err(|e| GenerateTokenError::UnexpectedError(e.into()))?;
+
+    Ok((record, new_token))
+}
+
+#[tracing::instrument(name = "Revoke Refresh Token", skip_all)]
+pub async fn revoke_refresh_token<T: RefreshTokenStore>(
+    refresh_token_store: Arc<RwLock<T>>,
+    token: &RefreshToken,
+) -> Result<(), GenerateTokenError> {
+    let mut refresh_token_store = refresh_token_store.write().await;
+    let record = match refresh_token_store.get_token(token).await {
+        Ok(record) => record,
+        Err(RefreshTokenStoreError::TokenNotFound) => return Ok(()),
+        Err(e) => return Err(GenerateTokenError::UnexpectedError(e.into())),
+    };
+    refresh_token_store
+        .revoke_family(&record.family_id)
+        .await
+        .map_err(|e| GenerateTokenError::UnexpectedError(e.into()))
+}
+
 #[tracing::instrument(name = "Compute Password Hash", skip_all)]
 pub async fn async_compute_password_hash(password: Secret<String>) -> Result<Secret<String>> {
     let password_hash = tokio::task::spawn_blocking(|| compute_password_hash(password)).await??;
@@ -223,8 +937,8 @@ pub async fn async_compute_password_hash(password: Secret<String>) -> Result<Sec
 
 pub fn compute_password_hash(password: Secret<String>) -> Result<Secret<String>> {
     let salt: SaltString = SaltString::generate(&mut rand::thread_rng());
-    let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, Params::new(15000, 2, 1, None)?);
-    let hash = argon2
+    let hash = PasswordHashPolicy::from_env()?
+        .hasher()
         .hash_password(password.expose_secret().as_bytes(), &salt)?
         .to_string();
 
@@ -236,7 +950,9 @@ pub fn compute_password_hash(password: Secret<String>) -> Result<Secret<String>>
 #[cfg(test)]
 mod tests {
     use crate::{
-        services::hashmap_banned_token_store::HashMapBannedTokenStore,
+        services::{
+            hashmap_banned_token_store::HashMapBannedTokenStore, hashmap_refresh_token_store::HashMapRefreshTokenStore,
+        },
         utils::constants::PASSWORD_RESET_TOKEN_TTL_SECONDS,
     };
 
@@ -249,7 +965,7 @@ mod tests {
     #[tokio::test]
     async fn test_generate_auth_cookie() {
         let email = str_to_valid_email("test@example.com");
-        let cookie = generate_auth_cookie(&email).unwrap();
+        let cookie = generate_auth_cookie(&email, &Uuid::new_v4(), &UserAccess::default()).unwrap();
         assert_eq!(cookie.name(), JWT_COOKIE_NAME);
         assert_eq!(cookie.value().split('.').count(), 3);
         assert_eq!(cookie.path(), Some("/"));
@@ -260,14 +976,14 @@ mod tests {
     #[tokio::test]
     async fn test_generate_auth_token() {
         let email = str_to_valid_email("test@example.com");
-        let secret = generate_auth_token(&email).unwrap();
+        let secret = generate_auth_token(&email, &Uuid::new_v4(), &UserAccess::default()).unwrap();
         assert_eq!(secret.expose_secret().split('.').count(), 3);
     }
 
     #[tokio::test]
     async fn test_validate_token_structure_with_valid_token() {
         let email = str_to_valid_email("test@example.com");
-        let token = generate_auth_token(&email).unwrap();
+        let token = generate_auth_token(&email, &Uuid::new_v4(), &UserAccess::default()).unwrap();
         let claims = validate_token_structure(token.expose_secret()).await.unwrap();
         assert_eq!(claims.sub.expose_secret(), "test@example.com");
 
@@ -279,6 +995,150 @@ mod tests {
         assert!(claims.exp > exp as Epoch);
     }
 
+    #[tokio::test]
+    async fn test_generate_auth_token_embeds_roles_and_scope() {
+        let email = str_to_valid_email("test@example.com");
+        let access = UserAccess::new(
+            vec!["admin".to_string()],
+            ["users:self", "users:admin"].map(String::from),
+        );
+        let token = generate_auth_token(&email, &Uuid::new_v4(), &access).unwrap();
+
+        let payload = token.expose_secret().split('.').nth(1).unwrap();
+        let payload: serde_json::Value = serde_json::from_slice(
+            &base64::engine::general_purpose::URL_SAFE_NO_PAD
+                .decode(payload)
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(payload["scope"], "users:admin users:self");
+        assert_eq!(payload["roles"], serde_json::json!(["admin"]));
+
+        let claims = validate_token_structure(token.expose_secret()).await.unwrap();
+        assert_eq!(claims.roles, access.roles);
+        assert_eq!(claims.scopes, access.scopes);
+        assert!(claims.has_scope("users:admin"));
+        assert!(!claims.has_scope("users:delete"));
+    }
+
+    #[tokio::test]
+    async fn test_generate_auth_token_sets_kid_header() {
+        let email = str_to_valid_email("test@example.com");
+        let token = generate_auth_token(&email, &Uuid::new_v4(), &UserAccess::default()).unwrap();
+        let header = jsonwebtoken::decode_header(token.expose_secret()).unwrap();
+        assert_eq!(header.kid.as_deref(), Some(read_key_ring().active_key().kid()));
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_structure_with_unknown_kid() {
+        let other_key = SigningKey::from_secret("other", &crate::utils::constants::JWT_SECRET);
+        let token = other_key.sign(&test_claims()).unwrap();
+        assert!(validate_token_structure(&token).await.is_err());
+    }
+
+    fn test_claims() -> Claims {
+        Claims {
+            sub: Secret::new("test@example.com".to_string()),
+            exp: (Utc::now().timestamp() + 600) as Epoch,
+            iat: Utc::now().timestamp() as Epoch,
+            jti: Uuid::new_v4().to_string(),
+            purpose: TokenPurpose::Auth,
+            sid: None,
+            roles: vec![],
+            scopes: vec![],
+            new_email: None,
+        }
+    }
+
+    fn hmac_key(kid: &str) -> SigningKey {
+        SigningKey::from_secret(kid, &Secret::new(format!("{kid}-secret")))
+    }
+
+    #[test]
+    fn test_key_ring_verifies_tokens_signed_before_and_after_rotation() {
+        let mut key_ring = KeyRing::new(hmac_key("key-1"));
+        let old_token = key_ring.sign(&test_claims()).unwrap();
+
+        key_ring.add_key(hmac_key("key-2")).unwrap();
+        assert_eq!(key_ring.active_key().kid(), "key-1");
+        key_ring.promote_key("key-2").unwrap();
+        let new_token = key_ring.sign(&test_claims()).unwrap();
+
+        assert_eq!(decode_header(&new_token).unwrap().kid.as_deref(), Some("key-2"));
+        assert!(key_ring.verify::<Claims>(&old_token).is_ok());
+        assert!(key_ring.verify::<Claims>(&new_token).is_ok());
+    }
+
+    #[test]
+    fn test_key_ring_rejects_tokens_from_retired_key() {
+        let mut key_ring = KeyRing::new(hmac_key("key-1"));
+        let old_token = key_ring.sign(&test_claims()).unwrap();
+        key_ring.add_key(hmac_key("key-2")).unwrap();
+        key_ring.promote_key("key-2").unwrap();
+
+        key_ring.retire_key("key-1").unwrap();
+
+        assert!(key_ring.verify::<Claims>(&old_token).is_err());
+        assert_eq!(key_ring.kids(), vec!["key-2"]);
+    }
+
+    #[test]
+    fn test_key_ring_rejects_invalid_changes() {
+        let mut key_ring = KeyRing::new(hmac_key("key-1"));
+        assert!(matches!(
+            key_ring.add_key(hmac_key("key-1")),
+            Err(KeyRingError::KeyAlreadyExists)
+        ));
+        assert!(matches!(
+            key_ring.promote_key("missing"),
+            Err(KeyRingError::KeyNotFound)
+        ));
+        assert!(matches!(key_ring.retire_key("missing"), Err(KeyRingError::KeyNotFound)));
+        assert!(matches!(
+            key_ring.retire_key("key-1"),
+            Err(KeyRingError::ActiveKeyRetired)
+        ));
+    }
+
+    #[test]
+    fn test_key_ring_rejects_token_without_kid() {
+        let key_ring = KeyRing::new(hmac_key("key-1"));
+        let token = jsonwebtoken::encode(
+            &jsonwebtoken::Header::default(),
+            &test_claims(),
+            &jsonwebtoken::EncodingKey::from_secret(b"key-1-secret"),
+        )
+        .unwrap();
+        assert!(key_ring.verify::<Claims>(&token).is_err());
+    }
+
+    #[test]
+    fn test_key_ring_jwks_publishes_every_asymmetric_key() {
+        let ec_key = SigningKey::from_pem(
+            jsonwebtoken::Algorithm::ES256,
+            include_bytes!("../../tests/fixtures/jwt/ec_private.pem"),
+            Some("ec-1"),
+        )
+        .unwrap();
+        let ed_key = SigningKey::from_pem(
+            jsonwebtoken::Algorithm::EdDSA,
+            include_bytes!("../../tests/fixtures/jwt/ed25519_private.pem"),
+            Some("ed-1"),
+        )
+        .unwrap();
+        let mut key_ring = KeyRing::new(hmac_key("key-1"));
+        key_ring.add_key(ec_key).unwrap();
+        key_ring.add_key(ed_key).unwrap();
+
+        let jwks = key_ring.jwks();
+        assert_eq!(jwks.keys.len(), 2);
+        assert!(jwks.find("ec-1").is_some());
+        assert!(jwks.find("ed-1").is_some());
+
+        key_ring.retire_key("ec-1").unwrap();
+        assert!(key_ring.jwks().find("ec-1").is_none());
+    }
+
     #[tokio::test]
     async fn test_validate_token_structure_with_invalid_token() {
         let token = "invalid_token".to_owned();
@@ -289,7 +1149,7 @@ mod tests {
     #[tokio::test]
     async fn test_validate_token_with_valid_token() {
         let email = str_to_valid_email("test@example.com");
-        let token = generate_auth_token(&email).unwrap();
+        let token = generate_auth_token(&email, &Uuid::new_v4(), &UserAccess::default()).unwrap();
         let banned_token_store = Arc::new(RwLock::new(HashMapBannedTokenStore::new()));
         let result = validate_token(banned_token_store, token).await;
 
@@ -319,7 +1179,7 @@ mod tests {
     #[tokio::test]
     async fn test_validate_token_with_banned_token() {
         let email = str_to_valid_email("test@example.com");
-        let token = generate_auth_token(&email).unwrap();
+        let token = generate_auth_token(&email, &Uuid::new_v4(), &UserAccess::default()).unwrap();
         let banned_token_store = Arc::new(RwLock::new(HashMapBannedTokenStore::new()));
 
         {
@@ -347,13 +1207,7 @@ mod tests {
         let email = str_to_valid_email("test@example.com");
         let token = generate_password_reset_token(&email).unwrap();
 
-        let claims = decode::<Claims>(
-            token.expose_secret(),
-            &DecodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
-            &Validation::default(),
-        )
-        .unwrap()
-        .claims;
+        let claims = read_key_ring().verify::<Claims>(token.expose_secret()).unwrap();
 
         let now = Utc::now().timestamp() as Epoch;
         assert!(claims.exp > now);
@@ -381,7 +1235,13 @@ mod tests {
         let claims = Claims {
             sub: email.as_ref().to_owned(),
             exp,
+            iat: Utc::now().timestamp() as Epoch,
+            jti: Uuid::new_v4().to_string(),
             purpose: TokenPurpose::Auth,
+            sid: None,
+            roles: vec![],
+            scopes: vec![],
+            new_email: None,
         };
         let token = create_token(&claims).unwrap();
 
@@ -399,7 +1259,13 @@ mod tests {
         let claims = Claims {
             sub: email.as_ref().to_owned(),
             exp,
+            iat: Utc::now().timestamp() as Epoch,
+            jti: Uuid::new_v4().to_string(),
             purpose: TokenPurpose::PasswordReset,
+            sid: None,
+            roles: vec![],
+            scopes: vec![],
+            new_email: None,
         };
         let token = create_token(&claims).unwrap();
         let banned_token_store = Arc::new(RwLock::new(HashMapBannedTokenStore::new()));
@@ -414,7 +1280,13 @@ mod tests {
         let claims = Claims {
             sub: Secret::new("invalid_email".to_string()),
             exp: (Utc::now().timestamp() + 3600) as Epoch,
+            iat: Utc::now().timestamp() as Epoch,
+            jti: Uuid::new_v4().to_string(),
             purpose: TokenPurpose::PasswordReset,
+            sid: None,
+            roles: vec![],
+            scopes: vec![],
+            new_email: None,
         };
         let token = create_token(&claims).unwrap();
         let banned_token_store = Arc::new(RwLock::new(HashMapBannedTokenStore::new()));
@@ -423,4 +1295,85 @@ mod tests {
         assert!(result.is_err());
         assert_eq!(result.unwrap_err().to_string(), "Token error");
     }
+
+    #[test]
+    fn test_authenticate_introspection_client() {
+        let encode = |credentials: &str| format!("Basic {}", STANDARD.encode(credentials));
+        let valid = encode(&format!(
+            "{}:{}",
+            INTROSPECTION_CLIENT_ID.as_str(),
+            INTROSPECTION_CLIENT_SECRET.expose_secret()
+        ));
+        assert!(authenticate_introspection_client(Some(&valid)).is_ok());
+
+        for invalid in [
+            None,
+            Some("Bearer token".to_string()),
+            Some("Basic not-base64!".to_string()),
+            Some(encode("no-colon")),
+            Some(encode(&format!(
+                "other-client:{}",
+                INTROSPECTION_CLIENT_SECRET.expose_secret()
+            ))),
+            Some(encode(&format!("{}:wrong", INTROSPECTION_CLIENT_ID.as_str()))),
+        ] {
+            assert!(matches!(
+                authenticate_introspection_client(invalid.as_deref()),
+                Err(AuthAPIError::InvalidClientCredentials)
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_generate_refresh_cookie() {
+        let token = RefreshToken::default();
+        let cookie = generate_refresh_cookie(&token);
+        assert_eq!(cookie.name(), REFRESH_TOKEN_COOKIE_NAME);
+        assert_eq!(cookie.value(), token.expose_secret_string());
+        assert_eq!(cookie.http_only(), Some(true));
+        assert_eq!(
+            cookie.max_age(),
+            Some(time::Duration::seconds(REFRESH_TOKEN_TTL_SECONDS))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rotate_refresh_token_issues_new_token_in_same_family() {
+        let email = str_to_valid_email("test@example.com");
+        let refresh_token_store = Arc::new(RwLock::new(HashMapRefreshTokenStore::new()));
+        let record = RefreshTokenRecord::new(email.clone());
+        let token = issue_refresh_token(refresh_token_store.clone(), record.clone())
+            .await
+            .unwrap();
+
+        let (rotated_record, new_token) = rotate_refresh_token(refresh_token_store.clone(), &token).await.unwrap();
+
+        assert_ne!(new_token, token);
+        assert_eq!(rotated_record, record);
+        let store = refresh_token_store.read().await;
+        assert_eq!(store.get_token(&new_token).await.unwrap().family_id, record.family_id);
+    }
+
+    #[tokio::test]
+    async fn test_rotate_refresh_token_reuse_revokes_family() {
+        let email = str_to_valid_email("test@example.com");
+        let refresh_token_store = Arc::new(RwLock::new(HashMapRefreshTokenStore::new()));
+        let token = issue_refresh_token(refresh_token_store.clone(), RefreshTokenRecord::new(email))
+            .await
+            .unwrap();
+        let (_, new_token) = rotate_refresh_token(refresh_token_store.clone(), &token).await.unwrap();
+
+        let result = rotate_refresh_token(refresh_token_store.clone(), &token).await;
+        assert!(matches!(result, Err(GenerateTokenError::ReusedToken)));
+
+        let result = rotate_refresh_token(refresh_token_store.clone(), &new_token).await;
+        assert!(matches!(result, Err(GenerateTokenError::TokenError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_rotate_refresh_token_with_unknown_token() {
+        let refresh_token_store = Arc::new(RwLock::new(HashMapRefreshTokenStore::new()));
+        let result = rotate_refresh_token(refresh_token_store, &RefreshToken::default()).await;
+        assert!(matches!(result, Err(GenerateTokenError::TokenError(_))));
+    }
 }
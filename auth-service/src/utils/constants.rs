@@ -7,14 +7,99 @@ use serde::Serialize;
 use tracing::debug;
 
 lazy_static! {
+    pub static ref ACCOUNT_DELETION_GRACE_DAYS: u32 = set_default_env_var(
+        env::ACCOUNT_DELETION_GRACE_DAYS_ENV_VAR,
+        DEFAULT_ACCOUNT_DELETION_GRACE_DAYS
+    )
+    .parse()
+    .expect("ACCOUNT_DELETION_GRACE_DAYS must be a non-negative integer.");
+    pub static ref ARGON2_ITERATIONS: u32 =
+        set_default_env_var(env::ARGON2_ITERATIONS_ENV_VAR, DEFAULT_ARGON2_ITERATIONS)
+            .parse()
+            .expect("ARGON2_ITERATIONS must be a positive integer.");
+    pub static ref ARGON2_MEMORY_KIB: u32 =
+        set_default_env_var(env::ARGON2_MEMORY_KIB_ENV_VAR, DEFAULT_ARGON2_MEMORY_KIB)
+            .parse()
+            .expect("ARGON2_MEMORY_KIB must be a positive integer.");
+    pub static ref ARGON2_PARALLELISM: u32 =
+        set_default_env_var(env::ARGON2_PARALLELISM_ENV_VAR, DEFAULT_ARGON2_PARALLELISM)
+            .parse()
+            .expect("ARGON2_PARALLELISM must be a positive integer.");
+    pub static ref AUTH_TOKEN_PRECEDENCE: String =
+        set_default_env_var(env::AUTH_TOKEN_PRECEDENCE_ENV_VAR, DEFAULT_AUTH_TOKEN_PRECEDENCE);
     pub static ref DATABASE_URL: Secret<String> = Secret::new(set_required_env_var(env::DATABASE_URL_ENV_VAR));
+    pub static ref EMAIL_VERIFICATION_MODE: String =
+        set_default_env_var(env::EMAIL_VERIFICATION_MODE_ENV_VAR, DEFAULT_EMAIL_VERIFICATION_MODE);
     pub static ref JWT_SECRET: Secret<String> = Secret::new(set_required_env_var(env::JWT_SECRET_ENV_VAR));
+    pub static ref JWT_ALGORITHM: String = set_default_env_var(env::JWT_ALGORITHM_ENV_VAR, DEFAULT_JWT_ALGORITHM);
+    pub static ref JWT_PRIVATE_KEY_PATH: Option<String> = set_optional_env_var(env::JWT_PRIVATE_KEY_PATH_ENV_VAR);
+    pub static ref JWT_KEY_ID: Option<String> = set_optional_env_var(env::JWT_KEY_ID_ENV_VAR);
+    pub static ref JWT_KEY_RING_PATH: Option<String> = set_optional_env_var(env::JWT_KEY_RING_PATH_ENV_VAR);
+    pub static ref INTROSPECTION_CLIENT_ID: String =
+        set_default_env_var(env::INTROSPECTION_CLIENT_ID_ENV_VAR, DEFAULT_INTROSPECTION_CLIENT_ID);
+    pub static ref INTROSPECTION_CLIENT_SECRET: Secret<String> =
+        Secret::new(set_required_env_var(env::INTROSPECTION_CLIENT_SECRET_ENV_VAR));
+    pub static ref LOGIN_FAILURE_DELAY_SECONDS: u64 = set_default_env_var(
+        env::LOGIN_FAILURE_DELAY_SECONDS_ENV_VAR,
+        DEFAULT_LOGIN_FAILURE_DELAY_SECONDS
+    )
+    .parse()
+    .expect("LOGIN_FAILURE_DELAY_SECONDS must be a non-negative integer.");
+    pub static ref LOGIN_LOCKOUT_SECONDS: u64 =
+        set_default_env_var(env::LOGIN_LOCKOUT_SECONDS_ENV_VAR, DEFAULT_LOGIN_LOCKOUT_SECONDS)
+            .parse()
+            .expect("LOGIN_LOCKOUT_SECONDS must be a positive integer.");
+    pub static ref LOGIN_LOCKOUT_THRESHOLD: u32 =
+        set_default_env_var(env::LOGIN_LOCKOUT_THRESHOLD_ENV_VAR, DEFAULT_LOGIN_LOCKOUT_THRESHOLD)
+            .parse()
+            .expect("LOGIN_LOCKOUT_THRESHOLD must be a positive integer.");
+    /// The longest any JWT checked against a user's not-before cutoff can live: auth tokens and the emailed link
+    /// tokens. The cutoff has to be kept at least this long, or tokens issued before it would pass once it expired.
+    pub static ref MAX_TOKEN_TTL_SECONDS: u64 = [
+        TOKEN_TTL_SECONDS as u64,
+        Time::Days7 as u64,
+        u64::from(*ACCOUNT_DELETION_GRACE_DAYS) * Time::Hours24 as u64,
+        *LOGIN_LOCKOUT_SECONDS,
+    ]
+    .into_iter()
+    .max()
+    .unwrap_or_default();
+    pub static ref PHONE_VERIFICATION_MAX_SENDS: u32 = set_default_env_var(
+        env::PHONE_VERIFICATION_MAX_SENDS_ENV_VAR,
+        DEFAULT_PHONE_VERIFICATION_MAX_SENDS
+    )
+    .parse()
+    .expect("PHONE_VERIFICATION_MAX_SENDS must be a non-negative integer.");
     pub static ref POSTMARK_AUTH_TOKEN: Secret<String> =
         Secret::new(set_required_env_var(env::POSTMARK_AUTH_TOKEN_ENV_VAR));
     pub static ref REDIS_HOST_NAME: String = set_default_env_var(env::REDIS_HOST_NAME_ENV_VAR, DEFAULT_REDIS_HOST_NAME);
     pub static ref REDIS_PASSWORD: Secret<String> = Secret::new(set_required_env_var(env::REDIS_PASSWORD_ENV_VAR));
     pub static ref REST_AUTH_SERVICE_URL: String =
         set_default_env_var(env::REST_AUTH_SERVICE_URL_ENV_VAR, "http://localhost/auth");
+    pub static ref TWILIO_ACCOUNT_SID: String = set_required_env_var(env::TWILIO_ACCOUNT_SID_ENV_VAR);
+    pub static ref TWILIO_AUTH_TOKEN: Secret<String> =
+        Secret::new(set_required_env_var(env::TWILIO_AUTH_TOKEN_ENV_VAR));
+    pub static ref TWILIO_PHONE_NUMBER: String = set_required_env_var(env::TWILIO_PHONE_NUMBER_ENV_VAR);
+    pub static ref TOTP_ISSUER: String = set_default_env_var(env::TOTP_ISSUER_ENV_VAR, DEFAULT_TOTP_ISSUER);
+    pub static ref TOTP_SKEW_STEPS: u64 = set_default_env_var(env::TOTP_SKEW_STEPS_ENV_VAR, DEFAULT_TOTP_SKEW_STEPS)
+        .parse()
+        .expect("TOTP_SKEW_STEPS must be a non-negative integer.");
+    pub static ref TWO_FA_MAX_ATTEMPTS: u32 =
+        set_default_env_var(env::TWO_FA_MAX_ATTEMPTS_ENV_VAR, DEFAULT_TWO_FA_MAX_ATTEMPTS)
+            .parse()
+            .expect("TWO_FA_MAX_ATTEMPTS must be a positive integer.");
+    pub static ref TWO_FA_MAX_RESENDS: u32 =
+        set_default_env_var(env::TWO_FA_MAX_RESENDS_ENV_VAR, DEFAULT_TWO_FA_MAX_RESENDS)
+            .parse()
+            .expect("TWO_FA_MAX_RESENDS must be a non-negative integer.");
+    pub static ref TWO_FA_RESEND_COOLDOWN_SECONDS: u64 = set_default_env_var(
+        env::TWO_FA_RESEND_COOLDOWN_SECONDS_ENV_VAR,
+        DEFAULT_TWO_FA_RESEND_COOLDOWN_SECONDS
+    )
+    .parse()
+    .expect("TWO_FA_RESEND_COOLDOWN_SECONDS must be a non-negative integer.");
+    pub static ref WEBAUTHN_ORIGIN: String = set_default_env_var(env::WEBAUTHN_ORIGIN_ENV_VAR, DEFAULT_WEBAUTHN_ORIGIN);
+    pub static ref WEBAUTHN_RP_ID: String = set_default_env_var(env::WEBAUTHN_RP_ID_ENV_VAR, DEFAULT_WEBAUTHN_RP_ID);
 }
 
 fn set_default_env_var(var_name: &str, default_value: &str) -> String {
@@ -28,6 +113,13 @@ fn set_default_env_var(var_name: &str, default_value: &str) -> String {
     }
 }
 
+fn set_optional_env_var(var_name: &str) -> Option<String> {
+    debug!("Attempting to obtain optional {var_name} environment variable");
+    dotenv().ok();
+    debug!("dotenv Ok");
+    std_env::var(var_name).ok().filter(|value| !value.is_empty())
+}
+
 fn set_required_env_var(var_name: &str) -> String {
     debug!("Attempting to obtain {var_name} environment variable");
     dotenv().ok();
@@ -40,12 +132,38 @@ fn set_required_env_var(var_name: &str) -> String {
 }
 
 pub mod env {
+    pub const ACCOUNT_DELETION_GRACE_DAYS_ENV_VAR: &str = "ACCOUNT_DELETION_GRACE_DAYS";
+    pub const ARGON2_ITERATIONS_ENV_VAR: &str = "ARGON2_ITERATIONS";
+    pub const ARGON2_MEMORY_KIB_ENV_VAR: &str = "ARGON2_MEMORY_KIB";
+    pub const ARGON2_PARALLELISM_ENV_VAR: &str = "ARGON2_PARALLELISM";
+    pub const AUTH_TOKEN_PRECEDENCE_ENV_VAR: &str = "AUTH_TOKEN_PRECEDENCE";
     pub const DATABASE_URL_ENV_VAR: &str = "DATABASE_URL";
+    pub const EMAIL_VERIFICATION_MODE_ENV_VAR: &str = "EMAIL_VERIFICATION_MODE";
+    pub const INTROSPECTION_CLIENT_ID_ENV_VAR: &str = "INTROSPECTION_CLIENT_ID";
+    pub const INTROSPECTION_CLIENT_SECRET_ENV_VAR: &str = "INTROSPECTION_CLIENT_SECRET";
     pub const JWT_SECRET_ENV_VAR: &str = "JWT_SECRET";
+    pub const JWT_ALGORITHM_ENV_VAR: &str = "JWT_ALGORITHM";
+    pub const JWT_PRIVATE_KEY_PATH_ENV_VAR: &str = "JWT_PRIVATE_KEY_PATH";
+    pub const JWT_KEY_ID_ENV_VAR: &str = "JWT_KEY_ID";
+    pub const JWT_KEY_RING_PATH_ENV_VAR: &str = "JWT_KEY_RING_PATH";
+    pub const LOGIN_FAILURE_DELAY_SECONDS_ENV_VAR: &str = "LOGIN_FAILURE_DELAY_SECONDS";
+    pub const LOGIN_LOCKOUT_SECONDS_ENV_VAR: &str = "LOGIN_LOCKOUT_SECONDS";
+    pub const LOGIN_LOCKOUT_THRESHOLD_ENV_VAR: &str = "LOGIN_LOCKOUT_THRESHOLD";
+    pub const PHONE_VERIFICATION_MAX_SENDS_ENV_VAR: &str = "PHONE_VERIFICATION_MAX_SENDS";
     pub const POSTMARK_AUTH_TOKEN_ENV_VAR: &str = "POSTMARK_AUTH_TOKEN";
     pub const REST_AUTH_SERVICE_URL_ENV_VAR: &str = "REST_AUTH_SERVICE_URL";
     pub const REDIS_HOST_NAME_ENV_VAR: &str = "REDIS_HOST_NAME";
     pub const REDIS_PASSWORD_ENV_VAR: &str = "REDIS_PASSWORD";
+    pub const TOTP_ISSUER_ENV_VAR: &str = "TOTP_ISSUER";
+    pub const TWILIO_ACCOUNT_SID_ENV_VAR: &str = "TWILIO_ACCOUNT_SID";
+    pub const TWILIO_AUTH_TOKEN_ENV_VAR: &str = "TWILIO_AUTH_TOKEN";
+    pub const TWILIO_PHONE_NUMBER_ENV_VAR: &str = "TWILIO_PHONE_NUMBER";
+    pub const TOTP_SKEW_STEPS_ENV_VAR: &str = "TOTP_SKEW_STEPS";
+    pub const TWO_FA_MAX_ATTEMPTS_ENV_VAR: &str = "TWO_FA_MAX_ATTEMPTS";
+    pub const TWO_FA_MAX_RESENDS_ENV_VAR: &str = "TWO_FA_MAX_RESENDS";
+    pub const TWO_FA_RESEND_COOLDOWN_SECONDS_ENV_VAR: &str = "TWO_FA_RESEND_COOLDOWN_SECONDS";
+    pub const WEBAUTHN_ORIGIN_ENV_VAR: &str = "WEBAUTHN_ORIGIN";
+    pub const WEBAUTHN_RP_ID_ENV_VAR: &str = "WEBAUTHN_RP_ID";
 }
 
 pub mod prod {
@@ -59,6 +177,13 @@ pub mod prod {
         pub const SENDER: &str = "do-not-reply@markmcclatchy.com";
         pub const TIMEOUT: Duration = std::time::Duration::from_secs(10);
     }
+
+    pub mod sms_client {
+        use std::time::Duration;
+
+        pub const BASE_URL: &str = "https://api.twilio.com";
+        pub const TIMEOUT: Duration = std::time::Duration::from_secs(10);
+    }
 }
 
 pub mod test {
@@ -74,12 +199,50 @@ pub mod test {
         pub const SENDER: &str = "test@email.com";
         pub const TIMEOUT: Duration = std::time::Duration::from_millis(200);
     }
+
+    pub mod sms_client {
+        use std::time::Duration;
+
+        pub const ACCOUNT_SID: &str = "test_account_sid";
+        pub const SENDER: &str = "+15005550006";
+        pub const TIMEOUT: Duration = std::time::Duration::from_millis(200);
+    }
 }
 
 pub const JWT_COOKIE_NAME: &str = "jwt";
 pub const TOKEN_TTL_SECONDS: i64 = Time::Minutes10 as i64;
 pub const PASSWORD_RESET_TOKEN_TTL_SECONDS: i64 = Time::Hours1 as i64;
+pub const REFRESH_TOKEN_COOKIE_NAME: &str = "refresh_token";
+pub const REFRESH_TOKEN_TTL_SECONDS: i64 = Time::Days30 as i64;
+pub const TRUSTED_DEVICE_COOKIE_NAME: &str = "trusted_device";
+pub const TRUSTED_DEVICE_TTL_SECONDS: i64 = Time::Days30 as i64;
+pub const RECOVERY_CODE_COUNT: usize = 10;
+pub const MAX_PENDING_TWO_FA_ATTEMPTS: usize = 5;
+pub const MAX_AUTH_EVENTS: usize = 50;
+pub const WEBAUTHN_CHALLENGE_TTL_SECONDS: i64 = Time::Minutes10 as i64;
 pub const DEFAULT_REDIS_HOST_NAME: &str = "redis";
+pub const DEFAULT_JWT_ALGORITHM: &str = "HS256";
+pub const DEFAULT_INTROSPECTION_CLIENT_ID: &str = "app-service";
+pub const DEFAULT_ACCOUNT_DELETION_GRACE_DAYS: &str = "0";
+pub const DEFAULT_ARGON2_ITERATIONS: &str = "2";
+pub const DEFAULT_ARGON2_MEMORY_KIB: &str = "15000";
+pub const DEFAULT_ARGON2_PARALLELISM: &str = "1";
+pub const DEFAULT_AUTH_TOKEN_PRECEDENCE: &str = "header";
+pub const DEFAULT_EMAIL_VERIFICATION_MODE: &str = "required";
+pub const DEFAULT_LOGIN_FAILURE_DELAY_SECONDS: &str = "1";
+pub const DEFAULT_LOGIN_LOCKOUT_SECONDS: &str = "900";
+pub const DEFAULT_LOGIN_LOCKOUT_THRESHOLD: &str = "5";
+pub const DEFAULT_PHONE_VERIFICATION_MAX_SENDS: &str = "5";
+/// Window over which `PHONE_VERIFICATION_MAX_SENDS` is counted.
+pub const PHONE_VERIFICATION_SEND_WINDOW_SECONDS: u64 = Time::Hours24 as u64;
+pub const DEFAULT_TOTP_ISSUER: &str = "Auth Service";
+pub const DEFAULT_TOTP_SKEW_STEPS: &str = "1";
+pub const DEFAULT_TWO_FA_MAX_ATTEMPTS: &str = "5";
+pub const DEFAULT_TWO_FA_MAX_RESENDS: &str = "3";
+pub const DEFAULT_TWO_FA_RESEND_COOLDOWN_SECONDS: &str = "30";
+pub const DEFAULT_WEBAUTHN_ORIGIN: &str = "http://localhost";
+pub const DEFAULT_WEBAUTHN_RP_ID: &str = "localhost";
+pub const WEBAUTHN_RP_NAME: &str = "Auth Service";
 
 pub type Epoch = u32;
 
@@ -88,6 +251,9 @@ pub enum Time {
     Minutes10 = 600,
     Minutes15 = 900,
     Hours1 = 3600,
+    Hours24 = 86400,
+    Days7 = 604800,
+    Days30 = 2592000,
 }
 
 impl std::fmt::Display for Time {
@@ -96,6 +262,9 @@ impl std::fmt::Display for Time {
             Self::Minutes10 => "10 Minutes",
             Self::Minutes15 => "15 Minutes",
             Self::Hours1 => "1 Hour",
+            Self::Hours24 => "24 Hours",
+            Self::Days7 => "7 Days",
+            Self::Days30 => "30 Days",
         };
         write!(f, "{time_str}")
     }
@@ -0,0 +1,366 @@
+use core::fmt;
+use std::fs;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use color_eyre::eyre::{eyre, Result};
+use jsonwebtoken::{
+    decode, decode_header, encode,
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, EllipticCurveKeyParameters, EllipticCurveKeyType, Jwk,
+        KeyAlgorithm, OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+    },
+    Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
+use ring::{
+    digest::{digest, SHA256},
+    rand::SystemRandom,
+    signature::{
+        EcdsaKeyPair, Ed25519KeyPair, KeyPair, RsaKeyPair, RsaPublicKeyComponents, ECDSA_P256_SHA256_FIXED_SIGNING,
+    },
+};
+use secrecy::{ExposeSecret, Secret};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::constants::{JWT_ALGORITHM, JWT_KEY_ID, JWT_PRIVATE_KEY_PATH, JWT_SECRET};
+
+pub const DEFAULT_HMAC_KEY_ID: &str = "default";
+
+/// A key used to sign and verify JWTs. Asymmetric keys also carry the public JWK
+/// published at `/.well-known/jwks.json`; HMAC keys never leave the service.
+#[derive(Clone)]
+pub struct SigningKey {
+    kid: String,
+    algorithm: Algorithm,
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    jwk: Option<Jwk>,
+}
+
+impl fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SigningKey")
+            .field("kid", &self.kid)
+            .field("algorithm", &self.algorithm)
+            .finish()
+    }
+}
+
+impl SigningKey {
+    pub fn from_secret(kid: &str, secret: &Secret<String>) -> Self {
+        Self {
+            kid: kid.to_string(),
+            algorithm: Algorithm::HS256,
+            encoding_key: EncodingKey::from_secret(secret.expose_secret().as_bytes()),
+            decoding_key: DecodingKey::from_secret(secret.expose_secret().as_bytes()),
+            jwk: None,
+        }
+    }
+
+    /// Loads a PKCS#8 private key (or PKCS#1 for RSA) and derives the public key from it.
+    /// Without an explicit `kid`, the RFC 7638 thumbprint of the public key is used.
+    pub fn from_pem(algorithm: Algorithm, private_key_pem: &[u8], kid: Option<&str>) -> Result<Self> {
+        let pem = pem::parse(private_key_pem)?;
+        let der = pem.contents();
+
+        let (encoding_key, params) = match algorithm {
+            Algorithm::RS256 => {
+                let key_pair = match pem.tag() {
+                    "RSA PRIVATE KEY" => RsaKeyPair::from_der(der),
+                    _ => RsaKeyPair::from_pkcs8(der),
+                }
+                .map_err(|e| eyre!("Invalid RSA private key: {e}"))?;
+                let public: RsaPublicKeyComponents<Vec<u8>> = key_pair.public().into();
+                let params = AlgorithmParameters::RSA(RSAKeyParameters {
+                    key_type: RSAKeyType::RSA,
+                    n: URL_SAFE_NO_PAD.encode(public.n),
+                    e: URL_SAFE_NO_PAD.encode(public.e),
+                });
+                (EncodingKey::from_rsa_pem(private_key_pem)?, params)
+            }
+            Algorithm::ES256 => {
+                let key_pair = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, der, &SystemRandom::new())
+                    .map_err(|e| eyre!("Invalid P-256 private key: {e}"))?;
+                // Uncompressed point: 0x04 || x || y
+                let point = key_pair.public_key().as_ref();
+                let params = AlgorithmParameters::EllipticCurve(EllipticCurveKeyParameters {
+                    key_type: EllipticCurveKeyType::EC,
+                    curve: EllipticCurve::P256,
+                    x: URL_SAFE_NO_PAD.encode(&point[1..33]),
+                    y: URL_SAFE_NO_PAD.encode(&point[33..65]),
+                });
+                (EncodingKey::from_ec_pem(private_key_pem)?, params)
+            }
+            Algorithm::EdDSA => {
+                let key_pair = Ed25519KeyPair::from_pkcs8_maybe_unchecked(der)
+                    .map_err(|e| eyre!("Invalid Ed25519 private key: {e}"))?;
+                let params = AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                    key_type: OctetKeyPairType::OctetKeyPair,
+                    curve: EllipticCurve::Ed25519,
+                    x: URL_SAFE_NO_PAD.encode(key_pair.public_key().as_ref()),
+                });
+                (EncodingKey::from_ed_pem(private_key_pem)?, params)
+            }
+            other => return Err(eyre!("Unsupported asymmetric JWT algorithm: {other:?}")),
+        };
+
+        let kid = kid.map(str::to_string).unwrap_or_else(|| thumbprint(&params));
+        let jwk = Jwk {
+            common: CommonParameters {
+                public_key_use: Some(PublicKeyUse::Signature),
+                key_algorithm: Some(key_algorithm(algorithm)?),
+                key_id: Some(kid.clone()),
+                ..Default::default()
+            },
+            algorithm: params,
+        };
+        let decoding_key = DecodingKey::from_jwk(&jwk)?;
+
+        Ok(Self {
+            kid,
+            algorithm,
+            encoding_key,
+            decoding_key,
+            jwk: Some(jwk),
+        })
+    }
+
+    /// Builds the signing key from `JWT_ALGORITHM`, falling back to the shared
+    /// `JWT_SECRET` when no asymmetric algorithm is configured.
+    pub fn from_env() -> Result<Self> {
+        let algorithm: Algorithm = JWT_ALGORITHM.parse()?;
+        if algorithm == Algorithm::HS256 {
+            let kid = JWT_KEY_ID.as_deref().unwrap_or(DEFAULT_HMAC_KEY_ID);
+            return Ok(Self::from_secret(kid, &JWT_SECRET));
+        }
+
+        let path = JWT_PRIVATE_KEY_PATH
+            .as_ref()
+            .ok_or(eyre!("JWT_PRIVATE_KEY_PATH must be set for {algorithm:?}"))?;
+        let private_key_pem = fs::read(path)?;
+        Self::from_pem(algorithm, &private_key_pem, JWT_KEY_ID.as_deref())
+    }
+
+    pub fn kid(&self) -> &str {
+        &self.kid
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    pub fn jwk(&self) -> Option<&Jwk> {
+        self.jwk.as_ref()
+    }
+
+    pub fn sign<T: Serialize>(&self, claims: &T) -> Result<String, jsonwebtoken::errors::Error> {
+        let mut header = Header::new(self.algorithm);
+        header.kid = Some(self.kid.clone());
+        encode(&header, claims, &self.encoding_key)
+    }
+
+    /// Verifies the token with this key only, rejecting tokens whose header names a
+    /// different `kid` or algorithm.
+    pub fn verify<T: DeserializeOwned>(&self, token: &str) -> Result<T> {
+        let header = decode_header(token)?;
+        if header.kid.as_deref() != Some(self.kid.as_str()) {
+            return Err(eyre!("Unknown signing key id: {:?}", header.kid));
+        }
+        let data = decode::<T>(token, &self.decoding_key, &Validation::new(self.algorithm))?;
+        Ok(data.claims)
+    }
+}
+
+/// The key ring file operators edit to add, promote and retire keys. `key_path`
+/// points at a PEM private key, or at the raw secret for `HS256` keys.
+#[derive(Debug, Deserialize)]
+pub struct KeyRingConfig {
+    pub active_kid: String,
+    pub keys: Vec<KeyConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct KeyConfig {
+    pub kid: String,
+    pub algorithm: Algorithm,
+    pub key_path: String,
+}
+
+impl KeyRingConfig {
+    pub fn from_file(path: &str) -> Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+}
+
+impl KeyConfig {
+    pub fn load(&self) -> Result<SigningKey> {
+        match self.algorithm {
+            Algorithm::HS256 => {
+                let secret = fs::read_to_string(&self.key_path)?.trim().to_string();
+                if secret.is_empty() {
+                    return Err(eyre!("HMAC secret for key {} must not be empty", self.kid));
+                }
+                Ok(SigningKey::from_secret(&self.kid, &Secret::new(secret)))
+            }
+            algorithm => SigningKey::from_pem(algorithm, &fs::read(&self.key_path)?, Some(&self.kid)),
+        }
+    }
+}
+
+fn key_algorithm(algorithm: Algorithm) -> Result<KeyAlgorithm> {
+    match algorithm {
+        Algorithm::RS256 => Ok(KeyAlgorithm::RS256),
+        Algorithm::ES256 => Ok(KeyAlgorithm::ES256),
+        Algorithm::EdDSA => Ok(KeyAlgorithm::EdDSA),
+        other => Err(eyre!("Unsupported asymmetric JWT algorithm: {other:?}")),
+    }
+}
+
+/// RFC 7638: SHA-256 over the required members in lexicographic order.
+fn thumbprint(params: &AlgorithmParameters) -> String {
+    let canonical = match params {
+        AlgorithmParameters::RSA(rsa) => format!(r#"{{"e":"{}","kty":"RSA","n":"{}"}}"#, rsa.e, rsa.n),
+        AlgorithmParameters::EllipticCurve(ec) => {
+            format!(r#"{{"crv":"P-256","kty":"EC","x":"{}","y":"{}"}}"#, ec.x, ec.y)
+        }
+        AlgorithmParameters::OctetKeyPair(okp) => format!(r#"{{"crv":"Ed25519","kty":"OKP","x":"{}"}}"#, okp.x),
+        AlgorithmParameters::OctetKey(oct) => format!(r#"{{"k":"{}","kty":"oct"}}"#, oct.value),
+    };
+    URL_SAFE_NO_PAD.encode(digest(&SHA256, canonical.as_bytes()))
+}
+
+//*******************************  TESTS  *******************************//
+
+#[cfg(test)]
+mod tests {
+    use jsonwebtoken::jwk::JwkSet;
+    use serde::Deserialize;
+
+    use super::*;
+
+    const RSA_PRIVATE_KEY: &[u8] = include_bytes!("../../tests/fixtures/jwt/rsa_private.pem");
+    const EC_PRIVATE_KEY: &[u8] = include_bytes!("../../tests/fixtures/jwt/ec_private.pem");
+    const ED25519_PRIVATE_KEY: &[u8] = include_bytes!("../../tests/fixtures/jwt/ed25519_private.pem");
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct TestClaims {
+        sub: String,
+        exp: u64,
+    }
+
+    fn test_claims() -> TestClaims {
+        TestClaims {
+            sub: "test@example.com".to_string(),
+            exp: (chrono::Utc::now().timestamp() + 600) as u64,
+        }
+    }
+
+    fn assert_round_trip_through_jwks(key: SigningKey) {
+        let claims = test_claims();
+        let token = key.sign(&claims).unwrap();
+
+        let header = decode_header(&token).unwrap();
+        assert_eq!(header.kid.as_deref(), Some(key.kid()));
+        assert_eq!(header.alg, key.algorithm());
+
+        // Verify the way another service would: using only the published JWK
+        let jwks: JwkSet = serde_json::from_value(serde_json::json!({ "keys": [key.jwk().unwrap()] })).unwrap();
+        let jwk = jwks.find(key.kid()).unwrap();
+        let decoded = decode::<TestClaims>(
+            &token,
+            &DecodingKey::from_jwk(jwk).unwrap(),
+            &Validation::new(key.algorithm()),
+        )
+        .unwrap();
+        assert_eq!(decoded.claims, claims);
+
+        assert_eq!(key.verify::<TestClaims>(&token).unwrap(), claims);
+    }
+
+    #[test]
+    fn test_rs256_key_signs_and_publishes_jwk() {
+        let key = SigningKey::from_pem(Algorithm::RS256, RSA_PRIVATE_KEY, None).unwrap();
+        assert!(matches!(key.jwk().unwrap().algorithm, AlgorithmParameters::RSA(_)));
+        assert_round_trip_through_jwks(key);
+    }
+
+    #[test]
+    fn test_es256_key_signs_and_publishes_jwk() {
+        let key = SigningKey::from_pem(Algorithm::ES256, EC_PRIVATE_KEY, None).unwrap();
+        assert!(matches!(
+            key.jwk().unwrap().algorithm,
+            AlgorithmParameters::EllipticCurve(_)
+        ));
+        assert_round_trip_through_jwks(key);
+    }
+
+    #[test]
+    fn test_eddsa_key_signs_and_publishes_jwk() {
+        let key = SigningKey::from_pem(Algorithm::EdDSA, ED25519_PRIVATE_KEY, None).unwrap();
+        assert!(matches!(
+            key.jwk().unwrap().algorithm,
+            AlgorithmParameters::OctetKeyPair(_)
+        ));
+        assert_round_trip_through_jwks(key);
+    }
+
+    #[test]
+    fn test_from_pem_uses_explicit_kid() {
+        let key = SigningKey::from_pem(Algorithm::ES256, EC_PRIVATE_KEY, Some("2024-01")).unwrap();
+        assert_eq!(key.kid(), "2024-01");
+        assert_eq!(key.jwk().unwrap().common.key_id.as_deref(), Some("2024-01"));
+    }
+
+    #[test]
+    fn test_from_pem_kid_defaults_to_stable_thumbprint() {
+        let first = SigningKey::from_pem(Algorithm::EdDSA, ED25519_PRIVATE_KEY, None).unwrap();
+        let second = SigningKey::from_pem(Algorithm::EdDSA, ED25519_PRIVATE_KEY, None).unwrap();
+        assert_eq!(first.kid(), second.kid());
+        assert_eq!(first.kid().len(), 43);
+    }
+
+    #[test]
+    fn test_from_pem_rejects_mismatched_algorithm() {
+        assert!(SigningKey::from_pem(Algorithm::ES256, RSA_PRIVATE_KEY, None).is_err());
+        assert!(SigningKey::from_pem(Algorithm::HS256, RSA_PRIVATE_KEY, None).is_err());
+    }
+
+    #[test]
+    fn test_hmac_key_has_no_public_jwk() {
+        let key = SigningKey::from_secret(DEFAULT_HMAC_KEY_ID, &Secret::new("secret".to_string()));
+        assert!(key.jwk().is_none());
+        let token = key.sign(&test_claims()).unwrap();
+        assert_eq!(decode_header(&token).unwrap().kid.as_deref(), Some(DEFAULT_HMAC_KEY_ID));
+    }
+
+    #[test]
+    fn test_key_config_loads_pem_and_hmac_keys() {
+        let fixtures = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/jwt");
+        let secret_path = std::env::temp_dir().join(format!("jwt-secret-{}", uuid::Uuid::new_v4()));
+        fs::write(&secret_path, "hmac-secret\n").unwrap();
+
+        let config: KeyRingConfig = serde_json::from_value(serde_json::json!({
+            "active_kid": "ec-1",
+            "keys": [
+                { "kid": "ec-1", "algorithm": "ES256", "key_path": format!("{fixtures}/ec_private.pem") },
+                { "kid": "hmac-1", "algorithm": "HS256", "key_path": secret_path },
+            ],
+        }))
+        .unwrap();
+        let keys: Vec<SigningKey> = config.keys.iter().map(|key| key.load().unwrap()).collect();
+        fs::remove_file(secret_path).unwrap();
+
+        assert_eq!(keys[0].kid(), "ec-1");
+        assert_eq!(keys[0].algorithm(), Algorithm::ES256);
+        assert_eq!(keys[1].kid(), "hmac-1");
+        assert!(keys[1].jwk().is_none());
+    }
+
+    #[test]
+    fn test_verify_rejects_token_signed_by_other_key() {
+        let rsa_key = SigningKey::from_pem(Algorithm::RS256, RSA_PRIVATE_KEY, None).unwrap();
+        let ec_key = SigningKey::from_pem(Algorithm::ES256, EC_PRIVATE_KEY, Some(rsa_key.kid())).unwrap();
+        let token = rsa_key.sign(&test_claims()).unwrap();
+        assert!(ec_key.verify::<TestClaims>(&token).is_err());
+    }
+}
@@ -1,3 +1,7 @@
 pub mod auth;
 pub mod constants;
+pub mod jwt_keys;
+pub mod password_hash;
+pub mod totp;
 pub mod tracing;
+pub mod webauthn;
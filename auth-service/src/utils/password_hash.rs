@@ -0,0 +1,210 @@
+use argon2::{Algorithm, Argon2, Params, PasswordHash, Version};
+use color_eyre::eyre::{eyre, Context, Result};
+use pbkdf2::Pbkdf2;
+use scrypt::Scrypt;
+
+use crate::utils::constants::{ARGON2_ITERATIONS, ARGON2_MEMORY_KIB, ARGON2_PARALLELISM};
+
+/// The Argon2 settings new password hashes are made with. Hashes made under any other settings still verify,
+/// and are replaced the next time their owner logs in.
+#[derive(Clone, Debug)]
+pub struct PasswordHashPolicy {
+    params: Params,
+}
+
+impl PasswordHashPolicy {
+    pub fn new(memory_kib: u32, iterations: u32, parallelism: u32) -> Result<Self> {
+        let params = Params::new(memory_kib, iterations, parallelism, None)
+            .map_err(|e| eyre!("Invalid Argon2 parameters: {e}"))?;
+        Ok(Self { params })
+    }
+
+    pub fn from_env() -> Result<Self> {
+        Self::new(*ARGON2_MEMORY_KIB, *ARGON2_ITERATIONS, *ARGON2_PARALLELISM)
+    }
+
+    pub fn hasher(&self) -> Argon2<'static> {
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, self.params.clone())
+    }
+
+    /// Whether `password_hash` was made under this policy. Anything else, including hashes from other
+    /// algorithms, is outdated.
+    pub fn is_current(&self, password_hash: &str) -> bool {
+        let Ok(hash) = PasswordHash::new(password_hash) else {
+            return false;
+        };
+        let Ok(params) = Params::try_from(&hash) else {
+            return false;
+        };
+        hash.algorithm == Algorithm::Argon2id.ident()
+            && hash.version == Some(Version::V0x13.into())
+            && params.m_cost() == self.params.m_cost()
+            && params.t_cost() == self.params.t_cost()
+            && params.p_cost() == self.params.p_cost()
+    }
+
+    /// How every hash made under this policy starts, so stores can count outdated hashes without parsing each.
+    pub fn hash_prefix(&self) -> String {
+        format!(
+            "${}$v={}$m={},t={},p={}$",
+            Algorithm::Argon2id.as_str(),
+            u32::from(Version::V0x13),
+            self.params.m_cost(),
+            self.params.t_cost(),
+            self.params.p_cost()
+        )
+    }
+}
+
+/// PHC algorithms a stored hash may use. Argon2 is what this service makes; the rest arrive with imported users.
+const SUPPORTED_PHC_ALGORITHMS: [&str; 6] = [
+    "argon2id",
+    "argon2i",
+    "argon2d",
+    "pbkdf2-sha256",
+    "pbkdf2-sha512",
+    "scrypt",
+];
+
+/// Whether `password_hash` is in a format [`verify_password_hash`] understands: a PHC string for one of the
+/// supported algorithms, or a bcrypt hash (`$2a$`, `$2b$`, `$2x$`, `$2y$`).
+pub fn is_supported_hash(password_hash: &str) -> bool {
+    if is_bcrypt_hash(password_hash) {
+        return true;
+    }
+    PasswordHash::new(password_hash)
+        .map(|hash| SUPPORTED_PHC_ALGORITHMS.contains(&hash.algorithm.as_str()))
+        .unwrap_or(false)
+}
+
+/// Checks `password` against a stored hash of any supported format. The algorithm and parameters come from the
+/// hash itself, so hashes from older policies and other systems still verify.
+pub fn verify_password_hash(password_hash: &str, password: &[u8]) -> Result<()> {
+    if is_bcrypt_hash(password_hash) {
+        return match bcrypt::verify(password, password_hash) {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(eyre!("Failed to verify password hash")),
+            Err(e) => Err(e).wrap_err("Failed to verify password hash"),
+        };
+    }
+    PasswordHash::new(password_hash)?
+        .verify_password(&[&Argon2::default(), &Pbkdf2, &Scrypt], password)
+        .wrap_err("Failed to verify password hash")
+}
+
+fn is_bcrypt_hash(password_hash: &str) -> bool {
+    ["$2a$", "$2b$", "$2x$", "$2y$"]
+        .iter()
+        .any(|prefix| password_hash.starts_with(prefix))
+        && password_hash.parse::<bcrypt::HashParts>().is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use argon2::{password_hash::SaltString, PasswordHasher};
+
+    use super::*;
+
+    fn hash_with(argon2: &Argon2) -> String {
+        let salt = SaltString::generate(&mut rand::thread_rng());
+        argon2.hash_password(b"P@ssw0rd", &salt).unwrap().to_string()
+    }
+
+    #[test]
+    fn test_hashes_from_policy_are_current() {
+        let policy = PasswordHashPolicy::new(15000, 2, 1).unwrap();
+        let hash = hash_with(&policy.hasher());
+
+        assert!(policy.is_current(&hash));
+        assert!(hash.starts_with(&policy.hash_prefix()));
+    }
+
+    #[test]
+    fn test_hashes_with_other_params_are_outdated() {
+        let policy = PasswordHashPolicy::new(15000, 3, 1).unwrap();
+        for hash in [
+            hash_with(&PasswordHashPolicy::new(15000, 2, 1).unwrap().hasher()),
+            hash_with(&Argon2::new(
+                Algorithm::Argon2i,
+                Version::V0x13,
+                Params::new(15000, 3, 1, None).unwrap(),
+            )),
+            hash_with(&Argon2::new(
+                Algorithm::Argon2id,
+                Version::V0x10,
+                Params::new(15000, 3, 1, None).unwrap(),
+            )),
+            "not a hash".to_string(),
+        ] {
+            assert!(!policy.is_current(&hash), "{hash}");
+            assert!(!hash.starts_with(&policy.hash_prefix()), "{hash}");
+        }
+    }
+
+    #[test]
+    fn test_legacy_hashes_verify_and_are_outdated() {
+        let policy = PasswordHashPolicy::new(15000, 2, 1).unwrap();
+        let salt = SaltString::generate(&mut rand::thread_rng());
+        let pbkdf2_params = pbkdf2::Params {
+            rounds: 1000,
+            output_length: 32,
+        };
+        for hash in [
+            bcrypt::hash("P@ssw0rd", 4).unwrap(),
+            Pbkdf2
+                .hash_password_customized(
+                    b"P@ssw0rd",
+                    Some(pbkdf2::Algorithm::Pbkdf2Sha256.ident()),
+                    None,
+                    pbkdf2_params,
+                    &salt,
+                )
+                .unwrap()
+                .to_string(),
+            Scrypt
+                .hash_password_customized(
+                    b"P@ssw0rd",
+                    None,
+                    None,
+                    scrypt::Params::new(4, 8, 1, 32).unwrap(),
+                    &salt,
+                )
+                .unwrap()
+                .to_string(),
+        ] {
+            assert!(is_supported_hash(&hash), "{hash}");
+            assert!(verify_password_hash(&hash, b"P@ssw0rd").is_ok(), "{hash}");
+            assert!(verify_password_hash(&hash, b"Wr0ngP@ssw0rd").is_err(), "{hash}");
+            assert!(!policy.is_current(&hash), "{hash}");
+            assert!(!hash.starts_with(&policy.hash_prefix()), "{hash}");
+        }
+    }
+
+    #[test]
+    fn test_argon2_hashes_verify() {
+        let hash = hash_with(&PasswordHashPolicy::new(8192, 1, 1).unwrap().hasher());
+
+        assert!(is_supported_hash(&hash));
+        assert!(verify_password_hash(&hash, b"P@ssw0rd").is_ok());
+        assert!(verify_password_hash(&hash, b"Wr0ngP@ssw0rd").is_err());
+    }
+
+    #[test]
+    fn test_unknown_hash_formats_are_unsupported() {
+        for hash in [
+            "P@ssw0rd",
+            "$1$saltsalt$qjXMvbEw8oaL.CzflDugX/",
+            "$2b$12$tooshort",
+            "$md5$salt$hash",
+        ] {
+            assert!(!is_supported_hash(hash), "{hash}");
+            assert!(verify_password_hash(hash, b"P@ssw0rd").is_err(), "{hash}");
+        }
+    }
+
+    #[test]
+    fn test_invalid_params_are_rejected() {
+        assert!(PasswordHashPolicy::new(1, 2, 1).is_err());
+        assert!(PasswordHashPolicy::new(15000, 0, 1).is_err());
+    }
+}
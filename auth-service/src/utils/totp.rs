@@ -0,0 +1,118 @@
+use color_eyre::eyre::{Context, Result};
+use data_encoding::BASE32_NOPAD;
+use rand::RngCore;
+use ring::hmac;
+use secrecy::{ExposeSecret, Secret};
+
+use crate::domain::email::Email;
+
+pub const TOTP_DIGITS: u32 = 6;
+pub const TOTP_PERIOD_SECONDS: u64 = 30;
+const TOTP_SECRET_BYTES: usize = 20;
+
+/// Generates a 160-bit secret, base32 encoded without padding as authenticator apps expect.
+pub fn generate_secret() -> Secret<String> {
+    let mut bytes = [0u8; TOTP_SECRET_BYTES];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    Secret::new(BASE32_NOPAD.encode(&bytes))
+}
+
+/// Key URI scanned by authenticator apps, e.g. `otpauth://totp/Issuer:user@example.com?secret=...`.
+pub fn otpauth_uri(secret: &Secret<String>, account: &Email, issuer: &str) -> Secret<String> {
+    let issuer = urlencoding::encode(issuer);
+    let account = urlencoding::encode(account.as_ref().expose_secret());
+    Secret::new(format!(
+        "otpauth://totp/{issuer}:{account}?secret={}&issuer={issuer}&algorithm=SHA1&digits={TOTP_DIGITS}&period={TOTP_PERIOD_SECONDS}",
+        secret.expose_secret()
+    ))
+}
+
+pub fn time_step(unix_seconds: u64) -> u64 {
+    unix_seconds / TOTP_PERIOD_SECONDS
+}
+
+/// RFC 6238 code for `step`, using HMAC-SHA1 and dynamic truncation from RFC 4226.
+pub fn code_at_step(key: &[u8], step: u64) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY, key);
+    let tag = hmac::sign(&key, &step.to_be_bytes());
+    let digest = tag.as_ref();
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        digest[offset],
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]) & 0x7fff_ffff;
+    format!(
+        "{:0width$}",
+        binary % 10u32.pow(TOTP_DIGITS),
+        width = TOTP_DIGITS as usize
+    )
+}
+
+/// Returns the time step `code` was generated for, accepting up to `skew` steps either side of `unix_seconds`.
+pub fn verify_code(secret: &Secret<String>, code: &str, unix_seconds: u64, skew: u64) -> Result<Option<u64>> {
+    let key = BASE32_NOPAD
+        .decode(secret.expose_secret().as_bytes())
+        .wrap_err("Invalid TOTP secret")?;
+    let current = time_step(unix_seconds);
+    let matched =
+        (current.saturating_sub(skew)..=current.saturating_add(skew)).find(|step| code_at_step(&key, *step) == code);
+    Ok(matched)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 6238 Appendix B, SHA1 key, truncated to 6 digits
+    const RFC_KEY: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn test_code_at_step_matches_rfc_6238_vectors() {
+        for (unix_seconds, code) in [
+            (59, "287082"),
+            (1111111109, "081804"),
+            (1111111111, "050471"),
+            (1234567890, "005924"),
+            (2000000000, "279037"),
+            (20000000000, "353130"),
+        ] {
+            assert_eq!(code_at_step(RFC_KEY, time_step(unix_seconds)), code);
+        }
+    }
+
+    #[test]
+    fn test_verify_code_accepts_codes_within_skew() {
+        let secret = Secret::new(BASE32_NOPAD.encode(RFC_KEY));
+        let now = 1234567890;
+        let previous = code_at_step(RFC_KEY, time_step(now) - 1);
+        let too_old = code_at_step(RFC_KEY, time_step(now) - 2);
+
+        assert_eq!(verify_code(&secret, "005924", now, 0).unwrap(), Some(time_step(now)));
+        assert_eq!(
+            verify_code(&secret, &previous, now, 1).unwrap(),
+            Some(time_step(now) - 1)
+        );
+        assert_eq!(verify_code(&secret, &previous, now, 0).unwrap(), None);
+        assert_eq!(verify_code(&secret, &too_old, now, 1).unwrap(), None);
+    }
+
+    #[test]
+    fn test_generated_secret_round_trips() {
+        let secret = generate_secret();
+        let key = BASE32_NOPAD.decode(secret.expose_secret().as_bytes()).unwrap();
+        assert_eq!(key.len(), TOTP_SECRET_BYTES);
+    }
+
+    #[test]
+    fn test_otpauth_uri() {
+        let secret = Secret::new("JBSWY3DPEHPK3PXP".to_string());
+        let email = Email::parse(Secret::new("user@example.com".to_string())).unwrap();
+        let uri = otpauth_uri(&secret, &email, "Auth Service");
+        assert_eq!(
+            uri.expose_secret(),
+            "otpauth://totp/Auth%20Service:user%40example.com?secret=JBSWY3DPEHPK3PXP&issuer=Auth%20Service&algorithm=SHA1&digits=6&period=30"
+        );
+    }
+}
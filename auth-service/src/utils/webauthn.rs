@@ -0,0 +1,325 @@
+use std::io::Cursor;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ciborium::Value;
+use color_eyre::eyre::{eyre, Context, Result};
+use ring::{
+    digest::{digest, SHA256},
+    signature::{self, RsaPublicKeyComponents, UnparsedPublicKey},
+};
+use serde::Deserialize;
+
+pub const FLAG_USER_PRESENT: u8 = 0x01;
+pub const FLAG_USER_VERIFIED: u8 = 0x04;
+pub const FLAG_ATTESTED_CREDENTIAL_DATA: u8 = 0x40;
+
+pub const COSE_ALG_ES256: i64 = -7;
+pub const COSE_ALG_RS256: i64 = -257;
+
+const COSE_KTY_EC2: i64 = 2;
+const COSE_KTY_RSA: i64 = 3;
+const COSE_CRV_P256: i64 = 1;
+
+/// Browsers may or may not pad base64url values, so accept both.
+pub fn decode_base64url(value: &str) -> Result<Vec<u8>> {
+    URL_SAFE_NO_PAD
+        .decode(value.trim_end_matches('='))
+        .wrap_err("Invalid base64url value")
+}
+
+pub fn encode_base64url(bytes: &[u8]) -> String {
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// The `clientDataJSON` the browser builds and the authenticator signs over.
+#[derive(Debug, Deserialize)]
+pub struct ClientData {
+    #[serde(rename = "type")]
+    pub ceremony_type: String,
+    pub challenge: String,
+    pub origin: String,
+}
+
+impl ClientData {
+    pub fn parse(client_data_json: &[u8]) -> Result<Self> {
+        serde_json::from_slice(client_data_json).wrap_err("Invalid client data")
+    }
+
+    /// The challenge itself is checked by looking it up in the challenge store.
+    pub fn verify(&self, ceremony_type: &str, origin: &str) -> Result<()> {
+        if self.ceremony_type != ceremony_type {
+            return Err(eyre!("Unexpected ceremony type {}", self.ceremony_type));
+        }
+        if self.origin != origin {
+            return Err(eyre!("Unexpected origin {}", self.origin));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct AttestedCredential {
+    pub credential_id: Vec<u8>,
+    /// The COSE_Key exactly as the authenticator encoded it
+    pub public_key: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct AuthenticatorData {
+    pub rp_id_hash: Vec<u8>,
+    pub flags: u8,
+    pub sign_count: u32,
+    pub attested_credential: Option<AttestedCredential>,
+}
+
+impl AuthenticatorData {
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 37 {
+            return Err(eyre!("Authenticator data is too short"));
+        }
+        let rp_id_hash = bytes[..32].to_vec();
+        let flags = bytes[32];
+        let sign_count = u32::from_be_bytes([bytes[33], bytes[34], bytes[35], bytes[36]]);
+
+        let attested_credential = match flags & FLAG_ATTESTED_CREDENTIAL_DATA {
+            0 => None,
+            _ => Some(parse_attested_credential(&bytes[37..])?),
+        };
+
+        Ok(Self {
+            rp_id_hash,
+            flags,
+            sign_count,
+            attested_credential,
+        })
+    }
+
+    /// Registration asks for `none` attestation, so only the authenticator data is read and the attestation
+    /// statement is ignored.
+    pub fn from_attestation_object(attestation_object: &[u8]) -> Result<Self> {
+        let value: Value = ciborium::de::from_reader(attestation_object).wrap_err("Invalid attestation object")?;
+        let auth_data = value
+            .as_map()
+            .and_then(|map| {
+                map.iter()
+                    .find(|(key, _)| key.as_text() == Some("authData"))
+                    .and_then(|(_, value)| value.as_bytes())
+            })
+            .ok_or_else(|| eyre!("Attestation object has no authenticator data"))?;
+        Self::parse(auth_data)
+    }
+
+    pub fn verify(&self, rp_id: &str, require_user_verification: bool) -> Result<()> {
+        if self.rp_id_hash != digest(&SHA256, rp_id.as_bytes()).as_ref() {
+            return Err(eyre!("Unexpected relying party id hash"));
+        }
+        if self.flags & FLAG_USER_PRESENT == 0 {
+            return Err(eyre!("User was not present"));
+        }
+        if require_user_verification && self.flags & FLAG_USER_VERIFIED == 0 {
+            return Err(eyre!("User was not verified"));
+        }
+        Ok(())
+    }
+}
+
+fn parse_attested_credential(bytes: &[u8]) -> Result<AttestedCredential> {
+    // 16 byte AAGUID, then a 2 byte credential id length
+    if bytes.len() < 18 {
+        return Err(eyre!("Attested credential data is too short"));
+    }
+    let id_length = u16::from_be_bytes([bytes[16], bytes[17]]) as usize;
+    let credential_id = bytes
+        .get(18..18 + id_length)
+        .ok_or_else(|| eyre!("Credential id is truncated"))?
+        .to_vec();
+
+    // The COSE key has no length prefix, so decode it to find where it ends
+    let key_bytes = &bytes[18 + id_length..];
+    let mut cursor = Cursor::new(key_bytes);
+    let _: Value = ciborium::de::from_reader(&mut cursor).wrap_err("Invalid credential public key")?;
+    let public_key = key_bytes[..cursor.position() as usize].to_vec();
+
+    Ok(AttestedCredential {
+        credential_id,
+        public_key,
+    })
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CosePublicKey {
+    Es256 { point: Vec<u8> },
+    Rs256 { n: Vec<u8>, e: Vec<u8> },
+}
+
+impl CosePublicKey {
+    pub fn parse(cose_key: &[u8]) -> Result<Self> {
+        let value: Value = ciborium::de::from_reader(cose_key).wrap_err("Invalid COSE key")?;
+        let map = value.as_map().ok_or_else(|| eyre!("COSE key is not a map"))?;
+        let int_param = |label: i64| map_get(map, label).and_then(value_to_i64);
+        let bytes_param = |label: i64| {
+            map_get(map, label)
+                .and_then(Value::as_bytes)
+                .cloned()
+                .ok_or_else(|| eyre!("COSE key is missing parameter {label}"))
+        };
+
+        match (int_param(1), int_param(3)) {
+            (Some(COSE_KTY_EC2), Some(COSE_ALG_ES256)) => {
+                if int_param(-1) != Some(COSE_CRV_P256) {
+                    return Err(eyre!("Unsupported elliptic curve"));
+                }
+                let (x, y) = (bytes_param(-2)?, bytes_param(-3)?);
+                if x.len() != 32 || y.len() != 32 {
+                    return Err(eyre!("Invalid P-256 coordinates"));
+                }
+                let point = [&[0x04][..], &x, &y].concat();
+                Ok(CosePublicKey::Es256 { point })
+            }
+            (Some(COSE_KTY_RSA), Some(COSE_ALG_RS256)) => Ok(CosePublicKey::Rs256 {
+                n: bytes_param(-1)?,
+                e: bytes_param(-2)?,
+            }),
+            (kty, alg) => Err(eyre!("Unsupported COSE key type {kty:?} with algorithm {alg:?}")),
+        }
+    }
+
+    pub fn verify(&self, message: &[u8], signature: &[u8]) -> Result<()> {
+        let result = match self {
+            CosePublicKey::Es256 { point } => {
+                UnparsedPublicKey::new(&signature::ECDSA_P256_SHA256_ASN1, point).verify(message, signature)
+            }
+            CosePublicKey::Rs256 { n, e } => {
+                RsaPublicKeyComponents { n, e }.verify(&signature::RSA_PKCS1_2048_8192_SHA256, message, signature)
+            }
+        };
+        result.map_err(|_| eyre!("Invalid signature"))
+    }
+}
+
+/// Assertion signatures cover the authenticator data followed by the SHA-256 of the client data JSON.
+pub fn verify_assertion_signature(
+    cose_key: &[u8],
+    authenticator_data: &[u8],
+    client_data_json: &[u8],
+    signature: &[u8],
+) -> Result<()> {
+    let signed_data = [authenticator_data, digest(&SHA256, client_data_json).as_ref()].concat();
+    CosePublicKey::parse(cose_key)?.verify(&signed_data, signature)
+}
+
+fn map_get(map: &[(Value, Value)], label: i64) -> Option<&Value> {
+    map.iter()
+        .find(|(key, _)| value_to_i64(key) == Some(label))
+        .map(|(_, value)| value)
+}
+
+fn value_to_i64(value: &Value) -> Option<i64> {
+    value.as_integer().and_then(|int| i64::try_from(int).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use ring::{
+        rand::SystemRandom,
+        signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING},
+    };
+
+    use super::*;
+
+    const RP_ID: &str = "localhost";
+
+    fn es256_key_pair() -> EcdsaKeyPair {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng).unwrap();
+        EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8.as_ref(), &rng).unwrap()
+    }
+
+    fn cose_key(key_pair: &EcdsaKeyPair) -> Vec<u8> {
+        let point = key_pair.public_key().as_ref();
+        let map = Value::Map(vec![
+            (Value::from(1), Value::from(COSE_KTY_EC2)),
+            (Value::from(3), Value::from(COSE_ALG_ES256)),
+            (Value::from(-1), Value::from(COSE_CRV_P256)),
+            (Value::from(-2), Value::Bytes(point[1..33].to_vec())),
+            (Value::from(-3), Value::Bytes(point[33..].to_vec())),
+        ]);
+        let mut bytes = Vec::new();
+        ciborium::ser::into_writer(&map, &mut bytes).unwrap();
+        bytes
+    }
+
+    fn authenticator_data(flags: u8, attested: Option<(&[u8], &[u8])>) -> Vec<u8> {
+        let mut data = digest(&SHA256, RP_ID.as_bytes()).as_ref().to_vec();
+        data.push(flags);
+        data.extend_from_slice(&7u32.to_be_bytes());
+        if let Some((credential_id, public_key)) = attested {
+            data.extend_from_slice(&[0u8; 16]);
+            data.extend_from_slice(&(credential_id.len() as u16).to_be_bytes());
+            data.extend_from_slice(credential_id);
+            data.extend_from_slice(public_key);
+        }
+        data
+    }
+
+    #[test]
+    fn test_parse_attested_credential() {
+        let key_pair = es256_key_pair();
+        let public_key = cose_key(&key_pair);
+        let flags = FLAG_USER_PRESENT | FLAG_ATTESTED_CREDENTIAL_DATA;
+        let data = authenticator_data(flags, Some((b"credential", &public_key)));
+
+        let parsed = AuthenticatorData::parse(&data).unwrap();
+        assert_eq!(parsed.sign_count, 7);
+        let credential = parsed.attested_credential.unwrap();
+        assert_eq!(credential.credential_id, b"credential");
+        assert_eq!(credential.public_key, public_key);
+        assert!(matches!(
+            CosePublicKey::parse(&credential.public_key).unwrap(),
+            CosePublicKey::Es256 { .. }
+        ));
+    }
+
+    #[test]
+    fn test_parse_rejects_truncated_data() {
+        assert!(AuthenticatorData::parse(&[0u8; 36]).is_err());
+
+        let data = authenticator_data(FLAG_USER_PRESENT | FLAG_ATTESTED_CREDENTIAL_DATA, None);
+        assert!(AuthenticatorData::parse(&data).is_err());
+    }
+
+    #[test]
+    fn test_verify_checks_rp_id_and_flags() {
+        let data = AuthenticatorData::parse(&authenticator_data(FLAG_USER_PRESENT, None)).unwrap();
+        assert!(data.verify(RP_ID, false).is_ok());
+        assert!(data.verify(RP_ID, true).is_err());
+        assert!(data.verify("example.com", false).is_err());
+
+        let data = AuthenticatorData::parse(&authenticator_data(0, None)).unwrap();
+        assert!(data.verify(RP_ID, false).is_err());
+    }
+
+    #[test]
+    fn test_verify_assertion_signature() {
+        let key_pair = es256_key_pair();
+        let public_key = cose_key(&key_pair);
+        let auth_data = authenticator_data(FLAG_USER_PRESENT | FLAG_USER_VERIFIED, None);
+        let client_data = br#"{"type":"webauthn.get","challenge":"abc","origin":"http://localhost"}"#;
+
+        let signed_data = [&auth_data[..], digest(&SHA256, client_data).as_ref()].concat();
+        let signature = key_pair.sign(&SystemRandom::new(), &signed_data).unwrap();
+
+        assert!(verify_assertion_signature(&public_key, &auth_data, client_data, signature.as_ref()).is_ok());
+        assert!(verify_assertion_signature(&public_key, &auth_data, b"{}", signature.as_ref()).is_err());
+    }
+
+    #[test]
+    fn test_client_data_verify() {
+        let client_data =
+            ClientData::parse(br#"{"type":"webauthn.create","challenge":"abc","origin":"http://localhost"}"#).unwrap();
+        assert_eq!(client_data.challenge, "abc");
+        assert!(client_data.verify("webauthn.create", "http://localhost").is_ok());
+        assert!(client_data.verify("webauthn.get", "http://localhost").is_err());
+        assert!(client_data.verify("webauthn.create", "https://evil.example").is_err());
+    }
+}
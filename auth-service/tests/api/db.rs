@@ -1,5 +1,6 @@
 use std::{fmt, ops::Deref, str::FromStr, sync::Arc};
 
+use argon2::{password_hash::SaltString, Algorithm, Argon2, Params, PasswordHasher, Version};
 use secrecy::{ExposeSecret, Secret};
 use sqlx::{
     postgres::{PgConnectOptions, PgPoolOptions},
@@ -119,6 +120,57 @@ pub async fn delete_database(db_name: &str) -> Result<(), String> {
     Ok(())
 }
 
+/// Replaces the user's password hash with one made under lighter Argon2 settings than the app's, as if it were
+/// stored before the settings changed.
+pub async fn set_outdated_password_hash(db_name: &str, email: &str, password: &str) {
+    let salt = SaltString::generate(&mut rand::thread_rng());
+    let password_hash = Argon2::new(
+        Algorithm::Argon2id,
+        Version::V0x13,
+        Params::new(8192, 1, 1, None).unwrap(),
+    )
+    .hash_password(password.as_bytes(), &salt)
+    .expect("Failed to hash password.")
+    .to_string();
+
+    let db_conn_string = format!("{}/{}", test::DATABASE_URL, db_name);
+    let mut connection = PgConnection::connect(&db_conn_string)
+        .await
+        .expect("Failed to connect to Postgres");
+    sqlx::query("UPDATE users SET password_hash = $1 WHERE email = $2")
+        .bind(password_hash)
+        .bind(email)
+        .execute(&mut connection)
+        .await
+        .expect("Failed to update password hash.");
+}
+
+/// Clears the user's email verification, as if they had never followed the link.
+pub async fn set_email_unverified(db_name: &str, email: &str) {
+    let db_conn_string = format!("{}/{}", test::DATABASE_URL, db_name);
+    let mut connection = PgConnection::connect(&db_conn_string)
+        .await
+        .expect("Failed to connect to Postgres");
+    sqlx::query("UPDATE users SET verified_at = NULL WHERE email = $1")
+        .bind(email)
+        .execute(&mut connection)
+        .await
+        .expect("Failed to clear email verification.");
+}
+
+/// Whether the user's row is marked for deletion.
+pub async fn is_deletion_scheduled(db_name: &str, email: &str) -> bool {
+    let db_conn_string = format!("{}/{}", test::DATABASE_URL, db_name);
+    let mut connection = PgConnection::connect(&db_conn_string)
+        .await
+        .expect("Failed to connect to Postgres");
+    sqlx::query_scalar("SELECT deleted_at IS NOT NULL FROM users WHERE email = $1")
+        .bind(email)
+        .fetch_one(&mut connection)
+        .await
+        .expect("Failed to read deletion state.")
+}
+
 pub async fn configure_redis() -> Arc<RwLock<redis::aio::ConnectionManager>> {
     let redis_hostname = test::REDIS_HOST_NAME.to_string();
     let redis_password = Some(REDIS_PASSWORD.to_owned());
@@ -0,0 +1,116 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use secrecy::{ExposeSecret, Secret};
+use tonic::Request;
+use uuid::Uuid;
+
+use auth_proto::IntrospectTokenRequest;
+use auth_service::{
+    domain::{data_stores::TrustedDevice, email::Email, user::UserAccess},
+    utils::{
+        auth::{generate_auth_token, trust_device},
+        constants::{INTROSPECTION_CLIENT_ID, INTROSPECTION_CLIENT_SECRET},
+    },
+};
+
+use crate::helpers::{get_random_email, GRPCTestApp};
+
+fn introspect_request(token: &str, client_secret: &str) -> Request<IntrospectTokenRequest> {
+    let mut request = Request::new(IntrospectTokenRequest {
+        token: token.to_string(),
+    });
+    let credentials = STANDARD.encode(format!("{}:{client_secret}", INTROSPECTION_CLIENT_ID.as_str()));
+    request
+        .metadata_mut()
+        .insert("authorization", format!("Basic {credentials}").parse().unwrap());
+    request
+}
+
+#[tokio::test]
+async fn grpc_introspect_returns_claims_for_active_token() {
+    let mut app = GRPCTestApp::new().await;
+    let email = get_random_email();
+    let token = generate_auth_token(
+        &Email::parse(Secret::new(email.clone())).unwrap(),
+        &Uuid::new_v4(),
+        &UserAccess::default(),
+    )
+    .unwrap();
+
+    let response = app
+        .client
+        .introspect_token(introspect_request(
+            token.expose_secret(),
+            INTROSPECTION_CLIENT_SECRET.expose_secret(),
+        ))
+        .await
+        .expect("[ERROR][grpc_introspect_returns_claims_for_active_token] Request failed")
+        .into_inner();
+
+    assert!(response.active);
+    assert_eq!(response.sub, email);
+    assert_eq!(response.purpose, "Auth");
+    assert!(response.exp > response.iat);
+    assert!(!response.jti.is_empty());
+}
+
+#[tokio::test]
+async fn grpc_introspect_returns_inactive_for_malformed_token() {
+    let mut app = GRPCTestApp::new().await;
+
+    let response = app
+        .client
+        .introspect_token(introspect_request(
+            "malformed",
+            INTROSPECTION_CLIENT_SECRET.expose_secret(),
+        ))
+        .await
+        .unwrap()
+        .into_inner();
+
+    assert_eq!(response, Default::default());
+}
+
+#[tokio::test]
+async fn grpc_introspect_returns_inactive_for_trusted_device_token() {
+    let mut app = GRPCTestApp::new().await;
+    let email = Email::parse(Secret::new(get_random_email())).unwrap();
+    let cookie = trust_device(
+        app.app_state.trusted_device_store.clone(),
+        TrustedDevice::new(email, None, None),
+    )
+    .await
+    .unwrap();
+
+    let response = app
+        .client
+        .introspect_token(introspect_request(
+            cookie.value(),
+            INTROSPECTION_CLIENT_SECRET.expose_secret(),
+        ))
+        .await
+        .unwrap()
+        .into_inner();
+
+    assert_eq!(response, Default::default());
+}
+
+#[tokio::test]
+async fn grpc_introspect_fails_with_invalid_client_credentials() {
+    let mut app = GRPCTestApp::new().await;
+
+    let error = app
+        .client
+        .introspect_token(introspect_request("malformed", "wrong-secret"))
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), tonic::Code::Unauthenticated);
+
+    let error = app
+        .client
+        .introspect_token(Request::new(IntrospectTokenRequest {
+            token: "malformed".to_string(),
+        }))
+        .await
+        .unwrap_err();
+    assert_eq!(error.code(), tonic::Code::Unauthenticated);
+}
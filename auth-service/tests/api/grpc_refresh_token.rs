@@ -0,0 +1,91 @@
+use secrecy::{ExposeSecret, Secret};
+use tonic::Request;
+
+use auth_proto::RefreshTokenRequest;
+use auth_service::{
+    domain::{
+        data_stores::{Session, UserStore},
+        email::Email,
+        password::Password,
+        user::{NewUser, TwoFAMethod},
+    },
+    utils::auth::{start_session, validate_token_structure},
+};
+
+use crate::helpers::{get_random_email, GRPCTestApp};
+
+async fn create_app_with_refresh_token() -> (GRPCTestApp, Email, String) {
+    let app = GRPCTestApp::new().await;
+    let email = Email::parse(Secret::new(get_random_email())).unwrap();
+    let password = Password::parse(Secret::new("P@ssw0rd".to_string())).await.unwrap();
+    app.app_state
+        .user_store
+        .write()
+        .await
+        .add_user(NewUser::new(email.clone(), password, TwoFAMethod::None))
+        .await
+        .unwrap();
+    let access = app.app_state.user_store.read().await.get_access(&email).await.unwrap();
+    let (_, refresh_cookie) = start_session(
+        app.app_state.session_store.clone(),
+        app.app_state.refresh_token_store.clone(),
+        Session::new(email.clone(), None, None),
+        &access,
+    )
+    .await
+    .expect("[ERROR][grpc_refresh_token] Failed to start session");
+
+    (app, email, refresh_cookie.value().to_owned())
+}
+
+#[tokio::test]
+async fn grpc_refresh_token_returns_new_tokens_for_valid_refresh_token() {
+    let (mut app, email, refresh_token) = create_app_with_refresh_token().await;
+
+    let request = Request::new(RefreshTokenRequest {
+        refresh_token: refresh_token.clone(),
+    });
+    let response = app
+        .client
+        .refresh_token(request)
+        .await
+        .expect("[ERROR][grpc_refresh_token_returns_new_tokens_for_valid_refresh_token] Request failed")
+        .into_inner();
+
+    assert_ne!(response.refresh_token, refresh_token);
+    let claims = validate_token_structure(&response.access_token).await.unwrap();
+    assert_eq!(claims.sub.expose_secret(), email.as_ref().expose_secret());
+    assert!(claims.sid.is_some());
+    assert_eq!(claims.scopes, vec!["users:self"]);
+}
+
+#[tokio::test]
+async fn grpc_refresh_token_fails_when_refresh_token_reused() {
+    let (mut app, _, refresh_token) = create_app_with_refresh_token().await;
+
+    let request = Request::new(RefreshTokenRequest {
+        refresh_token: refresh_token.clone(),
+    });
+    let rotated = app.client.refresh_token(request).await.unwrap().into_inner();
+
+    let request = Request::new(RefreshTokenRequest { refresh_token });
+    let error = app.client.refresh_token(request).await.unwrap_err();
+    assert_eq!(error.code(), tonic::Code::Unauthenticated);
+
+    let request = Request::new(RefreshTokenRequest {
+        refresh_token: rotated.refresh_token,
+    });
+    let error = app.client.refresh_token(request).await.unwrap_err();
+    assert_eq!(error.code(), tonic::Code::Unauthenticated);
+}
+
+#[tokio::test]
+async fn grpc_refresh_token_fails_with_invalid_refresh_token() {
+    let mut app = GRPCTestApp::new().await;
+
+    let request = Request::new(RefreshTokenRequest {
+        refresh_token: "invalid".to_string(),
+    });
+    let error = app.client.refresh_token(request).await.unwrap_err();
+    assert_eq!(error.code(), tonic::Code::Unauthenticated);
+}
@@ -2,9 +2,15 @@ use std::sync::Arc;
 use std::{fmt, panic};
 
 use auth_service::services::data_stores::redis_banned_token_store::RedisBannedTokenStore;
+use auth_service::services::data_stores::redis_login_failure_store::RedisLoginFailureStore;
 use auth_service::services::data_stores::redis_password_reset_token_store::RedisPasswordResetTokenStore;
-use auth_service::services::data_stores::redis_two_fa_code_store::RedisTwoFACodeStore;
+use auth_service::services::data_stores::redis_refresh_token_store::RedisRefreshTokenStore;
+use auth_service::services::data_stores::redis_session_store::RedisSessionStore;
+use auth_service::services::data_stores::redis_trusted_device_store::RedisTrustedDeviceStore;
+use auth_service::services::data_stores::redis_two_fa_code_store::{RedisTwoFACodeStore, PHONE_VERIFICATION_NAMESPACE};
+use auth_service::services::data_stores::redis_webauthn_challenge_store::RedisWebAuthnChallengeStore;
 use auth_service::services::postmark_email_client::PostmarkEmailClient;
+use auth_service::services::twilio_sms_client::TwilioSmsClient;
 use reqwest::cookie::Jar;
 use reqwest::Client;
 use secrecy::Secret;
@@ -20,6 +26,7 @@ use auth_service::{
     domain::{
         data_stores::{PasswordResetTokenStore, UserStore, UserStoreError},
         email::Email,
+        phone_number::PhoneNumber,
         user::User,
     },
     services::{
@@ -27,12 +34,21 @@ use auth_service::{
         concrete_app_services::{MemoryAppStateType, PersistentAppStateType},
         data_stores::postgres_user_store::PostgresUserStore,
         hashmap_banned_token_store::HashMapBannedTokenStore,
+        hashmap_login_failure_store::HashMapLoginFailureStore,
         hashmap_password_reset_token_store::HashMapPasswordResetTokenStore,
+        hashmap_refresh_token_store::HashMapRefreshTokenStore,
+        hashmap_session_store::HashMapSessionStore,
+        hashmap_trusted_device_store::HashMapTrustedDeviceStore,
         hashmap_two_fa_code_store::HashMapTwoFACodeStore,
         hashmap_user_store::HashmapUserStore,
+        hashmap_webauthn_challenge_store::HashMapWebAuthnChallengeStore,
         mock_email_client::MockEmailClient,
+        mock_sms_client::MockSmsClient,
+    },
+    utils::{
+        auth::EmailVerificationToken,
+        constants::{test, INTROSPECTION_CLIENT_ID, JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME},
     },
-    utils::constants::{test, JWT_COOKIE_NAME},
     GRPCApp, RESTApp,
 };
 use wiremock::MockServer;
@@ -46,6 +62,7 @@ pub struct RESTTestApp {
     pub app_state: PersistentAppStateType,
     pub test_db_name: String,
     pub email_server: MockServer,
+    pub sms_server: MockServer,
     clean_up_called: bool,
 }
 
@@ -55,12 +72,20 @@ impl RESTTestApp {
         let user_store = PostgresUserStore::new(pg_pool);
         let redis_conn = configure_redis().await;
         let email_server = MockServer::start().await;
+        let sms_server = MockServer::start().await;
         let app_state = AppState::new_arc(
             RedisBannedTokenStore::new(redis_conn.clone()),
             user_store,
             RedisTwoFACodeStore::new(redis_conn.clone()),
+            RedisTwoFACodeStore::with_namespace(redis_conn.clone(), PHONE_VERIFICATION_NAMESPACE),
             configure_postmark_email_client(email_server.uri()),
             RedisPasswordResetTokenStore::new(redis_conn.clone()),
+            RedisRefreshTokenStore::new(redis_conn.clone()),
+            RedisSessionStore::new(redis_conn.clone()),
+            RedisWebAuthnChallengeStore::new(redis_conn.clone()),
+            RedisTrustedDeviceStore::new(redis_conn.clone()),
+            configure_twilio_sms_client(sms_server.uri()),
+            RedisLoginFailureStore::new(redis_conn.clone()),
         );
         let address = String::from(test::APP_REST_ADDRESS);
 
@@ -88,6 +113,7 @@ impl RESTTestApp {
             app_state: app_state.clone(),
             test_db_name: db_name.to_string(),
             email_server,
+            sms_server,
             clean_up_called: false,
         }
     }
@@ -109,6 +135,159 @@ impl RESTTestApp {
             .expect("[RESTTestApp][post_signup] Failed to execute request.")
     }
 
+    pub async fn get_verify_email(&self, token: &str) -> reqwest::Response {
+        let client_url = format!("{}/verify-email", &self.address);
+        println!("[RESTTestApp][get_verify_email] Client URL: {client_url}");
+        self.http_client
+            .get(client_url)
+            .query(&[("token", token)])
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][get_verify_email] Failed to execute request.")
+    }
+
+    pub async fn post_resend_verification<Body: Serialize>(&self, body: &Body) -> reqwest::Response {
+        let client_url = format!("{}/resend-verification", &self.address);
+        println!("[RESTTestApp][post_resend_verification] Client URL: {client_url}");
+        self.http_client
+            .post(client_url)
+            .json(body)
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][post_resend_verification] Failed to execute request.")
+    }
+
+    pub async fn post_change_email<Body: Serialize>(&self, body: &Body) -> reqwest::Response {
+        let client_url = format!("{}/change-email", &self.address);
+        println!("[RESTTestApp][post_change_email] Client URL: {client_url}");
+        self.http_client
+            .post(client_url)
+            .json(body)
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][post_change_email] Failed to execute request.")
+    }
+
+    pub async fn get_change_email_confirm(&self, token: &str) -> reqwest::Response {
+        let client_url = format!("{}/change-email/confirm", &self.address);
+        println!("[RESTTestApp][get_change_email_confirm] Client URL: {client_url}");
+        self.http_client
+            .get(client_url)
+            .query(&[("token", token)])
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][get_change_email_confirm] Failed to execute request.")
+    }
+
+    pub async fn post_change_email_confirm(&self, token: &str) -> reqwest::Response {
+        let client_url = format!("{}/change-email/confirm", &self.address);
+        println!("[RESTTestApp][post_change_email_confirm] Client URL: {client_url}");
+        self.http_client
+            .post(client_url)
+            .json(&json!({ "token": token }))
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][post_change_email_confirm] Failed to execute request.")
+    }
+
+    pub async fn get_change_email_revert(&self, token: &str) -> reqwest::Response {
+        let client_url = format!("{}/change-email/revert", &self.address);
+        println!("[RESTTestApp][get_change_email_revert] Client URL: {client_url}");
+        self.http_client
+            .get(client_url)
+            .query(&[("token", token)])
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][get_change_email_revert] Failed to execute request.")
+    }
+
+    pub async fn post_change_email_revert(&self, token: &str) -> reqwest::Response {
+        let client_url = format!("{}/change-email/revert", &self.address);
+        println!("[RESTTestApp][post_change_email_revert] Client URL: {client_url}");
+        self.http_client
+            .post(client_url)
+            .json(&json!({ "token": token }))
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][post_change_email_revert] Failed to execute request.")
+    }
+
+    pub async fn delete_account<Body: Serialize>(&self, body: &Body) -> reqwest::Response {
+        let client_url = format!("{}/account", &self.address);
+        println!("[RESTTestApp][delete_account] Client URL: {client_url}");
+        self.http_client
+            .delete(client_url)
+            .json(body)
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][delete_account] Failed to execute request.")
+    }
+
+    pub async fn get_account_export(&self) -> reqwest::Response {
+        let client_url = format!("{}/account/export", &self.address);
+        println!("[RESTTestApp][get_account_export] Client URL: {client_url}");
+        self.http_client
+            .get(client_url)
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][get_account_export] Failed to execute request.")
+    }
+
+    pub async fn get_account_unlock(&self, token: &str) -> reqwest::Response {
+        let client_url = format!("{}/account/unlock", &self.address);
+        println!("[RESTTestApp][get_account_unlock] Client URL: {client_url}");
+        self.http_client
+            .get(client_url)
+            .query(&[("token", token)])
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][get_account_unlock] Failed to execute request.")
+    }
+
+    pub async fn post_account_unlock(&self, token: &str) -> reqwest::Response {
+        let client_url = format!("{}/account/unlock", &self.address);
+        println!("[RESTTestApp][post_account_unlock] Client URL: {client_url}");
+        self.http_client
+            .post(client_url)
+            .json(&json!({ "token": token }))
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][post_account_unlock] Failed to execute request.")
+    }
+
+    pub async fn get_account_restore(&self, token: &str) -> reqwest::Response {
+        let client_url = format!("{}/account/restore", &self.address);
+        println!("[RESTTestApp][get_account_restore] Client URL: {client_url}");
+        self.http_client
+            .get(client_url)
+            .query(&[("token", token)])
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][get_account_restore] Failed to execute request.")
+    }
+
+    pub async fn post_account_restore(&self, token: &str) -> reqwest::Response {
+        let client_url = format!("{}/account/restore", &self.address);
+        println!("[RESTTestApp][post_account_restore] Client URL: {client_url}");
+        self.http_client
+            .post(client_url)
+            .json(&json!({ "token": token }))
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][post_account_restore] Failed to execute request.")
+    }
+
+    pub async fn post_change_password<Body: Serialize>(&self, body: &Body) -> reqwest::Response {
+        let client_url = format!("{}/change-password", &self.address);
+        println!("[RESTTestApp][post_change_password] Client URL: {client_url}");
+        self.http_client
+            .post(client_url)
+            .json(body)
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][post_change_password] Failed to execute request.")
+    }
+
     pub async fn post_login<Body: Serialize>(&self, body: &Body) -> reqwest::Response {
         let client_url = format!("{}/login", &self.address);
         println!("[RESTTestApp][post_login] Client URL: {client_url}");
@@ -130,6 +309,295 @@ impl RESTTestApp {
             .expect("[ERROR][RESTTestApp][post_logout] Failed to execute request.")
     }
 
+    /// Sends the token only in the `Authorization` header, through a client without cookies.
+    pub async fn post_logout_with_bearer(&self, token: &str) -> reqwest::Response {
+        let client_url = format!("{}/logout", &self.address);
+        println!("[RESTTestApp][post_logout_with_bearer] Client URL: {client_url}");
+        Client::new()
+            .post(client_url)
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][post_logout_with_bearer] Failed to execute request.")
+    }
+
+    pub async fn post_verify_token_with_bearer(&self, token: &str) -> reqwest::Response {
+        let client_url = format!("{}/verify-token", &self.address);
+        println!("[RESTTestApp][post_verify_token_with_bearer] Client URL: {client_url}");
+        Client::new()
+            .post(client_url)
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][post_verify_token_with_bearer] Failed to execute request.")
+    }
+
+    pub async fn get_sessions_with_bearer(&self, token: &str) -> reqwest::Response {
+        let client_url = format!("{}/sessions", &self.address);
+        println!("[RESTTestApp][get_sessions_with_bearer] Client URL: {client_url}");
+        Client::new()
+            .get(client_url)
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][get_sessions_with_bearer] Failed to execute request.")
+    }
+
+    pub async fn get_password_hashes(&self) -> reqwest::Response {
+        let client_url = format!("{}/admin/password-hashes", &self.address);
+        println!("[RESTTestApp][get_password_hashes] Client URL: {client_url}");
+        self.http_client
+            .get(client_url)
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][get_password_hashes] Failed to execute request.")
+    }
+
+    pub async fn post_import_users(&self, content_type: &str, body: String) -> reqwest::Response {
+        let client_url = format!("{}/admin/users/import", &self.address);
+        println!("[RESTTestApp][post_import_users] Client URL: {client_url}");
+        self.http_client
+            .post(client_url)
+            .header(reqwest::header::CONTENT_TYPE, content_type)
+            .body(body)
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][post_import_users] Failed to execute request.")
+    }
+
+    pub async fn post_unlock_user<Body: Serialize>(&self, body: &Body) -> reqwest::Response {
+        let client_url = format!("{}/admin/users/unlock", &self.address);
+        println!("[RESTTestApp][post_unlock_user] Client URL: {client_url}");
+        self.http_client
+            .post(client_url)
+            .json(body)
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][post_unlock_user] Failed to execute request.")
+    }
+
+    pub async fn put_user_roles<Body: Serialize>(&self, body: &Body) -> reqwest::Response {
+        let client_url = format!("{}/admin/users/roles", &self.address);
+        println!("[RESTTestApp][put_user_roles] Client URL: {client_url}");
+        self.http_client
+            .put(client_url)
+            .json(body)
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][put_user_roles] Failed to execute request.")
+    }
+
+    pub async fn post_2fa_enable<Body: Serialize>(&self, body: &Body) -> reqwest::Response {
+        let client_url = format!("{}/2fa/enable", &self.address);
+        println!("[RESTTestApp][post_2fa_enable] Client URL: {client_url}");
+        self.http_client
+            .post(client_url)
+            .json(body)
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][post_2fa_enable] Failed to execute request.")
+    }
+
+    pub async fn post_2fa_disable<Body: Serialize>(&self, body: &Body) -> reqwest::Response {
+        let client_url = format!("{}/2fa/disable", &self.address);
+        println!("[RESTTestApp][post_2fa_disable] Client URL: {client_url}");
+        self.http_client
+            .post(client_url)
+            .json(body)
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][post_2fa_disable] Failed to execute request.")
+    }
+
+    pub async fn get_phone(&self) -> reqwest::Response {
+        let client_url = format!("{}/phone", &self.address);
+        println!("[RESTTestApp][get_phone] Client URL: {client_url}");
+        self.http_client
+            .get(client_url)
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][get_phone] Failed to execute request.")
+    }
+
+    pub async fn post_phone<Body: Serialize>(&self, body: &Body) -> reqwest::Response {
+        let client_url = format!("{}/phone", &self.address);
+        println!("[RESTTestApp][post_phone] Client URL: {client_url}");
+        self.http_client
+            .post(client_url)
+            .json(body)
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][post_phone] Failed to execute request.")
+    }
+
+    pub async fn post_phone_verify<Body: Serialize>(&self, body: &Body) -> reqwest::Response {
+        let client_url = format!("{}/phone/verify", &self.address);
+        println!("[RESTTestApp][post_phone_verify] Client URL: {client_url}");
+        self.http_client
+            .post(client_url)
+            .json(body)
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][post_phone_verify] Failed to execute request.")
+    }
+
+    pub async fn post_totp_enroll(&self) -> reqwest::Response {
+        let client_url = format!("{}/totp/enroll", &self.address);
+        println!("[RESTTestApp][post_totp_enroll] Client URL: {client_url}");
+        self.http_client
+            .post(client_url)
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][post_totp_enroll] Failed to execute request.")
+    }
+
+    pub async fn post_totp_confirm<Body: Serialize>(&self, body: &Body) -> reqwest::Response {
+        let client_url = format!("{}/totp/confirm", &self.address);
+        println!("[RESTTestApp][post_totp_confirm] Client URL: {client_url}");
+        self.http_client
+            .post(client_url)
+            .json(body)
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][post_totp_confirm] Failed to execute request.")
+    }
+
+    pub async fn get_recovery_codes(&self) -> reqwest::Response {
+        let client_url = format!("{}/recovery-codes", &self.address);
+        println!("[RESTTestApp][get_recovery_codes] Client URL: {client_url}");
+        self.http_client
+            .get(client_url)
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][get_recovery_codes] Failed to execute request.")
+    }
+
+    pub async fn post_recovery_codes<Body: Serialize>(&self, body: &Body) -> reqwest::Response {
+        let client_url = format!("{}/recovery-codes", &self.address);
+        println!("[RESTTestApp][post_recovery_codes] Client URL: {client_url}");
+        self.http_client
+            .post(client_url)
+            .json(body)
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][post_recovery_codes] Failed to execute request.")
+    }
+
+    pub async fn post_webauthn_register_start(&self) -> reqwest::Response {
+        let client_url = format!("{}/webauthn/register/start", &self.address);
+        println!("[RESTTestApp][post_webauthn_register_start] Client URL: {client_url}");
+        self.http_client
+            .post(client_url)
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][post_webauthn_register_start] Failed to execute request.")
+    }
+
+    pub async fn post_webauthn_register_finish<Body: Serialize>(&self, body: &Body) -> reqwest::Response {
+        let client_url = format!("{}/webauthn/register/finish", &self.address);
+        println!("[RESTTestApp][post_webauthn_register_finish] Client URL: {client_url}");
+        self.http_client
+            .post(client_url)
+            .json(body)
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][post_webauthn_register_finish] Failed to execute request.")
+    }
+
+    pub async fn post_webauthn_login_start<Body: Serialize>(&self, body: &Body) -> reqwest::Response {
+        let client_url = format!("{}/webauthn/login/start", &self.address);
+        println!("[RESTTestApp][post_webauthn_login_start] Client URL: {client_url}");
+        self.http_client
+            .post(client_url)
+            .json(body)
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][post_webauthn_login_start] Failed to execute request.")
+    }
+
+    pub async fn post_webauthn_login_finish<Body: Serialize>(&self, body: &Body) -> reqwest::Response {
+        let client_url = format!("{}/webauthn/login/finish", &self.address);
+        println!("[RESTTestApp][post_webauthn_login_finish] Client URL: {client_url}");
+        self.http_client
+            .post(client_url)
+            .json(body)
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][post_webauthn_login_finish] Failed to execute request.")
+    }
+
+    pub async fn post_webauthn_2fa_start<Body: Serialize>(&self, body: &Body) -> reqwest::Response {
+        let client_url = format!("{}/webauthn/2fa/start", &self.address);
+        println!("[RESTTestApp][post_webauthn_2fa_start] Client URL: {client_url}");
+        self.http_client
+            .post(client_url)
+            .json(body)
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][post_webauthn_2fa_start] Failed to execute request.")
+    }
+
+    pub async fn post_webauthn_2fa_finish<Body: Serialize>(&self, body: &Body) -> reqwest::Response {
+        let client_url = format!("{}/webauthn/2fa/finish", &self.address);
+        println!("[RESTTestApp][post_webauthn_2fa_finish] Client URL: {client_url}");
+        self.http_client
+            .post(client_url)
+            .json(body)
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][post_webauthn_2fa_finish] Failed to execute request.")
+    }
+
+    pub async fn post_logout_all(&self) -> reqwest::Response {
+        let client_url = format!("{}/logout-all", &self.address);
+        println!("[RESTTestApp][post_logout_all] Client URL: {client_url}");
+        self.http_client
+            .post(client_url)
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][post_logout_all] Failed to execute request.")
+    }
+
+    pub async fn get_sessions(&self) -> reqwest::Response {
+        let client_url = format!("{}/sessions", &self.address);
+        println!("[RESTTestApp][get_sessions] Client URL: {client_url}");
+        self.http_client
+            .get(client_url)
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][get_sessions] Failed to execute request.")
+    }
+
+    pub async fn delete_session(&self, session_id: &str) -> reqwest::Response {
+        let client_url = format!("{}/sessions/{session_id}", &self.address);
+        println!("[RESTTestApp][delete_session] Client URL: {client_url}");
+        self.http_client
+            .delete(client_url)
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][delete_session] Failed to execute request.")
+    }
+
+    pub async fn get_trusted_devices(&self) -> reqwest::Response {
+        let client_url = format!("{}/trusted-devices", &self.address);
+        println!("[RESTTestApp][get_trusted_devices] Client URL: {client_url}");
+        self.http_client
+            .get(client_url)
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][get_trusted_devices] Failed to execute request.")
+    }
+
+    pub async fn delete_trusted_device(&self, device_id: &str) -> reqwest::Response {
+        let client_url = format!("{}/trusted-devices/{device_id}", &self.address);
+        println!("[RESTTestApp][delete_trusted_device] Client URL: {client_url}");
+        self.http_client
+            .delete(client_url)
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][delete_trusted_device] Failed to execute request.")
+    }
+
     pub async fn post_verify_token<Body: Serialize>(&self, body: &Body) -> reqwest::Response {
         let client_url = format!("{}/verify-token", &self.address);
         println!("[RESTTestApp][post_verify_token] Client URL: {client_url}");
@@ -141,6 +609,39 @@ impl RESTTestApp {
             .expect("[ERROR][RESTTestApp][post_verify_token] Failed to execute request.")
     }
 
+    pub async fn post_refresh_token(&self) -> reqwest::Response {
+        let client_url = format!("{}/token/refresh", &self.address);
+        println!("[RESTTestApp][post_refresh_token] Client URL: {client_url}");
+        self.http_client
+            .post(client_url)
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][post_refresh_token] Failed to execute request.")
+    }
+
+    pub async fn post_introspect(&self, token: &str, client_secret: Option<&str>) -> reqwest::Response {
+        let client_url = format!("{}/introspect", &self.address);
+        println!("[RESTTestApp][post_introspect] Client URL: {client_url}");
+        let mut request = self.http_client.post(client_url).form(&[("token", token)]);
+        if let Some(client_secret) = client_secret {
+            request = request.basic_auth(INTROSPECTION_CLIENT_ID.as_str(), Some(client_secret));
+        }
+        request
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][post_introspect] Failed to execute request.")
+    }
+
+    pub async fn get_jwks(&self) -> reqwest::Response {
+        let client_url = format!("{}/.well-known/jwks.json", &self.address);
+        println!("[RESTTestApp][get_jwks] Client URL: {client_url}");
+        self.http_client
+            .get(client_url)
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][get_jwks] Failed to execute request.")
+    }
+
     pub async fn post_verify_2fa<Body: Serialize + fmt::Debug>(&self, body: &Body) -> reqwest::Response {
         let client_url = format!("{}/verify-2fa", &self.address);
         println!("[post_verify_2fa] {client_url}");
@@ -153,6 +654,17 @@ impl RESTTestApp {
             .expect("[ERROR][RESTTestApp][post_verify_2fa] Failed to execute request.")
     }
 
+    pub async fn post_resend_2fa<Body: Serialize>(&self, body: &Body) -> reqwest::Response {
+        let client_url = format!("{}/resend-2fa", &self.address);
+        println!("[RESTTestApp][post_resend_2fa] Client URL: {client_url}");
+        self.http_client
+            .post(client_url)
+            .json(body)
+            .send()
+            .await
+            .expect("[ERROR][RESTTestApp][post_resend_2fa] Failed to execute request.")
+    }
+
     pub async fn log_user_store(&self, fn_name: &str) {
         let user_store = self.app_state.user_store.read().await;
         println!("[{}] {:?}", fn_name, user_store);
@@ -209,8 +721,15 @@ impl GRPCTestApp {
             HashMapBannedTokenStore::new(),
             user_store,
             HashMapTwoFACodeStore::new(),
+            HashMapTwoFACodeStore::new(),
             MockEmailClient,
             HashMapPasswordResetTokenStore::new(),
+            HashMapRefreshTokenStore::new(),
+            HashMapSessionStore::new(),
+            HashMapWebAuthnChallengeStore::new(),
+            HashMapTrustedDeviceStore::new(),
+            MockSmsClient,
+            HashMapLoginFailureStore::new(),
         ));
         let address = String::from(test::APP_GRPC_ADDRESS);
 
@@ -266,6 +785,44 @@ pub async fn wait_for_user<'a, T: UserStore>(
     Err(UserStoreError::UserNotFound)
 }
 
+/// Returns the auth and refresh tokens set by the login.
+pub async fn signup_and_login(app: &RESTTestApp, email: &str) -> (String, String) {
+    let signup_body = json!({
+        "email": email,
+        "password": "P@ssw0rd",
+        "requires2FA": false,
+    });
+    assert_eq!(app.post_signup(&signup_body).await.status(), 201);
+    verify_email(app, email).await;
+    login(app, email).await
+}
+
+/// Follows the link that signup emails, so the new user can log in.
+pub async fn verify_email(app: &RESTTestApp, email: &str) {
+    let email = Email::parse(Secret::new(email.to_string())).unwrap();
+    let token = EmailVerificationToken::new(&email).unwrap();
+    let response = app.get_verify_email(&token.expose_secret_string()).await;
+    assert_eq!(response.status(), 200);
+}
+
+pub async fn login(app: &RESTTestApp, email: &str) -> (String, String) {
+    let login_body = json!({
+        "email": email,
+        "password": "P@ssw0rd",
+    });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status(), 200);
+
+    let cookie_value = |name: &str| {
+        response
+            .cookies()
+            .find(|c| c.name() == name)
+            .map(|c| c.value().to_string())
+            .expect("[ERROR][Test Helper][login] Missing login cookie")
+    };
+    (cookie_value(JWT_COOKIE_NAME), cookie_value(REFRESH_TOKEN_COOKIE_NAME))
+}
+
 pub async fn create_app_with_logged_in_token() -> (RESTTestApp, Secret<String>) {
     let app = RESTTestApp::new().await;
     let email = get_random_email();
@@ -276,6 +833,7 @@ pub async fn create_app_with_logged_in_token() -> (RESTTestApp, Secret<String>)
     });
     let signup_response = app.post_signup(&signup_body).await;
     assert_eq!(signup_response.status(), 201);
+    verify_email(&app, &email).await;
     let login_body = json!({
         "email": email,
         "password": "P@ssw0rd",
@@ -313,3 +871,20 @@ fn configure_postmark_email_client(base_url: String) -> PostmarkEmailClient {
 
     PostmarkEmailClient::new(base_url, sender, postmark_auth_token, http_client)
 }
+
+fn configure_twilio_sms_client(base_url: String) -> TwilioSmsClient {
+    let sender = PhoneNumber::parse(Secret::new(test::sms_client::SENDER.to_owned())).unwrap();
+
+    let http_client = Client::builder()
+        .timeout(test::sms_client::TIMEOUT)
+        .build()
+        .expect("Failed to build HTTP client");
+
+    TwilioSmsClient::new(
+        base_url,
+        sender,
+        test::sms_client::ACCOUNT_SID.to_owned(),
+        Secret::new("auth_token".to_owned()),
+        http_client,
+    )
+}
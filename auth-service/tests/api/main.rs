@@ -1,11 +1,30 @@
 mod db;
+mod grpc_introspect;
+mod grpc_refresh_token;
 mod grpc_signup;
 mod helpers;
+mod rest_account;
+mod rest_admin;
+mod rest_change_email;
+mod rest_change_password;
+mod rest_introspect;
+mod rest_jwks;
 mod rest_login;
 mod rest_logout;
+mod rest_logout_all;
 mod rest_password_reset;
+mod rest_phone;
+mod rest_recovery_codes;
+mod rest_refresh_token;
+mod rest_resend_2fa;
+mod rest_sessions;
 mod rest_signup;
+mod rest_totp;
+mod rest_trusted_devices;
+mod rest_two_fa_settings;
 mod rest_verify_2fa;
+mod rest_verify_email;
 mod rest_verify_token;
+mod rest_webauthn;
 mod root;
 mod user_store;
@@ -0,0 +1,230 @@
+use secrecy::Secret;
+use serde_json::json;
+use wiremock::{
+    matchers::{body_string_contains, method, path},
+    Mock, ResponseTemplate,
+};
+
+use auth_service::{
+    domain::{data_stores::UserStore, email::Email, user::AuthEventKind},
+    routes::account::{AccountExport, AccountResponse},
+    utils::auth::{AccountRestoreToken, EmailVerificationToken},
+};
+
+use crate::db::is_deletion_scheduled;
+use crate::helpers::{get_random_email, signup_and_login, verify_email, RESTTestApp};
+
+const TEST_PASSWORD: &str = "P@ssw0rd";
+
+fn parse_email(email: &str) -> Email {
+    Email::parse(Secret::new(email.to_string())).unwrap()
+}
+
+async fn create_logged_in_app(expected_email_calls: u64) -> (RESTTestApp, String, String) {
+    let app = RESTTestApp::new().await;
+    let email = get_random_email();
+    let (auth_token, _) = signup_and_login(&app, &email).await;
+
+    Mock::given(path("/email/withTemplate"))
+        .and(method("POST"))
+        .and(body_string_contains("account-deleted"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(expected_email_calls)
+        .mount(&app.email_server)
+        .await;
+
+    (app, email, auth_token)
+}
+
+async fn login_status(app: &RESTTestApp, email: &str) -> u16 {
+    app.post_login(&json!({ "email": email, "password": TEST_PASSWORD }))
+        .await
+        .status()
+        .as_u16()
+}
+
+#[tokio::test]
+async fn should_delete_account_and_notify() {
+    let (mut app, email, auth_token) = create_logged_in_app(1).await;
+
+    let response = app.delete_account(&json!({ "password": TEST_PASSWORD })).await;
+    assert_eq!(response.status(), 200);
+    let response: AccountResponse = response.json().await.unwrap();
+    assert_eq!(response.message, "Account deleted.");
+
+    let response = app.post_verify_token(&json!({ "token": auth_token })).await;
+    assert_eq!(response.status(), 401);
+    assert_eq!(login_status(&app, &email).await, 401);
+
+    // The address is free to sign up with again
+    let signup_body = json!({ "email": email, "password": TEST_PASSWORD, "requires2FA": false });
+    assert_eq!(app.post_signup(&signup_body).await.status(), 201);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_return_401_if_password_is_wrong() {
+    let (mut app, email, _) = create_logged_in_app(0).await;
+
+    let response = app.delete_account(&json!({ "password": "Wr0ngP@ssw0rd" })).await;
+    assert_eq!(response.status(), 401);
+    let response = app.delete_account(&json!({})).await;
+    assert_eq!(response.status(), 401);
+    assert_eq!(login_status(&app, &email).await, 200);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_return_400_without_auth_token() {
+    let mut app = RESTTestApp::new().await;
+
+    let response = app.delete_account(&json!({ "password": TEST_PASSWORD })).await;
+    assert_eq!(response.status(), 400);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_restore_account_scheduled_for_deletion() {
+    let (mut app, email, _) = create_logged_in_app(0).await;
+    app.app_state
+        .user_store
+        .write()
+        .await
+        .schedule_deletion(&parse_email(&email))
+        .await
+        .unwrap();
+    assert_eq!(login_status(&app, &email).await, 401);
+
+    let token = AccountRestoreToken::new(&parse_email(&email), 3600)
+        .unwrap()
+        .expose_secret_string();
+    let response = app.post_account_restore(&token).await;
+    assert_eq!(response.status(), 200);
+    let response: AccountResponse = response.json().await.unwrap();
+    assert_eq!(response.message, "Account restored successfully.");
+    assert_eq!(login_status(&app, &email).await, 200);
+
+    // The link only works once
+    assert_eq!(app.post_account_restore(&token).await.status(), 401);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn opening_restore_link_should_not_restore_account() {
+    let (mut app, email, _) = create_logged_in_app(0).await;
+    app.app_state
+        .user_store
+        .write()
+        .await
+        .schedule_deletion(&parse_email(&email))
+        .await
+        .unwrap();
+
+    let token = AccountRestoreToken::new(&parse_email(&email), 3600)
+        .unwrap()
+        .expose_secret_string();
+    let response = app.get_account_restore(&token).await;
+    assert_eq!(response.status(), 200);
+    assert!(response.text().await.unwrap().contains("<html"));
+
+    assert!(is_deletion_scheduled(&app.test_db_name, &email).await);
+    assert_eq!(login_status(&app, &email).await, 401);
+
+    // The page can still post the token back
+    assert_eq!(app.post_account_restore(&token).await.status(), 200);
+    assert!(!is_deletion_scheduled(&app.test_db_name, &email).await);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn signup_should_replace_account_scheduled_for_deletion() {
+    let (mut app, email, _) = create_logged_in_app(0).await;
+    app.app_state
+        .user_store
+        .write()
+        .await
+        .schedule_deletion(&parse_email(&email))
+        .await
+        .unwrap();
+
+    let new_password = "NewP@ssw0rd123";
+    let signup_body = json!({ "email": email, "password": new_password, "requires2FA": false });
+    assert_eq!(app.post_signup(&signup_body).await.status(), 201);
+    verify_email(&app, &email).await;
+    let response = app
+        .post_login(&json!({ "email": email, "password": new_password }))
+        .await;
+    assert_eq!(response.status(), 200);
+
+    // The old account is gone for good
+    let token = AccountRestoreToken::new(&parse_email(&email), 3600).unwrap();
+    let response = app.post_account_restore(&token.expose_secret_string()).await;
+    assert_eq!(response.status(), 401);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn restore_should_return_401_if_invalid_token() {
+    let (mut app, email, _) = create_logged_in_app(0).await;
+
+    assert_eq!(app.post_account_restore("invalid_token").await.status(), 401);
+    let token = EmailVerificationToken::new(&parse_email(&email)).unwrap();
+    let response = app.post_account_restore(&token.expose_secret_string()).await;
+    assert_eq!(response.status(), 401);
+    // Nothing to restore
+    let token = AccountRestoreToken::new(&parse_email(&email), 3600).unwrap();
+    let response = app.post_account_restore(&token.expose_secret_string()).await;
+    assert_eq!(response.status(), 401);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_export_account_data() {
+    let (mut app, email, _) = create_logged_in_app(0).await;
+    let response = app
+        .post_login(&json!({ "email": email, "password": "Wr0ngP@ssword" }))
+        .await;
+    assert_eq!(response.status(), 401);
+
+    let response = app.get_account_export().await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response.headers()["content-disposition"],
+        "attachment; filename=\"account-export.json\""
+    );
+    let body = response.text().await.unwrap();
+    assert!(!body.contains("password"));
+
+    let export: AccountExport = serde_json::from_str(&body).unwrap();
+    assert_eq!(export.email, email);
+    assert!(export.email_verified);
+    assert_eq!(export.roles, vec!["user"]);
+    assert_eq!(export.two_factor.method, "none");
+    assert!(!export.two_factor.totp_enabled);
+    assert_eq!(export.two_factor.recovery_codes_remaining, 0);
+    assert!(export.two_factor.webauthn_credentials.is_empty());
+    assert_eq!(export.two_factor.phone.phone_number, None);
+    assert_eq!(export.sessions.len(), 1);
+    assert!(export.sessions[0].current);
+    assert!(export.trusted_devices.is_empty());
+    let kinds: Vec<AuthEventKind> = export.auth_events.iter().map(|event| event.kind).collect();
+    assert_eq!(kinds, vec![AuthEventKind::SignInFailed, AuthEventKind::SignIn]);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn export_should_return_400_without_auth_token() {
+    let mut app = RESTTestApp::new().await;
+
+    assert_eq!(app.get_account_export().await.status(), 400);
+
+    app.clean_up().await.unwrap();
+}
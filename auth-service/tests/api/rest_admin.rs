@@ -0,0 +1,290 @@
+use argon2::password_hash::{PasswordHasher, SaltString};
+use pbkdf2::Pbkdf2;
+use secrecy::{ExposeSecret, Secret};
+use serde_json::json;
+
+use auth_service::{
+    api::rest::ErrorResponse,
+    domain::{
+        data_stores::{LoginFailureStore, UserStore},
+        email::Email,
+    },
+    routes::{
+        admin::{ImportUsersResponse, PasswordHashesResponse, SkippedImport, UserRolesResponse},
+        introspect::IntrospectResponse,
+    },
+    utils::{
+        auth::validate_token_structure,
+        constants::{INTROSPECTION_CLIENT_SECRET, LOGIN_LOCKOUT_SECONDS, LOGIN_LOCKOUT_THRESHOLD},
+    },
+};
+
+use crate::db::set_outdated_password_hash;
+use crate::helpers::{get_random_email, login, signup_and_login, RESTTestApp};
+
+async fn login_as_admin(app: &RESTTestApp) -> String {
+    let email = get_random_email();
+    signup_and_login(app, &email).await;
+    let admin_email = Email::parse(Secret::new(email.clone())).unwrap();
+    app.app_state
+        .user_store
+        .write()
+        .await
+        .set_roles(&admin_email, &["admin".to_string()])
+        .await
+        .unwrap();
+
+    let (token, _) = login(app, &email).await;
+    token
+}
+
+#[tokio::test]
+async fn should_embed_default_scope_in_login_token() {
+    let mut app = RESTTestApp::new().await;
+    let (token, _) = signup_and_login(&app, &get_random_email()).await;
+
+    let claims = validate_token_structure(&token).await.unwrap();
+    assert_eq!(claims.roles, vec!["user"]);
+    assert_eq!(claims.scopes, vec!["users:self"]);
+
+    let response = app
+        .post_introspect(&token, Some(INTROSPECTION_CLIENT_SECRET.expose_secret()))
+        .await
+        .json::<IntrospectResponse>()
+        .await
+        .unwrap();
+    assert_eq!(response.scope.as_deref(), Some("users:self"));
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_return_403_without_admin_scope() {
+    let mut app = RESTTestApp::new().await;
+    signup_and_login(&app, &get_random_email()).await;
+
+    let response = app
+        .put_user_roles(&json!({ "email": get_random_email(), "roles": ["admin"] }))
+        .await;
+    assert_eq!(response.status(), 403);
+    assert_eq!(
+        response.json::<ErrorResponse>().await.unwrap().error,
+        "Insufficient scope".to_owned()
+    );
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_let_admin_grant_roles() {
+    let mut app = RESTTestApp::new().await;
+    let user_email = get_random_email();
+    signup_and_login(&app, &user_email).await;
+    let admin_token = login_as_admin(&app).await;
+    assert!(validate_token_structure(&admin_token)
+        .await
+        .unwrap()
+        .has_scope("users:admin"));
+
+    let response = app
+        .put_user_roles(&json!({ "email": user_email, "roles": ["admin", "user"] }))
+        .await;
+    assert_eq!(response.status(), 200);
+    let response = response.json::<UserRolesResponse>().await.unwrap();
+    assert_eq!(response.roles, vec!["admin", "user"]);
+    assert_eq!(response.scopes, vec!["users:admin", "users:self"]);
+
+    let (user_token, _) = login(&app, &user_email).await;
+    let claims = validate_token_structure(&user_token).await.unwrap();
+    assert!(claims.has_scope("users:admin"));
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_reject_unknown_role_or_user() {
+    let mut app = RESTTestApp::new().await;
+    let user_email = get_random_email();
+    signup_and_login(&app, &user_email).await;
+    login_as_admin(&app).await;
+
+    let response = app
+        .put_user_roles(&json!({ "email": user_email, "roles": ["superuser"] }))
+        .await;
+    assert_eq!(response.status(), 400);
+    assert_eq!(
+        response.json::<ErrorResponse>().await.unwrap().error,
+        "Role not found".to_owned()
+    );
+
+    let response = app
+        .put_user_roles(&json!({ "email": get_random_email(), "roles": ["user"] }))
+        .await;
+    assert_eq!(response.status(), 404);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_let_admin_unlock_account() {
+    let mut app = RESTTestApp::new().await;
+    let user_email = get_random_email();
+    signup_and_login(&app, &user_email).await;
+    let email = Email::parse(Secret::new(user_email.clone())).unwrap();
+    let mut login_failure_store = app.app_state.login_failure_store.write().await;
+    for _ in 0..*LOGIN_LOCKOUT_THRESHOLD {
+        login_failure_store
+            .record_failure(&email, *LOGIN_LOCKOUT_SECONDS)
+            .await
+            .unwrap();
+    }
+    drop(login_failure_store);
+    let login_body = json!({ "email": user_email, "password": "P@ssw0rd" });
+    assert_eq!(app.post_login(&login_body).await.status(), 423);
+
+    // Only admins can unlock
+    let response = app.post_unlock_user(&json!({ "email": user_email })).await;
+    assert_eq!(response.status(), 403);
+    login_as_admin(&app).await;
+    let response = app.post_unlock_user(&json!({ "email": get_random_email() })).await;
+    assert_eq!(response.status(), 404);
+    let response = app.post_unlock_user(&json!({ "email": user_email })).await;
+    assert_eq!(response.status(), 200);
+
+    assert_eq!(app.post_login(&login_body).await.status(), 200);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_let_admin_count_outdated_password_hashes() {
+    let mut app = RESTTestApp::new().await;
+    let user_email = get_random_email();
+    signup_and_login(&app, &user_email).await;
+    set_outdated_password_hash(&app.test_db_name, &user_email, "P@ssw0rd").await;
+
+    assert_eq!(app.get_password_hashes().await.status(), 403);
+    login_as_admin(&app).await;
+
+    let response = app.get_password_hashes().await;
+    assert_eq!(response.status(), 200);
+    let response = response.json::<PasswordHashesResponse>().await.unwrap();
+    assert_eq!(response.total, 2);
+    assert_eq!(response.outdated, 1);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_let_admin_import_users_with_legacy_hashes() {
+    let mut app = RESTTestApp::new().await;
+    let (bcrypt_email, pbkdf2_email, existing_email) = (get_random_email(), get_random_email(), get_random_email());
+    signup_and_login(&app, &existing_email).await;
+    let bcrypt_hash = bcrypt::hash("P@ssw0rd", 4).unwrap();
+    let salt = SaltString::generate(&mut rand::thread_rng());
+    let pbkdf2_params = pbkdf2::Params {
+        rounds: 1000,
+        output_length: 32,
+    };
+    let pbkdf2_hash = Pbkdf2
+        .hash_password_customized(b"P@ssw0rd", None, None, pbkdf2_params, &salt)
+        .unwrap()
+        .to_string();
+    let jsonl = [
+        json!({ "email": bcrypt_email, "passwordHash": bcrypt_hash, "emailVerified": true }).to_string(),
+        String::new(),
+        json!({ "email": existing_email, "passwordHash": bcrypt_hash }).to_string(),
+        json!({ "email": "invalid_email", "passwordHash": bcrypt_hash }).to_string(),
+        json!({ "email": get_random_email(), "passwordHash": "P@ssw0rd" }).to_string(),
+        "not json".to_string(),
+    ]
+    .join("\n");
+
+    assert_eq!(
+        app.post_import_users("application/x-ndjson", jsonl.clone())
+            .await
+            .status(),
+        403
+    );
+    login_as_admin(&app).await;
+    assert_eq!(app.post_import_users("text/plain", jsonl.clone()).await.status(), 415);
+
+    let response = app.post_import_users("application/x-ndjson", jsonl).await;
+    assert_eq!(response.status(), 200);
+    let response = response.json::<ImportUsersResponse>().await.unwrap();
+    assert_eq!(response.imported, 1);
+    let skipped: Vec<u64> = response.skipped.iter().map(|skipped| skipped.line).collect();
+    assert_eq!(skipped, vec![3, 4, 5, 6]);
+    assert_eq!(
+        response.skipped[0],
+        SkippedImport {
+            line: 3,
+            error: "User already exists".to_string()
+        }
+    );
+
+    // PHC strings hold commas, so the hash column is quoted
+    let csv = format!(
+        "email,passwordHash,emailVerified\n{pbkdf2_email},\"{pbkdf2_hash}\",true\n{bcrypt_email},{bcrypt_hash},true\n"
+    );
+    let response = app.post_import_users("text/csv; charset=utf-8", csv).await;
+    assert_eq!(response.status(), 200);
+    let response = response.json::<ImportUsersResponse>().await.unwrap();
+    assert_eq!(response.imported, 1);
+    assert_eq!(response.skipped.len(), 1);
+    assert_eq!(response.skipped[0].line, 3);
+
+    let response = app
+        .get_password_hashes()
+        .await
+        .json::<PasswordHashesResponse>()
+        .await
+        .unwrap();
+    assert_eq!(response.outdated, 2);
+
+    // Imported users log in with their old passwords, and their hashes are upgraded as they do
+    for email in [&bcrypt_email, &pbkdf2_email] {
+        let login_body = json!({ "email": email, "password": "P@ssw0rd" });
+        assert_eq!(app.post_login(&login_body).await.status(), 200);
+    }
+    let counts = app
+        .app_state
+        .user_store
+        .read()
+        .await
+        .count_password_hashes()
+        .await
+        .unwrap();
+    assert_eq!(counts.outdated, 0);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn imported_user_with_weak_password_should_log_in() {
+    let mut app = RESTTestApp::new().await;
+    let email = get_random_email();
+    login_as_admin(&app).await;
+    // Fails today's strength rules, but it is the password the user already has
+    let password_hash = bcrypt::hash("hunter22", 4).unwrap();
+    let jsonl = json!({ "email": email, "passwordHash": password_hash, "emailVerified": true }).to_string();
+    let response = app.post_import_users("application/x-ndjson", jsonl).await;
+    assert_eq!(response.json::<ImportUsersResponse>().await.unwrap().imported, 1);
+
+    let login_body = json!({ "email": email, "password": "hunter22" });
+    assert_eq!(app.post_login(&login_body).await.status(), 200);
+    let counts = app
+        .app_state
+        .user_store
+        .read()
+        .await
+        .count_password_hashes()
+        .await
+        .unwrap();
+    assert_eq!(counts.outdated, 0);
+    assert_eq!(app.post_login(&login_body).await.status(), 200);
+    let wrong_login_body = json!({ "email": email, "password": "hunter2" });
+    assert_eq!(app.post_login(&wrong_login_body).await.status(), 401);
+
+    app.clean_up().await.unwrap();
+}
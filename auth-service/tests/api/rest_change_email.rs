@@ -0,0 +1,187 @@
+use secrecy::Secret;
+use serde_json::json;
+use wiremock::{
+    matchers::{body_string_contains, method, path},
+    Mock, ResponseTemplate,
+};
+
+use auth_service::{
+    domain::email::Email,
+    routes::change_email::ChangeEmailResponse,
+    utils::auth::{EmailChangeRevertToken, EmailChangeToken, EmailVerificationToken},
+};
+
+use crate::helpers::{get_random_email, login, signup_and_login, RESTTestApp};
+
+const TEST_PASSWORD: &str = "P@ssw0rd";
+
+fn parse_email(email: &str) -> Email {
+    Email::parse(Secret::new(email.to_string())).unwrap()
+}
+
+async fn mount_email_server(app: &RESTTestApp, template_alias: &str, expected_calls: u64) {
+    Mock::given(path("/email/withTemplate"))
+        .and(method("POST"))
+        .and(body_string_contains(format!("\"{template_alias}\"")))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(expected_calls)
+        .mount(&app.email_server)
+        .await;
+}
+
+async fn login_status(app: &RESTTestApp, email: &str) -> u16 {
+    app.post_login(&json!({ "email": email, "password": TEST_PASSWORD }))
+        .await
+        .status()
+        .as_u16()
+}
+
+/// Changes the signed-in user's address through the confirmation link. Returns the auth token from before and
+/// the confirmation token.
+async fn change_email(app: &RESTTestApp, email: &str, new_email: &str) -> (String, String) {
+    let (auth_token, _) = signup_and_login(app, email).await;
+
+    let body = json!({ "newEmail": new_email, "password": TEST_PASSWORD });
+    let response = app.post_change_email(&body).await;
+    assert_eq!(response.status(), 200);
+
+    let token = EmailChangeToken::new(&parse_email(email), &parse_email(new_email))
+        .unwrap()
+        .expose_secret_string();
+    let response = app.post_change_email_confirm(&token).await;
+    assert_eq!(response.status(), 200);
+    let response: ChangeEmailResponse = response.json().await.unwrap();
+    assert_eq!(response.message, "Email changed successfully.");
+
+    (auth_token, token)
+}
+
+#[tokio::test]
+async fn should_change_email_after_confirmation() {
+    let mut app = RESTTestApp::new().await;
+    let (email, new_email) = (get_random_email(), get_random_email());
+    mount_email_server(&app, "email-change", 1).await;
+    mount_email_server(&app, "email-change-revert", 1).await;
+
+    let (old_auth_token, _) = change_email(&app, &email, &new_email).await;
+
+    let response = app.post_verify_token(&json!({ "token": old_auth_token })).await;
+    assert_eq!(response.status(), 401);
+    assert_eq!(login_status(&app, &email).await, 401);
+    assert_eq!(login_status(&app, &new_email).await, 200);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn opening_confirmation_link_should_not_change_email() {
+    let mut app = RESTTestApp::new().await;
+    let (email, new_email) = (get_random_email(), get_random_email());
+    mount_email_server(&app, "email-change", 1).await;
+    signup_and_login(&app, &email).await;
+
+    let body = json!({ "newEmail": new_email, "password": TEST_PASSWORD });
+    assert_eq!(app.post_change_email(&body).await.status(), 200);
+
+    let token = EmailChangeToken::new(&parse_email(&email), &parse_email(&new_email)).unwrap();
+    let response = app.get_change_email_confirm(&token.expose_secret_string()).await;
+    assert_eq!(response.status(), 200);
+    assert!(response.text().await.unwrap().contains("<html"));
+    let token = EmailChangeRevertToken::new(&parse_email(&email), &parse_email(&new_email)).unwrap();
+    let response = app.get_change_email_revert(&token.expose_secret_string()).await;
+    assert_eq!(response.status(), 200);
+
+    assert_eq!(login_status(&app, &email).await, 200);
+    assert_eq!(login_status(&app, &new_email).await, 401);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_not_change_email_before_confirmation() {
+    let mut app = RESTTestApp::new().await;
+    let (email, new_email) = (get_random_email(), get_random_email());
+    mount_email_server(&app, "email-change", 1).await;
+    signup_and_login(&app, &email).await;
+
+    let body = json!({ "newEmail": new_email, "password": TEST_PASSWORD });
+    assert_eq!(app.post_change_email(&body).await.status(), 200);
+
+    assert_eq!(login_status(&app, &email).await, 200);
+    assert_eq!(login_status(&app, &new_email).await, 401);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_revert_email_change_from_previous_address() {
+    let mut app = RESTTestApp::new().await;
+    let (email, new_email) = (get_random_email(), get_random_email());
+    mount_email_server(&app, "email-change", 1).await;
+    mount_email_server(&app, "email-change-revert", 1).await;
+    let (_, confirm_token) = change_email(&app, &email, &new_email).await;
+    let (new_auth_token, _) = login(&app, &new_email).await;
+
+    let token = EmailChangeRevertToken::new(&parse_email(&new_email), &parse_email(&email)).unwrap();
+    let response = app.post_change_email_revert(&token.expose_secret_string()).await;
+    assert_eq!(response.status(), 200);
+
+    let response = app.post_verify_token(&json!({ "token": new_auth_token })).await;
+    assert_eq!(response.status(), 401);
+    assert_eq!(login_status(&app, &email).await, 200);
+    assert_eq!(login_status(&app, &new_email).await, 401);
+
+    // Neither link works a second time
+    let response = app.post_change_email_revert(&token.expose_secret_string()).await;
+    assert_eq!(response.status(), 401);
+    let response = app.post_change_email_confirm(&confirm_token).await;
+    assert_eq!(response.status(), 401);
+    assert_eq!(login_status(&app, &email).await, 200);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_return_401_if_password_is_wrong() {
+    let mut app = RESTTestApp::new().await;
+    mount_email_server(&app, "email-change", 0).await;
+    signup_and_login(&app, &get_random_email()).await;
+
+    let body = json!({ "newEmail": get_random_email(), "password": "Wr0ngP@ssw0rd" });
+    assert_eq!(app.post_change_email(&body).await.status(), 401);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_return_409_if_new_email_is_taken() {
+    let mut app = RESTTestApp::new().await;
+    let (email, other_email) = (get_random_email(), get_random_email());
+    mount_email_server(&app, "email-change", 0).await;
+    signup_and_login(&app, &other_email).await;
+    signup_and_login(&app, &email).await;
+
+    let body = json!({ "newEmail": other_email, "password": TEST_PASSWORD });
+    assert_eq!(app.post_change_email(&body).await.status(), 409);
+    let body = json!({ "newEmail": "invalid_email", "password": TEST_PASSWORD });
+    assert_eq!(app.post_change_email(&body).await.status(), 400);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn confirm_should_return_401_if_invalid_token() {
+    let mut app = RESTTestApp::new().await;
+    let email = get_random_email();
+    signup_and_login(&app, &email).await;
+
+    assert_eq!(app.post_change_email_confirm("invalid_token").await.status(), 401);
+    let token = EmailVerificationToken::new(&parse_email(&email)).unwrap();
+    let response = app.post_change_email_confirm(&token.expose_secret_string()).await;
+    assert_eq!(response.status(), 401);
+    let token = EmailChangeToken::new(&parse_email(&email), &parse_email(&get_random_email())).unwrap();
+    let response = app.post_change_email_revert(&token.expose_secret_string()).await;
+    assert_eq!(response.status(), 401);
+
+    app.clean_up().await.unwrap();
+}
@@ -0,0 +1,155 @@
+use serde_json::json;
+use wiremock::{
+    matchers::{body_string_contains, method, path},
+    Mock, ResponseTemplate,
+};
+
+use auth_service::routes::change_password::ChangePasswordResponse;
+
+use crate::helpers::{get_random_email, login, signup_and_login, RESTTestApp};
+
+const TEST_PASSWORD: &str = "P@ssw0rd";
+const NEW_PASSWORD: &str = "NewP@ssw0rd123";
+
+async fn create_logged_in_app(expected_email_calls: u64) -> (RESTTestApp, String) {
+    let app = RESTTestApp::new().await;
+    let email = get_random_email();
+
+    Mock::given(path("/email/withTemplate"))
+        .and(method("POST"))
+        .and(body_string_contains("email-verification"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    Mock::given(path("/email/withTemplate"))
+        .and(method("POST"))
+        .and(body_string_contains("password-changed"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(expected_email_calls)
+        .mount(&app.email_server)
+        .await;
+
+    signup_and_login(&app, &email).await;
+    (app, email)
+}
+
+#[tokio::test]
+async fn should_change_password_and_notify() {
+    let (mut app, email) = create_logged_in_app(1).await;
+
+    let body = json!({ "currentPassword": TEST_PASSWORD, "newPassword": NEW_PASSWORD });
+    let response = app.post_change_password(&body).await;
+    assert_eq!(response.status(), 200);
+    let response: ChangePasswordResponse = response.json().await.unwrap();
+    assert_eq!(response.message, "Password changed successfully.");
+
+    let response = app
+        .post_login(&json!({ "email": email, "password": TEST_PASSWORD }))
+        .await;
+    assert_eq!(response.status(), 401);
+    let response = app
+        .post_login(&json!({ "email": email, "password": NEW_PASSWORD }))
+        .await;
+    assert_eq!(response.status(), 200);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_count_wrong_current_password_towards_lockout() {
+    let (mut app, email) = create_logged_in_app(0).await;
+
+    let wrong_body = json!({ "currentPassword": "Inv@lid_passw0rd", "newPassword": NEW_PASSWORD });
+    for _ in 0..3 {
+        assert_eq!(app.post_change_password(&wrong_body).await.status(), 401);
+    }
+    // Even the right password has to wait out the delay, here and at login
+    let body = json!({ "currentPassword": TEST_PASSWORD, "newPassword": NEW_PASSWORD });
+    assert_eq!(app.post_change_password(&body).await.status(), 429);
+    let response = app
+        .post_login(&json!({ "email": email, "password": TEST_PASSWORD }))
+        .await;
+    assert_eq!(response.status(), 429);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_revoke_other_sessions_but_keep_current_one() {
+    let (mut app, email) = create_logged_in_app(1).await;
+    // The second login replaces the cookies, so it is the session making the change
+    let (other_auth_token, _) = login(&app, &email).await;
+    let (current_auth_token, _) = login(&app, &email).await;
+
+    let body = json!({ "currentPassword": TEST_PASSWORD, "newPassword": NEW_PASSWORD });
+    assert_eq!(app.post_change_password(&body).await.status(), 200);
+
+    let response = app.post_verify_token(&json!({ "token": other_auth_token })).await;
+    assert_eq!(response.status(), 401);
+    let response = app.post_verify_token(&json!({ "token": current_auth_token })).await;
+    assert_eq!(response.status(), 200);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_invalidate_pending_password_reset_link() {
+    let (mut app, email) = create_logged_in_app(1).await;
+    Mock::given(path("/email/withTemplate"))
+        .and(method("POST"))
+        .and(body_string_contains("password-reset"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.post_initiate_password_reset(&json!({ "email": email })).await;
+    let reset_token = app.get_password_reset_token(&email).await.unwrap();
+
+    let body = json!({ "currentPassword": TEST_PASSWORD, "newPassword": NEW_PASSWORD });
+    assert_eq!(app.post_change_password(&body).await.status(), 200);
+
+    let reset_body = json!({ "token": reset_token, "new_password": "Ot4erP@ssw0rd" });
+    assert_eq!(app.post_reset_password(&reset_body).await.status(), 401);
+    let response = app
+        .post_login(&json!({ "email": email, "password": NEW_PASSWORD }))
+        .await;
+    assert_eq!(response.status(), 200);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_return_401_if_current_password_is_wrong() {
+    let (mut app, email) = create_logged_in_app(0).await;
+
+    let body = json!({ "currentPassword": "Wr0ngP@ssw0rd", "newPassword": NEW_PASSWORD });
+    assert_eq!(app.post_change_password(&body).await.status(), 401);
+
+    let response = app
+        .post_login(&json!({ "email": email, "password": TEST_PASSWORD }))
+        .await;
+    assert_eq!(response.status(), 200);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_return_400_if_new_password_is_weak() {
+    let (mut app, _) = create_logged_in_app(0).await;
+
+    let body = json!({ "currentPassword": TEST_PASSWORD, "newPassword": "weak" });
+    assert_eq!(app.post_change_password(&body).await.status(), 400);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_return_400_without_auth_token() {
+    let mut app = RESTTestApp::new().await;
+
+    let body = json!({ "currentPassword": TEST_PASSWORD, "newPassword": NEW_PASSWORD });
+    assert_eq!(app.post_change_password(&body).await.status(), 400);
+
+    app.clean_up().await.unwrap();
+}
@@ -0,0 +1,116 @@
+use chrono::Utc;
+use secrecy::{ExposeSecret, Secret};
+use serde_json::Value;
+use uuid::Uuid;
+
+use auth_service::{
+    routes::introspect::IntrospectResponse,
+    utils::{
+        auth::{create_token, Claims, TokenPurpose},
+        constants::{Epoch, INTROSPECTION_CLIENT_SECRET},
+    },
+};
+
+use crate::helpers::{create_app_with_logged_in_token, RESTTestApp};
+
+fn client_secret() -> Option<&'static str> {
+    Some(INTROSPECTION_CLIENT_SECRET.expose_secret().as_str())
+}
+
+#[tokio::test]
+async fn should_return_claims_for_active_token() {
+    let (mut app, token) = create_app_with_logged_in_token().await;
+
+    let response = app.post_introspect(token.expose_secret(), client_secret()).await;
+    assert_eq!(response.status(), 200);
+
+    let body = response
+        .json::<IntrospectResponse>()
+        .await
+        .expect("[ERROR][should_return_claims_for_active_token] Could not deserialize response body");
+    assert!(body.active);
+    assert!(body.sub.unwrap().ends_with("@example.com"));
+    assert_eq!(body.purpose, Some(TokenPurpose::Auth));
+    assert!(body.exp.unwrap() > body.iat.unwrap());
+    assert!(Uuid::parse_str(&body.jti.unwrap()).is_ok());
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_return_same_inactive_response_for_expired_banned_non_auth_and_malformed_tokens() {
+    let (mut app, token) = create_app_with_logged_in_token().await;
+
+    let now = Utc::now().timestamp() as Epoch;
+    let expired_token = create_token(&Claims {
+        sub: Secret::new("expired@example.com".to_string()),
+        exp: now - 3600,
+        iat: now - 4200,
+        jti: Uuid::new_v4().to_string(),
+        purpose: TokenPurpose::Auth,
+        sid: None,
+        roles: vec![],
+        scopes: vec![],
+        new_email: None,
+    })
+    .unwrap();
+    // Validly signed and unexpired, but issued for a trusted device rather than a session
+    let trusted_device_token = create_token(&Claims {
+        sub: Secret::new("device@example.com".to_string()),
+        exp: now + 3600,
+        iat: now,
+        jti: Uuid::new_v4().to_string(),
+        purpose: TokenPurpose::TrustedDevice,
+        sid: None,
+        roles: vec![],
+        scopes: vec![],
+        new_email: None,
+    })
+    .unwrap();
+
+    let logout_response = app.post_logout().await;
+    assert_eq!(logout_response.status(), 200);
+
+    for inactive_token in [
+        expired_token.expose_secret(),
+        trusted_device_token.expose_secret(),
+        token.expose_secret(),
+        "malformed",
+    ] {
+        let response = app.post_introspect(inactive_token, client_secret()).await;
+        assert_eq!(response.status(), 200);
+        let body = response.json::<Value>().await.unwrap();
+        assert_eq!(body, serde_json::json!({ "active": false }));
+    }
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_return_401_if_client_not_authenticated() {
+    let (mut app, token) = create_app_with_logged_in_token().await;
+
+    let response = app.post_introspect(token.expose_secret(), None).await;
+    assert_eq!(response.status(), 401);
+
+    let response = app.post_introspect(token.expose_secret(), Some("wrong-secret")).await;
+    assert_eq!(response.status(), 401);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_return_422_if_token_missing() {
+    let mut app = RESTTestApp::new().await;
+
+    let response = app
+        .http_client
+        .post(format!("{}/introspect", app.address))
+        .form(&[("token_type_hint", "access_token")])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 422);
+
+    app.clean_up().await.unwrap();
+}
@@ -0,0 +1,47 @@
+use jsonwebtoken::{decode_header, jwk::JwkSet};
+use serde_json::json;
+
+use auth_service::utils::constants::JWT_COOKIE_NAME;
+
+use crate::helpers::{get_random_email, verify_email, RESTTestApp};
+
+#[tokio::test]
+async fn should_return_200_with_key_set() {
+    let mut app = RESTTestApp::new().await;
+
+    let response = app.get_jwks().await;
+    assert_eq!(response.status(), 200);
+
+    let jwks = response
+        .json::<JwkSet>()
+        .await
+        .expect("[ERROR][should_return_200_with_key_set] Could not deserialize JWKS");
+    assert!(jwks.keys.iter().all(|jwk| jwk.common.key_id.is_some()));
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_issue_auth_tokens_with_kid_header() {
+    let mut app = RESTTestApp::new().await;
+    let email = get_random_email();
+    let body = json!({
+        "email": email,
+        "password": "P@ssw0rd",
+        "requires2FA": false,
+    });
+    assert_eq!(app.post_signup(&body).await.status(), 201);
+    verify_email(&app, &email).await;
+
+    let response = app.post_login(&body).await;
+    assert_eq!(response.status(), 200);
+    let auth_cookie = response
+        .cookies()
+        .find(|c| c.name() == JWT_COOKIE_NAME)
+        .expect("[ERROR][should_issue_auth_tokens_with_kid_header] No auth cookie returned");
+
+    let header = decode_header(auth_cookie.value()).unwrap();
+    assert!(header.kid.is_some());
+
+    app.clean_up().await.unwrap();
+}
@@ -6,23 +6,24 @@ use serde_json::{json, Value};
 
 use auth_service::{
     domain::{
-        data_stores::{TwoFACodeStore, UserStore},
+        data_stores::{LoginAttemptId, LoginFailureStore, TwoFACodeStore, UserStore},
         email::Email,
         password::Password,
-        user::NewUser,
+        user::{NewUser, TwoFAMethod},
     },
     routes::login::TwoFactorAuthResponse,
     services::app_state::{AppServices, AppState},
     utils::{
-        auth::{validate_token, TokenPurpose},
-        constants::JWT_COOKIE_NAME,
+        auth::{validate_token, AccountUnlockToken, TokenPurpose},
+        constants::{JWT_COOKIE_NAME, LOGIN_LOCKOUT_SECONDS},
     },
 };
 use wiremock::{
-    matchers::{method, path},
+    matchers::{body_string_contains, method, path},
     Mock, ResponseTemplate,
 };
 
+use crate::db::{set_email_unverified, set_outdated_password_hash};
 use crate::helpers::{get_random_email, RESTTestApp};
 
 fn create_login_body(email: &str, password: &str) -> Value {
@@ -32,21 +33,22 @@ fn create_login_body(email: &str, password: &str) -> Value {
     })
 }
 
-async fn create_new_user(email: &str, password: &str, requires_2fa: bool) -> NewUser {
+async fn create_new_user(email: &str, password: &str, two_fa_method: TwoFAMethod) -> NewUser {
     let email = Email::parse(Secret::new(email.to_string())).unwrap();
     let password = Password::parse(Secret::new(password.to_string())).await.unwrap();
     NewUser {
         email,
         password,
-        requires_2fa,
+        two_fa_method,
     }
 }
 
-async fn create_existing_user<S: AppServices>(app_state: Arc<AppState<S>>, requires_2fa: bool) -> NewUser {
+async fn create_existing_user<S: AppServices>(app_state: Arc<AppState<S>>, two_fa_method: TwoFAMethod) -> NewUser {
     let random_email = get_random_email();
-    let user = create_new_user(&random_email, "P@assw0rd", requires_2fa).await;
+    let user = create_new_user(&random_email, "P@assw0rd", two_fa_method).await;
     let mut user_store = app_state.user_store.write().await;
     user_store.add_user(user.clone()).await.unwrap();
+    user_store.mark_email_verified(&user.email).await.unwrap();
     user
 }
 
@@ -68,10 +70,6 @@ async fn should_return_422_if_malformed_credentials(#[case] test_case: serde_jso
 }
 
 #[rstest]
-#[case::empty_password("test@example.com", "")]
-#[case::weak_password_no_special_char("test@example.com", "password")]
-#[case::weak_password_no_number("test@example.com", "Password")]
-#[case::weak_password_no_uppercase("test@example.com", "passw0rd")]
 #[case::invalid_email_no_at("test_example.com", "P@ssword123")]
 #[case::invalid_email_no_dot("test@example_com", "P@ssword123")]
 #[case::empty_email("", "P@ssword123")]
@@ -91,10 +89,33 @@ async fn should_return_400_if_invalid_input(#[case] email: &str, #[case] passwor
     app.clean_up().await.unwrap();
 }
 
+// Strength rules only apply when a password is set, so a weak one is just a wrong one
+#[rstest]
+#[case::empty_password("")]
+#[case::weak_password_no_special_char("password")]
+#[case::weak_password_no_number("Password")]
+#[case::weak_password_no_uppercase("passw0rd")]
+#[tokio::test]
+async fn should_return_401_if_weak_password_does_not_match(#[case] password: &str) {
+    let mut app = RESTTestApp::new().await;
+    let user = create_existing_user(app.app_state.clone(), TwoFAMethod::None).await;
+    let test_case = create_login_body(user.email.as_ref().expose_secret(), password);
+    let response = app.post_login(&test_case).await;
+
+    assert_eq!(
+        response.status(),
+        401,
+        "[ERROR][should_return_401_if_weak_password_does_not_match] Failed for input {:?}",
+        test_case
+    );
+
+    app.clean_up().await.unwrap();
+}
+
 #[tokio::test]
 async fn should_return_401_if_incorrect_credentials() {
     let mut app = RESTTestApp::new().await;
-    let user = create_existing_user(app.app_state.clone(), false).await;
+    let user = create_existing_user(app.app_state.clone(), TwoFAMethod::None).await;
     let login_body =
         json!({ "email": user.email.as_ref().expose_secret(),  "password": "Inv@lid_passw0rd".to_string() });
     let login_response = app.post_login(&login_body).await;
@@ -112,7 +133,7 @@ async fn should_return_401_if_incorrect_credentials() {
 #[tokio::test]
 async fn should_return_200_if_valid_credentials_and_2fs_disabled() {
     let mut app = RESTTestApp::new().await;
-    let user = create_existing_user(app.app_state.clone(), false).await;
+    let user = create_existing_user(app.app_state.clone(), TwoFAMethod::None).await;
     let login_body = create_login_body(
         user.email.as_ref().expose_secret(),
         user.password.as_ref().expose_secret(),
@@ -150,7 +171,7 @@ async fn should_return_200_if_valid_credentials_and_2fs_disabled() {
 #[tokio::test]
 async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
     let mut app = RESTTestApp::new().await;
-    let user = create_existing_user(app.app_state.clone(), true).await;
+    let user = create_existing_user(app.app_state.clone(), TwoFAMethod::Email).await;
     let login_body = create_login_body(
         user.email.as_ref().expose_secret(),
         user.password.as_ref().expose_secret(),
@@ -173,14 +194,216 @@ async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
         .await
         .expect("[ERROR][should_return_206_if_valid_credentials_and_2fa_enabled] Failed to parse login response body");
     assert_eq!(response_body.message, "2FA required");
+    assert_eq!(response_body.two_fa_method, TwoFAMethod::Email);
 
     let two_fa_code_store = app.app_state.two_fa_code_store.read().await;
-    let (login_attempt_id, _) = two_fa_code_store.get_code(&user.email).await.unwrap();
-    assert_eq!(
-        &response_body.login_attempt_id,
-        login_attempt_id.as_ref().expose_secret()
-    );
+    let login_attempt_id = LoginAttemptId::parse(Secret::new(response_body.login_attempt_id.clone())).unwrap();
+    assert!(two_fa_code_store.get_code(&user.email, &login_attempt_id).await.is_ok());
 
     drop(two_fa_code_store);
     app.clean_up().await.unwrap();
 }
+
+#[tokio::test]
+async fn should_delay_login_after_repeated_failures() {
+    let mut app = RESTTestApp::new().await;
+    let user = create_existing_user(app.app_state.clone(), TwoFAMethod::None).await;
+    let email = user.email.as_ref().expose_secret();
+    let wrong_login_body = create_login_body(email, "Inv@lid_passw0rd");
+    let login_body = create_login_body(email, user.password.as_ref().expose_secret());
+
+    for _ in 0..3 {
+        assert_eq!(app.post_login(&wrong_login_body).await.status(), 401);
+    }
+    // Even the right password has to wait out the delay
+    assert_eq!(app.post_login(&login_body).await.status(), 429);
+
+    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    assert_eq!(app.post_login(&login_body).await.status(), 200);
+    let failures = app
+        .app_state
+        .login_failure_store
+        .read()
+        .await
+        .get_failures(&user.email)
+        .await
+        .unwrap();
+    assert_eq!(failures, None);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_throttle_parallel_login_attempts() {
+    let mut app = RESTTestApp::new().await;
+    let user = create_existing_user(app.app_state.clone(), TwoFAMethod::None).await;
+    let wrong_login_body = create_login_body(user.email.as_ref().expose_secret(), "Inv@lid_passw0rd");
+
+    // Each attempt is counted before its password is checked, so the burst can't all pass on the same count
+    let responses = tokio::join!(
+        app.post_login(&wrong_login_body),
+        app.post_login(&wrong_login_body),
+        app.post_login(&wrong_login_body),
+        app.post_login(&wrong_login_body),
+        app.post_login(&wrong_login_body),
+        app.post_login(&wrong_login_body),
+        app.post_login(&wrong_login_body),
+        app.post_login(&wrong_login_body),
+    );
+    let statuses = [
+        responses.0.status(),
+        responses.1.status(),
+        responses.2.status(),
+        responses.3.status(),
+        responses.4.status(),
+        responses.5.status(),
+        responses.6.status(),
+        responses.7.status(),
+    ];
+    let checked = statuses.iter().filter(|status| status.as_u16() == 401).count();
+    assert!(checked <= 3, "{checked} attempts had their password checked");
+    // The rest are turned away as too soon, or as locked once the attempts in flight reach the threshold
+    assert!(statuses.iter().all(|status| [401, 429, 423].contains(&status.as_u16())));
+
+    let failures = app
+        .app_state
+        .login_failure_store
+        .read()
+        .await
+        .get_failures(&user.email)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(failures.count as usize, checked);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_lock_account_and_email_unlock_link() {
+    let mut app = RESTTestApp::new().await;
+    let user = create_existing_user(app.app_state.clone(), TwoFAMethod::None).await;
+    let email = user.email.as_ref().expose_secret();
+    let login_body = create_login_body(email, user.password.as_ref().expose_secret());
+
+    Mock::given(path("/email/withTemplate"))
+        .and(method("POST"))
+        .and(body_string_contains("account-locked"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let mut login_failure_store = app.app_state.login_failure_store.write().await;
+    for _ in 0..4 {
+        login_failure_store
+            .record_failure(&user.email, *LOGIN_LOCKOUT_SECONDS)
+            .await
+            .unwrap();
+    }
+    drop(login_failure_store);
+    // Wait out the delay from the fourth failure
+    tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+
+    let response = app.post_login(&create_login_body(email, "Inv@lid_passw0rd")).await;
+    assert_eq!(response.status(), 423);
+    assert_eq!(app.post_login(&login_body).await.status(), 423);
+
+    let token = AccountUnlockToken::new(&user.email, *LOGIN_LOCKOUT_SECONDS)
+        .unwrap()
+        .expose_secret_string();
+    // Opening the link only shows the page
+    let response = app.get_account_unlock(&token).await;
+    assert_eq!(response.status(), 200);
+    assert!(response.text().await.unwrap().contains("<html"));
+    assert_eq!(app.post_login(&login_body).await.status(), 423);
+
+    assert_eq!(app.post_account_unlock(&token).await.status(), 200);
+    assert_eq!(app.post_login(&login_body).await.status(), 200);
+
+    // The link only works once
+    assert_eq!(app.post_account_unlock(&token).await.status(), 401);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn unlock_should_return_401_if_invalid_token() {
+    let mut app = RESTTestApp::new().await;
+
+    assert_eq!(app.post_account_unlock("invalid_token").await.status(), 401);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_upgrade_outdated_password_hash_on_login() {
+    let mut app = RESTTestApp::new().await;
+    let user = create_existing_user(app.app_state.clone(), TwoFAMethod::None).await;
+    let (email, password) = (
+        user.email.as_ref().expose_secret(),
+        user.password.as_ref().expose_secret(),
+    );
+    set_outdated_password_hash(&app.test_db_name, email, password).await;
+    let counts = app
+        .app_state
+        .user_store
+        .read()
+        .await
+        .count_password_hashes()
+        .await
+        .unwrap();
+    assert_eq!(counts.outdated, 1);
+
+    // A failed login leaves the old hash in place
+    let response = app.post_login(&create_login_body(email, "Inv@lid_passw0rd")).await;
+    assert_eq!(response.status(), 401);
+    let counts = app
+        .app_state
+        .user_store
+        .read()
+        .await
+        .count_password_hashes()
+        .await
+        .unwrap();
+    assert_eq!(counts.outdated, 1);
+
+    assert_eq!(app.post_login(&create_login_body(email, password)).await.status(), 200);
+    let counts = app
+        .app_state
+        .user_store
+        .read()
+        .await
+        .count_password_hashes()
+        .await
+        .unwrap();
+    assert_eq!(counts.outdated, 0);
+    assert_eq!(app.post_login(&create_login_body(email, password)).await.status(), 200);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_not_upgrade_password_hash_for_unverified_email() {
+    let mut app = RESTTestApp::new().await;
+    let user = create_existing_user(app.app_state.clone(), TwoFAMethod::None).await;
+    let (email, password) = (
+        user.email.as_ref().expose_secret(),
+        user.password.as_ref().expose_secret(),
+    );
+    set_outdated_password_hash(&app.test_db_name, email, password).await;
+    set_email_unverified(&app.test_db_name, email).await;
+
+    assert_eq!(app.post_login(&create_login_body(email, password)).await.status(), 403);
+    let counts = app
+        .app_state
+        .user_store
+        .read()
+        .await
+        .count_password_hashes()
+        .await
+        .unwrap();
+    assert_eq!(counts.outdated, 1);
+
+    app.clean_up().await.unwrap();
+}
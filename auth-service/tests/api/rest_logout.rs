@@ -2,8 +2,9 @@ use auth_service::{
     api::rest::ErrorResponse, domain::data_stores::BannedTokenStore, utils::constants::JWT_COOKIE_NAME,
 };
 use reqwest::Url;
+use secrecy::Secret;
 
-use crate::helpers::{create_app_with_logged_in_token, RESTTestApp};
+use crate::helpers::{create_app_with_logged_in_token, get_random_email, signup_and_login, RESTTestApp};
 
 #[tokio::test]
 async fn should_return_200_if_valid_jwt_cookie() {
@@ -88,3 +89,32 @@ async fn should_return_401_if_invalid_token() {
 
     app.clean_up().await.unwrap();
 }
+
+#[tokio::test]
+async fn should_return_200_with_bearer_header() {
+    let mut app = RESTTestApp::new().await;
+    let (token, _) = signup_and_login(&app, &get_random_email()).await;
+
+    let logout_response = app.post_logout_with_bearer(&token).await;
+    assert_eq!(logout_response.status(), 200);
+
+    let banned_token_store = app.app_state.banned_token_store.read().await;
+    assert!(banned_token_store.check_token(Secret::new(token)).await.is_err());
+
+    drop(banned_token_store);
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_return_401_if_invalid_bearer_token() {
+    let mut app = RESTTestApp::new().await;
+
+    let logout_response = app.post_logout_with_bearer("invalid").await;
+    assert_eq!(logout_response.status(), 401);
+    assert_eq!(
+        logout_response.json::<ErrorResponse>().await.unwrap().error,
+        "Invalid auth token".to_owned()
+    );
+
+    app.clean_up().await.unwrap();
+}
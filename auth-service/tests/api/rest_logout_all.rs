@@ -0,0 +1,137 @@
+use chrono::Utc;
+use secrecy::{ExposeSecret, Secret};
+use serde_json::json;
+use uuid::Uuid;
+
+use auth_service::{
+    api::rest::ErrorResponse,
+    domain::{
+        data_stores::{RefreshToken, RefreshTokenStore, SessionStore},
+        email::Email,
+    },
+    utils::{
+        auth::{create_token, Claims, TokenPurpose},
+        constants::{Epoch, Time, JWT_COOKIE_NAME, MAX_TOKEN_TTL_SECONDS},
+    },
+};
+
+use crate::db::configure_redis;
+use crate::helpers::{get_random_email, login, signup_and_login, RESTTestApp};
+
+#[tokio::test]
+async fn should_revoke_every_session() {
+    let mut app = RESTTestApp::new().await;
+    let email = get_random_email();
+    let (first_auth_token, first_refresh_token) = signup_and_login(&app, &email).await;
+    let (second_auth_token, _) = login(&app, &email).await;
+
+    let response = app.post_logout_all().await;
+    assert_eq!(response.status(), 200);
+    let cookie = response
+        .cookies()
+        .find(|c| c.name() == JWT_COOKIE_NAME)
+        .expect("[ERROR][should_revoke_every_session] No cookie returned");
+    assert!(cookie.value().is_empty());
+
+    for token in [first_auth_token, second_auth_token] {
+        let response = app.post_verify_token(&json!({ "token": token })).await;
+        assert_eq!(response.status(), 401);
+    }
+
+    let refresh_token = RefreshToken::parse(Secret::new(first_refresh_token)).unwrap();
+    assert!(app
+        .app_state
+        .refresh_token_store
+        .read()
+        .await
+        .get_token(&refresh_token)
+        .await
+        .is_err());
+
+    let email = Email::parse(Secret::new(email)).unwrap();
+    let sessions = app
+        .app_state
+        .session_store
+        .read()
+        .await
+        .get_sessions(&email)
+        .await
+        .unwrap();
+    assert!(sessions.is_empty());
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_reject_tokens_issued_before_logout_all() {
+    let mut app = RESTTestApp::new().await;
+    let email = get_random_email();
+    signup_and_login(&app, &email).await;
+
+    let now = Utc::now().timestamp() as Epoch;
+    let stale_token = create_token(&Claims {
+        sub: Secret::new(email.clone()),
+        exp: now + 60,
+        iat: now - 60,
+        jti: Uuid::new_v4().to_string(),
+        purpose: TokenPurpose::Auth,
+        sid: None,
+        roles: vec![],
+        scopes: vec![],
+        new_email: None,
+    })
+    .unwrap();
+
+    let response = app
+        .post_verify_token(&json!({ "token": stale_token.expose_secret() }))
+        .await;
+    assert_eq!(response.status(), 200);
+
+    assert_eq!(app.post_logout_all().await.status(), 200);
+
+    let response = app
+        .post_verify_token(&json!({ "token": stale_token.expose_secret() }))
+        .await;
+    assert_eq!(response.status(), 401);
+
+    let (fresh_token, _) = login(&app, &email).await;
+    let response = app.post_verify_token(&json!({ "token": fresh_token })).await;
+    assert_eq!(response.status(), 200);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_keep_cutoff_until_longest_lived_token_expires() {
+    let mut app = RESTTestApp::new().await;
+    let email = get_random_email();
+    signup_and_login(&app, &email).await;
+
+    assert_eq!(app.post_logout_all().await.status(), 200);
+
+    // A revert link lasts 7 days, so a cutoff that expired sooner would let an older one through again
+    let redis_conn = configure_redis().await;
+    let ttl: i64 = redis::cmd("TTL")
+        .arg(format!("tokens_not_before:{email}"))
+        .query_async(&mut *redis_conn.write().await)
+        .await
+        .unwrap();
+    assert!(ttl > (Time::Days7 as i64) - 60, "{ttl}");
+    assert!(ttl <= *MAX_TOKEN_TTL_SECONDS as i64);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = RESTTestApp::new().await;
+
+    let response = app.post_logout_all().await;
+    assert_eq!(response.status(), 400);
+    assert_eq!(
+        response.json::<ErrorResponse>().await.unwrap().error,
+        "Missing auth token".to_owned()
+    );
+
+    app.clean_up().await.unwrap();
+}
@@ -1,4 +1,4 @@
-use crate::helpers::{get_random_email, RESTTestApp};
+use crate::helpers::{get_random_email, signup_and_login, verify_email, RESTTestApp};
 use auth_service::utils::{
     auth::{validate_token, TokenPurpose},
     constants::JWT_COOKIE_NAME,
@@ -130,6 +130,7 @@ async fn reset_password_should_return_200_with_cookie_if_valid_token() {
         signup_response
     );
     assert_eq!(signup_response.status(), 201);
+    verify_email(&app, &email).await;
 
     Mock::given(path("/email/withTemplate"))
         .and(method("POST"))
@@ -201,6 +202,44 @@ async fn reset_password_should_return_200_with_cookie_if_valid_token() {
     app.clean_up().await.unwrap();
 }
 
+#[tokio::test]
+async fn reset_password_should_revoke_existing_sessions() {
+    let mut app = RESTTestApp::new().await;
+    let email = get_random_email();
+    let (old_auth_token, _) = signup_and_login(&app, &email).await;
+
+    Mock::given(path("/email/withTemplate"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_initiate_password_reset(&json!({ "email": email })).await;
+    let reset_token = app.get_password_reset_token(&email).await.unwrap();
+
+    let reset_body = json!({
+        "token": reset_token,
+        "new_password": "NewP@ssw0rd123"
+    });
+    let reset_response = app.post_reset_password(&reset_body).await;
+    assert_eq!(reset_response.status(), 200);
+
+    let response = app.post_verify_token(&json!({ "token": old_auth_token })).await;
+    assert_eq!(response.status(), 401);
+
+    let new_auth_token = reset_response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("[ERROR][reset_password_should_revoke_existing_sessions] No auth cookie found")
+        .value()
+        .to_string();
+    let response = app.post_verify_token(&json!({ "token": new_auth_token })).await;
+    assert_eq!(response.status(), 200);
+
+    app.clean_up().await.unwrap();
+}
+
 #[tokio::test]
 async fn reset_password_should_return_401_if_invalid_token_structure() {
     let mut app = RESTTestApp::new().await;
@@ -0,0 +1,264 @@
+use secrecy::{ExposeSecret, Secret};
+use serde_json::json;
+use wiremock::{
+    matchers::{body_string_contains, method, path, path_regex},
+    Mock, ResponseTemplate,
+};
+
+use auth_service::{
+    api::rest::ErrorResponse,
+    domain::{
+        data_stores::{LoginAttemptId, TwoFACodeStore},
+        email::Email,
+        user::TwoFAMethod,
+    },
+    routes::{
+        login::TwoFactorAuthResponse,
+        phone::{PhoneNumberResponse, PhoneVerificationResponse},
+    },
+    utils::constants::PHONE_VERIFICATION_MAX_SENDS,
+};
+
+use crate::helpers::{get_random_email, signup_and_login, RESTTestApp};
+
+const TEST_PASSWORD: &str = "P@ssw0rd";
+const TEST_PHONE_NUMBER: &str = "+14155552671";
+
+async fn create_logged_in_app(expected_sms_calls: u64) -> (RESTTestApp, Email) {
+    let app = RESTTestApp::new().await;
+    let email = get_random_email();
+
+    Mock::given(path_regex(r"^/2010-04-01/Accounts/[^/]+/Messages\.json$"))
+        .and(method("POST"))
+        .and(body_string_contains("To=%2B14155552671"))
+        .respond_with(ResponseTemplate::new(201))
+        .expect(expected_sms_calls)
+        .mount(&app.sms_server)
+        .await;
+
+    signup_and_login(&app, &email).await;
+    (app, Email::parse(Secret::new(email)).unwrap())
+}
+
+async fn stored_code(app: &RESTTestApp, email: &Email, id: &str) -> String {
+    let id = LoginAttemptId::parse(Secret::new(id.to_string())).unwrap();
+    app.app_state
+        .phone_verification_code_store
+        .read()
+        .await
+        .get_code(email, &id)
+        .await
+        .unwrap()
+        .expose_secret_string()
+}
+
+/// Submits the test number and confirms it with the texted code.
+async fn verify_phone_number(app: &RESTTestApp, email: &Email) {
+    let response = app.post_phone(&json!({ "phoneNumber": TEST_PHONE_NUMBER })).await;
+    assert_eq!(response.status(), 200);
+    let verification: PhoneVerificationResponse = response.json().await.unwrap();
+
+    let code = stored_code(app, email, &verification.verification_id).await;
+    let verify_body = json!({
+        "verificationId": verification.verification_id,
+        "code": code,
+    });
+    assert_eq!(app.post_phone_verify(&verify_body).await.status(), 200);
+}
+
+#[tokio::test]
+async fn should_return_400_for_invalid_phone_number() {
+    let (mut app, _) = create_logged_in_app(0).await;
+
+    let response = app.post_phone(&json!({ "phoneNumber": "555-2671" })).await;
+    assert_eq!(response.status(), 400);
+    assert_eq!(
+        response.json::<ErrorResponse>().await.unwrap().error,
+        "Invalid phone number".to_owned()
+    );
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_verify_phone_number_with_texted_code() {
+    let (mut app, email) = create_logged_in_app(1).await;
+    Mock::given(path("/email/withTemplate"))
+        .and(method("POST"))
+        .and(body_string_contains("phone-number-changed"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_phone(&json!({ "phoneNumber": "+1 (415) 555-2671" })).await;
+    assert_eq!(response.status(), 200);
+    let verification: PhoneVerificationResponse = response.json().await.unwrap();
+
+    let phone: PhoneNumberResponse = app.get_phone().await.json().await.unwrap();
+    assert_eq!(phone.phone_number, None);
+    assert_eq!(phone.pending_phone_number.as_deref(), Some(TEST_PHONE_NUMBER));
+
+    let code = stored_code(&app, &email, &verification.verification_id).await;
+    let verify_body = json!({
+        "verificationId": verification.verification_id,
+        "code": code,
+    });
+    let response = app.post_phone_verify(&verify_body).await;
+    assert_eq!(response.status(), 200);
+    let phone: PhoneNumberResponse = response.json().await.unwrap();
+    assert_eq!(phone.phone_number.as_deref(), Some(TEST_PHONE_NUMBER));
+    assert_eq!(phone.pending_phone_number, None);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_reject_wrong_verification_code() {
+    let (mut app, email) = create_logged_in_app(1).await;
+
+    let response = app.post_phone(&json!({ "phoneNumber": TEST_PHONE_NUMBER })).await;
+    let verification: PhoneVerificationResponse = response.json().await.unwrap();
+    let code = stored_code(&app, &email, &verification.verification_id).await;
+    let wrong_code = if code == "000000" { "111111" } else { "000000" };
+
+    let verify_body = json!({
+        "verificationId": verification.verification_id,
+        "code": wrong_code,
+    });
+    assert_eq!(app.post_phone_verify(&verify_body).await.status(), 401);
+
+    let phone: PhoneNumberResponse = app.get_phone().await.json().await.unwrap();
+    assert_eq!(phone.phone_number, None);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn verification_id_should_not_be_accepted_as_login_attempt() {
+    let (mut app, email) = create_logged_in_app(1).await;
+
+    let response = app.post_phone(&json!({ "phoneNumber": TEST_PHONE_NUMBER })).await;
+    let verification: PhoneVerificationResponse = response.json().await.unwrap();
+    let code = stored_code(&app, &email, &verification.verification_id).await;
+
+    let verify_2fa_body = json!({
+        "email": email.as_ref().expose_secret(),
+        "loginAttemptId": verification.verification_id,
+        "2FACode": code,
+    });
+    assert_eq!(app.post_verify_2fa(&verify_2fa_body).await.status(), 401);
+
+    let resend_body = json!({
+        "email": email.as_ref().expose_secret(),
+        "loginAttemptId": verification.verification_id,
+    });
+    assert_eq!(app.post_resend_2fa(&resend_body).await.status(), 401);
+
+    // The code still confirms the number
+    let verify_body = json!({
+        "verificationId": verification.verification_id,
+        "code": code,
+    });
+    assert_eq!(app.post_phone_verify(&verify_body).await.status(), 200);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn enable_sms_should_require_verified_phone_number() {
+    let (mut app, _) = create_logged_in_app(0).await;
+
+    let body = json!({ "password": TEST_PASSWORD, "twoFAMethod": "sms" });
+    let response = app.post_2fa_enable(&body).await;
+    assert_eq!(response.status(), 400);
+    assert_eq!(
+        response.json::<ErrorResponse>().await.unwrap().error,
+        "Phone number not verified".to_owned()
+    );
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn login_should_text_code_when_sms_enabled() {
+    // One text to verify the number and one for the login
+    let (mut app, email) = create_logged_in_app(2).await;
+    // One email for the new number and one for the 2FA change
+    Mock::given(path("/email/withTemplate"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    verify_phone_number(&app, &email).await;
+    let body = json!({ "password": TEST_PASSWORD, "twoFAMethod": "sms" });
+    assert_eq!(app.post_2fa_enable(&body).await.status(), 200);
+
+    let login_body = json!({
+        "email": email.expose_secret_string(),
+        "password": TEST_PASSWORD,
+    });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status(), 206);
+    let response: TwoFactorAuthResponse = response.json().await.unwrap();
+    assert_eq!(response.two_fa_method, TwoFAMethod::Sms);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn changing_number_should_require_reauthentication_when_sms_enabled() {
+    // One text to verify the number and one for the replacement
+    let (mut app, email) = create_logged_in_app(2).await;
+    Mock::given(path("/email/withTemplate"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    verify_phone_number(&app, &email).await;
+    let body = json!({ "password": TEST_PASSWORD, "twoFAMethod": "sms" });
+    assert_eq!(app.post_2fa_enable(&body).await.status(), 200);
+
+    let response = app.post_phone(&json!({ "phoneNumber": TEST_PHONE_NUMBER })).await;
+    assert_eq!(response.status(), 401);
+    let body = json!({ "phoneNumber": TEST_PHONE_NUMBER, "password": "Wr0ngP@ssword" });
+    assert_eq!(app.post_phone(&body).await.status(), 401);
+
+    let phone: PhoneNumberResponse = app.get_phone().await.json().await.unwrap();
+    assert_eq!(phone.pending_phone_number, None);
+
+    let body = json!({ "phoneNumber": TEST_PHONE_NUMBER, "password": TEST_PASSWORD });
+    assert_eq!(app.post_phone(&body).await.status(), 200);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_limit_verification_texts_per_user() {
+    let max_sends = *PHONE_VERIFICATION_MAX_SENDS;
+    // The last text goes to another user
+    let (mut app, _) = create_logged_in_app(u64::from(max_sends) + 1).await;
+
+    for _ in 0..max_sends {
+        let response = app.post_phone(&json!({ "phoneNumber": TEST_PHONE_NUMBER })).await;
+        assert_eq!(response.status(), 200);
+    }
+
+    let response = app.post_phone(&json!({ "phoneNumber": TEST_PHONE_NUMBER })).await;
+    assert_eq!(response.status(), 429);
+    assert_eq!(
+        response.json::<ErrorResponse>().await.unwrap().error,
+        "Too many verification codes requested, please try again later".to_owned()
+    );
+
+    // Other users keep their own allowance
+    let other_email = get_random_email();
+    signup_and_login(&app, &other_email).await;
+    let response = app.post_phone(&json!({ "phoneNumber": TEST_PHONE_NUMBER })).await;
+    assert_eq!(response.status(), 200);
+
+    app.clean_up().await.unwrap();
+}
@@ -0,0 +1,162 @@
+use serde_json::{json, Value};
+use wiremock::{
+    matchers::{body_string_contains, method, path},
+    Mock, ResponseTemplate,
+};
+
+use auth_service::routes::{
+    login::TwoFactorAuthResponse,
+    recovery_codes::{RecoveryCodesResponse, RecoveryCodesStatusResponse},
+};
+
+use crate::helpers::{get_random_email, verify_email, RESTTestApp};
+
+const TEST_PASSWORD: &str = "P@ssw0rd";
+
+async fn signup_with_2fa(app: &RESTTestApp, email: &str) -> Vec<String> {
+    let signup_body = json!({
+        "email": email,
+        "password": TEST_PASSWORD,
+        "requires2FA": true,
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status(), 201);
+    verify_email(app, email).await;
+    let body: Value = response.json().await.unwrap();
+    serde_json::from_value(body["recoveryCodes"].clone()).unwrap()
+}
+
+async fn verify_with_code(app: &RESTTestApp, email: &str, code: &str) -> reqwest::Response {
+    let response = app
+        .post_login(&json!({ "email": email, "password": TEST_PASSWORD }))
+        .await;
+    assert_eq!(response.status(), 206);
+    let login_response: TwoFactorAuthResponse = response.json().await.unwrap();
+
+    let verify_body = json!({
+        "email": email,
+        "loginAttemptId": login_response.login_attempt_id,
+        "2FACode": code,
+    });
+    app.post_verify_2fa(&verify_body).await
+}
+
+async fn mount_email_server(app: &RESTTestApp) {
+    Mock::given(path("/email/withTemplate"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+}
+
+#[tokio::test]
+async fn signup_with_2fa_should_return_recovery_codes() {
+    let mut app = RESTTestApp::new().await;
+
+    let codes = signup_with_2fa(&app, &get_random_email()).await;
+    assert_eq!(codes.len(), 10);
+    assert!(codes
+        .iter()
+        .all(|code| code.len() == 11 && code.chars().nth(5) == Some('-')));
+
+    let signup_body = json!({
+        "email": get_random_email(),
+        "password": TEST_PASSWORD,
+        "requires2FA": false,
+    });
+    let body: Value = app.post_signup(&signup_body).await.json().await.unwrap();
+    assert!(body.get("recoveryCodes").is_none());
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn recovery_code_should_replace_2fa_code_once() {
+    let mut app = RESTTestApp::new().await;
+    mount_email_server(&app).await;
+    let email = get_random_email();
+    let codes = signup_with_2fa(&app, &email).await;
+
+    let response = verify_with_code(&app, &email, &codes[0].to_uppercase()).await;
+    assert_eq!(response.status(), 200);
+
+    let response = app.get_recovery_codes().await;
+    assert_eq!(response.status(), 200);
+    let status: RecoveryCodesStatusResponse = response.json().await.unwrap();
+    assert_eq!(status.remaining, 9);
+
+    let response = verify_with_code(&app, &email, &codes[0]).await;
+    assert_eq!(response.status(), 401);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn regenerating_should_invalidate_previous_codes() {
+    let mut app = RESTTestApp::new().await;
+    mount_email_server(&app).await;
+    let email = get_random_email();
+    let old_codes = signup_with_2fa(&app, &email).await;
+    assert_eq!(verify_with_code(&app, &email, &old_codes[0]).await.status(), 200);
+
+    let response = app.post_recovery_codes(&json!({ "password": TEST_PASSWORD })).await;
+    assert_eq!(response.status(), 200);
+    let new_codes: RecoveryCodesResponse = response.json().await.unwrap();
+    assert_eq!(new_codes.recovery_codes.len(), 10);
+
+    let status: RecoveryCodesStatusResponse = app.get_recovery_codes().await.json().await.unwrap();
+    assert_eq!(status.remaining, 10);
+
+    assert_eq!(verify_with_code(&app, &email, &old_codes[1]).await.status(), 401);
+    assert_eq!(
+        verify_with_code(&app, &email, &new_codes.recovery_codes[0])
+            .await
+            .status(),
+        200
+    );
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn recovery_code_endpoints_should_return_400_without_auth_token() {
+    let mut app = RESTTestApp::new().await;
+
+    assert_eq!(app.get_recovery_codes().await.status(), 400);
+    assert_eq!(
+        app.post_recovery_codes(&json!({ "password": TEST_PASSWORD }))
+            .await
+            .status(),
+        400
+    );
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn regenerating_should_require_reauthentication_and_notify() {
+    let mut app = RESTTestApp::new().await;
+    Mock::given(path("/email/withTemplate"))
+        .and(method("POST"))
+        .and(body_string_contains("recovery-codes-regenerated"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    mount_email_server(&app).await;
+    let email = get_random_email();
+    let codes = signup_with_2fa(&app, &email).await;
+    assert_eq!(verify_with_code(&app, &email, &codes[0]).await.status(), 200);
+
+    assert_eq!(app.post_recovery_codes(&json!({})).await.status(), 401);
+    let response = app.post_recovery_codes(&json!({ "password": "Wr0ngP@ssword" })).await;
+    assert_eq!(response.status(), 401);
+    // Neither attempt touched the existing codes
+    let status: RecoveryCodesStatusResponse = app.get_recovery_codes().await.json().await.unwrap();
+    assert_eq!(status.remaining, 9);
+
+    let response = app.post_recovery_codes(&json!({ "2FACode": codes[1] })).await;
+    assert_eq!(response.status(), 200);
+
+    app.clean_up().await.unwrap();
+}
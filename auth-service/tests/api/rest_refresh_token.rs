@@ -0,0 +1,153 @@
+use reqwest::Url;
+use secrecy::Secret;
+use serde_json::json;
+
+use auth_service::{
+    api::rest::ErrorResponse,
+    utils::{
+        auth::validate_token,
+        constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME},
+    },
+};
+
+use crate::helpers::{get_random_email, verify_email, RESTTestApp};
+
+const LOG_PREFIX: &str = "[TEST][rest_refresh_token]";
+
+async fn create_app_with_refresh_token() -> (RESTTestApp, String) {
+    let app = RESTTestApp::new().await;
+    let email = get_random_email();
+    let signup_body = json!({
+        "email": email,
+        "password": "P@ssw0rd",
+        "requires2FA": false,
+    });
+    let signup_response = app.post_signup(&signup_body).await;
+    assert_eq!(signup_response.status(), 201);
+    verify_email(&app, &email).await;
+
+    let login_body = json!({
+        "email": email,
+        "password": "P@ssw0rd",
+    });
+    let login_response = app.post_login(&login_body).await;
+    assert_eq!(login_response.status(), 200);
+
+    let refresh_cookie = login_response
+        .cookies()
+        .find(|c| c.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .unwrap_or_else(|| panic!("{LOG_PREFIX}[create_app_with_refresh_token] No refresh cookie returned"));
+    assert!(!refresh_cookie.value().is_empty());
+    let refresh_token = refresh_cookie.value().to_string();
+
+    (app, refresh_token)
+}
+
+fn set_refresh_cookie(app: &RESTTestApp, refresh_token: &str) {
+    app.cookie_jar.add_cookie_str(
+        &format!("{REFRESH_TOKEN_COOKIE_NAME}={refresh_token}; HttpOnly; SameSite=Lax; Path=/"),
+        &Url::parse(&app.address).expect("[ERROR][set_refresh_cookie] Failed to parse URL"),
+    );
+}
+
+#[tokio::test]
+async fn should_return_200_with_rotated_tokens_if_valid_refresh_cookie() {
+    let (mut app, refresh_token) = create_app_with_refresh_token().await;
+
+    let response = app.post_refresh_token().await;
+    assert_eq!(response.status(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|c| c.name() == JWT_COOKIE_NAME)
+        .unwrap_or_else(|| panic!("{LOG_PREFIX} No auth cookie returned"));
+    let claims = validate_token(
+        app.app_state.banned_token_store.clone(),
+        Secret::new(auth_cookie.value().to_string()),
+    )
+    .await;
+    assert!(claims.is_ok());
+
+    let new_refresh_cookie = response
+        .cookies()
+        .find(|c| c.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .unwrap_or_else(|| panic!("{LOG_PREFIX} No refresh cookie returned"));
+    assert_ne!(new_refresh_cookie.value(), refresh_token);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_return_400_if_refresh_cookie_missing() {
+    let mut app = RESTTestApp::new().await;
+
+    let response = app.post_refresh_token().await;
+    assert_eq!(response.status(), 400);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("[ERROR][should_return_400_if_refresh_cookie_missing] Could not deserialize response body")
+            .error,
+        "Missing auth token".to_owned()
+    );
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_return_401_if_invalid_refresh_token() {
+    let mut app = RESTTestApp::new().await;
+    set_refresh_cookie(&app, "invalid");
+
+    let response = app.post_refresh_token().await;
+    assert_eq!(response.status(), 401);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_return_401_and_revoke_family_if_refresh_token_reused() {
+    let (mut app, original_refresh_token) = create_app_with_refresh_token().await;
+
+    let response = app.post_refresh_token().await;
+    assert_eq!(response.status(), 200);
+    let rotated_refresh_token = response
+        .cookies()
+        .find(|c| c.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .unwrap_or_else(|| panic!("{LOG_PREFIX} No refresh cookie returned"))
+        .value()
+        .to_string();
+
+    set_refresh_cookie(&app, &original_refresh_token);
+    let response = app.post_refresh_token().await;
+    assert_eq!(
+        response.status(),
+        401,
+        "{LOG_PREFIX}[should_return_401_and_revoke_family_if_refresh_token_reused] Reused token was accepted"
+    );
+
+    set_refresh_cookie(&app, &rotated_refresh_token);
+    let response = app.post_refresh_token().await;
+    assert_eq!(
+        response.status(),
+        401,
+        "{LOG_PREFIX}[should_return_401_and_revoke_family_if_refresh_token_reused] Token family was not revoked"
+    );
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_return_401_if_refresh_token_used_after_logout() {
+    let (mut app, refresh_token) = create_app_with_refresh_token().await;
+
+    let logout_response = app.post_logout().await;
+    assert_eq!(logout_response.status(), 200);
+
+    set_refresh_cookie(&app, &refresh_token);
+    let response = app.post_refresh_token().await;
+    assert_eq!(response.status(), 401);
+
+    app.clean_up().await.unwrap();
+}
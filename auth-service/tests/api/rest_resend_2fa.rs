@@ -0,0 +1,120 @@
+use secrecy::{ExposeSecret, Secret};
+use serde_json::json;
+use uuid::Uuid;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use auth_service::{
+    domain::{
+        data_stores::{LoginAttemptId, TwoFACodeStore},
+        email::Email,
+    },
+    routes::login::TwoFactorAuthResponse,
+};
+
+use crate::helpers::{get_random_email, verify_email, RESTTestApp};
+
+const TEST_PASSWORD: &str = "P@ssw0rd";
+
+async fn create_app_with_login_response(expected_email_calls: u64) -> (RESTTestApp, TwoFactorAuthResponse, Email) {
+    let app = RESTTestApp::new().await;
+    let email = Email::parse(Secret::new(get_random_email())).unwrap();
+
+    Mock::given(path("/email/withTemplate"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        // Plus the verification email sent on signup
+        .expect(expected_email_calls + 1)
+        .mount(&app.email_server)
+        .await;
+
+    let signup_body = json!({
+        "email": email.as_ref().expose_secret(),
+        "password": TEST_PASSWORD,
+        "requires2FA": true,
+    });
+    assert_eq!(app.post_signup(&signup_body).await.status(), 201);
+    verify_email(&app, email.as_ref().expose_secret()).await;
+
+    let login_body = json!({
+        "email": email.as_ref().expose_secret(),
+        "password": TEST_PASSWORD,
+    });
+    let login_response = app.post_login(&login_body).await;
+    assert_eq!(login_response.status(), 206);
+
+    (app, login_response.json().await.unwrap(), email)
+}
+
+#[tokio::test]
+async fn should_return_422_if_malformed_input() {
+    let mut app = RESTTestApp::new().await;
+
+    let response = app.post_resend_2fa(&json!({ "email": "test@example.com" })).await;
+    assert_eq!(response.status(), 422);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_return_401_for_unknown_login_attempt() {
+    let (mut app, _, email) = create_app_with_login_response(1).await;
+
+    let resend_body = json!({
+        "email": email.as_ref().expose_secret(),
+        "loginAttemptId": Uuid::new_v4().to_string(),
+    });
+    let response = app.post_resend_2fa(&resend_body).await;
+    assert_eq!(response.status(), 401);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_send_new_code_for_same_login_attempt() {
+    let (mut app, login_response, email) = create_app_with_login_response(2).await;
+
+    let resend_body = json!({
+        "email": email.as_ref().expose_secret(),
+        "loginAttemptId": login_response.login_attempt_id,
+    });
+    let response = app.post_resend_2fa(&resend_body).await;
+    assert_eq!(response.status(), 200);
+    let resend_response: TwoFactorAuthResponse = response.json().await.unwrap();
+    assert_eq!(resend_response.login_attempt_id, login_response.login_attempt_id);
+
+    let login_attempt_id = LoginAttemptId::parse(Secret::new(login_response.login_attempt_id.clone())).unwrap();
+    let two_fa_code = app
+        .app_state
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&email, &login_attempt_id)
+        .await
+        .unwrap();
+
+    let verify_2fa_body = json!({
+        "email": email.as_ref().expose_secret(),
+        "loginAttemptId": login_response.login_attempt_id,
+        "2FACode": two_fa_code.expose_secret_string(),
+    });
+    assert_eq!(app.post_verify_2fa(&verify_2fa_body).await.status(), 200);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_return_429_if_resent_within_cooldown() {
+    let (mut app, login_response, email) = create_app_with_login_response(2).await;
+
+    let resend_body = json!({
+        "email": email.as_ref().expose_secret(),
+        "loginAttemptId": login_response.login_attempt_id,
+    });
+    assert_eq!(app.post_resend_2fa(&resend_body).await.status(), 200);
+    assert_eq!(app.post_resend_2fa(&resend_body).await.status(), 429);
+
+    app.clean_up().await.unwrap();
+}
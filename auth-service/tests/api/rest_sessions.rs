@@ -0,0 +1,112 @@
+use secrecy::Secret;
+use serde_json::json;
+use uuid::Uuid;
+
+use auth_service::{
+    api::rest::ErrorResponse,
+    domain::data_stores::{RefreshToken, RefreshTokenStore},
+    routes::sessions::SessionResponse,
+};
+
+use crate::helpers::{get_random_email, login, signup_and_login, RESTTestApp};
+
+#[tokio::test]
+async fn should_list_sessions_and_mark_current() {
+    let mut app = RESTTestApp::new().await;
+    let email = get_random_email();
+    signup_and_login(&app, &email).await;
+    login(&app, &email).await;
+
+    let response = app.get_sessions().await;
+    assert_eq!(response.status(), 200);
+    let sessions = response
+        .json::<Vec<SessionResponse>>()
+        .await
+        .expect("[ERROR][should_list_sessions_and_mark_current] Could not deserialize sessions");
+
+    assert_eq!(sessions.len(), 2);
+    assert_eq!(sessions.iter().filter(|session| session.current).count(), 1);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_revoke_other_session_tokens() {
+    let mut app = RESTTestApp::new().await;
+    let email = get_random_email();
+    let (old_auth_token, old_refresh_token) = signup_and_login(&app, &email).await;
+    login(&app, &email).await;
+
+    let sessions = app.get_sessions().await.json::<Vec<SessionResponse>>().await.unwrap();
+    let other_session = sessions.iter().find(|session| !session.current).unwrap();
+
+    let response = app.delete_session(&other_session.id).await;
+    assert_eq!(response.status(), 204);
+
+    let response = app.post_verify_token(&json!({ "token": old_auth_token })).await;
+    assert_eq!(response.status(), 401);
+
+    let refresh_token = RefreshToken::parse(Secret::new(old_refresh_token)).unwrap();
+    let refresh_token_store = app.app_state.refresh_token_store.read().await;
+    assert!(refresh_token_store.get_token(&refresh_token).await.is_err());
+    drop(refresh_token_store);
+
+    let sessions = app.get_sessions().await.json::<Vec<SessionResponse>>().await.unwrap();
+    assert_eq!(sessions.len(), 1);
+    assert!(sessions[0].current);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_return_404_for_unknown_session() {
+    let mut app = RESTTestApp::new().await;
+    signup_and_login(&app, &get_random_email()).await;
+
+    let response = app.delete_session(&Uuid::new_v4().to_string()).await;
+    assert_eq!(response.status(), 404);
+    assert_eq!(
+        response.json::<ErrorResponse>().await.unwrap().error,
+        "Session not found".to_owned()
+    );
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_return_404_for_another_users_session() {
+    let mut app = RESTTestApp::new().await;
+    signup_and_login(&app, &get_random_email()).await;
+    let sessions = app.get_sessions().await.json::<Vec<SessionResponse>>().await.unwrap();
+    let victim_session_id = sessions[0].id.clone();
+
+    signup_and_login(&app, &get_random_email()).await;
+    let response = app.delete_session(&victim_session_id).await;
+    assert_eq!(response.status(), 404);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = RESTTestApp::new().await;
+
+    let response = app.get_sessions().await;
+    assert_eq!(response.status(), 400);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_list_sessions_with_bearer_header() {
+    let mut app = RESTTestApp::new().await;
+    let (token, _) = signup_and_login(&app, &get_random_email()).await;
+
+    let response = app.get_sessions_with_bearer(&token).await;
+    assert_eq!(response.status(), 200);
+    let sessions = response.json::<Vec<SessionResponse>>().await.unwrap();
+    assert_eq!(sessions.len(), 1);
+    assert!(sessions[0].current);
+
+    app.clean_up().await.unwrap();
+}
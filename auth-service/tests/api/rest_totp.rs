@@ -0,0 +1,214 @@
+use chrono::Utc;
+use data_encoding::BASE32_NOPAD;
+use serde_json::json;
+use wiremock::{
+    matchers::{body_string_contains, method, path},
+    Mock, ResponseTemplate,
+};
+
+use auth_service::{
+    domain::user::TwoFAMethod,
+    routes::{login::TwoFactorAuthResponse, recovery_codes::RecoveryCodesResponse, totp::TotpEnrollmentResponse},
+    utils::{
+        constants::JWT_COOKIE_NAME,
+        totp::{code_at_step, time_step},
+    },
+};
+
+use crate::helpers::{get_random_email, signup_and_login, RESTTestApp};
+
+fn code_for(secret: &str, steps_ahead: u64) -> String {
+    let key = BASE32_NOPAD.decode(secret.as_bytes()).unwrap();
+    code_at_step(&key, time_step(Utc::now().timestamp() as u64) + steps_ahead)
+}
+
+async fn enroll_and_confirm(app: &RESTTestApp) -> String {
+    Mock::given(path("/email/withTemplate"))
+        .and(method("POST"))
+        .and(body_string_contains("two-fa-settings-changed"))
+        .respond_with(ResponseTemplate::new(200))
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let response = app.post_totp_enroll().await;
+    assert_eq!(response.status(), 200);
+    let enrollment: TotpEnrollmentResponse = response.json().await.unwrap();
+
+    let response = app
+        .post_totp_confirm(&json!({ "code": code_for(&enrollment.secret, 0), "password": "P@ssw0rd" }))
+        .await;
+    assert_eq!(response.status(), 200);
+    let confirmation: RecoveryCodesResponse = response.json().await.unwrap();
+    assert_eq!(confirmation.recovery_codes.len(), 10);
+    enrollment.secret
+}
+
+#[tokio::test]
+async fn enroll_should_return_secret_and_otpauth_uri() {
+    let mut app = RESTTestApp::new().await;
+    let email = get_random_email();
+    signup_and_login(&app, &email).await;
+
+    let response = app.post_totp_enroll().await;
+    assert_eq!(response.status(), 200);
+
+    let enrollment: TotpEnrollmentResponse = response.json().await.unwrap();
+    assert_eq!(BASE32_NOPAD.decode(enrollment.secret.as_bytes()).unwrap().len(), 20);
+    assert!(enrollment.otpauth_uri.starts_with("otpauth://totp/"));
+    assert!(enrollment
+        .otpauth_uri
+        .contains(&format!("secret={}", enrollment.secret)));
+    assert!(enrollment
+        .otpauth_uri
+        .contains(&urlencoding::encode(&email).to_string()));
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn enroll_should_return_400_without_auth_token() {
+    let mut app = RESTTestApp::new().await;
+
+    let response = app.post_totp_enroll().await;
+    assert_eq!(response.status(), 400);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn confirm_should_return_400_without_pending_enrollment() {
+    let mut app = RESTTestApp::new().await;
+    signup_and_login(&app, &get_random_email()).await;
+
+    let response = app
+        .post_totp_confirm(&json!({ "code": "123456", "password": "P@ssw0rd" }))
+        .await;
+    assert_eq!(response.status(), 400);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn confirm_should_return_400_for_wrong_code() {
+    let mut app = RESTTestApp::new().await;
+    signup_and_login(&app, &get_random_email()).await;
+
+    let enrollment: TotpEnrollmentResponse = app.post_totp_enroll().await.json().await.unwrap();
+    let wrong_code = match code_for(&enrollment.secret, 0).as_str() {
+        "123456" => "654321",
+        _ => "123456",
+    };
+
+    let response = app
+        .post_totp_confirm(&json!({ "code": wrong_code, "password": "P@ssw0rd" }))
+        .await;
+    assert_eq!(response.status(), 400);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn confirm_should_require_reauthentication() {
+    let mut app = RESTTestApp::new().await;
+    let email = get_random_email();
+    signup_and_login(&app, &email).await;
+
+    let enrollment: TotpEnrollmentResponse = app.post_totp_enroll().await.json().await.unwrap();
+    let code = code_for(&enrollment.secret, 0);
+    let response = app.post_totp_confirm(&json!({ "code": code })).await;
+    assert_eq!(response.status(), 401);
+    let response = app
+        .post_totp_confirm(&json!({ "code": code, "password": "Wr0ngP@ssword" }))
+        .await;
+    assert_eq!(response.status(), 401);
+
+    // Still signs in without a second factor
+    let response = app.post_login(&json!({ "email": email, "password": "P@ssw0rd" })).await;
+    assert_eq!(response.status(), 200);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn confirm_should_replace_existing_totp_only_with_current_code() {
+    let mut app = RESTTestApp::new().await;
+    signup_and_login(&app, &get_random_email()).await;
+    let old_secret = enroll_and_confirm(&app).await;
+    Mock::given(path("/email/withTemplate"))
+        .and(method("POST"))
+        .and(body_string_contains("two-fa-settings-changed"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let enrollment: TotpEnrollmentResponse = app.post_totp_enroll().await.json().await.unwrap();
+    let code = code_for(&enrollment.secret, 0);
+    let response = app
+        .post_totp_confirm(&json!({ "code": code, "2FACode": "123456" }))
+        .await;
+    assert_eq!(response.status(), 401);
+    let response = app
+        .post_totp_confirm(&json!({ "code": code, "2FACode": code_for(&old_secret, 1) }))
+        .await;
+    assert_eq!(response.status(), 200);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn login_should_verify_totp_code_without_sending_email() {
+    let mut app = RESTTestApp::new().await;
+    let email = get_random_email();
+    signup_and_login(&app, &email).await;
+    let secret = enroll_and_confirm(&app).await;
+
+    Mock::given(path("/email/withTemplate"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_login(&json!({ "email": email, "password": "P@ssw0rd" })).await;
+    assert_eq!(response.status(), 206);
+    let login_response: TwoFactorAuthResponse = response.json().await.unwrap();
+    assert_eq!(login_response.two_fa_method, TwoFAMethod::Totp);
+
+    // The confirmation burned the current step, so use the next one the skew window allows
+    let verify_body = json!({
+        "email": email,
+        "loginAttemptId": login_response.login_attempt_id,
+        "2FACode": code_for(&secret, 1),
+    });
+    let response = app.post_verify_2fa(&verify_body).await;
+    assert_eq!(response.status(), 200);
+    assert!(response.cookies().any(|c| c.name() == JWT_COOKIE_NAME));
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn verify_2fa_should_reject_replayed_totp_code() {
+    let mut app = RESTTestApp::new().await;
+    let email = get_random_email();
+    signup_and_login(&app, &email).await;
+    let secret = enroll_and_confirm(&app).await;
+    let code = code_for(&secret, 1);
+
+    for expected_status in [200, 401] {
+        let response = app.post_login(&json!({ "email": email, "password": "P@ssw0rd" })).await;
+        let login_response: TwoFactorAuthResponse = response.json().await.unwrap();
+
+        let verify_body = json!({
+            "email": email,
+            "loginAttemptId": login_response.login_attempt_id,
+            "2FACode": code,
+        });
+        let response = app.post_verify_2fa(&verify_body).await;
+        assert_eq!(response.status(), expected_status);
+    }
+
+    app.clean_up().await.unwrap();
+}
@@ -0,0 +1,213 @@
+use secrecy::{ExposeSecret, Secret};
+use serde_json::json;
+use uuid::Uuid;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use auth_service::{
+    api::rest::ErrorResponse,
+    domain::{
+        data_stores::{LoginAttemptId, TwoFACodeStore},
+        email::Email,
+    },
+    routes::{login::TwoFactorAuthResponse, trusted_devices::TrustedDeviceResponse},
+    utils::constants::TRUSTED_DEVICE_COOKIE_NAME,
+};
+
+use crate::helpers::{get_random_email, verify_email, RESTTestApp};
+
+const TEST_PASSWORD: &str = "P@ssw0rd";
+
+async fn create_app_with_2fa_user(expected_email_calls: u64) -> (RESTTestApp, Email) {
+    let app = RESTTestApp::new().await;
+    let email = Email::parse(Secret::new(get_random_email())).unwrap();
+
+    Mock::given(path("/email/withTemplate"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        // Plus the verification email sent on signup
+        .expect(expected_email_calls + 1)
+        .mount(&app.email_server)
+        .await;
+
+    signup_with_2fa(&app, &email).await;
+    (app, email)
+}
+
+async fn signup_with_2fa(app: &RESTTestApp, email: &Email) {
+    let signup_body = json!({
+        "email": email.as_ref().expose_secret(),
+        "password": TEST_PASSWORD,
+        "requires2FA": true,
+    });
+    assert_eq!(app.post_signup(&signup_body).await.status(), 201);
+    verify_email(app, email.as_ref().expose_secret()).await;
+}
+
+async fn post_login(app: &RESTTestApp, email: &Email) -> reqwest::Response {
+    let login_body = json!({
+        "email": email.as_ref().expose_secret(),
+        "password": TEST_PASSWORD,
+    });
+    app.post_login(&login_body).await
+}
+
+/// Logs in through 2FA and returns the `/verify-2fa` response.
+async fn login_with_2fa(app: &RESTTestApp, email: &Email, remember_device: bool) -> reqwest::Response {
+    let login_response = post_login(app, email).await;
+    assert_eq!(login_response.status(), 206);
+    let login_response: TwoFactorAuthResponse = login_response.json().await.unwrap();
+
+    let login_attempt_id = LoginAttemptId::parse(Secret::new(login_response.login_attempt_id.clone())).unwrap();
+    let two_fa_code = app
+        .app_state
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(email, &login_attempt_id)
+        .await
+        .unwrap();
+
+    let verify_2fa_body = json!({
+        "email": email.as_ref().expose_secret(),
+        "loginAttemptId": login_response.login_attempt_id,
+        "2FACode": two_fa_code.expose_secret_string(),
+        "rememberDevice": remember_device,
+    });
+    let response = app.post_verify_2fa(&verify_2fa_body).await;
+    assert_eq!(response.status(), 200);
+    response
+}
+
+fn has_trusted_device_cookie(response: &reqwest::Response) -> bool {
+    response
+        .cookies()
+        .any(|cookie| cookie.name() == TRUSTED_DEVICE_COOKIE_NAME)
+}
+
+#[tokio::test]
+async fn should_skip_2fa_on_remembered_device() {
+    let (mut app, email) = create_app_with_2fa_user(1).await;
+
+    let response = login_with_2fa(&app, &email, true).await;
+    assert!(has_trusted_device_cookie(&response));
+
+    assert_eq!(post_login(&app, &email).await.status(), 200);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_require_2fa_if_device_not_remembered() {
+    let (mut app, email) = create_app_with_2fa_user(2).await;
+
+    let response = login_with_2fa(&app, &email, false).await;
+    assert!(!has_trusted_device_cookie(&response));
+
+    assert_eq!(post_login(&app, &email).await.status(), 206);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_require_2fa_for_another_user_on_remembered_device() {
+    let (mut app, email) = create_app_with_2fa_user(3).await;
+    login_with_2fa(&app, &email, true).await;
+
+    let other_email = Email::parse(Secret::new(get_random_email())).unwrap();
+    signup_with_2fa(&app, &other_email).await;
+
+    assert_eq!(post_login(&app, &other_email).await.status(), 206);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_list_and_revoke_trusted_device() {
+    let (mut app, email) = create_app_with_2fa_user(2).await;
+    login_with_2fa(&app, &email, true).await;
+
+    let response = app.get_trusted_devices().await;
+    assert_eq!(response.status(), 200);
+    let devices = response.json::<Vec<TrustedDeviceResponse>>().await.unwrap();
+    assert_eq!(devices.len(), 1);
+    assert!(devices[0].current);
+
+    assert_eq!(app.delete_trusted_device(&devices[0].id).await.status(), 204);
+
+    let devices = app
+        .get_trusted_devices()
+        .await
+        .json::<Vec<TrustedDeviceResponse>>()
+        .await
+        .unwrap();
+    assert!(devices.is_empty());
+    assert_eq!(post_login(&app, &email).await.status(), 206);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_return_404_for_unknown_trusted_device() {
+    let (mut app, email) = create_app_with_2fa_user(1).await;
+    login_with_2fa(&app, &email, false).await;
+
+    let response = app.delete_trusted_device(&Uuid::new_v4().to_string()).await;
+    assert_eq!(response.status(), 404);
+    assert_eq!(
+        response.json::<ErrorResponse>().await.unwrap().error,
+        "Trusted device not found".to_owned()
+    );
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_revoke_trusted_devices_on_password_reset() {
+    // One 2FA email for each login and one for the reset link
+    let (mut app, email) = create_app_with_2fa_user(3).await;
+    login_with_2fa(&app, &email, true).await;
+
+    let reset_init_body = json!({ "email": email.as_ref().expose_secret() });
+    assert_eq!(app.post_initiate_password_reset(&reset_init_body).await.status(), 200);
+    let reset_token = app
+        .get_password_reset_token(email.as_ref().expose_secret())
+        .await
+        .unwrap();
+
+    let new_password = "NewP@ssw0rd123";
+    let reset_body = json!({
+        "token": reset_token,
+        "new_password": new_password,
+    });
+    assert_eq!(app.post_reset_password(&reset_body).await.status(), 200);
+
+    let login_body = json!({
+        "email": email.as_ref().expose_secret(),
+        "password": new_password,
+    });
+    assert_eq!(app.post_login(&login_body).await.status(), 206);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_revoke_trusted_devices_on_password_change() {
+    // One 2FA email for each login and one password change notification
+    let (mut app, email) = create_app_with_2fa_user(3).await;
+    login_with_2fa(&app, &email, true).await;
+
+    let new_password = "NewP@ssw0rd123";
+    let change_body = json!({ "currentPassword": TEST_PASSWORD, "newPassword": new_password });
+    assert_eq!(app.post_change_password(&change_body).await.status(), 200);
+
+    let login_body = json!({
+        "email": email.as_ref().expose_secret(),
+        "password": new_password,
+    });
+    assert_eq!(app.post_login(&login_body).await.status(), 206);
+
+    app.clean_up().await.unwrap();
+}
@@ -0,0 +1,158 @@
+use chrono::Utc;
+use data_encoding::BASE32_NOPAD;
+use secrecy::Secret;
+use serde_json::json;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use auth_service::{
+    api::rest::ErrorResponse,
+    domain::{data_stores::UserStore, email::Email, user::TwoFAMethod},
+    routes::{totp::TotpEnrollmentResponse, two_fa_settings::TwoFASettingsResponse},
+    utils::totp::{code_at_step, time_step},
+};
+
+use crate::helpers::{get_random_email, signup_and_login, RESTTestApp};
+
+const TEST_PASSWORD: &str = "P@ssw0rd";
+
+async fn create_logged_in_app(expected_email_calls: u64) -> (RESTTestApp, Email) {
+    let app = RESTTestApp::new().await;
+    let email = get_random_email();
+
+    Mock::given(path("/email/withTemplate"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        // Plus the verification email sent on signup
+        .expect(expected_email_calls + 1)
+        .mount(&app.email_server)
+        .await;
+
+    signup_and_login(&app, &email).await;
+    (app, Email::parse(Secret::new(email)).unwrap())
+}
+
+async fn stored_two_fa_method(app: &RESTTestApp, email: &Email) -> TwoFAMethod {
+    app.app_state
+        .user_store
+        .read()
+        .await
+        .get_user(email)
+        .await
+        .unwrap()
+        .two_fa_method
+}
+
+#[tokio::test]
+async fn enable_should_require_correct_password() {
+    let (mut app, email) = create_logged_in_app(0).await;
+
+    assert_eq!(app.post_2fa_enable(&json!({})).await.status(), 401);
+    let response = app.post_2fa_enable(&json!({ "password": "Wr0ngP@ssw0rd" })).await;
+    assert_eq!(response.status(), 401);
+    assert_eq!(stored_two_fa_method(&app, &email).await, TwoFAMethod::None);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn enable_should_switch_to_email_codes_and_notify() {
+    // One notification, then one 2FA email for the next login
+    let (mut app, email) = create_logged_in_app(2).await;
+
+    let response = app.post_2fa_enable(&json!({ "password": TEST_PASSWORD })).await;
+    assert_eq!(response.status(), 200);
+    let settings: TwoFASettingsResponse = response.json().await.unwrap();
+    assert_eq!(settings.two_fa_method, TwoFAMethod::Email);
+    assert_eq!(stored_two_fa_method(&app, &email).await, TwoFAMethod::Email);
+
+    let login_body = json!({
+        "email": email.expose_secret_string(),
+        "password": TEST_PASSWORD,
+    });
+    assert_eq!(app.post_login(&login_body).await.status(), 206);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn enable_should_return_409_if_already_enabled() {
+    let (mut app, _) = create_logged_in_app(1).await;
+
+    let body = json!({ "password": TEST_PASSWORD });
+    assert_eq!(app.post_2fa_enable(&body).await.status(), 200);
+
+    let response = app.post_2fa_enable(&body).await;
+    assert_eq!(response.status(), 409);
+    assert_eq!(
+        response.json::<ErrorResponse>().await.unwrap().error,
+        "2FA already enabled".to_owned()
+    );
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn disable_should_return_400_if_not_enabled() {
+    let (mut app, _) = create_logged_in_app(0).await;
+
+    let response = app.post_2fa_disable(&json!({ "password": TEST_PASSWORD })).await;
+    assert_eq!(response.status(), 400);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn disable_should_accept_password() {
+    let (mut app, email) = create_logged_in_app(2).await;
+    let body = json!({ "password": TEST_PASSWORD });
+    assert_eq!(app.post_2fa_enable(&body).await.status(), 200);
+
+    let response = app.post_2fa_disable(&body).await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(stored_two_fa_method(&app, &email).await, TwoFAMethod::None);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn disable_should_reject_code_for_email_2fa() {
+    let (mut app, email) = create_logged_in_app(1).await;
+    assert_eq!(
+        app.post_2fa_enable(&json!({ "password": TEST_PASSWORD }))
+            .await
+            .status(),
+        200
+    );
+
+    let response = app.post_2fa_disable(&json!({ "2FACode": "123456" })).await;
+    assert_eq!(response.status(), 401);
+    assert_eq!(stored_two_fa_method(&app, &email).await, TwoFAMethod::Email);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn disable_should_accept_current_totp_code() {
+    // One notification for the TOTP confirmation and one for disabling
+    let (mut app, email) = create_logged_in_app(2).await;
+
+    let enrollment: TotpEnrollmentResponse = app.post_totp_enroll().await.json().await.unwrap();
+    let key = BASE32_NOPAD.decode(enrollment.secret.as_bytes()).unwrap();
+    let step = time_step(Utc::now().timestamp() as u64);
+    let response = app
+        .post_totp_confirm(&json!({ "code": code_at_step(&key, step), "password": TEST_PASSWORD }))
+        .await;
+    assert_eq!(response.status(), 200);
+
+    // The confirmation code's step is spent, so use the next one
+    let response = app
+        .post_2fa_disable(&json!({ "2FACode": code_at_step(&key, step + 1) }))
+        .await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(stored_two_fa_method(&app, &email).await, TwoFAMethod::None);
+
+    app.clean_up().await.unwrap();
+}
@@ -4,16 +4,19 @@ use serde_json::{json, Value};
 use uuid::Uuid;
 
 use auth_service::{
-    domain::{data_stores::TwoFACodeStore, email::Email},
+    domain::{
+        data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore},
+        email::Email,
+    },
     routes::login::TwoFactorAuthResponse,
-    utils::constants::JWT_COOKIE_NAME,
+    utils::constants::{JWT_COOKIE_NAME, MAX_PENDING_TWO_FA_ATTEMPTS, TWO_FA_MAX_ATTEMPTS},
 };
 use wiremock::{
     matchers::{method, path},
     Mock, ResponseTemplate,
 };
 
-use crate::helpers::{get_random_email, RESTTestApp};
+use crate::helpers::{get_random_email, verify_email, RESTTestApp};
 
 const LOG_PREFIX: &str = "[TEST][rest_verify_2fa]";
 const TEST_EMAIL: &str = "test@example.com";
@@ -39,6 +42,20 @@ async fn get_two_fa_login_response(app: RESTTestApp, email: Email) -> (RESTTestA
     (app, login_response, email)
 }
 
+fn login_attempt_id(login_response: &TwoFactorAuthResponse) -> LoginAttemptId {
+    LoginAttemptId::parse(Secret::new(login_response.login_attempt_id.clone())).unwrap()
+}
+
+async fn get_stored_code(app: &RESTTestApp, email: &Email, login_response: &TwoFactorAuthResponse) -> TwoFACode {
+    app.app_state
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(email, &login_attempt_id(login_response))
+        .await
+        .unwrap()
+}
+
 async fn create_app_with_login_response(expected_email_calls: u64) -> (RESTTestApp, TwoFactorAuthResponse, Email) {
     let app = RESTTestApp::new().await;
     let email = get_valid_email();
@@ -46,7 +63,8 @@ async fn create_app_with_login_response(expected_email_calls: u64) -> (RESTTestA
     Mock::given(path("/email/withTemplate"))
         .and(method("POST"))
         .respond_with(ResponseTemplate::new(200))
-        .expect(expected_email_calls)
+        // Plus the verification email sent on signup
+        .expect(expected_email_calls + 1)
         .mount(&app.email_server)
         .await;
 
@@ -57,6 +75,7 @@ async fn create_app_with_login_response(expected_email_calls: u64) -> (RESTTestA
     });
     let signup_response = app.post_signup(&signup_body).await;
     assert_eq!(signup_response.status(), 201);
+    verify_email(&app, email.as_ref().expose_secret()).await;
 
     get_two_fa_login_response(app, email).await
 }
@@ -104,9 +123,7 @@ async fn should_return_400_if_invalid_input(
 async fn should_return_401_if_incorrect_credentials() {
     let (mut app, login_response, email) = create_app_with_login_response(1).await;
 
-    let two_fa_code_store = app.app_state.two_fa_code_store.read().await;
-    let (_, two_fa_code) = two_fa_code_store.get_code(&email).await.unwrap();
-    drop(two_fa_code_store);
+    let two_fa_code = get_stored_code(&app, &email, &login_response).await;
 
     let invalid_two_fa_code = match two_fa_code.as_ref().expose_secret().as_str() {
         "123456" => "654321".to_string(),
@@ -140,17 +157,66 @@ async fn should_return_401_if_incorrect_credentials() {
 }
 
 #[tokio::test]
-async fn should_return_401_if_old_code() {
-    let (app, login_response, email) = create_app_with_login_response(2).await;
+async fn should_return_401_if_code_from_other_login_attempt() {
+    let (app, first_login_response, email) = create_app_with_login_response(2).await;
+    let first_code = get_stored_code(&app, &email, &first_login_response).await;
+
+    let (mut app, second_login_response, email) = get_two_fa_login_response(app, email).await;
+    let second_code = get_stored_code(&app, &email, &second_login_response).await;
+    if first_code == second_code {
+        // Vanishingly unlikely, but the codes have to differ for this test to mean anything
+        app.clean_up().await.unwrap();
+        return;
+    }
+
+    let verify_2fa_body = json!({
+        "email": email.as_ref().expose_secret(),
+        "loginAttemptId": second_login_response.login_attempt_id,
+        "2FACode": first_code.as_ref().expose_secret().clone(),
+    });
 
-    let two_fa_code_store = app.app_state.two_fa_code_store.read().await;
-    let (_, two_fa_code) = two_fa_code_store.get_code(&email).await.unwrap();
-    drop(two_fa_code_store);
+    let verify_2fa_response = app.post_verify_2fa(&verify_2fa_body).await;
+    assert_eq!(verify_2fa_response.status(), 401);
+
+    app.clean_up().await.unwrap();
+}
 
-    let (mut app, _, email) = get_two_fa_login_response(app, email).await;
+#[tokio::test]
+async fn should_verify_concurrent_login_attempts_independently() {
+    let (app, first_login_response, email) = create_app_with_login_response(2).await;
+    let (mut app, second_login_response, email) = get_two_fa_login_response(app, email).await;
+
+    for login_response in [first_login_response, second_login_response] {
+        let two_fa_code = get_stored_code(&app, &email, &login_response).await;
+        let verify_2fa_body = json!({
+            "email": email.as_ref().expose_secret(),
+            "loginAttemptId": login_response.login_attempt_id,
+            "2FACode": two_fa_code.as_ref().expose_secret().clone(),
+        });
+
+        let verify_2fa_response = app.post_verify_2fa(&verify_2fa_body).await;
+        assert_eq!(verify_2fa_response.status(), 200);
+    }
 
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_return_401_if_login_attempt_belongs_to_other_user() {
+    let (app, login_response, email) = create_app_with_login_response(2).await;
+    let two_fa_code = get_stored_code(&app, &email, &login_response).await;
+
+    let other_email = get_valid_email();
+    let signup_body = json!({
+        "email": other_email.as_ref().expose_secret(),
+        "password": TEST_PASSWORD,
+        "requires2FA": false,
+    });
+    assert_eq!(app.post_signup(&signup_body).await.status(), 201);
+
+    let mut app = app;
     let verify_2fa_body = json!({
-        "email": email.as_ref().expose_secret(),
+        "email": other_email.as_ref().expose_secret(),
         "loginAttemptId": login_response.login_attempt_id,
         "2FACode": two_fa_code.as_ref().expose_secret().clone(),
     });
@@ -161,13 +227,34 @@ async fn should_return_401_if_old_code() {
     app.clean_up().await.unwrap();
 }
 
+#[tokio::test]
+async fn should_evict_oldest_login_attempt_past_limit() {
+    let (mut app, oldest_login_response, email) =
+        create_app_with_login_response(MAX_PENDING_TWO_FA_ATTEMPTS as u64 + 1).await;
+    let oldest_code = get_stored_code(&app, &email, &oldest_login_response).await;
+
+    for _ in 0..MAX_PENDING_TWO_FA_ATTEMPTS {
+        let (next_app, _, _) = get_two_fa_login_response(app, email.clone()).await;
+        app = next_app;
+    }
+
+    let verify_2fa_body = json!({
+        "email": email.as_ref().expose_secret(),
+        "loginAttemptId": oldest_login_response.login_attempt_id,
+        "2FACode": oldest_code.as_ref().expose_secret().clone(),
+    });
+
+    let verify_2fa_response = app.post_verify_2fa(&verify_2fa_body).await;
+    assert_eq!(verify_2fa_response.status(), 401);
+
+    app.clean_up().await.unwrap();
+}
+
 #[tokio::test]
 async fn should_return_401_if_code_used_twice() {
     let (mut app, login_response, email) = create_app_with_login_response(1).await;
 
-    let two_fa_code_store = app.app_state.two_fa_code_store.read().await;
-    let (_, two_fa_code) = two_fa_code_store.get_code(&email).await.unwrap();
-    drop(two_fa_code_store);
+    let two_fa_code = get_stored_code(&app, &email, &login_response).await;
 
     let verify_2fa_body = json!({
         "email": email.as_ref().expose_secret(),
@@ -188,9 +275,7 @@ async fn should_return_401_if_code_used_twice() {
 async fn should_return_200_if_correct_code() {
     let (mut app, login_response, email) = create_app_with_login_response(1).await;
 
-    let two_fa_code_store = app.app_state.two_fa_code_store.read().await;
-    let (_, two_fa_code) = two_fa_code_store.get_code(&email).await.unwrap();
-    drop(two_fa_code_store);
+    let two_fa_code = get_stored_code(&app, &email, &login_response).await;
 
     let verify_2fa_body = json!({
         "email": email.as_ref().expose_secret(),
@@ -210,3 +295,68 @@ async fn should_return_200_if_correct_code() {
 
     app.clean_up().await.unwrap();
 }
+
+#[tokio::test]
+async fn should_invalidate_login_attempt_after_too_many_wrong_codes() {
+    let (mut app, login_response, email) = create_app_with_login_response(1).await;
+
+    let two_fa_code = get_stored_code(&app, &email, &login_response).await;
+
+    let wrong_code = match two_fa_code.as_ref().expose_secret().as_str() {
+        "123456" => "654321",
+        _ => "123456",
+    };
+    let mut verify_2fa_body = json!({
+        "email": email.as_ref().expose_secret(),
+        "loginAttemptId": login_response.login_attempt_id,
+        "2FACode": wrong_code,
+    });
+
+    for _ in 0..*TWO_FA_MAX_ATTEMPTS {
+        let verify_2fa_response = app.post_verify_2fa(&verify_2fa_body).await;
+        assert_eq!(verify_2fa_response.status(), 401);
+    }
+
+    // The correct code no longer works; the user has to log in again
+    verify_2fa_body["2FACode"] = json!(two_fa_code.as_ref().expose_secret());
+    let verify_2fa_response = app.post_verify_2fa(&verify_2fa_body).await;
+    assert_eq!(verify_2fa_response.status(), 401);
+    assert!(app
+        .app_state
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&email, &login_attempt_id(&login_response))
+        .await
+        .is_err());
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_accept_correct_code_after_fewer_wrong_codes_than_limit() {
+    let (mut app, login_response, email) = create_app_with_login_response(1).await;
+
+    let two_fa_code = get_stored_code(&app, &email, &login_response).await;
+
+    let wrong_code = match two_fa_code.as_ref().expose_secret().as_str() {
+        "123456" => "654321",
+        _ => "123456",
+    };
+    let mut verify_2fa_body = json!({
+        "email": email.as_ref().expose_secret(),
+        "loginAttemptId": login_response.login_attempt_id,
+        "2FACode": wrong_code,
+    });
+
+    for _ in 1..*TWO_FA_MAX_ATTEMPTS {
+        let verify_2fa_response = app.post_verify_2fa(&verify_2fa_body).await;
+        assert_eq!(verify_2fa_response.status(), 401);
+    }
+
+    verify_2fa_body["2FACode"] = json!(two_fa_code.as_ref().expose_secret());
+    let verify_2fa_response = app.post_verify_2fa(&verify_2fa_body).await;
+    assert_eq!(verify_2fa_response.status(), 200);
+
+    app.clean_up().await.unwrap();
+}
@@ -0,0 +1,143 @@
+use secrecy::Secret;
+use serde_json::json;
+use wiremock::{
+    matchers::{body_string_contains, method, path},
+    Mock, ResponseTemplate,
+};
+
+use auth_service::{
+    api::rest::ErrorResponse,
+    domain::email::Email,
+    routes::verify_email::VerifyEmailResponse,
+    utils::auth::{EmailVerificationToken, PasswordResetToken},
+};
+
+use crate::helpers::{get_random_email, verify_email, RESTTestApp};
+
+const TEST_PASSWORD: &str = "P@ssw0rd";
+
+async fn create_app_with_unverified_user(expected_email_calls: u64) -> (RESTTestApp, String) {
+    let app = RESTTestApp::new().await;
+    let email = get_random_email();
+
+    Mock::given(path("/email/withTemplate"))
+        .and(method("POST"))
+        .and(body_string_contains("email-verification"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(expected_email_calls)
+        .mount(&app.email_server)
+        .await;
+
+    let signup_body = json!({
+        "email": email,
+        "password": TEST_PASSWORD,
+        "requires2FA": false,
+    });
+    assert_eq!(app.post_signup(&signup_body).await.status(), 201);
+    (app, email)
+}
+
+async fn login(app: &RESTTestApp, email: &str) -> reqwest::Response {
+    app.post_login(&json!({ "email": email, "password": TEST_PASSWORD }))
+        .await
+}
+
+#[tokio::test]
+async fn login_should_return_403_until_email_verified() {
+    let (mut app, email) = create_app_with_unverified_user(1).await;
+
+    let response = login(&app, &email).await;
+    assert_eq!(response.status(), 403);
+    assert_eq!(
+        response.json::<ErrorResponse>().await.unwrap().error,
+        "Email not verified".to_owned()
+    );
+
+    verify_email(&app, &email).await;
+    assert_eq!(login(&app, &email).await.status(), 200);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn password_reset_should_mark_email_verified() {
+    let (mut app, email) = create_app_with_unverified_user(1).await;
+    Mock::given(path("/email/withTemplate"))
+        .and(method("POST"))
+        .and(body_string_contains("password-reset"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_initiate_password_reset(&json!({ "email": email })).await;
+    let reset_token = app.get_password_reset_token(&email).await.unwrap();
+    let reset_body = json!({ "token": reset_token, "new_password": "NewP@ssw0rd123" });
+    assert_eq!(app.post_reset_password(&reset_body).await.status(), 200);
+
+    let login_body = json!({ "email": email, "password": "NewP@ssw0rd123" });
+    assert_eq!(app.post_login(&login_body).await.status(), 200);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn verify_email_should_return_200_if_already_verified() {
+    let (mut app, email) = create_app_with_unverified_user(1).await;
+    let token = EmailVerificationToken::new(&Email::parse(Secret::new(email.clone())).unwrap()).unwrap();
+
+    assert_eq!(app.get_verify_email(&token.expose_secret_string()).await.status(), 200);
+    let response = app.get_verify_email(&token.expose_secret_string()).await;
+    assert_eq!(response.status(), 200);
+    let response: VerifyEmailResponse = response.json().await.unwrap();
+    assert_eq!(response.message, "Email verified successfully.");
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn verify_email_should_return_401_if_invalid_token() {
+    let (mut app, email) = create_app_with_unverified_user(1).await;
+
+    assert_eq!(app.get_verify_email("invalid_token").await.status(), 401);
+
+    // Tokens issued for other purposes are rejected too
+    let reset_token = PasswordResetToken::new(&Email::parse(Secret::new(email.clone())).unwrap()).unwrap();
+    let response = app.get_verify_email(&reset_token.expose_secret_string()).await;
+    assert_eq!(response.status(), 401);
+    assert_eq!(login(&app, &email).await.status(), 403);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn resend_verification_should_send_email_only_for_unverified_users() {
+    // One on signup and one for the resend
+    let (mut app, email) = create_app_with_unverified_user(2).await;
+    let expected_message = "If the email belongs to an unverified account, a verification link has been sent.";
+
+    let response = app.post_resend_verification(&json!({ "email": email })).await;
+    assert_eq!(response.status(), 200);
+    let response: VerifyEmailResponse = response.json().await.unwrap();
+    assert_eq!(response.message, expected_message);
+
+    verify_email(&app, &email).await;
+    for email in [email, get_random_email()] {
+        let response = app.post_resend_verification(&json!({ "email": email })).await;
+        assert_eq!(response.status(), 200);
+        let response: VerifyEmailResponse = response.json().await.unwrap();
+        assert_eq!(response.message, expected_message);
+    }
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn resend_verification_should_return_400_if_invalid_email() {
+    let (mut app, _) = create_app_with_unverified_user(1).await;
+
+    let response = app.post_resend_verification(&json!({ "email": "invalid_email" })).await;
+    assert_eq!(response.status(), 400);
+
+    app.clean_up().await.unwrap();
+}
@@ -1,4 +1,10 @@
-use auth_service::{api::rest::ErrorResponse, domain::email::Email, utils::auth::generate_auth_token};
+use uuid::Uuid;
+
+use auth_service::{
+    api::rest::ErrorResponse,
+    domain::{data_stores::TrustedDevice, email::Email, user::UserAccess},
+    utils::auth::{generate_auth_token, trust_device},
+};
 use secrecy::{ExposeSecret, Secret};
 use serde_json::json;
 
@@ -24,7 +30,7 @@ async fn should_return_200_valid_token() {
     let mut app = RESTTestApp::new().await;
     let email = get_random_email();
     let email = Email::parse(Secret::new(email)).unwrap();
-    let token = generate_auth_token(&email).unwrap();
+    let token = generate_auth_token(&email, &Uuid::new_v4(), &UserAccess::default()).unwrap();
     let request_body = json!({ "token": token.expose_secret() });
     let response = app.post_verify_token(&request_body).await;
     assert_eq!(
@@ -91,3 +97,51 @@ async fn should_return_401_if_banned_token() {
 
     app.clean_up().await.unwrap();
 }
+
+#[tokio::test]
+async fn should_return_200_with_bearer_header() {
+    let mut app = RESTTestApp::new().await;
+    let email = Email::parse(Secret::new(get_random_email())).unwrap();
+    let token = generate_auth_token(&email, &Uuid::new_v4(), &UserAccess::default()).unwrap();
+
+    let response = app.post_verify_token_with_bearer(token.expose_secret()).await;
+    assert_eq!(response.status(), 200);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_return_400_without_body_or_bearer_header() {
+    let mut app = RESTTestApp::new().await;
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/verify-token", app.address))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+    assert_eq!(
+        response.json::<ErrorResponse>().await.unwrap().error,
+        "Missing auth token".to_owned()
+    );
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_return_401_for_trusted_device_token() {
+    let mut app = RESTTestApp::new().await;
+    let email = Email::parse(Secret::new(get_random_email())).unwrap();
+    let cookie = trust_device(
+        app.app_state.trusted_device_store.clone(),
+        TrustedDevice::new(email, None, None),
+    )
+    .await
+    .unwrap();
+
+    // Signed with the same key as auth tokens, but it only vouches for a device, not a session
+    let response = app.post_verify_token(&json!({ "token": cookie.value() })).await;
+    assert_eq!(response.status(), 401);
+
+    app.clean_up().await.unwrap();
+}
@@ -0,0 +1,447 @@
+use ciborium::Value;
+use ring::{
+    digest::{digest, SHA256},
+    rand::SystemRandom,
+    signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING},
+};
+use secrecy::Secret;
+use serde_json::json;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use auth_service::{
+    api::rest::ErrorResponse,
+    domain::{
+        data_stores::{LoginAttemptId, TwoFACodeStore, UserStore},
+        email::Email,
+    },
+    routes::{
+        login::TwoFactorAuthResponse,
+        webauthn::{CreationOptionsResponse, RequestOptionsResponse, WebAuthnCredentialResponse},
+    },
+    utils::{
+        constants::{JWT_COOKIE_NAME, WEBAUTHN_ORIGIN, WEBAUTHN_RP_ID},
+        webauthn::{encode_base64url, FLAG_ATTESTED_CREDENTIAL_DATA, FLAG_USER_PRESENT, FLAG_USER_VERIFIED},
+    },
+};
+
+use crate::db::set_email_unverified;
+use crate::helpers::{get_random_email, signup_and_login, verify_email, RESTTestApp};
+
+/// A software passkey: a P-256 key pair that answers ceremonies the way a browser and authenticator would.
+struct TestAuthenticator {
+    key_pair: EcdsaKeyPair,
+    credential_id: Vec<u8>,
+    sign_count: u32,
+    origin: String,
+}
+
+impl TestAuthenticator {
+    fn new() -> Self {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng).unwrap();
+        let key_pair = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8.as_ref(), &rng).unwrap();
+        Self {
+            key_pair,
+            credential_id: uuid::Uuid::new_v4().as_bytes().to_vec(),
+            sign_count: 0,
+            origin: WEBAUTHN_ORIGIN.to_string(),
+        }
+    }
+
+    fn id(&self) -> String {
+        encode_base64url(&self.credential_id)
+    }
+
+    fn client_data(&self, ceremony_type: &str, challenge: &str) -> Vec<u8> {
+        json!({ "type": ceremony_type, "challenge": challenge, "origin": self.origin })
+            .to_string()
+            .into_bytes()
+    }
+
+    fn authenticator_data(&self, flags: u8) -> Vec<u8> {
+        let mut data = digest(&SHA256, WEBAUTHN_RP_ID.as_bytes()).as_ref().to_vec();
+        data.push(flags);
+        data.extend_from_slice(&self.sign_count.to_be_bytes());
+        data
+    }
+
+    fn cose_key(&self) -> Vec<u8> {
+        let point = self.key_pair.public_key().as_ref();
+        let key = Value::Map(vec![
+            (Value::from(1), Value::from(2)),
+            (Value::from(3), Value::from(-7)),
+            (Value::from(-1), Value::from(1)),
+            (Value::from(-2), Value::Bytes(point[1..33].to_vec())),
+            (Value::from(-3), Value::Bytes(point[33..].to_vec())),
+        ]);
+        let mut bytes = Vec::new();
+        ciborium::ser::into_writer(&key, &mut bytes).unwrap();
+        bytes
+    }
+
+    fn register(&self, options: &CreationOptionsResponse) -> serde_json::Value {
+        let mut auth_data =
+            self.authenticator_data(FLAG_USER_PRESENT | FLAG_USER_VERIFIED | FLAG_ATTESTED_CREDENTIAL_DATA);
+        auth_data.extend_from_slice(&[0u8; 16]);
+        auth_data.extend_from_slice(&(self.credential_id.len() as u16).to_be_bytes());
+        auth_data.extend_from_slice(&self.credential_id);
+        auth_data.extend_from_slice(&self.cose_key());
+
+        let attestation_object = Value::Map(vec![
+            (Value::from("fmt"), Value::from("none")),
+            (Value::from("attStmt"), Value::Map(vec![])),
+            (Value::from("authData"), Value::Bytes(auth_data)),
+        ]);
+        let mut attestation_bytes = Vec::new();
+        ciborium::ser::into_writer(&attestation_object, &mut attestation_bytes).unwrap();
+
+        json!({
+            "id": self.id(),
+            "type": "public-key",
+            "response": {
+                "clientDataJSON": encode_base64url(&self.client_data("webauthn.create", &options.public_key.challenge)),
+                "attestationObject": encode_base64url(&attestation_bytes),
+            },
+        })
+    }
+
+    fn assert(&mut self, options: &RequestOptionsResponse, flags: u8) -> serde_json::Value {
+        self.sign_count += 1;
+        let auth_data = self.authenticator_data(flags);
+        let client_data = self.client_data("webauthn.get", &options.public_key.challenge);
+        let signed_data = [&auth_data[..], digest(&SHA256, &client_data).as_ref()].concat();
+        let signature = self.key_pair.sign(&SystemRandom::new(), &signed_data).unwrap();
+
+        json!({
+            "id": self.id(),
+            "type": "public-key",
+            "response": {
+                "clientDataJSON": encode_base64url(&client_data),
+                "authenticatorData": encode_base64url(&auth_data),
+                "signature": encode_base64url(signature.as_ref()),
+            },
+        })
+    }
+}
+
+async fn register_passkey(app: &RESTTestApp, authenticator: &TestAuthenticator) -> reqwest::Response {
+    let response = app.post_webauthn_register_start().await;
+    assert_eq!(response.status(), 200);
+    let options: CreationOptionsResponse = response.json().await.unwrap();
+
+    app.post_webauthn_register_finish(&json!({
+        "name": "Laptop",
+        "credential": authenticator.register(&options),
+    }))
+    .await
+}
+
+async fn login_options(app: &RESTTestApp, email: &str) -> RequestOptionsResponse {
+    let response = app.post_webauthn_login_start(&json!({ "email": email })).await;
+    assert_eq!(response.status(), 200);
+    response.json().await.unwrap()
+}
+
+#[tokio::test]
+async fn should_register_passkey_and_log_in_without_password() {
+    let mut app = RESTTestApp::new().await;
+    let email = get_random_email();
+    signup_and_login(&app, &email).await;
+    let mut authenticator = TestAuthenticator::new();
+
+    let response = register_passkey(&app, &authenticator).await;
+    assert_eq!(response.status(), 201);
+    let credential: WebAuthnCredentialResponse = response.json().await.unwrap();
+    assert_eq!(credential.id, authenticator.id());
+    assert_eq!(credential.name, "Laptop");
+
+    let options = login_options(&app, &email).await;
+    assert_eq!(options.public_key.user_verification, "required");
+    assert_eq!(options.public_key.allow_credentials.len(), 1);
+    assert_eq!(options.public_key.allow_credentials[0].id, authenticator.id());
+
+    let response = app
+        .post_webauthn_login_finish(&authenticator.assert(&options, FLAG_USER_PRESENT | FLAG_USER_VERIFIED))
+        .await;
+    assert_eq!(response.status(), 200);
+    assert!(response.cookies().any(|c| c.name() == JWT_COOKIE_NAME));
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn register_start_should_return_400_without_auth_token() {
+    let mut app = RESTTestApp::new().await;
+
+    let response = app.post_webauthn_register_start().await;
+    assert_eq!(response.status(), 400);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn register_should_return_409_for_existing_credential() {
+    let mut app = RESTTestApp::new().await;
+    signup_and_login(&app, &get_random_email()).await;
+    let authenticator = TestAuthenticator::new();
+
+    assert_eq!(register_passkey(&app, &authenticator).await.status(), 201);
+
+    let response = app.post_webauthn_register_start().await;
+    let options: CreationOptionsResponse = response.json().await.unwrap();
+    assert_eq!(options.public_key.exclude_credentials[0].id, authenticator.id());
+
+    let response = app
+        .post_webauthn_register_finish(&json!({ "credential": authenticator.register(&options) }))
+        .await;
+    assert_eq!(response.status(), 409);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn register_should_return_401_for_wrong_origin() {
+    let mut app = RESTTestApp::new().await;
+    signup_and_login(&app, &get_random_email()).await;
+    let mut authenticator = TestAuthenticator::new();
+    authenticator.origin = "https://evil.example".to_string();
+
+    let response = register_passkey(&app, &authenticator).await;
+    assert_eq!(response.status(), 401);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn login_finish_should_return_401_for_replayed_challenge() {
+    let mut app = RESTTestApp::new().await;
+    let email = get_random_email();
+    signup_and_login(&app, &email).await;
+    let mut authenticator = TestAuthenticator::new();
+    register_passkey(&app, &authenticator).await;
+
+    let options = login_options(&app, &email).await;
+    let assertion = authenticator.assert(&options, FLAG_USER_PRESENT | FLAG_USER_VERIFIED);
+
+    assert_eq!(app.post_webauthn_login_finish(&assertion).await.status(), 200);
+    assert_eq!(app.post_webauthn_login_finish(&assertion).await.status(), 401);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn login_finish_should_return_401_without_user_verification() {
+    let mut app = RESTTestApp::new().await;
+    let email = get_random_email();
+    signup_and_login(&app, &email).await;
+    let mut authenticator = TestAuthenticator::new();
+    register_passkey(&app, &authenticator).await;
+
+    let options = login_options(&app, &email).await;
+    let response = app
+        .post_webauthn_login_finish(&authenticator.assert(&options, FLAG_USER_PRESENT))
+        .await;
+    assert_eq!(response.status(), 401);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn login_finish_should_return_401_when_sign_count_goes_backwards() {
+    let mut app = RESTTestApp::new().await;
+    let email = get_random_email();
+    signup_and_login(&app, &email).await;
+    let mut authenticator = TestAuthenticator::new();
+    register_passkey(&app, &authenticator).await;
+
+    authenticator.sign_count = 10;
+    let options = login_options(&app, &email).await;
+    let response = app
+        .post_webauthn_login_finish(&authenticator.assert(&options, FLAG_USER_PRESENT | FLAG_USER_VERIFIED))
+        .await;
+    assert_eq!(response.status(), 200);
+
+    // A cloned authenticator would still be reporting an older counter
+    authenticator.sign_count = 5;
+    let options = login_options(&app, &email).await;
+    let response = app
+        .post_webauthn_login_finish(&authenticator.assert(&options, FLAG_USER_PRESENT | FLAG_USER_VERIFIED))
+        .await;
+    assert_eq!(response.status(), 401);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn login_finish_should_return_403_for_unverified_email() {
+    let mut app = RESTTestApp::new().await;
+    let email = get_random_email();
+    signup_and_login(&app, &email).await;
+    let mut authenticator = TestAuthenticator::new();
+    assert_eq!(register_passkey(&app, &authenticator).await.status(), 201);
+    set_email_unverified(&app.test_db_name, &email).await;
+
+    let options = login_options(&app, &email).await;
+    let response = app
+        .post_webauthn_login_finish(&authenticator.assert(&options, FLAG_USER_PRESENT | FLAG_USER_VERIFIED))
+        .await;
+    assert_eq!(response.status(), 403);
+    assert_eq!(
+        response.json::<ErrorResponse>().await.unwrap().error,
+        "Email not verified".to_owned()
+    );
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn passkey_login_should_fail_after_account_deletion() {
+    let mut app = RESTTestApp::new().await;
+    let email = get_random_email();
+    signup_and_login(&app, &email).await;
+    let mut authenticator = TestAuthenticator::new();
+    assert_eq!(register_passkey(&app, &authenticator).await.status(), 201);
+    let options = login_options(&app, &email).await;
+
+    // Deleted while the sign-in was under way, and still inside the grace period
+    app.app_state
+        .user_store
+        .write()
+        .await
+        .schedule_deletion(&Email::parse(Secret::new(email.clone())).unwrap())
+        .await
+        .unwrap();
+    let response = app
+        .post_webauthn_login_finish(&authenticator.assert(&options, FLAG_USER_PRESENT | FLAG_USER_VERIFIED))
+        .await;
+    assert_eq!(response.status(), 401);
+    assert!(!response.cookies().any(|c| c.name() == JWT_COOKIE_NAME));
+    let response = app.post_webauthn_login_start(&json!({ "email": email })).await;
+    assert_eq!(response.status(), 401);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn passkey_login_should_fail_after_delete_account() {
+    let mut app = RESTTestApp::new().await;
+    let email = get_random_email();
+    signup_and_login(&app, &email).await;
+    let authenticator = TestAuthenticator::new();
+    assert_eq!(register_passkey(&app, &authenticator).await.status(), 201);
+
+    assert_eq!(
+        app.delete_account(&json!({ "password": "P@ssw0rd" })).await.status(),
+        200
+    );
+
+    let response = app.post_webauthn_login_start(&json!({ "email": email })).await;
+    assert_eq!(response.status(), 401);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn login_start_should_return_401_without_registered_passkey() {
+    let mut app = RESTTestApp::new().await;
+    let email = get_random_email();
+    signup_and_login(&app, &email).await;
+
+    let response = app.post_webauthn_login_start(&json!({ "email": email })).await;
+    assert_eq!(response.status(), 401);
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn login_start_should_return_401_for_unknown_email() {
+    let mut app = RESTTestApp::new().await;
+
+    // Same answer as for a user without a passkey, so accounts can't be discovered this way
+    let response = app
+        .post_webauthn_login_start(&json!({ "email": get_random_email() }))
+        .await;
+    assert_eq!(response.status(), 401);
+    assert_eq!(
+        response.json::<ErrorResponse>().await.unwrap().error,
+        "Invalid credentials".to_owned()
+    );
+
+    app.clean_up().await.unwrap();
+}
+
+#[tokio::test]
+async fn should_accept_passkey_as_second_factor() {
+    let mut app = RESTTestApp::new().await;
+    let email = get_random_email();
+
+    Mock::given(path("/email/withTemplate"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(3)
+        .mount(&app.email_server)
+        .await;
+
+    let signup_body = json!({ "email": email, "password": "P@ssw0rd", "requires2FA": true });
+    assert_eq!(app.post_signup(&signup_body).await.status(), 201);
+    verify_email(&app, &email).await;
+    let login_body = json!({ "email": email, "password": "P@ssw0rd" });
+
+    // Log in with the emailed code once so the passkey can be registered
+    let login_response: TwoFactorAuthResponse = app.post_login(&login_body).await.json().await.unwrap();
+    let parsed_email = Email::parse(Secret::new(email.clone())).unwrap();
+    let login_attempt_id = LoginAttemptId::parse(Secret::new(login_response.login_attempt_id.clone())).unwrap();
+    let code = app
+        .app_state
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&parsed_email, &login_attempt_id)
+        .await
+        .unwrap();
+    let verify_body = json!({
+        "email": email,
+        "loginAttemptId": login_response.login_attempt_id,
+        "2FACode": code.expose_secret_string(),
+    });
+    assert_eq!(app.post_verify_2fa(&verify_body).await.status(), 200);
+
+    let mut authenticator = TestAuthenticator::new();
+    assert_eq!(register_passkey(&app, &authenticator).await.status(), 201);
+
+    let login_response: TwoFactorAuthResponse = app.post_login(&login_body).await.json().await.unwrap();
+
+    let response = app
+        .post_webauthn_2fa_start(&json!({ "email": email, "loginAttemptId": uuid::Uuid::new_v4().to_string() }))
+        .await;
+    assert_eq!(response.status(), 401);
+
+    let response = app
+        .post_webauthn_2fa_start(&json!({ "email": email, "loginAttemptId": login_response.login_attempt_id }))
+        .await;
+    assert_eq!(response.status(), 200);
+    let options: RequestOptionsResponse = response.json().await.unwrap();
+
+    // User verification is not required since the password was already checked
+    let response = app
+        .post_webauthn_2fa_finish(&authenticator.assert(&options, FLAG_USER_PRESENT))
+        .await;
+    assert_eq!(response.status(), 200);
+    assert!(response.cookies().any(|c| c.name() == JWT_COOKIE_NAME));
+
+    // The login attempt is finished, so its code can no longer be used
+    let login_attempt_id = LoginAttemptId::parse(Secret::new(login_response.login_attempt_id)).unwrap();
+    assert!(app
+        .app_state
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&parsed_email, &login_attempt_id)
+        .await
+        .is_err());
+
+    app.clean_up().await.unwrap();
+}
@@ -2,10 +2,13 @@ use auth_service::domain::{
     data_stores::{UserStore, UserStoreError},
     email::Email,
     password::Password,
-    user::NewUser,
+    phone_number::PhoneNumber,
+    user::{AuthEvent, AuthEventKind, ImportedUser, NewUser, PasswordHashCounts, TwoFAMethod},
 };
-use secrecy::Secret;
+use auth_service::utils::{auth::async_compute_password_hash, constants::MAX_AUTH_EVENTS};
+use secrecy::{ExposeSecret, Secret};
 
+use crate::db::set_outdated_password_hash;
 use crate::helpers::RESTTestApp;
 
 fn str_to_valid_email(email: &str) -> Email {
@@ -19,12 +22,12 @@ async fn test_add_user() {
 
     let email = str_to_valid_email("test@example.com");
     let password = Password::parse(Secret::new("P@ssw0rd123".to_string())).await.unwrap();
-    let new_user = NewUser::new(email.clone(), password.clone(), false);
+    let new_user = NewUser::new(email.clone(), password.clone(), TwoFAMethod::None);
 
     let result = user_store.add_user(new_user).await;
     assert!(result.is_ok());
 
-    let new_user = NewUser::new(email, password, false);
+    let new_user = NewUser::new(email, password, TwoFAMethod::None);
     let result = user_store.add_user(new_user).await;
     assert!(matches!(result, Err(UserStoreError::UserAlreadyExists)));
 
@@ -39,7 +42,7 @@ async fn test_get_user() {
 
     let email = str_to_valid_email("test@example.com");
     let password = Password::parse(Secret::new("P@ssw0rd123".to_string())).await.unwrap();
-    let new_user = NewUser::new(email.clone(), password, false);
+    let new_user = NewUser::new(email.clone(), password, TwoFAMethod::None);
 
     user_store.add_user(new_user).await.unwrap();
 
@@ -63,7 +66,7 @@ async fn test_update_password() {
 
     let email = str_to_valid_email("test@example.com");
     let password = Password::parse(Secret::new("P@ssw0rd123".to_string())).await.unwrap();
-    let new_user = NewUser::new(email.clone(), password.clone(), false);
+    let new_user = NewUser::new(email.clone(), password.clone(), TwoFAMethod::None);
 
     user_store.add_user(new_user).await.unwrap();
 
@@ -73,11 +76,11 @@ async fn test_update_password() {
     let result = user_store.update_password(&email, new_password.clone()).await;
     assert!(result.is_ok());
 
-    let result = user_store.validate_user(&email, &new_password).await;
+    let result = user_store.validate_user(&email, new_password.as_ref()).await;
     assert!(result.is_ok());
 
     let old_password = password;
-    let result = user_store.validate_user(&email, &old_password).await;
+    let result = user_store.validate_user(&email, old_password.as_ref()).await;
     assert!(matches!(result, Err(e) if e.to_string() == "Failed to verify password hash"));
 
     let non_existent_email = str_to_valid_email("nonexistent@example.com");
@@ -88,6 +91,43 @@ async fn test_update_password() {
     app.clean_up().await.unwrap();
 }
 
+#[sqlx::test]
+async fn test_update_2fa_settings() {
+    let mut app = RESTTestApp::new().await;
+    let mut user_store = app.app_state.user_store.write().await;
+
+    let email = str_to_valid_email("test@example.com");
+    let password = Password::parse(Secret::new("P@ssw0rd123".to_string())).await.unwrap();
+    user_store
+        .add_user(NewUser::new(email.clone(), password, TwoFAMethod::None))
+        .await
+        .unwrap();
+
+    user_store
+        .update_2fa_settings(&email, TwoFAMethod::Email)
+        .await
+        .unwrap();
+    assert_eq!(
+        user_store.get_user(&email).await.unwrap().two_fa_method,
+        TwoFAMethod::Email
+    );
+
+    user_store.update_2fa_settings(&email, TwoFAMethod::None).await.unwrap();
+    assert_eq!(
+        user_store.get_user(&email).await.unwrap().two_fa_method,
+        TwoFAMethod::None
+    );
+
+    let non_existent_email = str_to_valid_email("nonexistent@example.com");
+    let result = user_store
+        .update_2fa_settings(&non_existent_email, TwoFAMethod::Email)
+        .await;
+    assert!(matches!(result, Err(UserStoreError::UserNotFound)));
+
+    drop(user_store);
+    app.clean_up().await.unwrap();
+}
+
 #[sqlx::test]
 async fn test_validate_user() {
     let mut app = RESTTestApp::new().await;
@@ -95,19 +135,19 @@ async fn test_validate_user() {
 
     let email = str_to_valid_email("test@example.com");
     let password = Password::parse(Secret::new("P@ssw0rd123".to_string())).await.unwrap();
-    let new_user = NewUser::new(email.clone(), password.clone(), false);
+    let new_user = NewUser::new(email.clone(), password.clone(), TwoFAMethod::None);
 
     user_store.add_user(new_user).await.unwrap();
 
-    let result = user_store.validate_user(&email, &password).await;
+    let result = user_store.validate_user(&email, password.as_ref()).await;
     assert!(result.is_ok());
 
     let wrong_password = Password::parse(Secret::new("WrongP@ssw0rd".to_string())).await.unwrap();
-    let result = user_store.validate_user(&email, &wrong_password).await;
+    let result = user_store.validate_user(&email, wrong_password.as_ref()).await;
     assert!(matches!(result, Err(e) if e.to_string() == "Failed to verify password hash"));
 
     let non_existent_email = str_to_valid_email("nonexistent@example.com");
-    let result = user_store.validate_user(&non_existent_email, &password).await;
+    let result = user_store.validate_user(&non_existent_email, password.as_ref()).await;
     assert!(
         matches!(result, Err(e) if e.to_string() == "no rows returned by a query that expected to return at least one row")
     );
@@ -115,3 +155,436 @@ async fn test_validate_user() {
     drop(user_store);
     app.clean_up().await.unwrap();
 }
+
+#[sqlx::test]
+async fn test_totp_credential_lifecycle() {
+    let mut app = RESTTestApp::new().await;
+    let mut user_store = app.app_state.user_store.write().await;
+
+    let email = str_to_valid_email("test@example.com");
+    let password = Password::parse(Secret::new("P@ssw0rd123".to_string())).await.unwrap();
+    user_store
+        .add_user(NewUser::new(email.clone(), password, TwoFAMethod::Email))
+        .await
+        .unwrap();
+
+    let secret = Secret::new("JBSWY3DPEHPK3PXP".to_string());
+    let result = user_store.confirm_totp_secret(&email, &secret, 1).await;
+    assert!(matches!(result, Err(UserStoreError::TotpNotEnrolled)));
+    let result = user_store.use_totp_step(&email, 1).await;
+    assert!(matches!(result, Err(UserStoreError::TotpNotEnrolled)));
+
+    user_store
+        .set_pending_totp_secret(&email, secret.clone())
+        .await
+        .unwrap();
+    assert_eq!(
+        user_store.get_user(&email).await.unwrap().two_fa_method,
+        TwoFAMethod::Email
+    );
+
+    user_store.confirm_totp_secret(&email, &secret, 10).await.unwrap();
+    assert_eq!(
+        user_store.get_user(&email).await.unwrap().two_fa_method,
+        TwoFAMethod::Totp
+    );
+
+    let credential = user_store.get_totp_credential(&email).await.unwrap();
+    assert_eq!(credential.secret.unwrap().expose_secret(), secret.expose_secret());
+    assert!(credential.pending_secret.is_none());
+    assert_eq!(credential.last_used_step, Some(10));
+
+    let result = user_store.use_totp_step(&email, 10).await;
+    assert!(matches!(result, Err(UserStoreError::TotpCodeReused)));
+    assert!(user_store.use_totp_step(&email, 11).await.is_ok());
+
+    drop(user_store);
+    app.clean_up().await.unwrap();
+}
+
+#[sqlx::test]
+async fn test_disable_2fa() {
+    let mut app = RESTTestApp::new().await;
+    let mut user_store = app.app_state.user_store.write().await;
+
+    let email = str_to_valid_email("test@example.com");
+    let password = Password::parse(Secret::new("P@ssw0rd123".to_string())).await.unwrap();
+    user_store
+        .add_user(NewUser::new(email.clone(), password, TwoFAMethod::None))
+        .await
+        .unwrap();
+
+    let secret = Secret::new("JBSWY3DPEHPK3PXP".to_string());
+    user_store
+        .set_pending_totp_secret(&email, secret.clone())
+        .await
+        .unwrap();
+    user_store.confirm_totp_secret(&email, &secret, 1).await.unwrap();
+    user_store
+        .set_recovery_codes(&email, vec!["first".to_string()])
+        .await
+        .unwrap();
+
+    user_store.disable_2fa(&email).await.unwrap();
+
+    assert_eq!(
+        user_store.get_user(&email).await.unwrap().two_fa_method,
+        TwoFAMethod::None
+    );
+    assert!(user_store.get_totp_credential(&email).await.unwrap().secret.is_none());
+    assert_eq!(user_store.count_recovery_codes(&email).await.unwrap(), 0);
+
+    let result = user_store.disable_2fa(&str_to_valid_email("other@example.com")).await;
+    assert!(matches!(result, Err(UserStoreError::UserNotFound)));
+
+    drop(user_store);
+    app.clean_up().await.unwrap();
+}
+
+#[sqlx::test]
+async fn test_phone_number_lifecycle() {
+    let mut app = RESTTestApp::new().await;
+    let mut user_store = app.app_state.user_store.write().await;
+
+    let email = str_to_valid_email("test@example.com");
+    let password = Password::parse(Secret::new("P@ssw0rd123".to_string())).await.unwrap();
+    user_store
+        .add_user(NewUser::new(email.clone(), password, TwoFAMethod::None))
+        .await
+        .unwrap();
+
+    let user_phone_number = user_store.get_phone_number(&email).await.unwrap();
+    assert!(user_phone_number.phone_number.is_none());
+    let result = user_store.confirm_phone_number(&email, "verification-id").await;
+    assert!(matches!(result, Err(UserStoreError::PhoneVerificationNotFound)));
+
+    let phone_number = PhoneNumber::parse(Secret::new("+14155552671".to_string())).unwrap();
+    user_store
+        .set_pending_phone_number(&email, phone_number.clone(), "verification-id")
+        .await
+        .unwrap();
+    let user_phone_number = user_store.get_phone_number(&email).await.unwrap();
+    assert!(user_phone_number.phone_number.is_none());
+    assert_eq!(user_phone_number.pending_phone_number, Some(phone_number.clone()));
+
+    let result = user_store.confirm_phone_number(&email, "other-id").await;
+    assert!(matches!(result, Err(UserStoreError::PhoneVerificationNotFound)));
+    user_store
+        .confirm_phone_number(&email, "verification-id")
+        .await
+        .unwrap();
+
+    let user_phone_number = user_store.get_phone_number(&email).await.unwrap();
+    assert_eq!(user_phone_number.phone_number, Some(phone_number));
+    assert!(user_phone_number.pending_phone_number.is_none());
+    assert!(user_phone_number.pending_verification_id.is_none());
+
+    let non_existent_email = str_to_valid_email("nonexistent@example.com");
+    let result = user_store.get_phone_number(&non_existent_email).await;
+    assert!(matches!(result, Err(UserStoreError::UserNotFound)));
+
+    drop(user_store);
+    app.clean_up().await.unwrap();
+}
+
+#[sqlx::test]
+async fn test_mark_email_verified() {
+    let mut app = RESTTestApp::new().await;
+    let mut user_store = app.app_state.user_store.write().await;
+
+    let email = str_to_valid_email("test@example.com");
+    let password = Password::parse(Secret::new("P@ssw0rd123".to_string())).await.unwrap();
+    user_store
+        .add_user(NewUser::new(email.clone(), password, TwoFAMethod::None))
+        .await
+        .unwrap();
+    assert!(!user_store.get_user(&email).await.unwrap().email_verified);
+
+    user_store.mark_email_verified(&email).await.unwrap();
+    user_store.mark_email_verified(&email).await.unwrap();
+    assert!(user_store.get_user(&email).await.unwrap().email_verified);
+
+    let result = user_store
+        .mark_email_verified(&str_to_valid_email("unknown@example.com"))
+        .await;
+    assert!(matches!(result, Err(UserStoreError::UserNotFound)));
+
+    drop(user_store);
+    app.clean_up().await.unwrap();
+}
+
+#[sqlx::test]
+async fn test_change_email() {
+    let mut app = RESTTestApp::new().await;
+    let mut user_store = app.app_state.user_store.write().await;
+
+    let email = str_to_valid_email("test@example.com");
+    let new_email = str_to_valid_email("new@example.com");
+    let password = Password::parse(Secret::new("P@ssw0rd123".to_string())).await.unwrap();
+    for email in [&email, &new_email] {
+        user_store
+            .add_user(NewUser::new(email.clone(), password.clone(), TwoFAMethod::None))
+            .await
+            .unwrap();
+    }
+    let result = user_store.change_email(&email, &new_email).await;
+    assert!(matches!(result, Err(UserStoreError::UserAlreadyExists)));
+
+    let new_email = str_to_valid_email("newer@example.com");
+    let phone_number = PhoneNumber::parse(Secret::new("+14155552671".to_string())).unwrap();
+    user_store
+        .set_pending_phone_number(&email, phone_number.clone(), "verification-id")
+        .await
+        .unwrap();
+    user_store.change_email(&email, &new_email).await.unwrap();
+
+    assert!(matches!(
+        user_store.get_user(&email).await,
+        Err(UserStoreError::UserNotFound)
+    ));
+    assert!(user_store.validate_user(&new_email, password.as_ref()).await.is_ok());
+    assert_eq!(user_store.get_access(&new_email).await.unwrap().roles, vec!["user"]);
+    let user_phone_number = user_store.get_phone_number(&new_email).await.unwrap();
+    assert_eq!(user_phone_number.pending_phone_number, Some(phone_number));
+
+    let result = user_store.change_email(&email, &new_email).await;
+    assert!(matches!(result, Err(UserStoreError::UserNotFound)));
+
+    drop(user_store);
+    app.clean_up().await.unwrap();
+}
+
+#[sqlx::test]
+async fn test_delete_user() {
+    let mut app = RESTTestApp::new().await;
+    let mut user_store = app.app_state.user_store.write().await;
+
+    let email = str_to_valid_email("test@example.com");
+    let password = Password::parse(Secret::new("P@ssw0rd123".to_string())).await.unwrap();
+    user_store
+        .add_user(NewUser::new(email.clone(), password, TwoFAMethod::None))
+        .await
+        .unwrap();
+    let phone_number = PhoneNumber::parse(Secret::new("+14155552671".to_string())).unwrap();
+    user_store
+        .set_pending_phone_number(&email, phone_number, "verification-id")
+        .await
+        .unwrap();
+
+    user_store.delete_user(&email).await.unwrap();
+    assert!(matches!(
+        user_store.get_user(&email).await,
+        Err(UserStoreError::UserNotFound)
+    ));
+    let result = user_store.delete_user(&email).await;
+    assert!(matches!(result, Err(UserStoreError::UserNotFound)));
+
+    drop(user_store);
+    app.clean_up().await.unwrap();
+}
+
+#[sqlx::test]
+async fn test_auth_events() {
+    let mut app = RESTTestApp::new().await;
+    let mut user_store = app.app_state.user_store.write().await;
+
+    let email = str_to_valid_email("test@example.com");
+    let password = Password::parse(Secret::new("P@ssw0rd123".to_string())).await.unwrap();
+    user_store
+        .add_user(NewUser::new(email.clone(), password, TwoFAMethod::None))
+        .await
+        .unwrap();
+    assert!(user_store.get_auth_events(&email).await.unwrap().is_empty());
+
+    let sign_in = AuthEvent::new(
+        AuthEventKind::SignIn,
+        Some("203.0.113.7".to_string()),
+        Some("test-agent".to_string()),
+    );
+    user_store.add_auth_event(&email, sign_in.clone()).await.unwrap();
+    for _ in 0..MAX_AUTH_EVENTS {
+        user_store
+            .add_auth_event(&email, AuthEvent::new(AuthEventKind::SignInFailed, None, None))
+            .await
+            .unwrap();
+    }
+    let events = user_store.get_auth_events(&email).await.unwrap();
+    assert_eq!(events.len(), MAX_AUTH_EVENTS);
+    assert!(events.iter().all(|event| event.kind == AuthEventKind::SignInFailed));
+
+    user_store
+        .add_auth_event(&email, AuthEvent::new(AuthEventKind::PasswordChanged, None, None))
+        .await
+        .unwrap();
+    user_store.add_auth_event(&email, sign_in.clone()).await.unwrap();
+    let events = user_store.get_auth_events(&email).await.unwrap();
+    assert_eq!(events.len(), MAX_AUTH_EVENTS);
+    assert_eq!(events[0].kind, AuthEventKind::SignIn);
+    assert_eq!(events[0].ip_address, sign_in.ip_address);
+    assert_eq!(events[0].user_agent, sign_in.user_agent);
+    assert_eq!(
+        events[0].occurred_at.timestamp_micros(),
+        sign_in.occurred_at.timestamp_micros()
+    );
+    assert_eq!(events[1].kind, AuthEventKind::PasswordChanged);
+
+    let new_email = str_to_valid_email("new@example.com");
+    user_store.change_email(&email, &new_email).await.unwrap();
+    assert_eq!(
+        user_store.get_auth_events(&new_email).await.unwrap().len(),
+        MAX_AUTH_EVENTS
+    );
+
+    user_store.schedule_deletion(&new_email).await.unwrap();
+    assert!(matches!(
+        user_store.get_auth_events(&new_email).await,
+        Err(UserStoreError::UserNotFound)
+    ));
+    let result = user_store.add_auth_event(&new_email, sign_in).await;
+    assert!(matches!(result, Err(UserStoreError::UserNotFound)));
+
+    drop(user_store);
+    app.clean_up().await.unwrap();
+}
+
+#[sqlx::test]
+async fn test_scheduled_deletion() {
+    let mut app = RESTTestApp::new().await;
+    let mut user_store = app.app_state.user_store.write().await;
+
+    let email = str_to_valid_email("test@example.com");
+    let password = Password::parse(Secret::new("P@ssw0rd123".to_string())).await.unwrap();
+    user_store
+        .add_user(NewUser::new(email.clone(), password.clone(), TwoFAMethod::None))
+        .await
+        .unwrap();
+
+    user_store.schedule_deletion(&email).await.unwrap();
+    assert!(matches!(
+        user_store.get_user(&email).await,
+        Err(UserStoreError::UserNotFound)
+    ));
+    assert!(user_store.validate_user(&email, password.as_ref()).await.is_err());
+    assert!(matches!(
+        user_store.get_access(&email).await,
+        Err(UserStoreError::UserNotFound)
+    ));
+    assert!(matches!(
+        user_store.get_webauthn_credentials(&email).await,
+        Err(UserStoreError::UserNotFound)
+    ));
+    assert_eq!(user_store.purge_deleted_users(3600).await.unwrap(), 0);
+
+    user_store.restore_user(&email).await.unwrap();
+    assert!(user_store.validate_user(&email, password.as_ref()).await.is_ok());
+    let result = user_store.restore_user(&email).await;
+    assert!(matches!(result, Err(UserStoreError::UserNotFound)));
+
+    user_store.schedule_deletion(&email).await.unwrap();
+    assert_eq!(user_store.purge_deleted_users(0).await.unwrap(), 1);
+    let result = user_store.restore_user(&email).await;
+    assert!(matches!(result, Err(UserStoreError::UserNotFound)));
+
+    // Signing up again replaces an account that is waiting to be purged
+    user_store
+        .add_user(NewUser::new(email.clone(), password.clone(), TwoFAMethod::Email))
+        .await
+        .unwrap();
+    user_store.schedule_deletion(&email).await.unwrap();
+    user_store
+        .add_user(NewUser::new(email.clone(), password.clone(), TwoFAMethod::None))
+        .await
+        .unwrap();
+    assert_eq!(
+        user_store.get_user(&email).await.unwrap().two_fa_method,
+        TwoFAMethod::None
+    );
+    let result = user_store.restore_user(&email).await;
+    assert!(matches!(result, Err(UserStoreError::UserNotFound)));
+
+    drop(user_store);
+    app.clean_up().await.unwrap();
+}
+
+#[sqlx::test]
+async fn test_upgrade_password_hash() {
+    let mut app = RESTTestApp::new().await;
+    let mut user_store = app.app_state.user_store.write().await;
+
+    let email = str_to_valid_email("test@example.com");
+    let password = Password::parse(Secret::new("P@ssw0rd123".to_string())).await.unwrap();
+    user_store
+        .add_user(NewUser::new(email.clone(), password.clone(), TwoFAMethod::None))
+        .await
+        .unwrap();
+    assert_eq!(
+        user_store.count_password_hashes().await.unwrap(),
+        PasswordHashCounts { total: 1, outdated: 0 }
+    );
+    assert!(user_store.get_outdated_password_hash(&email).await.unwrap().is_none());
+
+    set_outdated_password_hash(&app.test_db_name, "test@example.com", "P@ssw0rd123").await;
+    assert_eq!(
+        user_store.count_password_hashes().await.unwrap(),
+        PasswordHashCounts { total: 1, outdated: 1 }
+    );
+    assert!(user_store.validate_user(&email, password.as_ref()).await.is_ok());
+
+    let outdated_hash = user_store.get_outdated_password_hash(&email).await.unwrap().unwrap();
+    let password_hash = async_compute_password_hash(password.as_ref().clone()).await.unwrap();
+    assert!(user_store
+        .replace_password_hash(&email, &outdated_hash, password_hash.clone())
+        .await
+        .unwrap());
+    // A hash that is no longer the stored one is left alone
+    assert!(!user_store
+        .replace_password_hash(&email, &outdated_hash, password_hash)
+        .await
+        .unwrap());
+    assert!(user_store.get_outdated_password_hash(&email).await.unwrap().is_none());
+    assert_eq!(
+        user_store.count_password_hashes().await.unwrap(),
+        PasswordHashCounts { total: 1, outdated: 0 }
+    );
+    assert!(user_store.validate_user(&email, password.as_ref()).await.is_ok());
+
+    drop(user_store);
+    app.clean_up().await.unwrap();
+}
+
+#[sqlx::test]
+async fn test_import_user() {
+    let mut app = RESTTestApp::new().await;
+    let mut user_store = app.app_state.user_store.write().await;
+
+    let email = str_to_valid_email("test@example.com");
+    let password = Password::parse(Secret::new("P@ssw0rd123".to_string())).await.unwrap();
+    let password_hash = Secret::new(bcrypt::hash("P@ssw0rd123", 4).unwrap());
+    let user = ImportedUser::parse(email.clone(), password_hash, true).unwrap();
+
+    user_store.import_user(user.clone()).await.unwrap();
+    let result = user_store.import_user(user).await;
+    assert!(matches!(result, Err(UserStoreError::UserAlreadyExists)));
+    assert!(user_store.get_user(&email).await.unwrap().email_verified);
+    assert_eq!(user_store.get_access(&email).await.unwrap().roles, vec!["user"]);
+    assert_eq!(
+        user_store.count_password_hashes().await.unwrap(),
+        PasswordHashCounts { total: 1, outdated: 1 }
+    );
+
+    assert!(user_store.validate_user(&email, password.as_ref()).await.is_ok());
+    let outdated_hash = user_store.get_outdated_password_hash(&email).await.unwrap().unwrap();
+    let password_hash = async_compute_password_hash(password.as_ref().clone()).await.unwrap();
+    assert!(user_store
+        .replace_password_hash(&email, &outdated_hash, password_hash)
+        .await
+        .unwrap());
+    assert_eq!(
+        user_store.count_password_hashes().await.unwrap(),
+        PasswordHashCounts { total: 1, outdated: 0 }
+    );
+    assert!(user_store.validate_user(&email, password.as_ref()).await.is_ok());
+
+    drop(user_store);
+    app.clean_up().await.unwrap();
+}
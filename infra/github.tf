@@ -12,6 +12,11 @@ resource "random_password" "jwt_secret" {
   special = false
 }
 
+resource "random_password" "introspection_client_secret" {
+  length  = 64
+  special = false
+}
+
 resource "github_actions_secret" "admin_email" {
   repository      = data.github_repository.live_rust_bootcamp.name
   secret_name     = "ADMIN_EMAIL"
@@ -36,6 +41,12 @@ resource "github_actions_secret" "droplet_password" {
   plaintext_value = var.droplet_root_password
 }
 
+resource "github_actions_secret" "introspection_client_secret" {
+  repository      = data.github_repository.live_rust_bootcamp.name
+  secret_name     = "INTROSPECTION_CLIENT_SECRET"
+  plaintext_value = random_password.introspection_client_secret.result
+}
+
 resource "github_actions_secret" "jwt_secret" {
   repository      = data.github_repository.live_rust_bootcamp.name
   secret_name     = "JWT_SECRET"
@@ -60,6 +71,24 @@ resource "github_actions_secret" "redis_password" {
   plaintext_value = var.redis_password
 }
 
+resource "github_actions_secret" "twilio_account_sid" {
+  repository      = data.github_repository.live_rust_bootcamp.name
+  secret_name     = "TWILIO_ACCOUNT_SID"
+  plaintext_value = var.twilio_account_sid
+}
+
+resource "github_actions_secret" "twilio_auth_token" {
+  repository      = data.github_repository.live_rust_bootcamp.name
+  secret_name     = "TWILIO_AUTH_TOKEN"
+  plaintext_value = var.twilio_auth_token
+}
+
+resource "github_actions_secret" "twilio_phone_number" {
+  repository      = data.github_repository.live_rust_bootcamp.name
+  secret_name     = "TWILIO_PHONE_NUMBER"
+  plaintext_value = var.twilio_phone_number
+}
+
 resource "github_actions_variable" "domain_name" {
   repository    = data.github_repository.live_rust_bootcamp.name
   variable_name = "DOMAIN_NAME"
@@ -12,3 +12,6 @@ variable "personal_email_address" {}
 variable "postgres_password" {}
 variable "postmark_auth_token" {}
 variable "subdomain_name" {}
+variable "twilio_account_sid" {}
+variable "twilio_auth_token" {}
+variable "twilio_phone_number" {}